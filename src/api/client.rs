@@ -1,12 +1,24 @@
 use super::{
     error::ApiError,
-    requests::{ApiRequest, StreamableApiRequest},
+    requests::{
+        staging::upload::content::{ContentSource, ContentType},
+        ApiRequest, StreamableApiRequest,
+    },
+    retry::{self, RetryPolicy},
+    throttle::{Bandwidth, Throttled, TokenBucket},
+};
+#[cfg(not(target_arch = "wasm32"))]
+use super::{
+    retry::{Activity, IdleTimeout},
+    throttle::Progress,
 };
 use bytes::Bytes;
+use chrono::Utc;
 use futures_core::stream::Stream;
+use futures_util::StreamExt;
 use reqwest::{
     header::{HeaderMap, HeaderValue},
-    Client as ReqwestClient, Url,
+    Client as ReqwestClient, Request, RequestBuilder, Response, Url,
 };
 use std::fmt::Debug;
 use tomb_crypt::prelude::{ApiToken, EcSignatureKey};
@@ -42,7 +54,7 @@ impl Credentials {
 }
 
 /// The audience for the API token
-const AUDIENCE: &str = "banyan-platform";
+pub(crate) const AUDIENCE: &str = "banyan-platform";
 
 #[derive(Debug, Clone)]
 /// Client for interacting with our API
@@ -55,6 +67,20 @@ pub struct Client {
     pub signing_key: Option<EcSignatureKey>,
     /// The current bearer token
     pub bearer_token: Option<String>,
+    /// How failed requests are retried
+    retry_policy: RetryPolicy,
+    /// When the body of the next request last made progress, which holds off its read timeout
+    #[cfg(not(target_arch = "wasm32"))]
+    activity: Option<Activity>,
+    /// How fast data may be uploaded, shared by every upload from this Client and its clones.
+    /// Browsers send request bodies whole, so there is nothing for it to limit there.
+    #[cfg_attr(target_arch = "wasm32", allow(dead_code))]
+    upload_limit: Option<TokenBucket>,
+    /// How fast data may be downloaded, shared by every download to this Client and its clones
+    download_limit: Option<TokenBucket>,
+    /// Where the progress of transfers is reported
+    #[cfg(not(target_arch = "wasm32"))]
+    progress: Progress,
     /// The reqwest client
     reqwest_client: ReqwestClient,
 }
@@ -66,21 +92,80 @@ impl Client {
     /// # Returns
     /// * `Self` - The client
     pub fn new(remote_core: &str) -> Result<Self, ApiError> {
-        let mut default_headers = HeaderMap::new();
-        default_headers.insert("Content-Type", HeaderValue::from_static("application/json"));
-        let reqwest_client = ReqwestClient::builder()
-            .default_headers(default_headers)
-            .build()?;
-
+        let retry_policy = RetryPolicy::default();
         Ok(Self {
             remote_core: Url::parse(remote_core)?,
             claims: None,
             signing_key: None,
             bearer_token: None,
-            reqwest_client,
+            retry_policy,
+            #[cfg(not(target_arch = "wasm32"))]
+            activity: None,
+            upload_limit: None,
+            download_limit: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            progress: Progress::default(),
+            reqwest_client: build_reqwest_client(&retry_policy)?,
         })
     }
 
+    /// Set how failed requests are retried and timed out
+    /// # Arguments
+    /// * `retry_policy` - The policy to apply to every request
+    pub fn with_retry_policy(&mut self, retry_policy: RetryPolicy) -> Result<(), ApiError> {
+        self.reqwest_client = build_reqwest_client(&retry_policy)?;
+        self.retry_policy = retry_policy;
+        Ok(())
+    }
+
+    /// Limit how fast data is uploaded and downloaded, across all transfers at once
+    /// # Arguments
+    /// * `bandwidth` - The limits to apply to every transfer
+    pub fn with_bandwidth(&mut self, bandwidth: Bandwidth) {
+        let limit = |limit: Option<u64>| limit.filter(|limit| *limit > 0).map(TokenBucket::new);
+        self.upload_limit = limit(bandwidth.upload);
+        self.download_limit = limit(bandwidth.download);
+    }
+
+    /// Set where the progress of transfers is reported
+    /// # Arguments
+    /// * `progress` - Where to report
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_progress(&mut self, progress: Progress) {
+        self.progress = progress;
+    }
+
+    /// Get where the progress of transfers is reported
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn progress(&self) -> &Progress {
+        &self.progress
+    }
+
+    /// Turn content into the body of the next request, uploaded within the bandwidth limit and reporting its progress
+    /// # Arguments
+    /// * `source` - The content to upload
+    /// * `length` - The number of bytes in the content
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn upload_body(&mut self, source: ContentSource, length: u64) -> ContentType {
+        let progress = self.progress.transfer("Uploading", Some(length));
+        let activity = Activity::default();
+        self.activity = Some(activity.clone());
+        ContentType::wrap_stream(
+            Throttled::new(source, self.upload_limit.clone())
+                .with_progress(progress)
+                .inspect(move |_| activity.touch()),
+        )
+    }
+
+    /// Turn content into a request body. Browsers send request bodies whole, so there is nothing to throttle
+    /// # Arguments
+    /// * `source` - The content to upload
+    /// * `length` - The number of bytes in the content
+    #[cfg(target_arch = "wasm32")]
+    pub fn upload_body(&mut self, source: ContentSource, _length: u64) -> ContentType {
+        source
+    }
+
     /// Set a new remote endpoint
     /// # Arguments
     /// * `remote` - The base URL for the API
@@ -180,7 +265,7 @@ impl Client {
         }
 
         // Send the request and obtain the response
-        let response = request_builder.send().await?;
+        let response = self.send(request_builder).await?;
 
         // If the call succeeded
         if response.status().is_success() {
@@ -215,7 +300,7 @@ impl Client {
             request_builder = request_builder.bearer_auth(bearer_token);
         }
 
-        let response = request_builder.send().await?;
+        let response = self.send(request_builder).await?;
 
         if response.status().is_success() {
             Ok(())
@@ -248,7 +333,7 @@ impl Client {
             request_builder = request_builder.bearer_auth(bearer_token);
         }
 
-        let response = request_builder.send().await?;
+        let response = self.send(request_builder).await?;
 
         if response.status().is_success() {
             response
@@ -281,7 +366,7 @@ impl Client {
             request_builder = request_builder.bearer_auth(bearer_token);
         }
 
-        let response = request_builder.send().await?;
+        let response = self.send(request_builder).await?;
 
         if response.status().is_success() {
             Ok(())
@@ -299,12 +384,13 @@ impl Client {
         }
     }
 
-    /// Stream a response from the API that implements StreamableApiRequest
+    /// Stream a response from the API that implements StreamableApiRequest.
+    /// The response body fails if it goes longer than the read timeout without making progress.
     pub async fn stream<T: StreamableApiRequest>(
         &mut self,
         request: T,
         base_url: &Url,
-    ) -> Result<impl Stream<Item = Result<Bytes, reqwest::Error>>, ApiError> {
+    ) -> Result<impl Stream<Item = Result<Bytes, ApiError>>, ApiError> {
         let add_authentication = request.requires_authentication();
         let mut request_builder = request.build_request(base_url, &self.reqwest_client);
         if add_authentication {
@@ -312,10 +398,23 @@ impl Client {
             request_builder = request_builder.bearer_auth(bearer_token);
         }
 
-        let response = request_builder.send().await?;
+        let response = self.send(request_builder).await?;
 
         if response.status().is_success() {
-            Ok(response.bytes_stream())
+            #[cfg(not(target_arch = "wasm32"))]
+            let progress = self
+                .progress
+                .transfer("Downloading", response.content_length());
+            #[cfg(not(target_arch = "wasm32"))]
+            let body = IdleTimeout::new(response.bytes_stream(), self.retry_policy.read_timeout);
+            #[cfg(target_arch = "wasm32")]
+            let body = response
+                .bytes_stream()
+                .map(|chunk| chunk.map_err(ApiError::http));
+            let stream = Throttled::new(body, self.download_limit.clone());
+            #[cfg(not(target_arch = "wasm32"))]
+            let stream = stream.with_progress(progress);
+            Ok(stream)
         } else {
             if response.status() == reqwest::StatusCode::NOT_FOUND {
                 // Handle 404 specifically
@@ -329,6 +428,87 @@ impl Client {
             Err(ApiError::from(err))
         }
     }
+
+    /// Send a request, retrying it according to the retry policy wherever that is safe.
+    /// Requests with streaming bodies can't be replayed, so they are only ever sent once.
+    async fn send(&mut self, request_builder: RequestBuilder) -> Result<Response, ApiError> {
+        // Every request is timed by its own body, and no one else's
+        #[cfg(not(target_arch = "wasm32"))]
+        let activity = self.activity.take().unwrap_or_default();
+        let policy = self.retry_policy;
+        let mut request = request_builder.build()?;
+        let method = request.method().clone();
+        let idempotent = retry::is_idempotent(&method);
+        let started = Utc::now();
+        let mut attempt = 0;
+        loop {
+            let replay = request.try_clone();
+            #[cfg(not(target_arch = "wasm32"))]
+            let result = self.execute(request, &activity).await;
+            #[cfg(target_arch = "wasm32")]
+            let result = self.execute(request).await;
+            let retryable = match &result {
+                Ok(response) => RetryPolicy::retries_status(&method, response.status()),
+                Err(err) => err.is_transient(idempotent),
+            };
+            let delay = policy.delay(attempt, result.as_ref().ok().map(Response::headers));
+            let elapsed = (Utc::now() - started).to_std().unwrap_or_default();
+            match replay {
+                Some(replay)
+                    if retryable
+                        && attempt < policy.max_retries
+                        && elapsed + delay <= policy.max_elapsed =>
+                {
+                    retry::sleep(delay).await;
+                    attempt += 1;
+                    request = replay;
+                }
+                _ => return result,
+            }
+        }
+    }
+
+    /// Send a request once, giving up if no response arrives in time.
+    /// The clock restarts whenever the request body makes progress, so long uploads aren't cut off.
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn execute(&self, request: Request, activity: &Activity) -> Result<Response, ApiError> {
+        let read_timeout = self.retry_policy.read_timeout;
+        let sent = tokio::time::Instant::now();
+        let response = self.reqwest_client.execute(request);
+        tokio::pin!(response);
+        loop {
+            let active = activity.last().max(sent);
+            tokio::select! {
+                result = &mut response => return result.map_err(ApiError::from),
+                _ = tokio::time::sleep_until(active + read_timeout) => {
+                    if activity.last() <= active {
+                        return Err(ApiError::timeout(read_timeout));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Send a request once
+    #[cfg(target_arch = "wasm32")]
+    async fn execute(&self, request: Request) -> Result<Response, ApiError> {
+        self.reqwest_client
+            .execute(request)
+            .await
+            .map_err(ApiError::from)
+    }
+}
+
+/// Build the underlying HTTP client, which holds the connection timeout
+fn build_reqwest_client(retry_policy: &RetryPolicy) -> Result<ReqwestClient, ApiError> {
+    let mut default_headers = HeaderMap::new();
+    default_headers.insert("Content-Type", HeaderValue::from_static("application/json"));
+    let builder = ReqwestClient::builder().default_headers(default_headers);
+    #[cfg(not(target_arch = "wasm32"))]
+    let builder = builder.connect_timeout(retry_policy.connect_timeout);
+    #[cfg(target_arch = "wasm32")]
+    let _ = retry_policy;
+    Ok(builder.build()?)
 }
 
 // #[cfg(not(target_arch = "wasm32"))]
@@ -1,11 +1,11 @@
 use colored::Colorize;
 use std::error::Error;
 use std::fmt::{self, Display, Formatter};
+use std::time::Duration;
 use tomb_crypt::prelude::TombCryptError;
 use url::ParseError;
 
 #[cfg(test)]
-#[cfg(feature = "integration-tests")]
 use crate::{
     WnfsError,
     {blockstore::BlockStoreError, car::error::CarError, filesystem::FilesystemError},
@@ -70,6 +70,56 @@ impl ApiError {
             kind: ApiErrorKind::MissingData(String::from(msg)),
         }
     }
+
+    /// No response arrived in time
+    pub fn timeout(duration: Duration) -> Self {
+        Self {
+            kind: ApiErrorKind::Timeout(duration),
+        }
+    }
+
+    /// The HTTP status the request failed with, if it got that far
+    pub(crate) fn status(&self) -> Option<reqwest::StatusCode> {
+        match &self.kind {
+            ApiErrorKind::HttpResponse(status) => Some(*status),
+            ApiErrorKind::ReqwestGeneral(err)
+            | ApiErrorKind::HttpClient(err)
+            | ApiErrorKind::ResponseFormat(err) => err.status(),
+            _ => None,
+        }
+    }
+
+    /// Whether the request failed without ever hearing back from the server
+    pub(crate) fn is_unreachable(&self) -> bool {
+        match &self.kind {
+            ApiErrorKind::ReqwestGeneral(err) | ApiErrorKind::HttpClient(err) => {
+                err.status().is_none() && !err.is_builder()
+            }
+            ApiErrorKind::Timeout(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Whether the request was never sent for lack of credentials
+    pub(crate) fn is_auth_unavailable(&self) -> bool {
+        matches!(self.kind, ApiErrorKind::AuthUnavailable)
+    }
+
+    /// Whether sending the request again might succeed, given whether it is safe to send twice
+    pub(crate) fn is_transient(&self, idempotent: bool) -> bool {
+        match &self.kind {
+            ApiErrorKind::ReqwestGeneral(err) | ApiErrorKind::HttpClient(err) => {
+                // Requests which never connected were never seen by the server
+                #[cfg(not(target_arch = "wasm32"))]
+                let unsent = err.is_connect();
+                #[cfg(target_arch = "wasm32")]
+                let unsent = false;
+                unsent || (idempotent && (err.is_timeout() || err.is_request()))
+            }
+            ApiErrorKind::Timeout(_) => idempotent,
+            _ => false,
+        }
+    }
 }
 
 impl From<Box<dyn std::error::Error + Send + Sync + 'static>> for ApiError {
@@ -102,8 +152,13 @@ impl Display for ApiError {
             }
             ApiErrorKind::Parse(err) => format!("{} {err}", "PARSING ERROR:".underline()),
             ApiErrorKind::MissingData(msg) => format!("{} {msg}", "MISSING DATA:".underline()),
+            ApiErrorKind::Timeout(duration) => {
+                format!(
+                    "{} no response within {duration:?}",
+                    "TIMED OUT:".underline()
+                )
+            }
             #[cfg(test)]
-            #[cfg(feature = "integration-tests")]
             ApiErrorKind::Filesystem(err) => format!("{} {err}", "FILESYSTEM ERROR:".underline()),
         };
 
@@ -150,9 +205,10 @@ enum ApiErrorKind {
     Parse(ParseError),
     /// Missing data for performing a request
     MissingData(String),
-    /// When we're performing integration tests we also want Filesystem Errors
+    /// No response arrived in time
+    Timeout(Duration),
+    /// Tests also want Filesystem Errors
     #[cfg(test)]
-    #[cfg(feature = "integration-tests")]
     Filesystem(Box<FilesystemError>),
 }
 
@@ -175,7 +231,6 @@ impl From<ParseError> for ApiError {
 }
 
 #[cfg(test)]
-#[cfg(feature = "integration-tests")]
 impl From<FilesystemError> for ApiError {
     fn from(value: FilesystemError) -> Self {
         Self {
@@ -185,7 +240,6 @@ impl From<FilesystemError> for ApiError {
 }
 
 #[cfg(test)]
-#[cfg(feature = "integration-tests")]
 impl From<WnfsError> for ApiError {
     fn from(value: WnfsError) -> Self {
         Self {
@@ -195,7 +249,6 @@ impl From<WnfsError> for ApiError {
 }
 
 #[cfg(test)]
-#[cfg(feature = "integration-tests")]
 impl From<CarError> for ApiError {
     fn from(value: CarError) -> Self {
         Self {
@@ -207,7 +260,6 @@ impl From<CarError> for ApiError {
 }
 
 #[cfg(test)]
-#[cfg(feature = "integration-tests")]
 impl From<BlockStoreError> for ApiError {
     fn from(value: BlockStoreError) -> Self {
         Self {
@@ -0,0 +1,1435 @@
+use crate::{
+    api::{
+        client::{Client, AUDIENCE},
+        models::{
+            account::Account,
+            bucket::{BucketType, StorageClass},
+            metadata::MetadataState,
+        },
+    },
+    car::{error::CarError, v2::CarV2},
+};
+use axum::{
+    async_trait,
+    body::Bytes,
+    extract::{DefaultBodyLimit, FromRequestParts, Json, Multipart, Path, State},
+    http::{
+        header::{AUTHORIZATION, RETRY_AFTER},
+        request::Parts,
+        HeaderMap, Request, StatusCode,
+    },
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, post, put},
+    Router,
+};
+use chrono::Utc;
+use jwt_simple::prelude::{
+    ECDSAP384PublicKeyLike, ES384PublicKey, NoCustomClaims, VerificationOptions,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
+    io::Cursor,
+    net::TcpListener,
+    sync::{Arc, Mutex, MutexGuard},
+};
+use tomb_crypt::{
+    hex_fingerprint,
+    prelude::{EcPublicEncryptionKey, EcPublicSignatureKey, PublicKey},
+};
+use uuid::Uuid;
+
+/// The storage quota given to every account
+const USAGE_LIMIT: u64 = 50 * 1024 * 1024 * 1024;
+
+/// An in-process stand-in for the core and staging services, holding everything in memory.
+/// Bearer tokens are verified against the device API keys registered with it, just as they are remotely.
+#[derive(Clone)]
+pub(crate) struct MockServer {
+    /// Base URL of the core service
+    pub core_url: String,
+    /// Base URL of the staging service
+    pub staging_url: String,
+    shared: Shared,
+}
+
+impl MockServer {
+    /// Serve both services on ephemeral local ports for as long as the current Tokio runtime lives
+    pub fn start() -> Self {
+        let core = TcpListener::bind("127.0.0.1:0").expect("unable to bind mock core");
+        let staging = TcpListener::bind("127.0.0.1:0").expect("unable to bind mock staging");
+        let core_url = format!("http://{}", core.local_addr().expect("no core address"));
+        let staging_url = format!(
+            "http://{}",
+            staging.local_addr().expect("no staging address")
+        );
+
+        let shared = Shared {
+            state: Arc::new(Mutex::new(MockState::default())),
+            staging_url: staging_url.clone(),
+        };
+        serve(core, core_router(shared.clone()));
+        serve(staging, staging_router(shared.clone()));
+
+        Self {
+            core_url,
+            staging_url,
+            shared,
+        }
+    }
+
+    /// Answer the next requests to either service with these statuses instead of handling them
+    pub fn fail_next(&self, statuses: &[StatusCode]) {
+        self.shared.lock().failures.extend(statuses);
+    }
+
+    /// Refuse the next uploads of content, without failing any other request
+    pub fn fail_uploads(&self, count: usize) {
+        self.shared.lock().failed_uploads += count;
+    }
+
+    /// Serve different content for a block than was uploaded, as a faulty storage provider might
+    pub fn corrupt_block(&self, cid: &str) {
+        if let Some(block) = self.shared.lock().blocks.get_mut(cid) {
+            block.push(0);
+        }
+    }
+
+    /// A Client of the core service without any credentials
+    pub fn client(&self) -> Client {
+        Client::new(&self.core_url).expect("unable to create mock client")
+    }
+
+    /// A Client of the core service with credentials for a brand new account
+    pub async fn authenticated_client(&self) -> Client {
+        let mut client = self.client();
+        Account::create_fake(&mut client)
+            .await
+            .expect("unable to create mock account");
+        client
+    }
+}
+
+fn serve(listener: TcpListener, router: Router) {
+    let server = axum::Server::from_tcp(listener)
+        .expect("unable to serve mock")
+        .serve(router.into_make_service());
+    tokio::spawn(server);
+}
+
+/// State shared by the core and staging services
+#[derive(Clone)]
+struct Shared {
+    state: Arc<Mutex<MockState>>,
+    /// Handed out as the storage host for uploads
+    staging_url: String,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, MockState> {
+        self.state.lock().expect("mock state poisoned")
+    }
+}
+
+#[derive(Default)]
+struct MockState {
+    device_keys: BTreeMap<Uuid, DeviceKey>,
+    buckets: BTreeMap<Uuid, Bucket>,
+    bucket_keys: BTreeMap<Uuid, BucketKey>,
+    metadata: BTreeMap<Uuid, Metadata>,
+    snapshots: BTreeMap<Uuid, Snapshot>,
+    /// Storage authorizations issued by core, and the accounts they were issued to
+    authorizations: BTreeMap<String, Uuid>,
+    /// Public keys granted access to staging, and the accounts they belong to
+    grants: Vec<(Uuid, String)>,
+    /// Content of every block uploaded to staging
+    blocks: BTreeMap<String, Vec<u8>>,
+    /// Statuses with which to answer upcoming requests
+    failures: VecDeque<StatusCode>,
+    /// How many of the upcoming uploads to refuse
+    failed_uploads: usize,
+}
+
+struct DeviceKey {
+    user_id: Uuid,
+    pem: String,
+    fingerprint: String,
+}
+
+struct Bucket {
+    owner: Uuid,
+    name: String,
+    r#type: BucketType,
+    storage_class: StorageClass,
+}
+
+struct BucketKey {
+    bucket_id: Uuid,
+    approved: bool,
+    pem: String,
+    fingerprint: String,
+}
+
+struct Metadata {
+    bucket_id: Uuid,
+    root_cid: String,
+    metadata_cid: String,
+    previous_cid: Option<String>,
+    data_size: u64,
+    state: MetadataState,
+    created_at: i64,
+    updated_at: i64,
+    snapshot_id: Option<Uuid>,
+    /// Whether its content has made it to staging
+    stored: bool,
+    car: Bytes,
+}
+
+struct Snapshot {
+    bucket_id: Uuid,
+    metadata_id: Uuid,
+    size: u64,
+    created_at: i64,
+}
+
+impl MockState {
+    /// Find a Bucket, as long as it belongs to this account
+    fn bucket(&self, user_id: Uuid, bucket_id: Uuid) -> Result<&Bucket, Failure> {
+        self.buckets
+            .get(&bucket_id)
+            .filter(|bucket| bucket.owner == user_id)
+            .ok_or_else(not_found)
+    }
+
+    fn bucket_key(&self, bucket_id: Uuid, id: Uuid) -> Result<&BucketKey, Failure> {
+        self.bucket_keys
+            .get(&id)
+            .filter(|key| key.bucket_id == bucket_id)
+            .ok_or_else(not_found)
+    }
+
+    fn metadata(&self, bucket_id: Uuid, id: Uuid) -> Result<&Metadata, Failure> {
+        self.metadata
+            .get(&id)
+            .filter(|metadata| metadata.bucket_id == bucket_id)
+            .ok_or_else(not_found)
+    }
+
+    fn snapshot(&self, bucket_id: Uuid, id: Uuid) -> Result<&Snapshot, Failure> {
+        self.snapshots
+            .get(&id)
+            .filter(|snapshot| snapshot.bucket_id == bucket_id)
+            .ok_or_else(not_found)
+    }
+
+    /// Make this Metadata current, outdating whichever was before it
+    fn make_current(&mut self, id: Uuid) {
+        let Some(bucket_id) = self.metadata.get(&id).map(|metadata| metadata.bucket_id) else {
+            return;
+        };
+        let now = Utc::now().timestamp();
+        for (metadata_id, metadata) in self.metadata.iter_mut() {
+            if *metadata_id == id {
+                metadata.state = MetadataState::Current;
+                metadata.updated_at = now;
+            } else if metadata.bucket_id == bucket_id && metadata.state == MetadataState::Current {
+                metadata.state = MetadataState::Outdated;
+                metadata.updated_at = now;
+            }
+        }
+    }
+
+    /// Bytes stored by the current Metadata of a Bucket
+    fn usage(&self, bucket_id: Uuid) -> u64 {
+        self.metadata
+            .values()
+            .filter(|metadata| {
+                metadata.bucket_id == bucket_id
+                    && metadata.state == MetadataState::Current
+                    && metadata.stored
+            })
+            .map(|metadata| metadata.data_size)
+            .sum()
+    }
+
+    fn issue_authorization(&mut self, user_id: Uuid) -> String {
+        let authorization = Uuid::new_v4().to_string();
+        self.authorizations.insert(authorization.clone(), user_id);
+        authorization
+    }
+}
+
+impl Metadata {
+    fn to_json(&self, id: Uuid) -> Value {
+        json!({
+            "id": id,
+            "root_cid": self.root_cid,
+            "metadata_cid": self.metadata_cid,
+            "previous_cid": self.previous_cid,
+            "data_size": self.data_size,
+            "state": self.state,
+            "created_at": self.created_at,
+            "updated_at": self.updated_at,
+            "snapshot_id": self.snapshot_id,
+        })
+    }
+}
+
+impl Snapshot {
+    fn to_json(&self, id: Uuid) -> Value {
+        json!({
+            "id": id,
+            "metadata_id": self.metadata_id,
+            "size": self.size,
+            "created_at": self.created_at,
+        })
+    }
+}
+
+/// An error response, shaped like the ones the real services send
+#[derive(Debug)]
+struct Failure(StatusCode, String);
+
+impl IntoResponse for Failure {
+    fn into_response(self) -> Response {
+        (self.0, Json(json!({ "msg": self.1 }))).into_response()
+    }
+}
+
+fn not_found() -> Failure {
+    Failure(StatusCode::NOT_FOUND, "not found".to_string())
+}
+
+fn unauthorized() -> Failure {
+    Failure(StatusCode::UNAUTHORIZED, "invalid bearer token".to_string())
+}
+
+fn bad_request(msg: &str) -> Failure {
+    Failure(StatusCode::BAD_REQUEST, msg.to_string())
+}
+
+type Reply = Result<Json<Value>, Failure>;
+
+fn bearer(headers: &HeaderMap) -> Result<&str, Failure> {
+    headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .ok_or_else(unauthorized)
+}
+
+/// Find the account whose key signed this ES384 bearer token
+fn verify<'a>(token: &str, keys: impl Iterator<Item = (Uuid, &'a str)>) -> Result<Uuid, Failure> {
+    let options = VerificationOptions {
+        allowed_audiences: Some(HashSet::from([AUDIENCE.to_string()])),
+        ..Default::default()
+    };
+    keys.filter_map(|(user_id, pem)| Some((user_id, ES384PublicKey::from_pem(pem).ok()?)))
+        .find_map(|(user_id, key)| {
+            let claims = key
+                .verify_token::<NoCustomClaims>(token, Some(options.clone()))
+                .ok()?;
+            (claims.subject? == user_id.to_string()).then_some(user_id)
+        })
+        .ok_or_else(unauthorized)
+}
+
+/// An account authenticated with core by one of its device API keys
+struct Caller(Uuid);
+
+#[async_trait]
+impl FromRequestParts<Shared> for Caller {
+    type Rejection = Failure;
+
+    async fn from_request_parts(parts: &mut Parts, shared: &Shared) -> Result<Self, Failure> {
+        let token = bearer(&parts.headers)?;
+        let state = shared.lock();
+        let keys = state
+            .device_keys
+            .values()
+            .map(|key| (key.user_id, key.pem.as_str()));
+        verify(token, keys).map(Caller)
+    }
+}
+
+/// An account authenticated with staging by a key it was granted access for
+struct Grantee(Uuid);
+
+#[async_trait]
+impl FromRequestParts<Shared> for Grantee {
+    type Rejection = Failure;
+
+    async fn from_request_parts(parts: &mut Parts, shared: &Shared) -> Result<Self, Failure> {
+        let token = bearer(&parts.headers)?;
+        let state = shared.lock();
+        let keys = state
+            .grants
+            .iter()
+            .map(|(user_id, pem)| (*user_id, pem.as_str()));
+        verify(token, keys).map(Grantee)
+    }
+}
+
+/// Fingerprint a PEM encoded public key the same way clients do.
+/// tomb-crypt futures aren't Send, so they're driven on a blocking thread instead of a server task.
+async fn fingerprint<K: PublicKey + 'static>(pem: String) -> Result<String, Failure> {
+    tokio::task::spawn_blocking(move || {
+        futures::executor::block_on(async {
+            let key = K::import(pem.as_bytes()).await.ok()?;
+            let fingerprint = key.fingerprint().await.ok()?;
+            Some(hex_fingerprint(fingerprint.as_slice()))
+        })
+    })
+    .await
+    .ok()
+    .flatten()
+    .ok_or_else(|| bad_request("invalid public key"))
+}
+
+/// Split a multipart upload into its JSON request data and CAR file
+async fn read_upload(mut multipart: Multipart) -> Result<(Bytes, Bytes), Failure> {
+    let (mut data, mut car) = (None, None);
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|err| bad_request(&err.to_string()))?
+    {
+        let name = field.name().map(str::to_string);
+        let bytes = field
+            .bytes()
+            .await
+            .map_err(|err| bad_request(&err.to_string()))?;
+        match name.as_deref() {
+            Some("request-data") => data = Some(bytes),
+            Some("car-upload") => car = Some(bytes),
+            _ => {}
+        }
+    }
+    data.zip(car)
+        .ok_or_else(|| bad_request("expected request-data and car-upload"))
+}
+
+/// Every block within a CARv2, keyed by CID
+fn car_blocks(car: &[u8]) -> Result<Vec<(String, Vec<u8>)>, CarError> {
+    let mut reader = Cursor::new(car);
+    let car = CarV2::read_bytes(&mut reader)?;
+    let cids = car.car.index.borrow().get_all_cids();
+    cids.into_iter()
+        .map(|cid| Ok((cid.to_string(), car.get_block(&cid, &mut reader)?.content)))
+        .collect()
+}
+
+/// Fail requests on demand, asking clients to retry straight away
+async fn inject_failures<B>(
+    State(shared): State<Shared>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let failure = shared.lock().failures.pop_front();
+    match failure {
+        Some(status) => (
+            status,
+            [(RETRY_AFTER, "0")],
+            Json(json!({ "msg": "injected failure" })),
+        )
+            .into_response(),
+        None => next.run(request).await,
+    }
+}
+
+fn core_router(shared: Shared) -> Router {
+    Router::new()
+        .route("/api/v1/auth/fake_user", post(create_fake_account))
+        .route("/api/v1/auth/who_am_i", get(who_am_i))
+        .route(
+            "/api/v1/auth/device_api_key",
+            get(read_device_keys).post(create_device_key),
+        )
+        .route(
+            "/api/v1/auth/device_api_key/:id",
+            get(read_device_key).delete(delete_device_key),
+        )
+        .route("/api/v1/buckets", get(read_buckets).post(create_bucket))
+        .route("/api/v1/buckets/usage", get(total_usage))
+        .route("/api/v1/buckets/usage_limit", get(usage_limit))
+        .route(
+            "/api/v1/buckets/:bucket_id",
+            get(read_bucket).put(update_bucket).delete(delete_bucket),
+        )
+        .route("/api/v1/buckets/:bucket_id/usage", get(bucket_usage))
+        .route(
+            "/api/v1/buckets/:bucket_id/authorization_grants",
+            get(authorization_grants),
+        )
+        .route(
+            "/api/v1/buckets/:bucket_id/keys",
+            get(read_bucket_keys).post(create_bucket_key),
+        )
+        .route(
+            "/api/v1/buckets/:bucket_id/keys/:id",
+            get(read_bucket_key).delete(delete_bucket_key),
+        )
+        .route(
+            "/api/v1/buckets/:bucket_id/keys/:id/reject",
+            post(reject_bucket_key),
+        )
+        .route(
+            "/api/v1/buckets/:bucket_id/metadata",
+            get(read_all_metadata).post(push_metadata),
+        )
+        .route(
+            "/api/v1/buckets/:bucket_id/metadata/current",
+            get(read_current_metadata),
+        )
+        .route(
+            "/api/v1/buckets/:bucket_id/metadata/:id",
+            get(read_metadata),
+        )
+        .route(
+            "/api/v1/buckets/:bucket_id/metadata/:id/pull",
+            get(pull_metadata),
+        )
+        .route(
+            "/api/v1/buckets/:bucket_id/metadata/:id/snapshot",
+            post(create_snapshot),
+        )
+        .route("/api/v1/buckets/:bucket_id/snapshots", get(read_snapshots))
+        .route(
+            "/api/v1/buckets/:bucket_id/snapshots/:id",
+            get(read_snapshot),
+        )
+        .route(
+            "/api/v1/buckets/:bucket_id/snapshots/:id/restore",
+            put(restore_snapshot),
+        )
+        .route("/api/v1/blocks/locate", post(locate_blocks))
+        .layer(DefaultBodyLimit::disable())
+        .layer(middleware::from_fn_with_state(
+            shared.clone(),
+            inject_failures,
+        ))
+        .with_state(shared)
+}
+
+fn staging_router(shared: Shared) -> Router {
+    Router::new()
+        .route("/api/v1/client_grant", post(create_grant))
+        .route("/api/v1/upload", post(upload_content))
+        .route("/api/v1/blocks/:cid", get(pull_block))
+        .layer(DefaultBodyLimit::disable())
+        .layer(middleware::from_fn_with_state(
+            shared.clone(),
+            inject_failures,
+        ))
+        .with_state(shared)
+}
+
+#[derive(Deserialize)]
+struct NewAccount {
+    device_api_key_pem: String,
+}
+
+async fn create_fake_account(State(shared): State<Shared>, Json(body): Json<NewAccount>) -> Reply {
+    let fingerprint = fingerprint::<EcPublicSignatureKey>(body.device_api_key_pem.clone()).await?;
+    let user_id = Uuid::new_v4();
+    shared.lock().device_keys.insert(
+        Uuid::new_v4(),
+        DeviceKey {
+            user_id,
+            pem: body.device_api_key_pem,
+            fingerprint,
+        },
+    );
+    Ok(Json(json!({ "id": user_id })))
+}
+
+async fn who_am_i(Caller(user_id): Caller) -> Reply {
+    Ok(Json(json!({ "user_id": user_id })))
+}
+
+#[derive(Deserialize)]
+struct NewKey {
+    pem: String,
+}
+
+async fn create_device_key(
+    State(shared): State<Shared>,
+    Caller(user_id): Caller,
+    Json(body): Json<NewKey>,
+) -> Reply {
+    let fingerprint = fingerprint::<EcPublicSignatureKey>(body.pem.clone()).await?;
+    let id = Uuid::new_v4();
+    shared.lock().device_keys.insert(
+        id,
+        DeviceKey {
+            user_id,
+            pem: body.pem,
+            fingerprint: fingerprint.clone(),
+        },
+    );
+    Ok(Json(json!({ "id": id, "fingerprint": fingerprint })))
+}
+
+async fn read_device_keys(State(shared): State<Shared>, Caller(user_id): Caller) -> Reply {
+    let state = shared.lock();
+    let keys: Vec<Value> = state
+        .device_keys
+        .iter()
+        .filter(|(_, key)| key.user_id == user_id)
+        .map(|(id, key)| json!({ "id": id, "pem": key.pem, "fingerprint": key.fingerprint }))
+        .collect();
+    Ok(Json(json!(keys)))
+}
+
+async fn read_device_key(
+    State(shared): State<Shared>,
+    Caller(user_id): Caller,
+    Path(id): Path<Uuid>,
+) -> Reply {
+    let state = shared.lock();
+    let key = state
+        .device_keys
+        .get(&id)
+        .filter(|key| key.user_id == user_id)
+        .ok_or_else(not_found)?;
+    Ok(Json(
+        json!({ "id": id, "pem": key.pem, "fingerprint": key.fingerprint }),
+    ))
+}
+
+async fn delete_device_key(
+    State(shared): State<Shared>,
+    Caller(user_id): Caller,
+    Path(id): Path<Uuid>,
+) -> Reply {
+    let mut state = shared.lock();
+    match state.device_keys.get(&id) {
+        Some(key) if key.user_id == user_id => {
+            state.device_keys.remove(&id);
+            Ok(Json(json!(null)))
+        }
+        _ => Err(not_found()),
+    }
+}
+
+#[derive(Deserialize)]
+struct NewBucket {
+    name: String,
+    r#type: BucketType,
+    storage_class: StorageClass,
+    initial_bucket_key_pem: String,
+}
+
+async fn create_bucket(
+    State(shared): State<Shared>,
+    Caller(user_id): Caller,
+    Json(body): Json<NewBucket>,
+) -> Reply {
+    let fingerprint =
+        fingerprint::<EcPublicEncryptionKey>(body.initial_bucket_key_pem.clone()).await?;
+    let (id, key_id) = (Uuid::new_v4(), Uuid::new_v4());
+    let mut state = shared.lock();
+    state.buckets.insert(
+        id,
+        Bucket {
+            owner: user_id,
+            name: body.name.clone(),
+            r#type: body.r#type,
+            storage_class: body.storage_class,
+        },
+    );
+    // The creator's key has access from the start
+    state.bucket_keys.insert(
+        key_id,
+        BucketKey {
+            bucket_id: id,
+            approved: true,
+            pem: body.initial_bucket_key_pem,
+            fingerprint: fingerprint.clone(),
+        },
+    );
+    Ok(Json(json!({
+        "id": id,
+        "name": body.name,
+        "type": body.r#type,
+        "storage_class": body.storage_class,
+        "initial_bucket_key": { "id": key_id, "approved": true, "fingerprint": fingerprint },
+    })))
+}
+
+fn bucket_json(id: Uuid, bucket: &Bucket) -> Value {
+    json!({
+        "id": id,
+        "name": bucket.name,
+        "type": bucket.r#type,
+        "storage_class": bucket.storage_class,
+    })
+}
+
+async fn read_buckets(State(shared): State<Shared>, Caller(user_id): Caller) -> Reply {
+    let state = shared.lock();
+    let buckets: Vec<Value> = state
+        .buckets
+        .iter()
+        .filter(|(_, bucket)| bucket.owner == user_id)
+        .map(|(id, bucket)| bucket_json(*id, bucket))
+        .collect();
+    Ok(Json(json!(buckets)))
+}
+
+async fn read_bucket(
+    State(shared): State<Shared>,
+    Caller(user_id): Caller,
+    Path(id): Path<Uuid>,
+) -> Reply {
+    let state = shared.lock();
+    Ok(Json(bucket_json(id, state.bucket(user_id, id)?)))
+}
+
+#[derive(Deserialize)]
+struct BucketUpdate {
+    name: String,
+    storage_class: Option<StorageClass>,
+}
+
+async fn update_bucket(
+    State(shared): State<Shared>,
+    Caller(user_id): Caller,
+    Path(id): Path<Uuid>,
+    Json(body): Json<BucketUpdate>,
+) -> Reply {
+    let mut state = shared.lock();
+    state.bucket(user_id, id)?;
+    if let Some(bucket) = state.buckets.get_mut(&id) {
+        bucket.name = body.name;
+        if let Some(storage_class) = body.storage_class {
+            bucket.storage_class = storage_class;
+        }
+    }
+    Ok(Json(json!(null)))
+}
+
+async fn delete_bucket(
+    State(shared): State<Shared>,
+    Caller(user_id): Caller,
+    Path(id): Path<Uuid>,
+) -> Reply {
+    let mut state = shared.lock();
+    state.bucket(user_id, id)?;
+    state.buckets.remove(&id);
+    state.bucket_keys.retain(|_, key| key.bucket_id != id);
+    state
+        .metadata
+        .retain(|_, metadata| metadata.bucket_id != id);
+    state
+        .snapshots
+        .retain(|_, snapshot| snapshot.bucket_id != id);
+    Ok(Json(json!({})))
+}
+
+async fn total_usage(State(shared): State<Shared>, Caller(user_id): Caller) -> Reply {
+    let state = shared.lock();
+    let size: u64 = state
+        .buckets
+        .iter()
+        .filter(|(_, bucket)| bucket.owner == user_id)
+        .map(|(id, _)| state.usage(*id))
+        .sum();
+    Ok(Json(json!({ "size": size })))
+}
+
+async fn usage_limit(Caller(_): Caller) -> Reply {
+    Ok(Json(json!({ "size": USAGE_LIMIT })))
+}
+
+async fn bucket_usage(
+    State(shared): State<Shared>,
+    Caller(user_id): Caller,
+    Path(id): Path<Uuid>,
+) -> Reply {
+    let state = shared.lock();
+    state.bucket(user_id, id)?;
+    Ok(Json(json!({ "size": state.usage(id) })))
+}
+
+/// A fresh authorization for staging, once the Bucket has content stored there
+async fn authorization_grants(
+    State(shared): State<Shared>,
+    Caller(user_id): Caller,
+    Path(bucket_id): Path<Uuid>,
+) -> Reply {
+    let mut state = shared.lock();
+    state.bucket(user_id, bucket_id)?;
+    if !state
+        .metadata
+        .values()
+        .any(|metadata| metadata.bucket_id == bucket_id && metadata.stored)
+    {
+        return Err(not_found());
+    }
+    let authorization = state.issue_authorization(user_id);
+    Ok(Json(json!({ "authorization_token": authorization })))
+}
+
+async fn create_bucket_key(
+    State(shared): State<Shared>,
+    Caller(user_id): Caller,
+    Path(bucket_id): Path<Uuid>,
+    Json(body): Json<NewKey>,
+) -> Reply {
+    let fingerprint = fingerprint::<EcPublicEncryptionKey>(body.pem.clone()).await?;
+    let id = Uuid::new_v4();
+    let mut state = shared.lock();
+    state.bucket(user_id, bucket_id)?;
+    // Keys are approved by pushing Metadata which lists them as valid
+    state.bucket_keys.insert(
+        id,
+        BucketKey {
+            bucket_id,
+            approved: false,
+            pem: body.pem,
+            fingerprint: fingerprint.clone(),
+        },
+    );
+    Ok(Json(
+        json!({ "id": id, "approved": false, "fingerprint": fingerprint }),
+    ))
+}
+
+fn bucket_key_json(id: Uuid, key: &BucketKey) -> Value {
+    json!({
+        "id": id,
+        "approved": key.approved,
+        "pem": key.pem,
+        "fingerprint": key.fingerprint,
+    })
+}
+
+async fn read_bucket_keys(
+    State(shared): State<Shared>,
+    Caller(user_id): Caller,
+    Path(bucket_id): Path<Uuid>,
+) -> Reply {
+    let state = shared.lock();
+    state.bucket(user_id, bucket_id)?;
+    let keys: Vec<Value> = state
+        .bucket_keys
+        .iter()
+        .filter(|(_, key)| key.bucket_id == bucket_id)
+        .map(|(id, key)| bucket_key_json(*id, key))
+        .collect();
+    Ok(Json(json!(keys)))
+}
+
+async fn read_bucket_key(
+    State(shared): State<Shared>,
+    Caller(user_id): Caller,
+    Path((bucket_id, id)): Path<(Uuid, Uuid)>,
+) -> Reply {
+    let state = shared.lock();
+    state.bucket(user_id, bucket_id)?;
+    Ok(Json(bucket_key_json(id, state.bucket_key(bucket_id, id)?)))
+}
+
+async fn delete_bucket_key(
+    State(shared): State<Shared>,
+    Caller(user_id): Caller,
+    Path((bucket_id, id)): Path<(Uuid, Uuid)>,
+) -> Reply {
+    let mut state = shared.lock();
+    state.bucket(user_id, bucket_id)?;
+    let approved = state.bucket_key(bucket_id, id)?.approved;
+    state.bucket_keys.remove(&id);
+    Ok(Json(json!({ "id": id, "approved": approved })))
+}
+
+async fn reject_bucket_key(
+    State(shared): State<Shared>,
+    Caller(user_id): Caller,
+    Path((bucket_id, id)): Path<(Uuid, Uuid)>,
+) -> Reply {
+    let mut state = shared.lock();
+    state.bucket(user_id, bucket_id)?;
+    // Rejecting a key which already has access changes nothing
+    if state.bucket_key(bucket_id, id)?.approved {
+        return Ok(Json(json!({ "id": id, "approved": true })));
+    }
+    state.bucket_keys.remove(&id);
+    Ok(Json(json!({ "id": id, "approved": false })))
+}
+
+#[derive(Deserialize)]
+struct PushedMetadata {
+    expected_data_size: u64,
+    root_cid: String,
+    metadata_cid: String,
+    previous_cid: Option<String>,
+    valid_keys: Vec<String>,
+    deleted_block_cids: BTreeSet<String>,
+}
+
+async fn push_metadata(
+    State(shared): State<Shared>,
+    Caller(user_id): Caller,
+    Path(bucket_id): Path<Uuid>,
+    multipart: Multipart,
+) -> Reply {
+    let (data, car) = read_upload(multipart).await?;
+    let body: PushedMetadata =
+        serde_json::from_slice(&data).map_err(|err| bad_request(&err.to_string()))?;
+
+    let mut state = shared.lock();
+    state.bucket(user_id, bucket_id)?;
+    // Listing a key as valid approves it
+    for key in state.bucket_keys.values_mut() {
+        if key.bucket_id == bucket_id && body.valid_keys.contains(&key.fingerprint) {
+            key.approved = true;
+        }
+    }
+    for cid in &body.deleted_block_cids {
+        state.blocks.remove(cid);
+    }
+
+    let id = Uuid::new_v4();
+    let now = Utc::now().timestamp();
+    state.metadata.insert(
+        id,
+        Metadata {
+            bucket_id,
+            root_cid: body.root_cid,
+            metadata_cid: body.metadata_cid,
+            previous_cid: body.previous_cid,
+            data_size: body.expected_data_size,
+            state: MetadataState::Pending,
+            created_at: now,
+            updated_at: now,
+            snapshot_id: None,
+            stored: false,
+            car,
+        },
+    );
+
+    // Metadata without new content is current straight away, otherwise it waits on an upload
+    let (storage_host, storage_authorization) = if body.expected_data_size == 0 {
+        state.make_current(id);
+        (None, None)
+    } else {
+        let authorization = state.issue_authorization(user_id);
+        (Some(shared.staging_url.clone()), Some(authorization))
+    };
+    let metadata_state = state.metadata[&id].state;
+    Ok(Json(json!({
+        "id": id,
+        "state": metadata_state,
+        "storage_host": storage_host,
+        "storage_authorization": storage_authorization,
+    })))
+}
+
+async fn read_all_metadata(
+    State(shared): State<Shared>,
+    Caller(user_id): Caller,
+    Path(bucket_id): Path<Uuid>,
+) -> Reply {
+    let state = shared.lock();
+    state.bucket(user_id, bucket_id)?;
+    let metadata: Vec<Value> = state
+        .metadata
+        .iter()
+        .filter(|(_, metadata)| metadata.bucket_id == bucket_id)
+        .map(|(id, metadata)| metadata.to_json(*id))
+        .collect();
+    Ok(Json(json!(metadata)))
+}
+
+async fn read_current_metadata(
+    State(shared): State<Shared>,
+    Caller(user_id): Caller,
+    Path(bucket_id): Path<Uuid>,
+) -> Reply {
+    let state = shared.lock();
+    state.bucket(user_id, bucket_id)?;
+    state
+        .metadata
+        .iter()
+        .find(|(_, metadata)| {
+            metadata.bucket_id == bucket_id && metadata.state == MetadataState::Current
+        })
+        .map(|(id, metadata)| Json(metadata.to_json(*id)))
+        .ok_or_else(not_found)
+}
+
+async fn read_metadata(
+    State(shared): State<Shared>,
+    Caller(user_id): Caller,
+    Path((bucket_id, id)): Path<(Uuid, Uuid)>,
+) -> Reply {
+    let state = shared.lock();
+    state.bucket(user_id, bucket_id)?;
+    Ok(Json(state.metadata(bucket_id, id)?.to_json(id)))
+}
+
+async fn pull_metadata(
+    State(shared): State<Shared>,
+    Caller(user_id): Caller,
+    Path((bucket_id, id)): Path<(Uuid, Uuid)>,
+) -> Result<Bytes, Failure> {
+    let state = shared.lock();
+    state.bucket(user_id, bucket_id)?;
+    Ok(state.metadata(bucket_id, id)?.car.clone())
+}
+
+async fn create_snapshot(
+    State(shared): State<Shared>,
+    Caller(user_id): Caller,
+    Path((bucket_id, metadata_id)): Path<(Uuid, Uuid)>,
+    Json(_active_cids): Json<Value>,
+) -> Reply {
+    let mut state = shared.lock();
+    state.bucket(user_id, bucket_id)?;
+    let size = state.metadata(bucket_id, metadata_id)?.data_size;
+    let id = Uuid::new_v4();
+    state.snapshots.insert(
+        id,
+        Snapshot {
+            bucket_id,
+            metadata_id,
+            size,
+            created_at: Utc::now().timestamp(),
+        },
+    );
+    if let Some(metadata) = state.metadata.get_mut(&metadata_id) {
+        metadata.snapshot_id = Some(id);
+    }
+    Ok(Json(json!({ "id": id })))
+}
+
+async fn read_snapshots(
+    State(shared): State<Shared>,
+    Caller(user_id): Caller,
+    Path(bucket_id): Path<Uuid>,
+) -> Reply {
+    let state = shared.lock();
+    state.bucket(user_id, bucket_id)?;
+    let snapshots: Vec<Value> = state
+        .snapshots
+        .iter()
+        .filter(|(_, snapshot)| snapshot.bucket_id == bucket_id)
+        .map(|(id, snapshot)| snapshot.to_json(*id))
+        .collect();
+    Ok(Json(json!(snapshots)))
+}
+
+async fn read_snapshot(
+    State(shared): State<Shared>,
+    Caller(user_id): Caller,
+    Path((bucket_id, id)): Path<(Uuid, Uuid)>,
+) -> Reply {
+    let state = shared.lock();
+    state.bucket(user_id, bucket_id)?;
+    Ok(Json(state.snapshot(bucket_id, id)?.to_json(id)))
+}
+
+async fn restore_snapshot(
+    State(shared): State<Shared>,
+    Caller(user_id): Caller,
+    Path((bucket_id, id)): Path<(Uuid, Uuid)>,
+) -> Reply {
+    let mut state = shared.lock();
+    state.bucket(user_id, bucket_id)?;
+    let metadata_id = state.snapshot(bucket_id, id)?.metadata_id;
+    state.make_current(metadata_id);
+    Ok(Json(json!({ "metadata_id": metadata_id })))
+}
+
+async fn locate_blocks(
+    State(shared): State<Shared>,
+    Caller(_): Caller,
+    Json(cids): Json<Vec<String>>,
+) -> Result<Json<HashMap<String, Vec<String>>>, Failure> {
+    let state = shared.lock();
+    // Blocks stored nowhere are listed under "NA"
+    let (found, missing) = cids
+        .into_iter()
+        .partition(|cid| state.blocks.contains_key(cid));
+    Ok(Json(HashMap::from([
+        (shared.staging_url.clone(), found),
+        ("NA".to_string(), missing),
+    ])))
+}
+
+#[derive(Deserialize)]
+struct NewGrant {
+    public_key: String,
+}
+
+/// Staging trusts a key once it's presented alongside an authorization issued by core
+async fn create_grant(
+    State(shared): State<Shared>,
+    headers: HeaderMap,
+    Json(body): Json<NewGrant>,
+) -> Reply {
+    let authorization = bearer(&headers)?;
+    let mut state = shared.lock();
+    let user_id = *state
+        .authorizations
+        .get(authorization)
+        .ok_or_else(unauthorized)?;
+    state.grants.push((user_id, body.public_key));
+    Ok(Json(json!(null)))
+}
+
+#[derive(Deserialize)]
+struct PushedContent {
+    metadata_id: Uuid,
+    content_hash: String,
+}
+
+async fn upload_content(
+    State(shared): State<Shared>,
+    Grantee(user_id): Grantee,
+    multipart: Multipart,
+) -> Reply {
+    let (data, car) = read_upload(multipart).await?;
+    let body: PushedContent =
+        serde_json::from_slice(&data).map_err(|err| bad_request(&err.to_string()))?;
+    if blake3::hash(&car).to_string() != body.content_hash {
+        return Err(bad_request("content hash mismatch"));
+    }
+    let blocks = car_blocks(&car).map_err(|err| bad_request(&err.to_string()))?;
+
+    let mut state = shared.lock();
+    if state.failed_uploads > 0 {
+        state.failed_uploads -= 1;
+        return Err(Failure(
+            StatusCode::INSUFFICIENT_STORAGE,
+            "injected upload failure".to_string(),
+        ));
+    }
+    let bucket_id = state
+        .metadata
+        .get(&body.metadata_id)
+        .ok_or_else(not_found)?
+        .bucket_id;
+    state.bucket(user_id, bucket_id)?;
+    state.blocks.extend(blocks);
+    if let Some(metadata) = state.metadata.get_mut(&body.metadata_id) {
+        metadata.stored = true;
+    }
+    state.make_current(body.metadata_id);
+    Ok(Json(json!({})))
+}
+
+async fn pull_block(
+    State(shared): State<Shared>,
+    Grantee(_): Grantee,
+    Path(cid): Path<String>,
+) -> Result<Vec<u8>, Failure> {
+    shared
+        .lock()
+        .blocks
+        .get(&cid)
+        .cloned()
+        .ok_or_else(not_found)
+}
+
+#[cfg(test)]
+mod test {
+    use super::MockServer;
+    use crate::{
+        api::{
+            client::Credentials,
+            error::ApiError,
+            models::{
+                account::Account,
+                bucket::{Bucket, BucketType, StorageClass},
+                bucket_key::BucketKey,
+                metadata::{Metadata, MetadataState},
+                storage_ticket::StorageTicket,
+            },
+            requests::{
+                core::buckets::metadata::push::PushMetadata,
+                staging::upload::content::UploadContent,
+            },
+            retry::RetryPolicy,
+            throttle::Bandwidth,
+            utils::{generate_api_key, generate_bucket_key},
+        },
+        blockstore::{
+            BanyanApiBlockStore, BanyanBlockStore, BlockCache, BlockStoreError,
+            CarV2MemoryBlockStore, RootedBlockStore, DEFAULT_CACHE_CAPACITY,
+        },
+        filesystem::{FilesystemError, FsMetadata},
+    };
+    use futures_util::StreamExt;
+    use std::{
+        collections::BTreeSet,
+        fs::remove_dir_all,
+        path::Path,
+        time::{Duration, Instant},
+    };
+    use tomb_crypt::prelude::{EcEncryptionKey, PrivateKey, PublicKey};
+
+    #[tokio::test]
+    async fn bearer_tokens() -> Result<(), ApiError> {
+        let server = MockServer::start();
+        // No token
+        assert!(Account::who_am_i(&mut server.client()).await.is_err());
+        // A token signed by a registered device key
+        let mut client = server.authenticated_client().await;
+        let account = Account::who_am_i(&mut client).await?;
+        assert_eq!(account.id.to_string(), client.subject()?);
+        // A token for that account signed by a key it never registered
+        let (signing_key, _) = generate_api_key().await;
+        let mut forger = server.client();
+        forger.with_credentials(Credentials {
+            user_id: account.id,
+            signing_key,
+        });
+        assert!(Account::who_am_i(&mut forger).await.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn buckets_and_keys() -> Result<(), ApiError> {
+        let server = MockServer::start();
+        let mut client = server.authenticated_client().await;
+        let (_, initial_key_pem) = generate_bucket_key().await;
+        let (mut bucket, initial_key) = Bucket::create(
+            "buckets_and_keys".to_string(),
+            initial_key_pem,
+            BucketType::Interactive,
+            StorageClass::Hot,
+            &mut client,
+        )
+        .await?;
+        assert!(initial_key.approved);
+        assert_eq!(Bucket::read_all(&mut client).await?.len(), 1);
+
+        // The storage class can change after creation
+        bucket.storage_class = StorageClass::Cold;
+        bucket.update(&mut client).await?;
+        let read = Bucket::read(&mut client, bucket.id).await?;
+        assert_eq!(read.storage_class, StorageClass::Cold);
+        assert_eq!(read.r#type, BucketType::Interactive);
+
+        // Other accounts can't see the Bucket
+        let mut stranger = server.authenticated_client().await;
+        assert!(Bucket::read(&mut stranger, bucket.id).await.is_err());
+
+        // New keys await approval until they are rejected
+        let (_, key_pem) = generate_bucket_key().await;
+        let key = BucketKey::create(bucket.id, key_pem, &mut client).await?;
+        assert!(!key.approved);
+        assert_eq!(BucketKey::read_all(bucket.id, &mut client).await?.len(), 2);
+        BucketKey::reject(bucket.id, key.id, &mut client).await?;
+        assert_eq!(BucketKey::read_all(bucket.id, &mut client).await?.len(), 1);
+
+        bucket.delete(&mut client).await?;
+        assert!(Bucket::read_all(&mut client).await?.is_empty());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn slow_upload() -> Result<(), ApiError> {
+        let server = MockServer::start();
+        let mut client = server.authenticated_client().await;
+        let wrapping_key = EcEncryptionKey::generate().await?;
+        let initial_key_pem =
+            String::from_utf8(wrapping_key.public_key()?.export().await?).expect("could not utf8");
+        let (bucket, _) = Bucket::create(
+            "slow_upload".to_string(),
+            initial_key_pem,
+            BucketType::Interactive,
+            StorageClass::Hot,
+            &mut client,
+        )
+        .await?;
+
+        // Enough content to be uploaded in several chunks
+        let metadata_store = CarV2MemoryBlockStore::new()?;
+        let content_store = CarV2MemoryBlockStore::new()?;
+        let mut fs = FsMetadata::init(&wrapping_key).await?;
+        fs.write(
+            &["big.bin".to_string()],
+            &metadata_store,
+            &content_store,
+            vec![0; 512 * 1024],
+        )
+        .await?;
+        fs.save(&metadata_store, &content_store).await?;
+        let (metadata, host, authorization) = Metadata::push(
+            PushMetadata {
+                bucket_id: bucket.id,
+                expected_data_size: content_store.data_size(),
+                root_cid: content_store
+                    .get_root()
+                    .ok_or(ApiError::missing_data("root_cid"))?
+                    .to_string(),
+                metadata_cid: metadata_store
+                    .get_root()
+                    .ok_or(ApiError::missing_data("metadata_cid"))?
+                    .to_string(),
+                previous_cid: None,
+                valid_keys: fs.share_manager.public_fingerprints(),
+                deleted_block_cids: BTreeSet::new(),
+                metadata_stream: metadata_store.get_data().into(),
+            },
+            &mut client,
+        )
+        .await?;
+        let storage_ticket = StorageTicket {
+            host: host.expect("no storage host"),
+            authorization: authorization.expect("no storage authorization"),
+        };
+        storage_ticket.create_grant(&mut client).await?;
+
+        // The upload takes longer than the read timeout, but never stalls for that long
+        client.with_retry_policy(RetryPolicy {
+            read_timeout: Duration::from_millis(500),
+            ..RetryPolicy::none()
+        })?;
+        client.with_bandwidth(Bandwidth {
+            upload: Some(256 * 1024),
+            download: None,
+        });
+        let started = Instant::now();
+        content_store
+            .upload(storage_ticket.host, metadata.id, &mut client)
+            .await?;
+        assert!(started.elapsed() > Duration::from_secs(1));
+        let current = Metadata::read_current(bucket.id, &mut client).await?;
+        assert_eq!(current.id, metadata.id);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn push_upload_pull() -> Result<(), ApiError> {
+        let server = MockServer::start();
+        let mut client = server.authenticated_client().await;
+        let wrapping_key = EcEncryptionKey::generate().await?;
+        let initial_key_pem =
+            String::from_utf8(wrapping_key.public_key()?.export().await?).expect("could not utf8");
+        let (bucket, _) = Bucket::create(
+            "push_upload_pull".to_string(),
+            initial_key_pem,
+            BucketType::Interactive,
+            StorageClass::Hot,
+            &mut client,
+        )
+        .await?;
+
+        // Write a file
+        let metadata_store = CarV2MemoryBlockStore::new()?;
+        let content_store = CarV2MemoryBlockStore::new()?;
+        let mut fs = FsMetadata::init(&wrapping_key).await?;
+        fs.write(
+            &["cat.txt".to_string()],
+            &metadata_store,
+            &content_store,
+            b"cat content".to_vec(),
+        )
+        .await?;
+        fs.save(&metadata_store, &content_store).await?;
+        let root_cid = content_store
+            .get_root()
+            .ok_or(ApiError::missing_data("root_cid"))?;
+
+        // Push its metadata, which awaits the content
+        let (metadata, host, authorization) = Metadata::push(
+            PushMetadata {
+                bucket_id: bucket.id,
+                expected_data_size: content_store.data_size(),
+                root_cid: root_cid.to_string(),
+                metadata_cid: metadata_store
+                    .get_root()
+                    .ok_or(ApiError::missing_data("metadata_cid"))?
+                    .to_string(),
+                previous_cid: None,
+                valid_keys: fs.share_manager.public_fingerprints(),
+                deleted_block_cids: BTreeSet::new(),
+                metadata_stream: metadata_store.get_data().into(),
+            },
+            &mut client,
+        )
+        .await?;
+        assert_eq!(metadata.state, MetadataState::Pending);
+        assert!(Metadata::read_current(bucket.id, &mut client)
+            .await
+            .is_err());
+
+        // Upload the content to staging
+        let storage_ticket = StorageTicket {
+            host: host.expect("no storage host"),
+            authorization: authorization.expect("no storage authorization"),
+        };
+        storage_ticket.create_grant(&mut client).await?;
+        content_store
+            .upload(storage_ticket.host, metadata.id, &mut client)
+            .await?;
+        let current = Metadata::read_current(bucket.id, &mut client).await?;
+        assert_eq!(current.id, metadata.id);
+        assert_eq!(bucket.usage(&mut client).await?, content_store.data_size());
+
+        // Pull the metadata back down
+        let mut stream = current.pull(&mut client).await?;
+        let mut pulled = Vec::new();
+        while let Some(chunk) = stream.next().await {
+            pulled.extend_from_slice(&chunk?);
+        }
+        assert_eq!(pulled, metadata_store.get_data());
+
+        // Find and fetch content blocks from staging
+        let api_store = BanyanApiBlockStore::from(client.clone());
+        api_store.find_cids(BTreeSet::from([root_cid])).await?;
+        assert_eq!(
+            api_store.get_block(&root_cid).await?.to_vec(),
+            content_store.get_block(&root_cid).await?.to_vec()
+        );
+
+        // Prefetch every block of the file into a cache
+        let cache_path = Path::new("test")
+            .join("block_cache")
+            .join("push_upload_pull");
+        if cache_path.exists() {
+            remove_dir_all(&cache_path).map_err(BlockStoreError::from)?;
+        }
+        let cache = BlockCache::new(&cache_path, DEFAULT_CACHE_CAPACITY)?;
+        let file = fs
+            .get_node(&["cat.txt".to_string()], &metadata_store)
+            .await?
+            .ok_or(ApiError::missing_data("cat.txt"))?
+            .as_file()
+            .map_err(|err| FilesystemError::wnfs(Box::from(err)))?;
+        let cids = file
+            .get_cids(&fs.forest, &metadata_store)
+            .await
+            .map_err(|err| FilesystemError::wnfs(Box::from(err)))?;
+        BanyanApiBlockStore::from(client.clone())
+            .with_cache(cache.clone())
+            .prefetch(cids.clone())
+            .await?;
+
+        // Which is enough to read them without knowing where they are stored
+        let cached_store = BanyanApiBlockStore::from(client.clone()).with_cache(cache);
+        for cid in cids {
+            assert_eq!(
+                cached_store.get_block(&cid).await?.to_vec(),
+                content_store.get_block(&cid).await?.to_vec()
+            );
+        }
+        remove_dir_all(cache_path).map_err(BlockStoreError::from)?;
+
+        // Corrupt content is turned away
+        server.corrupt_block(&root_cid.to_string());
+        let api_store = BanyanApiBlockStore::from(client.clone());
+        api_store.find_cids(BTreeSet::from([root_cid])).await?;
+        assert!(api_store
+            .get_block(&root_cid)
+            .await
+            .is_err_and(|err| err.is_hash_mismatch()));
+        Ok(())
+    }
+}
@@ -2,8 +2,15 @@
 pub mod client;
 /// Error
 pub(crate) mod error;
+/// In-memory core and staging services, for testing the API offline
+#[cfg(all(test, not(target_arch = "wasm32")))]
+pub(crate) mod mock;
 /// Our API models, along with CRUD implementations
 pub mod models;
 /// Our API request implementation
 pub mod requests;
+/// Retry and timeout policy for our API client
+pub mod retry;
+/// Bandwidth limits and progress reporting for our API client's transfers
+pub mod throttle;
 mod utils;
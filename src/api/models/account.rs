@@ -78,19 +78,18 @@ impl Account {
     }
 }
 
-#[cfg(feature = "integration-tests")]
 #[cfg(test)]
 pub mod test {
-    use crate::api::{client::Client, error::ApiError, models::account::Account};
+    use crate::api::{client::Client, error::ApiError, mock::MockServer, models::account::Account};
 
+    /// A Client with a brand new account, on a mock server of its own
     pub async fn authenticated_client() -> Client {
-        let mut client = Client::new("http://127.0.0.1:3001").unwrap();
-        let _ = Account::create_fake(&mut client).await.unwrap();
-        client
+        MockServer::start().authenticated_client().await
     }
 
+    /// A Client without any credentials, on a mock server of its own
     pub async fn unauthenticated_client() -> Client {
-        Client::new("http://127.0.0.1:3001").unwrap()
+        MockServer::start().client()
     }
 
     #[tokio::test]
@@ -21,15 +21,25 @@ use crate::api::{
 
 use super::snapshot::Snapshot;
 
-#[derive(Debug, Deserialize, Serialize, PartialEq, Eq, Copy, Clone)]
+#[derive(Debug, Deserialize, Serialize, PartialEq, Eq, Copy, Clone, Default)]
 #[serde(rename_all = "snake_case")]
 /// Possible types of Bucket
 pub enum BucketType {
     /// A bucket for storing backups (Cold)
     Backup,
     /// A bucket for storing interactive data (Hot)
+    #[default]
     Interactive,
 }
+impl BucketType {
+    /// The storage class a Bucket of this type is created with unless told otherwise
+    pub fn default_storage_class(&self) -> StorageClass {
+        match self {
+            BucketType::Backup => StorageClass::Cold,
+            BucketType::Interactive => StorageClass::Hot,
+        }
+    }
+}
 impl Display for BucketType {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -49,11 +59,12 @@ impl FromStr for BucketType {
     }
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq, Default)]
 #[serde(rename_all = "snake_case")]
 /// Possible storage classes for Bucket
 pub enum StorageClass {
     /// Hot storage
+    #[default]
     Hot,
     /// Warm storage
     Warm,
@@ -165,11 +176,12 @@ impl Bucket {
     }
 
     /// Update the bucket fields. Puts the current local values against the current remote values.
-    /// For now only updates to 'name' will be processed, all others will be ignored
+    /// For now only updates to 'name' and 'storage_class' will be processed, all others will be ignored
     pub async fn update(&self, client: &mut Client) -> Result<(), ApiError> {
         let update_request = UpdateBucket {
             bucket_id: String::from(self.id),
             name: self.name.clone(),
+            storage_class: self.storage_class.clone(),
         };
 
         client.call_no_content(update_request).await
@@ -238,13 +250,13 @@ impl Bucket {
 }
 
 #[cfg(test)]
-#[cfg(feature = "integration-tests")]
 pub mod test {
     use crate::api::{
         client::Client,
         error::ApiError,
+        mock::MockServer,
         models::{
-            account::test::{authenticated_client, unauthenticated_client},
+            account::test::authenticated_client,
             bucket::{Bucket, BucketType, StorageClass},
             bucket_key::BucketKey,
             metadata::test::push_metadata_and_snapshot,
@@ -302,9 +314,10 @@ pub mod test {
     }
     #[tokio::test]
     async fn create_read_unauthorized() -> Result<(), ApiError> {
-        let mut good_client = authenticated_client().await;
+        let server = MockServer::start();
+        let mut good_client = server.authenticated_client().await;
         let (bucket, _) = create_bucket(&mut good_client).await?;
-        let mut bad_client = authenticated_client().await;
+        let mut bad_client = server.authenticated_client().await;
         let read_bucket = Bucket::read(&mut bad_client, bucket.id).await;
         assert!(read_bucket.is_err());
         let read_bucket = Bucket::read(&mut bad_client, fake_bucket().id).await;
@@ -375,11 +388,14 @@ pub mod test {
     }
     #[tokio::test]
     async fn create_delete_unauthorized() -> Result<(), ApiError> {
-        let mut good_client = authenticated_client().await;
+        let server = MockServer::start();
+        let mut good_client = server.authenticated_client().await;
         let (bucket, _) = create_bucket(&mut good_client).await?;
-        let mut bad_client = unauthenticated_client().await;
+        let mut bad_client = server.client();
         let delete_result = bucket.delete(&mut bad_client).await;
         assert!(delete_result.is_err());
+        // The Bucket is still there for its owner
+        Bucket::read(&mut good_client, bucket.id).await?;
         Ok(())
     }
     #[tokio::test]
@@ -135,7 +135,6 @@ impl BucketKey {
     }
 }
 
-#[cfg(feature = "integration-tests")]
 #[cfg(test)]
 mod test {
     use crate::{
@@ -197,10 +196,13 @@ mod test {
         let bucket_key = BucketKey::create(bucket.id, pem, &mut client).await?;
         let bucket_keys = BucketKey::read_all(bucket.id, &mut client).await?;
         assert_eq!(bucket_keys.len(), 2);
-        assert_eq!(bucket_key.id, bucket_keys[1].id);
-        assert_eq!(bucket_key.bucket_id, bucket_keys[1].bucket_id);
-        assert_eq!(bucket_key.approved, bucket_keys[1].approved);
-        assert_eq!(bucket_key.pem, bucket_keys[1].pem);
+        let read_bucket_key = bucket_keys
+            .iter()
+            .find(|key| key.id == bucket_key.id)
+            .expect("new bucket key not listed");
+        assert_eq!(bucket_key.bucket_id, read_bucket_key.bucket_id);
+        assert_eq!(bucket_key.approved, read_bucket_key.approved);
+        assert_eq!(bucket_key.pem, read_bucket_key.pem);
         Ok(())
     }
 
@@ -258,6 +260,9 @@ mod test {
         let (bucket, initial_bucket_key) = create_bucket(&mut client).await?;
         assert!(initial_bucket_key.approved);
         BucketKey::reject(bucket.id, initial_bucket_key.id, &mut client).await?;
+        let read_bucket_key =
+            BucketKey::read(bucket.id, initial_bucket_key.id, &mut client).await?;
+        assert!(read_bucket_key.approved);
         Ok(())
     }
 
@@ -1,4 +1,6 @@
+use colored::Colorize;
 use serde::{Deserialize, Serialize};
+use std::fmt::Display;
 use uuid::Uuid;
 
 use crate::api::{
@@ -25,6 +27,17 @@ pub struct DeviceApiKey {
     pub fingerprint: String,
 }
 
+impl Display for DeviceApiKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "{}\nid:\t\t{}\nfingerprint:\t{}",
+            "| DEVICE KEY INFO |".yellow(),
+            self.id,
+            self.fingerprint
+        ))
+    }
+}
+
 impl DeviceApiKey {
     /// Create a new instance of this model or data structure. Attaches the associated credentials to the client.
     pub async fn create(pem: String, client: &mut Client) -> Result<Self, ApiError> {
@@ -73,12 +86,20 @@ impl DeviceApiKey {
     pub async fn delete_by_id(client: &mut Client, id: Uuid) -> Result<(), ApiError> {
         client.call_no_content(DeleteDeviceApiKey { id }).await
     }
+
+    /// Display the DeviceApiKey, noting whether it belongs to this device
+    pub fn context_fmt(&self, my_fingerprint: &String) -> String {
+        if &self.fingerprint == my_fingerprint {
+            format!("{}\n{}", "| THIS IS YOUR DEVICE |".green(), self)
+        } else {
+            format!("{}", self)
+        }
+    }
 }
 
 // TODO: wasm tests
 
 #[cfg(test)]
-#[cfg(feature = "integration-tests")]
 mod test {
     use crate::api::{
         error::ApiError,
@@ -113,10 +134,14 @@ mod test {
         let (_, pem) = generate_api_key().await;
         let create = DeviceApiKey::create(pem, &mut client).await?;
         let read_all = DeviceApiKey::read_all(&mut client).await?;
-        assert!(!read_all.is_empty());
-        assert_eq!(create.id, read_all[1].id);
-        assert_eq!(create.pem, read_all[1].pem);
-        assert_eq!(create.fingerprint, read_all[1].fingerprint);
+        // The key the account was created with, and this one
+        assert_eq!(read_all.len(), 2);
+        let read = read_all
+            .iter()
+            .find(|key| key.id == create.id)
+            .expect("new device key not listed");
+        assert_eq!(create.pem, read.pem);
+        assert_eq!(create.fingerprint, read.fingerprint);
         Ok(())
     }
 
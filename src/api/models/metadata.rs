@@ -123,7 +123,7 @@ impl Metadata {
     pub async fn pull(
         &self,
         client: &mut Client,
-    ) -> Result<impl Stream<Item = Result<Bytes, reqwest::Error>>, ApiError> {
+    ) -> Result<impl Stream<Item = Result<Bytes, ApiError>>, ApiError> {
         let base_url = client.remote_core.clone();
         client
             .stream(
@@ -190,13 +190,11 @@ impl Metadata {
     }
 }
 
-#[cfg(feature = "integration-tests")]
 #[cfg(test)]
 pub(crate) mod test {
     use futures_util::stream::StreamExt;
     use reqwest::Body;
-    use serial_test::serial;
-    use std::{collections::BTreeSet, time::Duration};
+    use std::collections::BTreeSet;
     use tomb_crypt::prelude::{EcEncryptionKey, PrivateKey, PublicKey};
     use uuid::Uuid;
     use wnfs::{libipld::Cid, private::PrivateNode};
@@ -340,9 +338,7 @@ pub(crate) mod test {
         })
     }
 
-    // TODO: this test fails if not serial. This should be fixed
     #[tokio::test]
-    #[serial]
     async fn push_and_pull() -> Result<(), ApiError> {
         let mut setup = setup_and_push_metadata("push_read_pull").await?;
         assert_eq!(setup.metadata.bucket_id, setup.bucket.id);
@@ -361,11 +357,11 @@ pub(crate) mod test {
         while let Some(chunk) = stream.next().await {
             data.extend_from_slice(&chunk.unwrap());
         }
+        assert_eq!(data, setup.content_store.get_data());
         Ok(())
     }
 
     #[tokio::test]
-    #[serial]
     async fn push_and_pull_and_snapshot() -> Result<(), ApiError> {
         let mut setup = setup_and_push_metadata("push_read_pull_snapshot").await?;
         // Create a grant and upload content
@@ -383,8 +379,6 @@ pub(crate) mod test {
             )
             .await?;
 
-        std::thread::sleep(Duration::new(3, 0));
-
         let mut active_cids = BTreeSet::<Cid>::new();
         let all_nodes = setup.fs.get_all_nodes(&setup.metadata_store).await?;
         for (node, _) in all_nodes {
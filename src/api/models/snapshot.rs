@@ -53,9 +53,8 @@ impl Snapshot {
 }
 
 #[cfg(test)]
-#[cfg(feature = "integration-tests")]
 mod test {
-    use std::{collections::BTreeSet, thread, time::Duration};
+    use std::collections::BTreeSet;
 
     use crate::{
         api::{
@@ -72,23 +71,19 @@ mod test {
     };
 
     #[tokio::test]
-    #[ignore = "snapshot creation not yet finished"]
     async fn restore() -> Result<(), ApiError> {
         let mut client = authenticated_client().await;
         let (bucket, _) = create_bucket(&mut client).await?;
         let (metadata, _, _) = push_empty_metadata(bucket.id, &mut client).await?;
         let snapshot_id = metadata.snapshot(BTreeSet::new(), &mut client).await?;
 
-        thread::sleep(Duration::new(1, 0));
-
-        println!("searching for snapshot_id {}", snapshot_id);
-
         // Create a Snapshot object after reading it down
         let snapshots = client
             .call(ReadAllSnapshots {
                 bucket_id: bucket.id,
             })
             .await?;
+        assert_eq!(snapshots.0[0].id, snapshot_id);
         let snapshot = snapshots.0[0].to_snapshot(bucket.id);
         let restored_metadata_id = snapshot.restore(&mut client).await?;
         assert_eq!(restored_metadata_id, metadata.id);
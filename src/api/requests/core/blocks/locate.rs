@@ -49,7 +49,6 @@ impl Display for LocationRequestError {
 impl Error for LocationRequestError {}
 
 #[cfg(test)]
-#[cfg(feature = "integration-tests")]
 mod test {
     use std::collections::BTreeSet;
     use wnfs::libipld::Cid;
@@ -87,9 +86,6 @@ mod test {
             )
             .await?;
 
-        // Wait for the content to be uploaded
-        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
-
         let blockstore_client = setup.client.clone();
         let api_blockstore = BanyanApiBlockStore::from(blockstore_client);
         let node = setup
@@ -4,13 +4,14 @@ use std::fmt::{self, Display, Formatter};
 use reqwest::{Client, RequestBuilder, Url};
 use serde::{Deserialize, Serialize};
 
-use crate::api::requests::ApiRequest;
+use crate::api::{models::bucket::StorageClass, requests::ApiRequest};
 
 #[derive(Serialize)]
 pub struct UpdateBucket {
     #[serde(skip)]
     pub bucket_id: String,
     pub name: String,
+    pub storage_class: StorageClass,
 }
 
 #[derive(Deserialize)]
@@ -4,7 +4,6 @@ pub mod authorization;
 pub mod create;
 
 #[cfg(test)]
-#[cfg(feature = "integration-tests")]
 mod test {
     use crate::api::{
         error::ApiError, models::metadata::test::setup_and_push_metadata,
@@ -41,9 +40,6 @@ mod test {
             )
             .await?;
 
-        // Sleep to allow upload report to be processed
-        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
-
         // Successfully get a new bearer token which can access the new grants
         setup.bucket.get_grants_token(&mut setup.client).await?;
         Ok(())
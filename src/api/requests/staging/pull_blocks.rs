@@ -48,7 +48,6 @@ impl Display for PullBlockError {
 impl Error for PullBlockError {}
 
 #[cfg(test)]
-#[cfg(feature = "integration-tests")]
 mod test {
     use std::collections::BTreeSet;
 
@@ -81,9 +80,6 @@ mod test {
             )
             .await?;
 
-        // Sleep to allow block locations to be updated
-        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
-
         let mut cids = <BTreeSet<Cid>>::new();
         for bucket in setup.content_store.car.car.index.borrow().clone().buckets {
             cids.extend(bucket.map.into_keys().collect::<BTreeSet<Cid>>());
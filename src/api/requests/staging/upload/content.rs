@@ -1,7 +1,17 @@
 use async_trait::async_trait;
+#[cfg(not(target_arch = "wasm32"))]
+use bytes::Bytes;
+#[cfg(not(target_arch = "wasm32"))]
+use futures_core::stream::Stream;
+#[cfg(not(target_arch = "wasm32"))]
+use futures_util::StreamExt;
+#[cfg(not(target_arch = "wasm32"))]
+use std::pin::Pin;
 use uuid::Uuid;
 
 use super::push::PushContent;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::utils::progress::{ProgressEvent, ProgressSink};
 use crate::{
     api::{client::Client, error::ApiError},
     blockstore::CarV2MemoryBlockStore,
@@ -12,12 +22,22 @@ pub type ContentType = reqwest::Body;
 #[cfg(target_arch = "wasm32")]
 pub type ContentType = std::io::Cursor<Vec<u8>>;
 
+/// Content as it is read from where it is kept, before being turned into a request body
+#[cfg(not(target_arch = "wasm32"))]
+pub type ContentSource = Pin<Box<dyn Stream<Item = Result<Bytes, std::io::Error>> + Send + Sync>>;
+#[cfg(target_arch = "wasm32")]
+pub type ContentSource = ContentType;
+
+/// Size of the chunks in which content held in memory is uploaded
+#[cfg(not(target_arch = "wasm32"))]
+const CHUNK_SIZE: usize = 64 * 1024;
+
 #[async_trait(?Send)]
 pub trait UploadContent {
     type UploadError: From<ApiError>;
 
     fn get_hash(&self) -> Result<String, Self::UploadError>;
-    async fn get_body(&self) -> Result<ContentType, Self::UploadError>;
+    async fn get_body(&self) -> Result<ContentSource, Self::UploadError>;
     fn get_length(&self) -> Result<u64, Self::UploadError>;
 
     async fn upload(
@@ -26,11 +46,12 @@ pub trait UploadContent {
         metadata_id: Uuid,
         client: &mut Client,
     ) -> Result<(), Self::UploadError> {
+        let content_len = self.get_length()?;
         let push_content = PushContent {
             host_url,
             metadata_id,
-            content: self.get_body().await?,
-            content_len: self.get_length()?,
+            content: client.upload_body(self.get_body().await?, content_len),
+            content_len,
             content_hash: self.get_hash()?,
         };
 
@@ -39,6 +60,48 @@ pub trait UploadContent {
             .await
             .map_err(|err| err.into())
     }
+
+    /// Upload, reporting how many bytes have gone out as the upload goes on
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn upload_with_progress(
+        &self,
+        host_url: String,
+        metadata_id: Uuid,
+        client: &mut Client,
+        progress: &dyn ProgressSink,
+    ) -> Result<(), Self::UploadError> {
+        let content_len = self.get_length()?;
+        // The body has to be sent on its own, so chunks are counted there and reported from here
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let counted: ContentSource = Box::pin(self.get_body().await?.inspect(move |chunk| {
+            if let Ok(chunk) = chunk {
+                let _ = sender.send(chunk.len() as u64);
+            }
+        }));
+        let push_content = PushContent {
+            host_url,
+            metadata_id,
+            content: client.upload_body(counted, content_len),
+            content_len,
+            content_hash: self.get_hash()?,
+        };
+
+        let upload = client.multipart_no_content(push_content);
+        tokio::pin!(upload);
+        let mut uploaded = 0;
+        loop {
+            tokio::select! {
+                result = &mut upload => return result.map_err(|err| err.into()),
+                Some(length) = receiver.recv() => {
+                    uploaded += length;
+                    progress.report(ProgressEvent::BytesUploaded {
+                        uploaded,
+                        total: content_len,
+                    });
+                }
+            }
+        }
+    }
 }
 
 #[async_trait(?Send)]
@@ -46,27 +109,35 @@ impl UploadContent for CarV2MemoryBlockStore {
     type UploadError = ApiError;
 
     fn get_hash(&self) -> Result<String, Self::UploadError> {
-        let data = self.get_data();
-        let mut hasher = blake3::Hasher::new();
-        hasher.update(&data);
-        Ok(hasher.finalize().to_string())
+        Ok(self.with_data(|data| {
+            let mut hasher = blake3::Hasher::new();
+            hasher.update(data);
+            hasher.finalize().to_string()
+        }))
     }
 
-    async fn get_body(&self) -> Result<ContentType, Self::UploadError> {
+    async fn get_body(&self) -> Result<ContentSource, Self::UploadError> {
         #[cfg(target_arch = "wasm32")]
         return Ok(std::io::Cursor::new(self.get_data()));
 
+        // Chunks share the one copy of the data rather than each copying their part
         #[cfg(not(target_arch = "wasm32"))]
-        return Ok(self.get_data().into());
+        {
+            let data = Bytes::from(self.get_data());
+            return Ok(Box::pin(futures_util::stream::iter(
+                (0..data.len())
+                    .step_by(CHUNK_SIZE)
+                    .map(move |start| Ok(data.slice(start..data.len().min(start + CHUNK_SIZE)))),
+            )));
+        }
     }
 
     fn get_length(&self) -> Result<u64, Self::UploadError> {
-        Ok(self.get_data().len() as u64)
+        Ok(self.with_data(|data| data.len() as u64))
     }
 }
 
 #[cfg(test)]
-#[cfg(feature = "integration-tests")]
 mod test {
     use crate::api::{
         error::ApiError, models::metadata::test::setup_and_push_metadata,
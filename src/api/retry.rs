@@ -0,0 +1,304 @@
+#[cfg(not(target_arch = "wasm32"))]
+use super::error::ApiError;
+use chrono::{DateTime, Utc};
+#[cfg(not(target_arch = "wasm32"))]
+use futures_core::stream::Stream;
+use rand::Rng;
+use reqwest::{
+    header::{HeaderMap, RETRY_AFTER},
+    Method, StatusCode,
+};
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+#[cfg(not(target_arch = "wasm32"))]
+use tokio::time::{Instant, Sleep};
+
+/// How the Client retries failed requests, and how long it waits on them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Number of retries after the first attempt
+    pub max_retries: u32,
+    /// Longest possible delay before the first retry, doubling with each one after it
+    pub initial_backoff: Duration,
+    /// Cap on the delay between two attempts
+    pub max_backoff: Duration,
+    /// No retry is attempted once this much time would have passed since the first attempt
+    pub max_elapsed: Duration,
+    /// Time allowed to establish a connection (native only)
+    pub connect_timeout: Duration,
+    /// Time allowed to go without a response while the request body isn't making progress either (native only)
+    pub read_timeout: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 4,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(10),
+            max_elapsed: Duration::from_secs(60),
+            connect_timeout: Duration::from_secs(10),
+            read_timeout: Duration::from_secs(60),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Send every request exactly once
+    pub fn none() -> Self {
+        Self {
+            max_retries: 0,
+            ..Self::default()
+        }
+    }
+
+    /// Whether a request can be sent again after a response with this status
+    pub(crate) fn retries_status(method: &Method, status: StatusCode) -> bool {
+        match status {
+            // Rate limited requests were turned away before being handled
+            StatusCode::TOO_MANY_REQUESTS => true,
+            StatusCode::INTERNAL_SERVER_ERROR
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT => is_idempotent(method),
+            _ => false,
+        }
+    }
+
+    /// Delay before the retry following this many failed attempts, honoring Retry-After if the server sent it
+    pub(crate) fn delay(&self, attempt: u32, headers: Option<&HeaderMap>) -> Duration {
+        if let Some(retry_after) = headers.and_then(retry_after) {
+            return retry_after;
+        }
+        // Full jitter, so that clients which failed together don't retry together
+        let backoff = self
+            .initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_backoff);
+        Duration::from_millis(rand::thread_rng().gen_range(0..=backoff.as_millis() as u64))
+    }
+}
+
+/// When the body of a single request last made progress.
+/// Uploads which keep going aren't timed out, however long they take.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone)]
+pub(crate) struct Activity(Arc<Mutex<Instant>>);
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Default for Activity {
+    fn default() -> Self {
+        Self(Arc::new(Mutex::new(Instant::now())))
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Activity {
+    /// Note that a request body made progress just now
+    pub(crate) fn touch(&self) {
+        if let Ok(mut last) = self.0.lock() {
+            *last = Instant::now();
+        }
+    }
+
+    /// The last time a request body made progress
+    pub(crate) fn last(&self) -> Instant {
+        self.0
+            .lock()
+            .map(|last| *last)
+            .unwrap_or_else(|_| Instant::now())
+    }
+}
+
+/// A response body which fails once the server has sent nothing for too long.
+/// Only time spent waiting on the server counts, not time spent by whoever reads it.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) struct IdleTimeout<S> {
+    inner: Pin<Box<S>>,
+    timeout: Duration,
+    deadline: Pin<Box<Sleep>>,
+    /// Whether the next chunk has been asked for but hasn't arrived yet
+    waiting: bool,
+    expired: bool,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<S> IdleTimeout<S> {
+    /// Fail a stream which goes this long without producing anything
+    pub(crate) fn new(inner: S, timeout: Duration) -> Self {
+        Self {
+            inner: Box::pin(inner),
+            timeout,
+            deadline: Box::pin(tokio::time::sleep(timeout)),
+            waiting: false,
+            expired: false,
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<S, B> Stream for IdleTimeout<S>
+where
+    S: Stream<Item = Result<B, reqwest::Error>>,
+{
+    type Item = Result<B, ApiError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if this.expired {
+            return Poll::Ready(None);
+        }
+        if !this.waiting {
+            this.waiting = true;
+            this.deadline.as_mut().reset(Instant::now() + this.timeout);
+        }
+        match this.inner.as_mut().poll_next(cx) {
+            Poll::Ready(item) => {
+                this.waiting = false;
+                Poll::Ready(item.map(|chunk| chunk.map_err(ApiError::http)))
+            }
+            Poll::Pending => match this.deadline.as_mut().poll(cx) {
+                Poll::Ready(()) => {
+                    this.expired = true;
+                    Poll::Ready(Some(Err(ApiError::timeout(this.timeout))))
+                }
+                Poll::Pending => Poll::Pending,
+            },
+        }
+    }
+}
+
+/// Methods which have the same effect no matter how many times they are sent
+pub(crate) fn is_idempotent(method: &Method) -> bool {
+    matches!(
+        *method,
+        Method::GET | Method::HEAD | Method::PUT | Method::DELETE | Method::OPTIONS
+    )
+}
+
+/// Parse a Retry-After header, which holds either a number of seconds or an HTTP date
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = DateTime::parse_from_rfc2822(value).ok()?;
+    // Dates in the past mean there is no need to wait
+    Some(
+        (date.with_timezone(&Utc) - Utc::now())
+            .to_std()
+            .unwrap_or_default(),
+    )
+}
+
+/// Wait before the next attempt
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) async fn sleep(duration: Duration) {
+    tokio::time::sleep(duration).await
+}
+
+/// Wait before the next attempt
+#[cfg(target_arch = "wasm32")]
+pub(crate) async fn sleep(duration: Duration) {
+    use wasm_bindgen::JsCast;
+    let promise = js_sys::Promise::new(&mut |resolve, _| {
+        let global = js_sys::global();
+        if let Ok(set_timeout) = js_sys::Reflect::get(&global, &"setTimeout".into()) {
+            let _ = set_timeout.unchecked_into::<js_sys::Function>().call2(
+                &global,
+                &resolve,
+                &(duration.as_millis() as f64).into(),
+            );
+        }
+    });
+    let _ = wasm_bindgen_futures::JsFuture::from(promise).await;
+}
+
+#[cfg(test)]
+mod test {
+    #[cfg(not(target_arch = "wasm32"))]
+    use super::IdleTimeout;
+    use super::RetryPolicy;
+    #[cfg(not(target_arch = "wasm32"))]
+    use crate::api::{error::ApiError, mock::MockServer, models::account::Account};
+    #[cfg(not(target_arch = "wasm32"))]
+    use futures_util::{stream, StreamExt};
+    use reqwest::{
+        header::{HeaderMap, HeaderValue, RETRY_AFTER},
+        Method, StatusCode,
+    };
+    use std::time::Duration;
+
+    #[test]
+    fn retryable() {
+        assert!(RetryPolicy::retries_status(
+            &Method::POST,
+            StatusCode::TOO_MANY_REQUESTS
+        ));
+        assert!(RetryPolicy::retries_status(
+            &Method::GET,
+            StatusCode::BAD_GATEWAY
+        ));
+        // The server may have acted on it already
+        assert!(!RetryPolicy::retries_status(
+            &Method::POST,
+            StatusCode::BAD_GATEWAY
+        ));
+        assert!(!RetryPolicy::retries_status(
+            &Method::GET,
+            StatusCode::NOT_FOUND
+        ));
+    }
+
+    #[test]
+    fn backoff() {
+        let policy = RetryPolicy::default();
+        for attempt in 0..10 {
+            assert!(policy.delay(attempt, None) <= policy.max_backoff);
+        }
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("7"));
+        assert_eq!(policy.delay(0, Some(&headers)), Duration::from_secs(7));
+        headers.insert(
+            RETRY_AFTER,
+            HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"),
+        );
+        assert_eq!(policy.delay(0, Some(&headers)), Duration::ZERO);
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn retries_transient_failures() -> Result<(), ApiError> {
+        let server = MockServer::start();
+        let mut client = server.authenticated_client().await;
+
+        server.fail_next(&[StatusCode::BAD_GATEWAY, StatusCode::TOO_MANY_REQUESTS]);
+        Account::who_am_i(&mut client).await?;
+
+        client.with_retry_policy(RetryPolicy::none())?;
+        server.fail_next(&[StatusCode::BAD_GATEWAY]);
+        assert!(Account::who_am_i(&mut client).await.is_err());
+        Ok(())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    #[tokio::test]
+    async fn stalled_bodies_time_out() {
+        let timeout = Duration::from_millis(50);
+        let chunks =
+            stream::iter([Ok::<_, reqwest::Error>(vec![0u8; 10])]).chain(stream::pending());
+        let mut body = IdleTimeout::new(chunks, timeout);
+        assert!(body.next().await.unwrap().is_ok());
+        // Time the reader takes doesn't count against the server
+        tokio::time::sleep(timeout * 2).await;
+        let err = body.next().await.unwrap().unwrap_err();
+        assert!(err.is_unreachable());
+        assert!(body.next().await.is_none());
+    }
+}
@@ -0,0 +1,234 @@
+use chrono::{DateTime, Utc};
+use futures_core::stream::Stream;
+use serde::{Deserialize, Serialize};
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::Duration,
+};
+
+/// Limits on how fast the Client transfers data, in bytes per second
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Bandwidth {
+    /// Most bytes sent per second, if limited
+    pub upload: Option<u64>,
+    /// Most bytes received per second, if limited
+    pub download: Option<u64>,
+}
+
+/// Where the Client reports on the progress of its transfers
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone, Default)]
+pub enum Progress {
+    /// Nowhere
+    #[default]
+    Hidden,
+    /// On a bar of its own for every transfer large enough to warrant one
+    PerTransfer,
+    /// On a single bar that every transfer adds to
+    Shared(indicatif::ProgressBar),
+}
+
+/// Transfers smaller than this finish too quickly for a bar of their own to be useful
+#[cfg(not(target_arch = "wasm32"))]
+const PROGRESS_THRESHOLD: u64 = 1 << 20;
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Progress {
+    /// Report on a batch of transfers together, if reporting at all
+    pub fn batch(&self, message: &str) -> Self {
+        match self {
+            Progress::Hidden => Progress::Hidden,
+            Progress::PerTransfer => Progress::Shared(bytes_progress_bar(message, 0)),
+            Progress::Shared(bar) => Progress::Shared(bar.clone()),
+        }
+    }
+
+    /// The bar on which to report a transfer of this many bytes, if the number is known
+    pub(crate) fn transfer(&self, message: &str, length: Option<u64>) -> indicatif::ProgressBar {
+        match self {
+            Progress::PerTransfer if length.map_or(true, |len| len >= PROGRESS_THRESHOLD) => {
+                bytes_progress_bar(message, length.unwrap_or(0))
+            }
+            Progress::Shared(bar) => {
+                bar.inc_length(length.unwrap_or(0));
+                bar.clone()
+            }
+            _ => indicatif::ProgressBar::hidden(),
+        }
+    }
+}
+
+/// Create a progress bar for displaying bytes transferred, with rate and ETA
+#[cfg(not(target_arch = "wasm32"))]
+fn bytes_progress_bar(message: &str, length: u64) -> indicatif::ProgressBar {
+    let progress_bar = indicatif::ProgressBar::new(length).with_message(message.to_string());
+    progress_bar.set_style(
+        indicatif::ProgressStyle::default_bar()
+            .template(
+                "{spinner:.green} {msg} [{elapsed_precise}] {bar:40.cyan/blue} {bytes}/{total_bytes} {bytes_per_sec} ETA {eta}",
+            )
+            .unwrap(),
+    );
+    progress_bar
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+type Delay = Pin<Box<tokio::time::Sleep>>;
+#[cfg(target_arch = "wasm32")]
+type Delay = Pin<Box<dyn Future<Output = ()>>>;
+
+#[cfg(not(target_arch = "wasm32"))]
+fn delay(duration: Duration) -> Delay {
+    Box::pin(tokio::time::sleep(duration))
+}
+
+#[cfg(target_arch = "wasm32")]
+fn delay(duration: Duration) -> Delay {
+    Box::pin(super::retry::sleep(duration))
+}
+
+/// Most time a TokenBucket spends idle that it can make up for with a burst
+const BURST: Duration = Duration::from_secs(1);
+
+/// A number of bytes per second shared by every transfer drawing on it, however many run at once
+#[derive(Debug, Clone)]
+pub struct TokenBucket(Arc<Mutex<Schedule>>);
+
+#[derive(Debug)]
+struct Schedule {
+    /// Most bytes per second
+    limit: u64,
+    /// When every byte taken so far will have been paid for
+    paid_until: DateTime<Utc>,
+}
+
+impl TokenBucket {
+    /// Share a limit of this many bytes per second
+    pub fn new(limit: u64) -> Self {
+        Self(Arc::new(Mutex::new(Schedule {
+            limit: limit.max(1),
+            paid_until: Utc::now(),
+        })))
+    }
+
+    /// Take bytes which were just transferred, returning how long to wait before transferring any more
+    fn take(&self, bytes: u64) -> Option<Duration> {
+        let mut schedule = self.0.lock().ok()?;
+        let now = Utc::now();
+        let burst = chrono::Duration::from_std(BURST).ok()?;
+        let cost = chrono::Duration::from_std(Duration::from_secs_f64(
+            bytes as f64 / schedule.limit as f64,
+        ))
+        .ok()?;
+        schedule.paid_until = schedule.paid_until.max(now - burst) + cost;
+        (schedule.paid_until - now)
+            .to_std()
+            .ok()
+            .filter(|wait| !wait.is_zero())
+    }
+}
+
+/// A stream of bytes which goes no faster than its limit, reporting its progress as it goes
+pub struct Throttled<S> {
+    inner: Pin<Box<S>>,
+    /// Where bytes are drawn from, if limited
+    limit: Option<TokenBucket>,
+    /// Wait to finish before the next chunk is passed on
+    delay: Option<Delay>,
+    #[cfg(not(target_arch = "wasm32"))]
+    progress: indicatif::ProgressBar,
+}
+
+impl<S> Throttled<S> {
+    /// Throttle a stream to the bytes per second left over by everything else drawing on the same limit
+    pub fn new(inner: S, limit: Option<TokenBucket>) -> Self {
+        Self {
+            inner: Box::pin(inner),
+            limit,
+            delay: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            progress: indicatif::ProgressBar::hidden(),
+        }
+    }
+
+    /// Report progress on a bar
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_progress(mut self, progress: indicatif::ProgressBar) -> Self {
+        self.progress = progress;
+        self
+    }
+}
+
+impl<S, B, E> Stream for Throttled<S>
+where
+    S: Stream<Item = Result<B, E>>,
+    B: AsRef<[u8]>,
+{
+    type Item = Result<B, E>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if let Some(delay) = this.delay.as_mut() {
+            if delay.as_mut().poll(cx).is_pending() {
+                return Poll::Pending;
+            }
+            this.delay = None;
+        }
+
+        let item = match this.inner.as_mut().poll_next(cx) {
+            Poll::Ready(item) => item,
+            Poll::Pending => return Poll::Pending,
+        };
+        if let Some(Ok(chunk)) = &item {
+            let length = chunk.as_ref().len() as u64;
+            #[cfg(not(target_arch = "wasm32"))]
+            this.progress.inc(length);
+            this.delay = this
+                .limit
+                .as_ref()
+                .and_then(|limit| limit.take(length))
+                .map(delay);
+        }
+        Poll::Ready(item)
+    }
+}
+
+#[cfg(test)]
+#[cfg(not(target_arch = "wasm32"))]
+mod test {
+    use super::{Throttled, TokenBucket};
+    use futures_util::{future::join, stream, StreamExt};
+    use std::time::{Duration, Instant};
+
+    fn chunks() -> impl futures_core::Stream<Item = Result<Vec<u8>, ()>> {
+        stream::iter((0..5).map(|_| Ok(vec![0u8; 100])))
+    }
+
+    #[tokio::test]
+    async fn throttles() {
+        let started = Instant::now();
+        let received = Throttled::new(chunks(), Some(TokenBucket::new(1000)))
+            .collect::<Vec<_>>()
+            .await;
+        assert_eq!(received.len(), 5);
+        // 500 bytes at 1000 bytes per second
+        assert!(started.elapsed() >= Duration::from_millis(400));
+    }
+
+    #[tokio::test]
+    async fn shares_limit() {
+        let limit = TokenBucket::new(1000);
+        let started = Instant::now();
+        let (first, second) = join(
+            Throttled::new(chunks(), Some(limit.clone())).collect::<Vec<_>>(),
+            Throttled::new(chunks(), Some(limit)).collect::<Vec<_>>(),
+        )
+        .await;
+        assert_eq!(first.len() + second.len(), 10);
+        // 1000 bytes between them at 1000 bytes per second
+        assert!(started.elapsed() >= Duration::from_millis(900));
+    }
+}
@@ -1,19 +1,28 @@
-use crate::api::{
-    client::Client,
-    error::ApiError,
-    requests::{core::blocks::locate::LocationRequest, staging::pull_blocks::PullBlock},
+#[cfg(not(target_arch = "wasm32"))]
+use crate::api::throttle::Progress;
+use crate::{
+    api::{
+        client::Client,
+        error::ApiError,
+        requests::{core::blocks::locate::LocationRequest, staging::pull_blocks::PullBlock},
+    },
+    car::v1::Block,
 };
 use async_trait::async_trait;
-use futures_util::StreamExt;
+use futures_util::{stream, StreamExt};
 use reqwest::Url;
 use std::{
     borrow::Cow,
     cell::RefCell,
     collections::{BTreeSet, HashMap},
 };
-use wnfs::libipld::{Cid, IpldCodec};
+use tracing::warn;
+use wnfs::libipld::{multihash::Code, Cid, IpldCodec};
 
-use super::{BanyanBlockStore, BlockStoreError};
+use super::{BanyanBlockStore, BlockCache, BlockStoreError};
+
+/// Number of blocks fetched at once unless told otherwise
+const DEFAULT_CONCURRENCY: usize = 8;
 
 /// A network-based BlockStore designed to interface with a Kubo node or an API which mirrors it
 
@@ -22,6 +31,12 @@ pub struct BanyanApiBlockStore {
     client: Client,
     /// Known remote endpoints of Blocks
     block_locations: RefCell<HashMap<String, Vec<String>>>,
+    /// Blocks fetched ahead of time which couldn't be cached, handed out once
+    prefetched: RefCell<HashMap<Cid, Vec<u8>>>,
+    /// Blocks fetched in the past
+    cache: Option<BlockCache>,
+    /// Number of blocks fetched at once
+    concurrency: usize,
 }
 
 impl From<Client> for BanyanApiBlockStore {
@@ -29,11 +44,26 @@ impl From<Client> for BanyanApiBlockStore {
         Self {
             client,
             block_locations: RefCell::new(HashMap::new()),
+            prefetched: RefCell::new(HashMap::new()),
+            cache: None,
+            concurrency: DEFAULT_CONCURRENCY,
         }
     }
 }
 
 impl BanyanApiBlockStore {
+    /// Keep fetched blocks in a cache, and look there before fetching them again
+    pub fn with_cache(mut self, cache: BlockCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Set the number of blocks fetched at once
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
     /// Find the locations associated with a set of CIDs for fast querying on lookup
     pub async fn find_cids(&self, cids: BTreeSet<Cid>) -> Result<(), ApiError> {
         let request = LocationRequest { cids };
@@ -43,21 +73,52 @@ impl BanyanApiBlockStore {
         block_locations.extend(response);
         Ok(())
     }
-}
 
-#[async_trait(?Send)]
-impl BanyanBlockStore for BanyanApiBlockStore {
-    /// Stores an array of bytes in the block store.
-    async fn put_block(&self, _bytes: Vec<u8>, _codec: IpldCodec) -> Result<Cid, BlockStoreError> {
-        Err(BlockStoreError::wnfs(Box::from(
-            "Cannot put block in API store",
-        )))
-    }
+    /// Fetch a set of blocks ahead of time, several at once, so that reading them is fast.
+    /// Blocks which fail to arrive are left for `get_block` to try again and report on.
+    pub async fn prefetch(&self, cids: BTreeSet<Cid>) -> Result<(), ApiError> {
+        let mut missing = BTreeSet::new();
+        for cid in cids {
+            let cached = match &self.cache {
+                Some(cache) => cache.contains(&cid).await,
+                None => false,
+            };
+            if !cached && !self.prefetched.borrow().contains_key(&cid) {
+                missing.insert(cid);
+            }
+        }
+        if missing.is_empty() {
+            return Ok(());
+        }
 
-    /// Retrieves an array of bytes from the block store with given CID.
-    #[allow(clippy::await_holding_refcell_ref)]
-    async fn get_block(&self, cid: &Cid) -> Result<Cow<'_, Vec<u8>>, BlockStoreError> {
+        self.find_cids(missing.clone()).await?;
+        // Report on the whole batch at once rather than block by block
+        #[allow(unused_mut)]
         let mut client = self.client.clone();
+        #[cfg(not(target_arch = "wasm32"))]
+        client.with_progress(self.client.progress().batch("Fetching blocks"));
+        let client = &client;
+        let mut fetches = stream::iter(missing)
+            .map(|cid| async move { (cid, self.fetch(client, &cid).await) })
+            .buffer_unordered(self.concurrency);
+        while let Some((cid, result)) = fetches.next().await {
+            match result {
+                Ok(data) => self.keep(&cid, data).await,
+                Err(err) => warn!("unable to prefetch block {cid}: {err}"),
+            }
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if let (Progress::PerTransfer, Progress::Shared(bar)) =
+            (self.client.progress(), client.progress())
+        {
+            bar.finish_and_clear();
+        }
+        Ok(())
+    }
+
+    /// Pull a block from the first location known to hold it, and check that it is what we asked for
+    async fn fetch(&self, client: &Client, cid: &Cid) -> Result<Vec<u8>, BlockStoreError> {
+        let mut client = client.clone();
         // Pull the first url that has the block from the map of url: [block_id]
         let mut maybe_url = None;
         for (url, cids) in self.block_locations.borrow().iter() {
@@ -79,8 +140,86 @@ impl BanyanBlockStore for BanyanApiBlockStore {
             .map_err(|err| BlockStoreError::wnfs(Box::from(err)))?;
         let mut data = Vec::new();
         while let Some(chunk) = stream.next().await {
-            data.extend_from_slice(&chunk.unwrap());
+            data.extend_from_slice(&chunk.map_err(|err| BlockStoreError::wnfs(Box::from(err)))?);
+        }
+
+        verify(cid, &data)?;
+        Ok(data)
+    }
+
+    /// Hold on to a verified block until it is read
+    async fn keep(&self, cid: &Cid, data: Vec<u8>) {
+        if let Some(cache) = &self.cache {
+            match cache.put(cid, &data).await {
+                Ok(()) => return,
+                Err(err) => warn!("unable to cache block {cid}: {err}"),
+            }
+        }
+        self.prefetched.borrow_mut().insert(*cid, data);
+    }
+}
+
+/// Check that content hashes to the CID it was requested by, following the same rules as `Block::new`
+fn verify(cid: &Cid, content: &[u8]) -> Result<(), BlockStoreError> {
+    let found = IpldCodec::try_from(cid.codec())
+        .ok()
+        .zip(Code::try_from(cid.hash().code()).ok())
+        .map(|(codec, code)| Block::cid(content, codec, code));
+    match found {
+        Some(found) if found.hash() == cid.hash() => Ok(()),
+        found => Err(BlockStoreError::hash_mismatch(*cid, found)),
+    }
+}
+
+#[async_trait(?Send)]
+impl BanyanBlockStore for BanyanApiBlockStore {
+    /// Stores an array of bytes in the block store.
+    async fn put_block(&self, _bytes: Vec<u8>, _codec: IpldCodec) -> Result<Cid, BlockStoreError> {
+        Err(BlockStoreError::wnfs(Box::from(
+            "Cannot put block in API store",
+        )))
+    }
+
+    /// Retrieves an array of bytes from the block store with given CID.
+    async fn get_block(&self, cid: &Cid) -> Result<Cow<'_, Vec<u8>>, BlockStoreError> {
+        let prefetched = self.prefetched.borrow_mut().remove(cid);
+        if let Some(data) = prefetched {
+            return Ok(Cow::Owned(data));
+        }
+        if let Some(cache) = &self.cache {
+            if let Some(data) = cache.get(cid).await {
+                return Ok(Cow::Owned(data));
+            }
+        }
+
+        let data = self.fetch(&self.client, cid).await?;
+        if let Some(cache) = &self.cache {
+            if let Err(err) = cache.put(cid, &data).await {
+                warn!("unable to cache block {cid}: {err}");
+            }
         }
         Ok(Cow::Owned(data))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::verify;
+    use crate::car::v1::Block;
+    use wnfs::libipld::{multihash::Code, IpldCodec};
+
+    #[test]
+    fn verify_blocks() {
+        let block = Block::new(b"block content".to_vec(), IpldCodec::Raw).unwrap();
+        assert!(verify(&block.cid, &block.content).is_ok());
+        // Other hash functions are held to what their CIDs say
+        let blake3 = Block::cid(&block.content, IpldCodec::DagCbor, Code::Blake3_256);
+        assert!(verify(&blake3, &block.content).is_ok());
+
+        let err = verify(&block.cid, b"something else").unwrap_err();
+        assert!(err.is_hash_mismatch());
+        assert!(verify(&blake3, b"something else")
+            .unwrap_err()
+            .is_hash_mismatch());
+    }
+}
@@ -0,0 +1,177 @@
+use super::BlockStoreError;
+use std::{
+    fs::{create_dir_all, read_dir, remove_file, rename, File},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::SystemTime,
+};
+use wnfs::libipld::Cid;
+
+/// Number of bytes a BlockCache holds unless told otherwise
+pub const DEFAULT_CACHE_CAPACITY: u64 = 1 << 30;
+
+/// A size-capped cache of verified blocks on disk, keyed by CID.
+/// The blocks which were read least recently are evicted first.
+#[derive(Debug, Clone)]
+pub struct BlockCache {
+    /// Directory holding one file per block
+    path: PathBuf,
+    /// Most bytes the cache may hold
+    capacity: u64,
+    /// Bytes currently held
+    used: Arc<AtomicU64>,
+}
+
+impl BlockCache {
+    /// Use a directory as a cache, creating it if it doesn't exist yet
+    pub fn new(path: &Path, capacity: u64) -> Result<Self, BlockStoreError> {
+        create_dir_all(path)?;
+        let used = entries(path)?.iter().map(|entry| entry.size).sum();
+        Ok(Self {
+            path: path.to_path_buf(),
+            capacity,
+            used: Arc::new(AtomicU64::new(used)),
+        })
+    }
+
+    /// Whether a block is cached
+    pub async fn contains(&self, cid: &Cid) -> bool {
+        self.block_path(cid).exists()
+    }
+
+    /// Read a block, if it is cached
+    pub async fn get(&self, cid: &Cid) -> Option<Vec<u8>> {
+        let path = self.block_path(cid);
+        let data = std::fs::read(&path).ok()?;
+        // Mark the block as recently used
+        if let Ok(file) = File::options().append(true).open(&path) {
+            let _ = file.set_modified(SystemTime::now());
+        }
+        Some(data)
+    }
+
+    /// Cache a block which has already been verified against its CID
+    pub async fn put(&self, cid: &Cid, data: &[u8]) -> Result<(), BlockStoreError> {
+        let path = self.block_path(cid);
+        let size = data.len() as u64;
+        if size > self.capacity || path.exists() {
+            return Ok(());
+        }
+        // Write elsewhere first, so that no one ever reads a partial block
+        let partial = self
+            .path
+            .join(format!("{cid}.{}.partial", std::process::id()));
+        std::fs::write(&partial, data)?;
+        rename(&partial, &path)?;
+        if self.used.fetch_add(size, Ordering::SeqCst) + size > self.capacity {
+            self.evict()?;
+        }
+        Ok(())
+    }
+
+    fn block_path(&self, cid: &Cid) -> PathBuf {
+        self.path.join(cid.to_string())
+    }
+
+    /// Remove the least recently used blocks until the cache is back under 90% of its capacity
+    fn evict(&self) -> Result<(), BlockStoreError> {
+        let mut entries = entries(&self.path)?;
+        entries.sort_by_key(|entry| entry.modified);
+        let mut used: u64 = entries.iter().map(|entry| entry.size).sum();
+        let target = self.capacity / 10 * 9;
+        for entry in entries {
+            if used <= target {
+                break;
+            }
+            // Another process sharing this cache may have evicted it already
+            let _ = remove_file(&entry.path);
+            used = used.saturating_sub(entry.size);
+        }
+        self.used.store(used, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+/// A block in the cache
+struct Entry {
+    path: PathBuf,
+    size: u64,
+    modified: SystemTime,
+}
+
+/// List the blocks in a cache directory, skipping any which are still being written
+fn entries(path: &Path) -> Result<Vec<Entry>, BlockStoreError> {
+    let mut entries = Vec::new();
+    for entry in read_dir(path)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if metadata.is_file() && entry.path().extension().is_none() {
+            entries.push(Entry {
+                path: entry.path(),
+                size: metadata.len(),
+                modified: metadata.modified()?,
+            });
+        }
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod test {
+    use super::BlockCache;
+    use crate::blockstore::BlockStoreError;
+    use std::{
+        fs::{remove_dir_all, File},
+        path::Path,
+        time::{Duration, SystemTime},
+    };
+    use wnfs::libipld::{
+        multihash::{Code, MultihashDigest},
+        Cid, IpldCodec,
+    };
+
+    fn block(index: u8) -> (Cid, Vec<u8>) {
+        let data = vec![index; 100];
+        let cid = Cid::new_v1(IpldCodec::Raw.into(), Code::Sha2_256.digest(&data));
+        (cid, data)
+    }
+
+    /// Pretend a cached block was last used some seconds ago
+    fn last_used(cache: &BlockCache, cid: &Cid, seconds_ago: u64) -> Result<(), BlockStoreError> {
+        let file = File::options().append(true).open(cache.block_path(cid))?;
+        file.set_modified(SystemTime::now() - Duration::from_secs(seconds_ago))?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn evicts_least_recently_used() -> Result<(), BlockStoreError> {
+        let path = Path::new("test").join("block_cache").join("evicts");
+        if path.exists() {
+            remove_dir_all(&path)?;
+        }
+        let cache = BlockCache::new(&path, 350)?;
+        let blocks = (0..3).map(block).collect::<Vec<_>>();
+        for (index, (cid, data)) in blocks.iter().enumerate() {
+            cache.put(cid, data).await?;
+            // The first block was used longest ago
+            last_used(&cache, cid, 60 - index as u64)?;
+        }
+        // Reading the first block makes the second the least recently used
+        assert_eq!(cache.get(&blocks[0].0).await, Some(blocks[0].1.clone()));
+
+        let (cid, data) = block(3);
+        cache.put(&cid, &data).await?;
+        assert!(cache.contains(&cid).await);
+        assert!(cache.contains(&blocks[0].0).await);
+        assert!(!cache.contains(&blocks[1].0).await);
+
+        // Reopening the cache picks up what it already holds
+        let reopened = BlockCache::new(&path, 350)?;
+        assert_eq!(reopened.get(&cid).await, Some(data));
+        remove_dir_all(path)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,200 @@
+use super::BlockStoreError;
+use js_sys::{Array, Date, Uint8Array};
+use rexie::{ObjectStore, Rexie, TransactionMode};
+use std::{cell::Cell, fmt::Debug, rc::Rc};
+use wasm_bindgen::{JsCast, JsValue};
+use wnfs::libipld::Cid;
+
+/// Number of bytes a BlockCache holds unless told otherwise
+pub const DEFAULT_CACHE_CAPACITY: u64 = 256 << 20;
+
+/// Object store holding the content of every block
+const BLOCKS_STORE: &str = "blocks";
+/// Object store holding the size and time of last use of every block
+const USAGE_STORE: &str = "usage";
+
+/// A size-capped cache of verified blocks in IndexedDB, keyed by CID.
+/// The blocks which were read least recently are evicted first.
+#[derive(Clone)]
+pub struct BlockCache {
+    database: Rc<Rexie>,
+    /// Most bytes the cache may hold
+    capacity: u64,
+    /// Bytes currently held
+    used: Rc<Cell<u64>>,
+}
+
+impl Debug for BlockCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BlockCache")
+            .field("database", &self.database.name())
+            .field("capacity", &self.capacity)
+            .field("used", &self.used.get())
+            .finish()
+    }
+}
+
+impl BlockCache {
+    /// Open an IndexedDB database as a cache, creating it if it doesn't exist yet
+    pub async fn open(name: &str, capacity: u64) -> Result<Self, BlockStoreError> {
+        let database = Rexie::builder(name)
+            .version(1)
+            .add_object_store(ObjectStore::new(BLOCKS_STORE))
+            .add_object_store(ObjectStore::new(USAGE_STORE))
+            .build()
+            .await
+            .map_err(indexeddb_error)?;
+        let cache = Self {
+            database: Rc::new(database),
+            capacity,
+            used: Rc::new(Cell::new(0)),
+        };
+        let used = cache
+            .usage()
+            .await
+            .map_err(indexeddb_error)?
+            .iter()
+            .map(|entry| entry.size)
+            .sum();
+        cache.used.set(used);
+        Ok(cache)
+    }
+
+    /// Whether a block is cached
+    pub async fn contains(&self, cid: &Cid) -> bool {
+        matches!(self.read(cid).await, Ok(Some(_)))
+    }
+
+    /// Read a block, if it is cached
+    pub async fn get(&self, cid: &Cid) -> Option<Vec<u8>> {
+        let data = self.read(cid).await.ok()??;
+        // Mark the block as recently used
+        let _ = self.touch(cid, data.len() as u64).await;
+        Some(data)
+    }
+
+    /// Cache a block which has already been verified against its CID
+    pub async fn put(&self, cid: &Cid, data: &[u8]) -> Result<(), BlockStoreError> {
+        let size = data.len() as u64;
+        if size > self.capacity || self.contains(cid).await {
+            return Ok(());
+        }
+        self.write(cid, data).await.map_err(indexeddb_error)?;
+        self.used.set(self.used.get() + size);
+        if self.used.get() > self.capacity {
+            self.evict().await.map_err(indexeddb_error)?;
+        }
+        Ok(())
+    }
+
+    async fn read(&self, cid: &Cid) -> rexie::Result<Option<Vec<u8>>> {
+        let transaction = self
+            .database
+            .transaction(&[BLOCKS_STORE], TransactionMode::ReadOnly)?;
+        let value = transaction.store(BLOCKS_STORE)?.get(&key(cid)).await?;
+        transaction.done().await?;
+        Ok(value
+            .dyn_into::<Uint8Array>()
+            .ok()
+            .map(|array| array.to_vec()))
+    }
+
+    async fn write(&self, cid: &Cid, data: &[u8]) -> rexie::Result<()> {
+        let transaction = self
+            .database
+            .transaction(&[BLOCKS_STORE, USAGE_STORE], TransactionMode::ReadWrite)?;
+        transaction
+            .store(BLOCKS_STORE)?
+            .put(&Uint8Array::from(data).into(), Some(&key(cid)))
+            .await?;
+        transaction
+            .store(USAGE_STORE)?
+            .put(&usage(data.len() as u64), Some(&key(cid)))
+            .await?;
+        transaction.done().await
+    }
+
+    async fn touch(&self, cid: &Cid, size: u64) -> rexie::Result<()> {
+        let transaction = self
+            .database
+            .transaction(&[USAGE_STORE], TransactionMode::ReadWrite)?;
+        transaction
+            .store(USAGE_STORE)?
+            .put(&usage(size), Some(&key(cid)))
+            .await?;
+        transaction.done().await
+    }
+
+    /// List the blocks in the cache
+    async fn usage(&self) -> rexie::Result<Vec<Entry>> {
+        let transaction = self
+            .database
+            .transaction(&[USAGE_STORE], TransactionMode::ReadOnly)?;
+        let all = transaction
+            .store(USAGE_STORE)?
+            .get_all(None, None, None, None)
+            .await?;
+        transaction.done().await?;
+        Ok(all
+            .into_iter()
+            .filter_map(|(key, value)| {
+                let value = value.dyn_into::<Array>().ok()?;
+                Some(Entry {
+                    key,
+                    size: value.get(0).as_f64()? as u64,
+                    used_at: value.get(1).as_f64()?,
+                })
+            })
+            .collect())
+    }
+
+    /// Remove the least recently used blocks until the cache is back under 90% of its capacity
+    async fn evict(&self) -> rexie::Result<()> {
+        let mut entries = self.usage().await?;
+        entries.sort_by(|a, b| a.used_at.total_cmp(&b.used_at));
+        let mut used: u64 = entries.iter().map(|entry| entry.size).sum();
+        let target = self.capacity / 10 * 9;
+
+        let transaction = self
+            .database
+            .transaction(&[BLOCKS_STORE, USAGE_STORE], TransactionMode::ReadWrite)?;
+        let blocks = transaction.store(BLOCKS_STORE)?;
+        let usage = transaction.store(USAGE_STORE)?;
+        for entry in entries {
+            if used <= target {
+                break;
+            }
+            blocks.delete(&entry.key).await?;
+            usage.delete(&entry.key).await?;
+            used = used.saturating_sub(entry.size);
+        }
+        transaction.done().await?;
+        self.used.set(used);
+        Ok(())
+    }
+}
+
+/// A block in the cache
+struct Entry {
+    key: JsValue,
+    size: u64,
+    /// Milliseconds since the epoch
+    used_at: f64,
+}
+
+fn key(cid: &Cid) -> JsValue {
+    JsValue::from_str(&cid.to_string())
+}
+
+/// Usage record for a block of this size, used just now
+fn usage(size: u64) -> JsValue {
+    Array::of2(
+        &JsValue::from_f64(size as f64),
+        &JsValue::from_f64(Date::now()),
+    )
+    .into()
+}
+
+fn indexeddb_error(err: rexie::Error) -> BlockStoreError {
+    BlockStoreError::wnfs(Box::from(format!("IndexedDB: {err}")))
+}
@@ -1,12 +1,17 @@
 use crate::{
     blockstore::{BlockStoreError, RootedBlockStore},
-    car::{error::CarError, v1::Block, v2::CarV2},
+    car::{
+        error::CarError,
+        v1::{Block, Header},
+        v2::CarV2,
+        Streamable,
+    },
 };
 use async_trait::async_trait;
 use serde::de::Error;
 use serde::{Deserialize, Serialize};
 use std::cell::RefCell;
-use std::io::Write;
+use std::io::{Seek, SeekFrom, Write};
 use std::{borrow::Cow, io::Cursor};
 use wnfs::libipld::{Cid, IpldCodec};
 
@@ -67,6 +72,62 @@ impl CarV2MemoryBlockStore {
         self.save();
         self.data.borrow().clone().into_inner()
     }
+
+    /// Save, then look at the data in place rather than copying it
+    pub fn with_data<T>(&self, f: impl FnOnce(&[u8]) -> T) -> T {
+        self.save();
+        f(self.data.borrow().get_ref())
+    }
+}
+
+/// A state a [`CarV2MemoryBlockStore`] can be rolled back to,
+/// holding its headers and index but none of its blocks
+#[derive(Debug, Clone)]
+pub struct CarV2Mark {
+    car: CarV2,
+    /// Every byte before the first block
+    head: Vec<u8>,
+    /// Where the last block ended
+    end: u64,
+}
+
+impl CarV2MemoryBlockStore {
+    /// Mark the current state of the store, so that blocks put after it can be discarded
+    pub fn mark(&self) -> Result<CarV2Mark, BlockStoreError> {
+        self.save();
+        let data: &mut Cursor<Vec<u8>> = &mut self.data.borrow_mut();
+        let start = self.blocks_start(data)?;
+        Ok(CarV2Mark {
+            car: self.car.clone(),
+            head: data.get_ref()[..start as usize].to_vec(),
+            end: self.car.header.borrow().index_offset,
+        })
+    }
+
+    /// Discard every block put and any root set since the mark was made
+    pub fn rollback(&mut self, mark: CarV2Mark) -> Result<(), BlockStoreError> {
+        let data: &mut Cursor<Vec<u8>> = &mut self.data.borrow_mut();
+        // Saving moves every block along if the CarV1 header changed size since the mark
+        let shift = self.blocks_start(data)? as i64 - mark.head.len() as i64;
+        let start = (mark.head.len() as i64 + shift) as usize;
+        let end = (mark.end as i64 + shift) as usize;
+        let blocks = data
+            .get_ref()
+            .get(start..end)
+            .ok_or(CarError::end_of_data())?;
+        let mut rolled_back = mark.head;
+        rolled_back.extend_from_slice(blocks);
+        *data = Cursor::new(rolled_back);
+        self.car = mark.car;
+        Ok(())
+    }
+
+    /// Where the first block is, according to the CarV1 header last saved
+    fn blocks_start(&self, data: &mut Cursor<Vec<u8>>) -> Result<u64, CarError> {
+        data.seek(SeekFrom::Start(self.car.header.borrow().data_offset))?;
+        Header::read_bytes(data)?;
+        Ok(data.stream_position()?)
+    }
 }
 
 #[async_trait(?Send)]
@@ -168,6 +229,30 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    #[serial]
+    async fn rollback() -> Result<(), BlockStoreError> {
+        let mut store = CarV2MemoryBlockStore::new()?;
+        let kitty_bytes = "Hello Kitty!".as_bytes().to_vec();
+        let kitty_cid = store.put_block(kitty_bytes.clone(), IpldCodec::Raw).await?;
+        let mark = store.mark()?;
+        let marked_data = store.get_data();
+
+        // Setting the first root grows the header, moving every block when saved
+        let puppy_cid = store
+            .put_block("Hello Puppy!".as_bytes().to_vec(), IpldCodec::Raw)
+            .await?;
+        store.set_root(&puppy_cid);
+        store.save();
+
+        store.rollback(mark)?;
+        assert_eq!(store.get_root(), None);
+        assert_eq!(kitty_bytes, store.get_block(&kitty_cid).await?.to_vec());
+        assert!(store.get_block(&puppy_cid).await.is_err());
+        assert_eq!(store.get_data(), marked_data);
+        Ok(())
+    }
+
     #[tokio::test]
     #[serial]
     async fn carv2memoryblockstore() -> Result<(), BlockStoreError> {
@@ -5,6 +5,7 @@ use std::{
 };
 
 use crate::{car::error::CarError, WnfsError};
+use wnfs::libipld::Cid;
 
 #[derive(Debug)]
 pub struct BlockStoreError {
@@ -43,6 +44,22 @@ impl BlockStoreError {
             kind: BlockStoreErrorKind::Wnfs(err),
         }
     }
+
+    pub fn hash_mismatch(expected: Cid, found: Option<Cid>) -> Self {
+        Self {
+            kind: BlockStoreErrorKind::HashMismatch { expected, found },
+        }
+    }
+
+    /// Whether content was served which doesn't match the CID it was requested by
+    pub fn is_hash_mismatch(&self) -> bool {
+        matches!(self.kind, BlockStoreErrorKind::HashMismatch { .. })
+    }
+
+    /// Whether a CAR file could not be read
+    pub fn is_car(&self) -> bool {
+        matches!(self.kind, BlockStoreErrorKind::Car(_))
+    }
 }
 
 impl Display for BlockStoreError {
@@ -62,6 +79,16 @@ impl Display for BlockStoreError {
             }
             BlockStoreErrorKind::Car(err) => format!("{} {err}", "CAR ERROR:".underline()),
             BlockStoreErrorKind::Wnfs(err) => format!("{} {err}", "WNFS ERROR:".underline()),
+            BlockStoreErrorKind::HashMismatch { expected, found } => match found {
+                Some(found) => format!(
+                    "{} content served for block {expected} hashes to {found}",
+                    "HASH MISMATCH:".underline()
+                ),
+                None => format!(
+                    "{} content served for block {expected} can't be hashed the way its CID requires",
+                    "HASH MISMATCH:".underline()
+                ),
+            },
         };
 
         f.write_str(&string)
@@ -75,6 +102,7 @@ pub enum BlockStoreErrorKind {
     Exists(PathBuf),
     Car(CarError),
     Wnfs(WnfsError),
+    HashMismatch { expected: Cid, found: Option<Cid> },
 }
 
 impl From<CarError> for BlockStoreError {
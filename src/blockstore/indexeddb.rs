@@ -0,0 +1,294 @@
+use super::{BanyanBlockStore, BlockStoreError, CarV2MemoryBlockStore, RootedBlockStore};
+use crate::car::v1::Block;
+use async_trait::async_trait;
+use js_sys::Uint8Array;
+use rexie::{ObjectStore, Rexie, TransactionMode};
+use std::{borrow::Cow, cell::Cell, collections::BTreeSet, fmt::Debug, rc::Rc, str::FromStr};
+use wasm_bindgen::{JsCast, JsValue};
+use wnfs::libipld::{Cid, IpldCodec};
+
+/// Object store holding the content of every block
+const BLOCKS_STORE: &str = "blocks";
+/// Object store holding the root and any other small records kept alongside the blocks
+const STATE_STORE: &str = "state";
+/// Key of the root CID in the state store
+const ROOT_KEY: &str = "root";
+
+/// A BlockStore kept in IndexedDB, so that its blocks outlive the page that wrote them.
+/// The root is held in memory once set, and only written to IndexedDB on `save`.
+#[derive(Clone)]
+pub struct IndexedDbBlockStore {
+    database: Rc<Rexie>,
+    root: Rc<Cell<Option<Cid>>>,
+}
+
+impl Debug for IndexedDbBlockStore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("IndexedDbBlockStore")
+            .field("database", &self.database.name())
+            .field("root", &self.root.get())
+            .finish()
+    }
+}
+
+impl IndexedDbBlockStore {
+    /// Open an IndexedDB database as a BlockStore, creating it if it doesn't exist yet
+    pub async fn open(name: &str) -> Result<Self, BlockStoreError> {
+        let database = Rexie::builder(name)
+            .version(1)
+            .add_object_store(ObjectStore::new(BLOCKS_STORE))
+            .add_object_store(ObjectStore::new(STATE_STORE))
+            .build()
+            .await
+            .map_err(indexeddb_error)?;
+        let store = Self {
+            database: Rc::new(database),
+            root: Rc::new(Cell::new(None)),
+        };
+        let root = store
+            .get_state(ROOT_KEY)
+            .await?
+            .and_then(|root| Cid::from_str(&root).ok());
+        store.root.set(root);
+        Ok(store)
+    }
+
+    /// Write the root to IndexedDB
+    pub async fn save(&self) -> Result<(), BlockStoreError> {
+        match self.root.get() {
+            Some(root) => self.put_state(ROOT_KEY, &root.to_string()).await,
+            None => Ok(()),
+        }
+    }
+
+    /// Read a record kept alongside the blocks, if there is one
+    pub async fn get_state(&self, key: &str) -> Result<Option<String>, BlockStoreError> {
+        let transaction = self
+            .database
+            .transaction(&[STATE_STORE], TransactionMode::ReadOnly)
+            .map_err(indexeddb_error)?;
+        let value = transaction
+            .store(STATE_STORE)
+            .map_err(indexeddb_error)?
+            .get(&JsValue::from_str(key))
+            .await
+            .map_err(indexeddb_error)?;
+        transaction.done().await.map_err(indexeddb_error)?;
+        Ok(value.as_string())
+    }
+
+    /// Keep a record alongside the blocks
+    pub async fn put_state(&self, key: &str, value: &str) -> Result<(), BlockStoreError> {
+        let transaction = self
+            .database
+            .transaction(&[STATE_STORE], TransactionMode::ReadWrite)
+            .map_err(indexeddb_error)?;
+        transaction
+            .store(STATE_STORE)
+            .map_err(indexeddb_error)?
+            .put(&JsValue::from_str(value), Some(&JsValue::from_str(key)))
+            .await
+            .map_err(indexeddb_error)?;
+        transaction.done().await.map_err(indexeddb_error)
+    }
+
+    /// The CIDs of every block held
+    pub async fn cids(&self) -> Result<BTreeSet<Cid>, BlockStoreError> {
+        let transaction = self
+            .database
+            .transaction(&[BLOCKS_STORE], TransactionMode::ReadOnly)
+            .map_err(indexeddb_error)?;
+        let keys = transaction
+            .store(BLOCKS_STORE)
+            .map_err(indexeddb_error)?
+            .get_all_keys(None, None)
+            .await
+            .map_err(indexeddb_error)?;
+        transaction.done().await.map_err(indexeddb_error)?;
+        Ok(keys.iter().filter_map(cid).collect())
+    }
+
+    /// Remove every block, keeping the root
+    pub async fn clear(&self) -> Result<(), BlockStoreError> {
+        let transaction = self
+            .database
+            .transaction(&[BLOCKS_STORE], TransactionMode::ReadWrite)
+            .map_err(indexeddb_error)?;
+        transaction
+            .store(BLOCKS_STORE)
+            .map_err(indexeddb_error)?
+            .clear()
+            .await
+            .map_err(indexeddb_error)?;
+        transaction.done().await.map_err(indexeddb_error)
+    }
+
+    /// Remove every block but those given
+    pub async fn retain(&self, cids: &BTreeSet<Cid>) -> Result<(), BlockStoreError> {
+        let transaction = self
+            .database
+            .transaction(&[BLOCKS_STORE], TransactionMode::ReadWrite)
+            .map_err(indexeddb_error)?;
+        let blocks = transaction.store(BLOCKS_STORE).map_err(indexeddb_error)?;
+        let held: BTreeSet<Cid> = blocks
+            .get_all_keys(None, None)
+            .await
+            .map_err(indexeddb_error)?
+            .iter()
+            .filter_map(cid)
+            .collect();
+        for stale in held.difference(cids) {
+            blocks.delete(&key(stale)).await.map_err(indexeddb_error)?;
+        }
+        transaction.done().await.map_err(indexeddb_error)
+    }
+
+    /// Hold exactly the blocks of a CAR and take on its root, all in a single transaction.
+    /// Blocks the CAR doesn't hold are removed, so versions moved on from don't build up.
+    pub async fn save_car(&self, car: &CarV2MemoryBlockStore) -> Result<(), BlockStoreError> {
+        let cids: BTreeSet<Cid> = car
+            .car
+            .car
+            .index
+            .borrow()
+            .get_all_cids()
+            .into_iter()
+            .collect();
+        let transaction = self
+            .database
+            .transaction(&[BLOCKS_STORE, STATE_STORE], TransactionMode::ReadWrite)
+            .map_err(indexeddb_error)?;
+        let blocks = transaction.store(BLOCKS_STORE).map_err(indexeddb_error)?;
+        let held: BTreeSet<Cid> = blocks
+            .get_all_keys(None, None)
+            .await
+            .map_err(indexeddb_error)?
+            .iter()
+            .filter_map(cid)
+            .collect();
+        for stale in held.difference(&cids) {
+            blocks.delete(&key(stale)).await.map_err(indexeddb_error)?;
+        }
+        for missing in cids.difference(&held) {
+            let content = car.get_block(missing).await?;
+            blocks
+                .put(
+                    &Uint8Array::from(content.as_slice()).into(),
+                    Some(&key(missing)),
+                )
+                .await
+                .map_err(indexeddb_error)?;
+        }
+        if let Some(root) = car.get_root() {
+            transaction
+                .store(STATE_STORE)
+                .map_err(indexeddb_error)?
+                .put(
+                    &JsValue::from_str(&root.to_string()),
+                    Some(&JsValue::from_str(ROOT_KEY)),
+                )
+                .await
+                .map_err(indexeddb_error)?;
+            self.set_root(&root);
+        }
+        transaction.done().await.map_err(indexeddb_error)
+    }
+
+    /// Copy every block into a CAR held in memory, with the same root
+    pub async fn to_car(&self) -> Result<CarV2MemoryBlockStore, BlockStoreError> {
+        let transaction = self
+            .database
+            .transaction(&[BLOCKS_STORE], TransactionMode::ReadOnly)
+            .map_err(indexeddb_error)?;
+        let blocks = transaction
+            .store(BLOCKS_STORE)
+            .map_err(indexeddb_error)?
+            .get_all(None, None, None, None)
+            .await
+            .map_err(indexeddb_error)?;
+        transaction.done().await.map_err(indexeddb_error)?;
+
+        let car = CarV2MemoryBlockStore::new()?;
+        for (key, content) in blocks {
+            let Some(cid) = cid(&key) else {
+                continue;
+            };
+            let content = content
+                .dyn_into::<Uint8Array>()
+                .map_err(|_| BlockStoreError::wnfs(Box::from(format!("block {cid} is not bytes"))))?
+                .to_vec();
+            let codec = IpldCodec::try_from(cid.codec()).map_err(|err| {
+                BlockStoreError::wnfs(Box::from(format!("unknown codec in {cid}: {err}")))
+            })?;
+            car.put_block(content, codec).await?;
+        }
+        if let Some(root) = self.root.get() {
+            car.set_root(&root);
+        }
+        Ok(car)
+    }
+
+    async fn write(&self, cid: &Cid, content: &[u8]) -> Result<(), BlockStoreError> {
+        let transaction = self
+            .database
+            .transaction(&[BLOCKS_STORE], TransactionMode::ReadWrite)
+            .map_err(indexeddb_error)?;
+        transaction
+            .store(BLOCKS_STORE)
+            .map_err(indexeddb_error)?
+            .put(&Uint8Array::from(content).into(), Some(&key(cid)))
+            .await
+            .map_err(indexeddb_error)?;
+        transaction.done().await.map_err(indexeddb_error)
+    }
+}
+
+#[async_trait(?Send)]
+impl BanyanBlockStore for IndexedDbBlockStore {
+    async fn get_block(&self, cid: &Cid) -> Result<Cow<'_, Vec<u8>>, BlockStoreError> {
+        let transaction = self
+            .database
+            .transaction(&[BLOCKS_STORE], TransactionMode::ReadOnly)
+            .map_err(indexeddb_error)?;
+        let value = transaction
+            .store(BLOCKS_STORE)
+            .map_err(indexeddb_error)?
+            .get(&key(cid))
+            .await
+            .map_err(indexeddb_error)?;
+        transaction.done().await.map_err(indexeddb_error)?;
+        value
+            .dyn_into::<Uint8Array>()
+            .map(|array| Cow::Owned(array.to_vec()))
+            .map_err(|_| BlockStoreError::wnfs(Box::from(format!("no block {cid} in IndexedDB"))))
+    }
+
+    async fn put_block(&self, content: Vec<u8>, codec: IpldCodec) -> Result<Cid, BlockStoreError> {
+        let block = Block::new(content, codec)?;
+        self.write(&block.cid, &block.content).await?;
+        Ok(block.cid)
+    }
+}
+
+#[async_trait(?Send)]
+impl RootedBlockStore for IndexedDbBlockStore {
+    fn get_root(&self) -> Option<Cid> {
+        self.root.get()
+    }
+
+    fn set_root(&self, root: &Cid) {
+        self.root.set(Some(*root))
+    }
+}
+
+fn key(cid: &Cid) -> JsValue {
+    JsValue::from_str(&cid.to_string())
+}
+
+fn cid(key: &JsValue) -> Option<Cid> {
+    Cid::from_str(&key.as_string()?).ok()
+}
+
+fn indexeddb_error(err: rexie::Error) -> BlockStoreError {
+    BlockStoreError::wnfs(Box::from(format!("IndexedDB: {err}")))
+}
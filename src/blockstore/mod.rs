@@ -1,8 +1,14 @@
 mod api;
 #[cfg(not(target_arch = "wasm32"))]
+mod cache_disk;
+#[cfg(target_arch = "wasm32")]
+mod cache_indexeddb;
+#[cfg(not(target_arch = "wasm32"))]
 mod carv2_disk;
 mod carv2_memory;
 mod error;
+#[cfg(target_arch = "wasm32")]
+mod indexeddb;
 mod memory;
 #[cfg(not(target_arch = "wasm32"))]
 mod multi_carv2_disk;
@@ -11,9 +17,15 @@ mod wnfs_trait;
 
 pub use api::BanyanApiBlockStore;
 #[cfg(not(target_arch = "wasm32"))]
+pub use cache_disk::{BlockCache, DEFAULT_CACHE_CAPACITY};
+#[cfg(target_arch = "wasm32")]
+pub use cache_indexeddb::{BlockCache, DEFAULT_CACHE_CAPACITY};
+#[cfg(not(target_arch = "wasm32"))]
 pub use carv2_disk::CarV2DiskBlockStore;
-pub use carv2_memory::CarV2MemoryBlockStore;
+pub use carv2_memory::{CarV2Mark, CarV2MemoryBlockStore};
 pub(crate) use error::BlockStoreError;
+#[cfg(target_arch = "wasm32")]
+pub use indexeddb::IndexedDbBlockStore;
 pub use memory::MemoryBlockStore;
 #[cfg(not(target_arch = "wasm32"))]
 pub use multi_carv2_disk::MultiCarV2DiskBlockStore;
@@ -1,6 +1,6 @@
 use super::{BanyanBlockStore, CarV2DiskBlockStore};
 use crate::{
-    api::requests::staging::upload::content::{ContentType, UploadContent},
+    api::requests::staging::upload::content::{ContentSource, UploadContent},
     blockstore::{BlockStoreError, RootedBlockStore},
     car::error::CarError,
     WnfsError,
@@ -12,6 +12,7 @@ use std::{
     borrow::Cow,
     path::{Path, PathBuf},
 };
+use tokio_util::io::ReaderStream;
 use wnfs::libipld::{Cid, IpldCodec};
 
 /// CARv2 MultiCarV2DiskBlockStore across multiple CAR files using File IO
@@ -157,8 +158,9 @@ impl UploadContent for MultiCarV2DiskBlockStore {
         Ok(hasher.finalize().to_string())
     }
 
-    async fn get_body(&self) -> Result<ContentType, Self::UploadError> {
-        Ok(tokio::fs::File::open(&self.get_delta()?.path).await?.into())
+    async fn get_body(&self) -> Result<ContentSource, Self::UploadError> {
+        let file = tokio::fs::File::open(&self.get_delta()?.path).await?;
+        Ok(Box::pin(ReaderStream::new(file)))
     }
 
     fn get_length(&self) -> Result<u64, Self::UploadError> {
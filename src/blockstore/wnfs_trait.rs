@@ -9,6 +9,8 @@ use crate::blockstore::{
     BanyanApiBlockStore, BlockStoreError, CarV2MemoryBlockStore, MemoryBlockStore,
 };
 
+#[cfg(target_arch = "wasm32")]
+use crate::blockstore::IndexedDbBlockStore;
 #[cfg(not(target_arch = "wasm32"))]
 use crate::blockstore::{CarV2DiskBlockStore, MultiCarV2DiskBlockStore};
 
@@ -48,3 +50,5 @@ impl_wnfs_blockstore!(CarV2MemoryBlockStore);
 impl_wnfs_blockstore!(CarV2DiskBlockStore);
 #[cfg(not(target_arch = "wasm32"))]
 impl_wnfs_blockstore!(MultiCarV2DiskBlockStore);
+#[cfg(target_arch = "wasm32")]
+impl_wnfs_blockstore!(IndexedDbBlockStore);
@@ -23,10 +23,8 @@ pub struct Block {
 impl Block {
     /// Given some data, create a Cid and varint to match
     pub fn new(content: Vec<u8>, codec: IpldCodec) -> Result<Self, CarError> {
-        // Compute the SHA256 hash of the bytes
-        let hash = Code::Sha2_256.digest(&content);
-        // Represent the hash as a CID V1
-        let cid = Cid::new_v1(codec.into(), hash);
+        // Identify the content by its SHA256 hash
+        let cid = Self::cid(&content, codec, Code::Sha2_256);
         let varint = (cid.encoded_len() + content.len()) as u128;
         // Create new
         Ok(Self {
@@ -36,6 +34,11 @@ impl Block {
         })
     }
 
+    /// Represent the hash of some content as a CID V1
+    pub fn cid(content: &[u8], codec: IpldCodec, code: Code) -> Cid {
+        Cid::new_v1(codec.into(), code.digest(content))
+    }
+
     /// Read the Varint and Cid from stream only
     pub fn start_read<R: Read + Seek>(mut r: R) -> Result<(u128, Cid), CarError> {
         // Read the varint
@@ -1,6 +1,15 @@
 use clap::{command, Parser};
 
 use super::{commands::TombCommand, verbosity::MyVerbosity};
+use crate::native::{
+    configuration::{
+        globalconfig::{validate_profile_name, Selection},
+        xdg::Homes,
+    },
+    NativeError,
+};
+use bytesize::ByteSize;
+use std::path::PathBuf;
 
 /// Arguments to tomb
 #[derive(Parser, Debug)]
@@ -12,4 +21,39 @@ pub struct Args {
     /// Verbosity level.
     #[arg(short, long, help = "verbosity level", default_value = "normal")]
     pub verbose: MyVerbosity,
+    /// Profile to use, instead of the one set with `profile use`. Also read from BANYAN_PROFILE
+    #[arg(long, global = true)]
+    pub profile: Option<String>,
+    /// Directory to keep all configuration and data in. Also read from BANYAN_HOME
+    #[arg(long, global = true)]
+    pub config_dir: Option<PathBuf>,
+    /// Most bytes uploaded per second, like 2MiB, or 0 for no limit. Also read from BANYAN_LIMIT_UPLOAD
+    #[arg(long, global = true)]
+    pub limit_upload: Option<ByteSize>,
+    /// Most bytes downloaded per second, like 2MiB, or 0 for no limit. Also read from BANYAN_LIMIT_DOWNLOAD
+    #[arg(long, global = true)]
+    pub limit_download: Option<ByteSize>,
+}
+
+impl Args {
+    /// The configuration every command runs with: the requested directory, profile and rate limits,
+    /// falling back on the environment for anything not given
+    pub fn selection(&self) -> Result<Selection, NativeError> {
+        let homes = match &self.config_dir {
+            Some(config_dir) => Homes::within(config_dir)?,
+            None => Homes::from_env()?,
+        };
+        let mut selection = Selection::with_env(homes)?;
+        if let Some(profile) = &self.profile {
+            validate_profile_name(profile)?;
+            selection.profile = Some(profile.clone());
+        }
+        if let Some(limit) = &self.limit_upload {
+            selection.limit_upload = Some(limit.as_u64());
+        }
+        if let Some(limit) = &self.limit_download {
+            selection.limit_download = Some(limit.as_u64());
+        }
+        Ok(selection)
+    }
 }
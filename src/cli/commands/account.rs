@@ -1,4 +1,4 @@
-use super::RunnableCommand;
+use super::{DevicesCommand, RunnableCommand};
 use crate::{
     api::{
         client::Credentials,
@@ -35,18 +35,23 @@ pub enum AccountCommand {
     WhoAmI,
     /// Get info about Account usage
     Usage,
+    /// Manage the Devices registered to this Account
+    Devices {
+        /// Subcommand
+        #[clap(subcommand)]
+        command: DevicesCommand,
+    },
 }
 
 #[async_trait(?Send)]
 impl RunnableCommand<NativeError> for AccountCommand {
-    async fn run_internal(self) -> Result<String, NativeError> {
-        let mut global = GlobalConfig::from_disk().await?;
+    async fn run_internal(self, mut global: GlobalConfig) -> Result<String, NativeError> {
         let mut client = global.get_client().await?;
 
         // Process the command
         match self {
             AccountCommand::RegisterDevice => {
-                let private_device_key = GlobalConfig::from_disk().await?.api_key().await?;
+                let private_device_key = global.api_key().await?;
                 let public_device_key = private_device_key.public_key()?;
 
                 // If this device api key is alreaddy registered
@@ -176,6 +181,7 @@ impl RunnableCommand<NativeError> for AccountCommand {
 
                 Ok(output)
             }
+            AccountCommand::Devices { command } => command.run_internal(global).await,
         }
     }
 }
@@ -1,6 +1,7 @@
 use super::RunnableCommand;
 use crate::native::{configuration::globalconfig::GlobalConfig, NativeError};
 use async_trait::async_trait;
+use bytesize::ByteSize;
 use clap::Subcommand;
 use colored::Colorize;
 use reqwest::Url;
@@ -18,12 +19,20 @@ pub enum ApiCommand {
     },
     /// Return the endpoint to the original values
     Reset,
+    /// Display or set limits on how fast data is transferred
+    Limit {
+        /// Most bytes uploaded per second, like 2MiB, or 0 for no limit
+        #[arg(long)]
+        upload: Option<ByteSize>,
+        /// Most bytes downloaded per second, like 2MiB, or 0 for no limit
+        #[arg(long)]
+        download: Option<ByteSize>,
+    },
 }
 
 #[async_trait(?Send)]
 impl RunnableCommand<NativeError> for ApiCommand {
-    async fn run_internal(self) -> Result<String, NativeError> {
-        let mut global = GlobalConfig::from_disk().await?;
+    async fn run_internal(self, mut global: GlobalConfig) -> Result<String, NativeError> {
         match self {
             ApiCommand::Display => Ok(format!(
                 "{}\n{}\n",
@@ -44,6 +53,26 @@ impl RunnableCommand<NativeError> for ApiCommand {
                 global.set_endpoint(endpoint)?;
                 Ok(format!("{}", "<< ENDPOINTS HAVE BEEN RESET >>".green()))
             }
+            ApiCommand::Limit { upload, download } => {
+                if upload.is_some() || download.is_some() {
+                    global.set_bandwidth(
+                        upload.map(|limit| limit.as_u64()),
+                        download.map(|limit| limit.as_u64()),
+                    )?;
+                }
+                let bandwidth = global.bandwidth()?;
+                let display = |limit: Option<u64>| {
+                    limit.map_or("unlimited".to_string(), |rate| {
+                        format!("{}/s", ByteSize(rate))
+                    })
+                };
+                Ok(format!(
+                    "{}\nupload:\t\t{}\ndownload:\t{}",
+                    "| BANDWIDTH LIMITS |".yellow(),
+                    display(bandwidth.upload),
+                    display(bandwidth.download)
+                ))
+            }
         }
     }
 }
@@ -0,0 +1,148 @@
+use super::RunnableCommand;
+use crate::{
+    api::{
+        client::{Client, Credentials},
+        models::device_api_key::DeviceApiKey,
+    },
+    native::{
+        configuration::{globalconfig::GlobalConfig, keys::new_api_key},
+        NativeError,
+    },
+};
+use async_trait::async_trait;
+use clap::Subcommand;
+use colored::Colorize;
+use tomb_crypt::{
+    hex_fingerprint,
+    prelude::{EcSignatureKey, PrivateKey, PublicKey},
+};
+use uuid::Uuid;
+
+/// Subcommand for Device API Keys
+#[derive(Subcommand, Clone, Debug)]
+pub enum DevicesCommand {
+    /// List all Devices registered to this Account
+    Ls,
+    /// Get information about an individual Device
+    Info {
+        /// Device Key fingerprint
+        #[arg(short, long)]
+        fingerprint: String,
+    },
+    /// Revoke a Device's access to this Account
+    Revoke {
+        /// Device Key fingerprint
+        #[arg(short, long)]
+        fingerprint: String,
+        /// Allow revoking the key this device is currently using
+        #[arg(long, default_value_t = false)]
+        force: bool,
+        /// When revoking this device's own key, register a freshly generated one in its place
+        #[arg(long, default_value_t = false)]
+        reregister: bool,
+    },
+}
+
+#[async_trait(?Send)]
+impl RunnableCommand<NativeError> for DevicesCommand {
+    async fn run_internal(self, mut global: GlobalConfig) -> Result<String, NativeError> {
+        let mut client = global.get_client().await?;
+        let my_fingerprint = hex_fingerprint(
+            global
+                .api_key()
+                .await?
+                .public_key()?
+                .fingerprint()
+                .await?
+                .as_slice(),
+        );
+
+        match self {
+            DevicesCommand::Ls => DeviceApiKey::read_all(&mut client)
+                .await
+                .map(|keys| {
+                    keys.iter().fold(String::new(), |acc, key| {
+                        format!("{}\n\n{}", acc, key.context_fmt(&my_fingerprint))
+                    })
+                })
+                .map_err(NativeError::api),
+            DevicesCommand::Info { fingerprint } => {
+                let id = get_device_id(&mut client, &fingerprint).await?;
+                DeviceApiKey::read(&mut client, id)
+                    .await
+                    .map(|key| key.context_fmt(&my_fingerprint))
+                    .map_err(NativeError::api)
+            }
+            DevicesCommand::Revoke {
+                fingerprint,
+                force,
+                reregister,
+            } => {
+                let id = get_device_id(&mut client, &fingerprint).await?;
+                let is_current = fingerprint == my_fingerprint;
+                if is_current && !force {
+                    return Err(NativeError::custom_error(
+                        "This is the key this device is using. Pass --force to revoke it anyway.",
+                    ));
+                }
+
+                // Register the replacement while the current key can still authenticate
+                let replacement = if is_current && reregister {
+                    let api_key = EcSignatureKey::generate().await?;
+                    let pem = String::from_utf8(api_key.public_key()?.export().await?)?;
+                    let device_key = DeviceApiKey::create(pem, &mut client).await?;
+                    Some((api_key, device_key))
+                } else {
+                    None
+                };
+
+                DeviceApiKey::delete_by_id(&mut client, id).await?;
+                let mut output = format!(
+                    "{}\nfingerprint:\t{}",
+                    "<< REVOKED DEVICE KEY SUCCESSFULLY >>".green(),
+                    fingerprint
+                );
+
+                if is_current {
+                    match replacement {
+                        // Switch this device over to the new key
+                        Some((api_key, device_key)) => {
+                            let credentials = Credentials::new(client.subject()?, api_key)
+                                .map_err(|_| NativeError::bad_data())?;
+                            client.with_credentials(credentials);
+                            global.save_client(client).await?;
+                            output = format!(
+                                "{output}\nnew_device_key_fingerprint:\t{}",
+                                device_key.fingerprint
+                            );
+                        }
+                        // This device can no longer authenticate, so give it a fresh key to register with
+                        None => {
+                            client.logout();
+                            global.save_client(client).await?;
+                            new_api_key(&global.api_key_path).await?;
+                            output = format!(
+                                "{output}\n{}",
+                                "This device has been logged out. Run `banyan account register-device` to log back in.".yellow()
+                            );
+                        }
+                    }
+                }
+
+                Ok(output)
+            }
+        }
+    }
+}
+
+/// Find the id of the Device Key with a given fingerprint
+async fn get_device_id(client: &mut Client, fingerprint: &str) -> Result<Uuid, NativeError> {
+    DeviceApiKey::read_all(client)
+        .await?
+        .into_iter()
+        .find(|key| key.fingerprint == fingerprint)
+        .map(|key| key.id)
+        .ok_or(NativeError::custom_error(
+            "No Device Key with that fingerprint is registered to this Account",
+        ))
+}
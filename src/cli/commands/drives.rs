@@ -1,11 +1,13 @@
 use crate::{
+    api::models::bucket::{BucketType, StorageClass},
     cli::{
-        commands::{prompt_for_bool, KeyCommand, MetadataCommand, RunnableCommand},
+        commands::{prompt_for_bool, FsCommand, KeyCommand, MetadataCommand, RunnableCommand},
+        progress::TerminalProgress,
         specifiers::DriveSpecifier,
     },
     native::{
         configuration::globalconfig::GlobalConfig,
-        operations::{prepare, restore},
+        operations::{prepare, restore, usage, verify},
         sync::OmniBucket,
         NativeError,
     },
@@ -29,6 +31,12 @@ pub enum DrivesCommand {
         /// Drive Root
         #[arg(short, long)]
         origin: Option<PathBuf>,
+        /// What the Drive is used for: interactive or backup
+        #[arg(long = "type", default_value_t = BucketType::Interactive)]
+        drive_type: BucketType,
+        /// Storage class: hot, warm or cold. Backups default to cold, everything else to hot
+        #[arg(long)]
+        storage_class: Option<StorageClass>,
     },
     /// Prepare a Drive for Pushing by encrypting new data
     Prepare {
@@ -47,13 +55,41 @@ pub enum DrivesCommand {
         drive_specifier: DriveSpecifier,
     },
     /// Sync Drive data to or from remote
-    Sync(DriveSpecifier),
+    Sync {
+        /// Drive in question
+        #[clap(flatten)]
+        drive_specifier: DriveSpecifier,
+
+        /// Snapshot the Drive once its content is uploaded. On by default for backups
+        #[arg(long, num_args = 0..=1, default_missing_value = "true")]
+        snapshot: Option<bool>,
+    },
+    /// Change the storage class of a Drive
+    Update {
+        /// Drive in question
+        #[clap(flatten)]
+        drive_specifier: DriveSpecifier,
+
+        /// Storage class: hot, warm or cold
+        #[arg(long)]
+        storage_class: StorageClass,
+    },
+    /// Check that remote storage serves every block of a Drive intact
+    Verify(DriveSpecifier),
     /// Delete a Drive
     Delete(DriveSpecifier),
     /// Drive info
     Info(DriveSpecifier),
     /// Drive data usage
-    Usage(DriveSpecifier),
+    Usage {
+        /// Drive in question
+        #[clap(flatten)]
+        drive_specifier: DriveSpecifier,
+
+        /// Break usage down by directory, file type and version, from the local copy of the Drive
+        #[arg(long)]
+        breakdown: bool,
+    },
     /// Get information on Drive Metadata
     Metadata {
         /// Subcommand
@@ -66,15 +102,21 @@ pub enum DrivesCommand {
         #[clap(subcommand)]
         subcommand: KeyCommand,
     },
+    /// Files and directories within a Drive
+    Fs {
+        /// Subcommand
+        #[clap(subcommand)]
+        subcommand: FsCommand,
+    },
 }
 
 #[async_trait(?Send)]
 impl RunnableCommand<NativeError> for DrivesCommand {
-    async fn run_internal(self) -> Result<String, NativeError> {
+    async fn run_internal(self, global: GlobalConfig) -> Result<String, NativeError> {
         match self {
             // List all Buckets tracked remotely and locally
             DrivesCommand::Ls => {
-                let omnis = OmniBucket::ls().await?;
+                let omnis = OmniBucket::ls(&global).await?;
                 if !omnis.is_empty() {
                     Ok(omnis
                         .iter()
@@ -84,9 +126,17 @@ impl RunnableCommand<NativeError> for DrivesCommand {
                 }
             }
             // Create a new Bucket. This attempts to create the Bucket both locally and remotely, but settles for a simple local creation if remote permissions fail
-            DrivesCommand::Create { name, origin } => {
+            DrivesCommand::Create {
+                name,
+                origin,
+                drive_type,
+                storage_class,
+            } => {
                 let origin = origin.unwrap_or(current_dir()?);
-                let omni = OmniBucket::create(&name, &origin).await?;
+                let storage_class =
+                    storage_class.unwrap_or_else(|| drive_type.default_storage_class());
+                let omni =
+                    OmniBucket::create(&global, &name, &origin, drive_type, storage_class).await?;
                 let output = format!("{}\n{}", "<< NEW DRIVE CREATED >>".green(), omni);
                 Ok(output)
             }
@@ -95,34 +145,93 @@ impl RunnableCommand<NativeError> for DrivesCommand {
                 follow_links,
             } => {
                 prepare::pipeline(
-                    OmniBucket::from_specifier(&drive_specifier).await,
+                    &global,
+                    OmniBucket::from_specifier(&global, &drive_specifier).await,
                     follow_links,
+                    &TerminalProgress::default(),
                 )
                 .await
             }
             DrivesCommand::Restore { drive_specifier } => {
-                restore::pipeline(OmniBucket::from_specifier(&drive_specifier).await).await
+                restore::pipeline(
+                    &global,
+                    OmniBucket::from_specifier(&global, &drive_specifier).await,
+                    &TerminalProgress::default(),
+                )
+                .await
+            }
+            DrivesCommand::Sync {
+                drive_specifier,
+                snapshot,
+            } => {
+                OmniBucket::from_specifier(&global, &drive_specifier)
+                    .await
+                    .sync_bucket(&global, snapshot, &TerminalProgress::default())
+                    .await
             }
-            DrivesCommand::Sync(drive_specifier) => {
-                OmniBucket::from_specifier(&drive_specifier)
+            DrivesCommand::Update {
+                drive_specifier,
+                storage_class,
+            } => {
+                OmniBucket::from_specifier(&global, &drive_specifier)
                     .await
-                    .sync_bucket()
+                    .update_storage_class(&global, storage_class)
                     .await
             }
+            DrivesCommand::Verify(drive_specifier) => {
+                verify::pipeline(
+                    &global,
+                    OmniBucket::from_specifier(&global, &drive_specifier).await,
+                )
+                .await
+            }
             DrivesCommand::Delete(drive_specifier) => {
-                let omni = OmniBucket::from_specifier(&drive_specifier).await;
+                let omni = OmniBucket::from_specifier(&global, &drive_specifier).await;
                 let local_deletion = prompt_for_bool("Do you want to delete this Bucket locally?");
                 let remote_deletion =
                     prompt_for_bool("Do you want to delete this Bucket remotely?");
-                omni.delete(local_deletion, remote_deletion).await
+                omni.delete(&global, local_deletion, remote_deletion).await
             }
             DrivesCommand::Info(drive_specifier) => {
-                let omni = OmniBucket::from_specifier(&drive_specifier).await;
+                let omni = OmniBucket::from_specifier(&global, &drive_specifier).await;
                 Ok(format!("{omni}"))
             }
-            DrivesCommand::Usage(drive_specifier) => {
-                let mut client = GlobalConfig::from_disk().await?.get_client().await?;
-                let remote = OmniBucket::from_specifier(&drive_specifier)
+            DrivesCommand::Usage {
+                drive_specifier,
+                breakdown: true,
+            } => {
+                let local = OmniBucket::from_specifier(&global, &drive_specifier)
+                    .await
+                    .get_local()?;
+                let breakdown = usage::breakdown(&global, &local).await?;
+                let mut output = format!("{}", "| USAGE BREAKDOWN |".blue());
+                output = format!("{output}\nby directory:");
+                for (directory, size) in &breakdown.by_directory {
+                    output = format!("{output}\n{directory}\t{}", ByteSize(*size));
+                }
+                output = format!("{output}\nby file type:");
+                for (mime_type, size) in &breakdown.by_mime_type {
+                    output = format!("{output}\n{mime_type}\t{}", ByteSize(*size));
+                }
+                output = format!("{output}\nby version:");
+                for (age, size) in &breakdown.by_version {
+                    let version = match age {
+                        0 => "current".to_string(),
+                        age => format!("-{age}"),
+                    };
+                    output = format!("{output}\n{version}\t{}", ByteSize(*size));
+                }
+                Ok(format!(
+                    "{output}\nblocks:\t\t{}\nstored:\t\t{}",
+                    breakdown.blocks,
+                    ByteSize(breakdown.stored)
+                ))
+            }
+            DrivesCommand::Usage {
+                drive_specifier, ..
+            } => {
+                let mut client = global.get_client().await?;
+                let remote = OmniBucket::from_specifier(&global, &drive_specifier)
                     .await
                     .get_remote()?;
                 remote
@@ -138,8 +247,9 @@ impl RunnableCommand<NativeError> for DrivesCommand {
                     })
                     .map_err(NativeError::api)
             }
-            DrivesCommand::Metadata { subcommand } => subcommand.run_internal().await,
-            DrivesCommand::Keys { subcommand } => subcommand.run_internal().await,
+            DrivesCommand::Metadata { subcommand } => subcommand.run_internal(global).await,
+            DrivesCommand::Keys { subcommand } => subcommand.run_internal(global).await,
+            DrivesCommand::Fs { subcommand } => subcommand.run_internal(global).await,
         }
     }
 }
@@ -0,0 +1,91 @@
+use crate::{
+    cli::{commands::RunnableCommand, specifiers::DriveSpecifier},
+    native::{
+        configuration::globalconfig::GlobalConfig, operations::tag, sync::OmniBucket, NativeError,
+    },
+};
+use async_trait::async_trait;
+use clap::Subcommand;
+use std::path::PathBuf;
+
+/// Subcommand for the files and directories within a Drive
+#[derive(Subcommand, Clone, Debug)]
+pub enum FsCommand {
+    /// Read and write tags on files
+    Meta {
+        /// Subcommand
+        #[clap(subcommand)]
+        subcommand: MetaCommand,
+    },
+}
+
+/// Subcommand for file tags
+#[derive(Subcommand, Clone, Debug)]
+pub enum MetaCommand {
+    /// Show the tags on a file or directory
+    Get {
+        /// Drive in question
+        #[clap(flatten)]
+        drive_specifier: DriveSpecifier,
+        /// Path within the Drive
+        path: PathBuf,
+        /// Only show this tag
+        key: Option<String>,
+    },
+    /// Tag a file
+    Set {
+        /// Drive in question
+        #[clap(flatten)]
+        drive_specifier: DriveSpecifier,
+        /// Path within the Drive
+        path: PathBuf,
+        /// Name of the tag
+        key: String,
+        /// Value of the tag. Leave it out to remove the tag
+        value: Option<String>,
+    },
+}
+
+#[async_trait(?Send)]
+impl RunnableCommand<NativeError> for FsCommand {
+    async fn run_internal(self, global: GlobalConfig) -> Result<String, NativeError> {
+        match self {
+            FsCommand::Meta { subcommand } => subcommand.run_internal(global).await,
+        }
+    }
+}
+
+#[async_trait(?Send)]
+impl RunnableCommand<NativeError> for MetaCommand {
+    async fn run_internal(self, global: GlobalConfig) -> Result<String, NativeError> {
+        match self {
+            MetaCommand::Get {
+                drive_specifier,
+                path,
+                key,
+            } => {
+                let local = OmniBucket::from_specifier(&global, &drive_specifier)
+                    .await
+                    .get_local()?;
+                tag::get(&global, &local, &path, key).await
+            }
+            MetaCommand::Set {
+                drive_specifier,
+                path,
+                key,
+                value,
+            } => {
+                let local = OmniBucket::from_specifier(&global, &drive_specifier)
+                    .await
+                    .get_local()?;
+                let removed = value.is_none();
+                tag::set(&global, local, &path, &key, value).await?;
+                if removed {
+                    Ok(format!("removed {key} from {}", path.display()))
+                } else {
+                    Ok(format!("tagged {} with {key}", path.display()))
+                }
+            }
+        }
+    }
+}
@@ -1,6 +1,11 @@
 use crate::{
     api::{client::Client, models::bucket_key::BucketKey},
-    native::{configuration::globalconfig::GlobalConfig, sync::OmniBucket, NativeError},
+    cli::progress::TerminalProgress,
+    native::{
+        configuration::{globalconfig::GlobalConfig, lock::FileLock},
+        sync::{LocalBucket, OmniBucket, SyncState},
+        NativeError,
+    },
 };
 
 use super::{
@@ -12,7 +17,7 @@ use clap::Subcommand;
 use colored::Colorize;
 use tomb_crypt::{
     hex_fingerprint,
-    prelude::{PrivateKey, PublicKey},
+    prelude::{EcPublicEncryptionKey, PrivateKey, PublicKey},
 };
 use uuid::Uuid;
 
@@ -29,12 +34,15 @@ pub enum KeyCommand {
     Delete(KeySpecifier),
     /// Reject or remove a key and sync that witht the remote endpoint
     Reject(KeySpecifier),
+    /// Approve a pending Key request by sharing the Drive with it
+    Approve(KeySpecifier),
+    /// Revoke a Key's access to the Drive, re-keying the Drive for all remaining Keys
+    Revoke(KeySpecifier),
 }
 
 #[async_trait(?Send)]
 impl RunnableCommand<NativeError> for KeyCommand {
-    async fn run_internal(self) -> Result<String, NativeError> {
-        let global = GlobalConfig::from_disk().await?;
+    async fn run_internal(self, global: GlobalConfig) -> Result<String, NativeError> {
         let mut client = global.get_client().await?;
         match self {
             KeyCommand::RequestAccess(drive_specifier) => {
@@ -45,7 +53,7 @@ impl RunnableCommand<NativeError> for KeyCommand {
                 let pem = String::from_utf8(public_key.export().await?)?;
 
                 // Get Drive
-                let omni = OmniBucket::from_specifier(&drive_specifier).await;
+                let omni = OmniBucket::from_specifier(&global, &drive_specifier).await;
                 if let Ok(id) = omni.get_id() {
                     let existing_keys = BucketKey::read_all(id, &mut client).await?;
                     if let Some(existing_key) = existing_keys
@@ -67,7 +75,7 @@ impl RunnableCommand<NativeError> for KeyCommand {
                 }
             }
             KeyCommand::Ls(drive_specifier) => {
-                let omni = OmniBucket::from_specifier(&drive_specifier).await;
+                let omni = OmniBucket::from_specifier(&global, &drive_specifier).await;
                 let id = omni.get_id().unwrap();
                 let my_fingerprint = hex_fingerprint(
                     &global
@@ -88,7 +96,7 @@ impl RunnableCommand<NativeError> for KeyCommand {
                     .map_err(NativeError::api)
             }
             KeyCommand::Info(ks) => {
-                let (bucket_id, id) = get_key_info(&client, &ks).await?;
+                let (bucket_id, id) = get_key_info(&global, &client, &ks).await?;
                 let my_fingerprint = hex_fingerprint(
                     &global
                         .wrapping_key()
@@ -104,28 +112,130 @@ impl RunnableCommand<NativeError> for KeyCommand {
                     .map_err(NativeError::api)
             }
             KeyCommand::Delete(ks) => {
-                let (bucket_id, id) = get_key_info(&client, &ks).await?;
+                let (bucket_id, id) = get_key_info(&global, &client, &ks).await?;
                 BucketKey::delete_by_id(bucket_id, id, &mut client)
                     .await
                     .map(|id| format!("<< DELETED KEY SUCCESSFULLY >>\nid:\t{}", id))
                     .map_err(NativeError::api)
             }
             KeyCommand::Reject(ks) => {
-                let (bucket_id, id) = get_key_info(&client, &ks).await?;
+                let (bucket_id, id) = get_key_info(&global, &client, &ks).await?;
                 BucketKey::reject(bucket_id, id, &mut client)
                     .await
                     .map(|_| format!("{}", "<< REJECTED KEY SUCCESSFULLY >>".green()))
                     .map_err(NativeError::api)
             }
+            KeyCommand::Approve(ks) => {
+                let (bucket_id, id) = get_key_info(&global, &client, &ks).await?;
+                let bucket_key = BucketKey::read(bucket_id, id, &mut client).await?;
+                if bucket_key.approved {
+                    return Err(NativeError::custom_error(
+                        "This Key has already been approved!",
+                    ));
+                }
+                let recipient = EcPublicEncryptionKey::import(bucket_key.pem.as_bytes()).await?;
+
+                let mut omni = OmniBucket::from_specifier(&global, &ks.drive_specifier).await;
+                let (drive_lock, mut local) = lock_writable_local(&global, &omni)?;
+
+                // Share the Drive with the new key
+                let wrapping_key = global.wrapping_key().await?;
+                let mut fs = local.unlock_fs(&wrapping_key).await?;
+                fs.share_with(&recipient, &local.metadata).await?;
+                local.save_fs(&mut fs).await?;
+                global.reload()?.update_config(&local)?;
+                omni.set_local(local);
+
+                // Pushing metadata which lists the key as valid approves it
+                omni.sync_state = SyncState::Ahead;
+                let sync_result = omni
+                    .sync_bucket_locked(
+                        &global,
+                        Some(drive_lock),
+                        None,
+                        &TerminalProgress::default(),
+                    )
+                    .await?;
+
+                BucketKey::read(bucket_id, id, &mut client)
+                    .await
+                    .map(|key| {
+                        format!(
+                            "{}\n{}\n{}",
+                            "<< APPROVED KEY SUCCESSFULLY >>".green(),
+                            key,
+                            sync_result
+                        )
+                    })
+                    .map_err(NativeError::api)
+            }
+            KeyCommand::Revoke(ks) => {
+                let (bucket_id, id) = get_key_info(&global, &client, &ks).await?;
+                let mut omni = OmniBucket::from_specifier(&global, &ks.drive_specifier).await;
+                let (drive_lock, mut local) = lock_writable_local(&global, &omni)?;
+
+                // Remove the key and rotate the Drive
+                let wrapping_key = global.wrapping_key().await?;
+                let mut fs = local.unlock_fs(&wrapping_key).await?;
+                fs.revoke(&ks.fingerprint, &local.metadata, &local.content)
+                    .await?;
+                local.save_fs(&mut fs).await?;
+                global.reload()?.update_config(&local)?;
+                omni.set_local(local);
+
+                // Push the new metadata, which no longer lists the key as valid
+                omni.sync_state = SyncState::Ahead;
+                let sync_result = omni
+                    .sync_bucket_locked(
+                        &global,
+                        Some(drive_lock),
+                        None,
+                        &TerminalProgress::default(),
+                    )
+                    .await?;
+
+                // Remove the key server side as well
+                BucketKey::delete_by_id(bucket_id, id, &mut client)
+                    .await
+                    .map(|_| {
+                        format!(
+                            "{}\n{}",
+                            "<< REVOKED KEY SUCCESSFULLY >>".green(),
+                            sync_result
+                        )
+                    })
+                    .map_err(NativeError::api)
+            }
+        }
+    }
+}
+
+/// Take exclusive access to the LocalBucket of a Drive whose metadata is about to be rewritten and pushed
+fn lock_writable_local(
+    global: &GlobalConfig,
+    omni: &OmniBucket,
+) -> Result<(FileLock, LocalBucket), NativeError> {
+    let (drive_lock, mut local) = global.lock_bucket(&omni.get_local()?)?;
+    match omni.sync_state {
+        // Everything is already persisted remotely, so write to a fresh delta
+        SyncState::AllSynced => local.content.add_delta()?,
+        // The latest delta still has to be pushed anyway
+        SyncState::Ahead => {}
+        _ => {
+            return Err(NativeError::custom_error(
+                "Drive must be synced before modifying its Keys",
+            ))
         }
     }
+    Ok((drive_lock, local))
 }
 
 async fn get_key_info(
+    global: &GlobalConfig,
     client: &Client,
     key_specifier: &KeySpecifier,
 ) -> Result<(Uuid, Uuid), NativeError> {
-    let bucket_id = OmniBucket::from_specifier(&key_specifier.drive_specifier)
+    let bucket_id = OmniBucket::from_specifier(global, &key_specifier.drive_specifier)
         .await
         .get_id()?;
 
@@ -0,0 +1,149 @@
+use super::{
+    prompt_for_bool, prompt_for_new_passphrase, prompt_for_passphrase, prompt_for_string,
+    RunnableCommand,
+};
+use crate::native::{
+    configuration::{
+        bundle::KeyBundle,
+        globalconfig::GlobalConfig,
+        keys::{
+            decrypt_key_file, encrypt_key_file, key_passphrase, save_api_key, save_wrapping_key,
+        },
+        recovery::{from_recovery_phrase, to_recovery_phrase},
+    },
+    operations::rotate,
+    NativeError,
+};
+use async_trait::async_trait;
+use clap::Subcommand;
+use colored::Colorize;
+use std::{fs::File, io::Write, path::PathBuf};
+use tomb_crypt::{
+    hex_fingerprint,
+    prelude::{EcEncryptionKey, EcSignatureKey, PrivateKey, PublicKey},
+};
+
+/// Subcommand for managing the keys stored on this device
+#[derive(Subcommand, Clone, Debug)]
+pub enum LocalKeysCommand {
+    /// Export the keys on this device as a passphrase encrypted bundle
+    Export {
+        /// Bundle output path
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// Import keys from a passphrase encrypted bundle
+    Import {
+        /// Bundle input path
+        #[arg(short, long)]
+        input: PathBuf,
+    },
+    /// Encrypt the keys on this device at rest with a passphrase
+    Encrypt,
+    /// Store the keys on this device in plaintext again
+    Decrypt,
+    /// Print the recovery phrase for the wrapping key
+    RecoveryPhrase,
+    /// Restore the wrapping key from its recovery phrase
+    Recover,
+    /// Replace the wrapping key, moving every local Drive over to the new one. Safe to re-run if interrupted
+    Rotate {
+        /// Retire the old key even if some Drives only exist remotely and can't be moved over
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[async_trait(?Send)]
+impl RunnableCommand<NativeError> for LocalKeysCommand {
+    async fn run_internal(self, global: GlobalConfig) -> Result<String, NativeError> {
+        match self {
+            LocalKeysCommand::Export { output } => {
+                let bundle = KeyBundle::from_config(&global).await?;
+                let passphrase = prompt_for_new_passphrase()?;
+                let envelope = bundle.seal(&passphrase)?;
+                File::create(&output)?.write_all(&envelope.to_bytes()?)?;
+                Ok(format!(
+                    "{}\npath:\t{}",
+                    "<< EXPORTED KEYS SUCCESSFULLY >>".green(),
+                    output.display()
+                ))
+            }
+            LocalKeysCommand::Import { input } => {
+                let bundle =
+                    KeyBundle::from_file(&input, &prompt_for_passphrase("Bundle passphrase: ")?)?;
+                let wrapping_key = EcEncryptionKey::import(bundle.wrapping_key.as_bytes()).await?;
+                let fingerprint = key_fingerprint(&wrapping_key).await?;
+                // Replacing a different key locks this device out of any Drive it alone can read
+                if let Ok(existing_key) = global.wrapping_key().await {
+                    if fingerprint != key_fingerprint(&existing_key).await?
+                        && !prompt_for_bool("Replace the existing wrapping key on this device?")
+                    {
+                        return Err(NativeError::custom_error("Import cancelled"));
+                    }
+                }
+                save_wrapping_key(&global.wrapping_key_path, &wrapping_key).await?;
+                if let Some(api_key) = bundle.api_key {
+                    let api_key = EcSignatureKey::import(api_key.as_bytes()).await?;
+                    save_api_key(&global.api_key_path, api_key).await?;
+                }
+                Ok(format!(
+                    "{}\nwrapping_key_fingerprint:\t{}",
+                    "<< IMPORTED KEYS SUCCESSFULLY >>".green(),
+                    fingerprint
+                ))
+            }
+            LocalKeysCommand::Encrypt => {
+                let passphrase = prompt_for_new_passphrase()?;
+                encrypt_key_file(&global.wrapping_key_path, &passphrase)?;
+                if global.api_key_path.exists() {
+                    encrypt_key_file(&global.api_key_path, &passphrase)?;
+                }
+                Ok(format!(
+                    "{}\n{}",
+                    "<< KEYS ENCRYPTED AT REST >>".green(),
+                    "Set BANYAN_KEY_PASSPHRASE to avoid being prompted for the passphrase.".blue()
+                ))
+            }
+            LocalKeysCommand::Decrypt => {
+                let passphrase = key_passphrase()?;
+                decrypt_key_file(&global.wrapping_key_path, &passphrase)?;
+                if global.api_key_path.exists() {
+                    decrypt_key_file(&global.api_key_path, &passphrase)?;
+                }
+                Ok(format!("{}", "<< KEYS STORED IN PLAINTEXT >>".green()))
+            }
+            LocalKeysCommand::RecoveryPhrase => {
+                let wrapping_key = global.wrapping_key().await?;
+                Ok(format!(
+                    "{}\n{}\n\n{}",
+                    "| RECOVERY PHRASE |".yellow(),
+                    "Anyone with these words can read your Drives. Keep them offline.".red(),
+                    to_recovery_phrase(&wrapping_key).await?
+                ))
+            }
+            LocalKeysCommand::Recover => {
+                let phrase = prompt_for_string("Enter your recovery phrase:")?;
+                let wrapping_key = from_recovery_phrase(&phrase).await?;
+                if global.wrapping_key_path.exists()
+                    && !prompt_for_bool("Replace the existing wrapping key on this device?")
+                {
+                    return Err(NativeError::custom_error("Recovery cancelled"));
+                }
+                save_wrapping_key(&global.wrapping_key_path, &wrapping_key).await?;
+                Ok(format!(
+                    "{}\nwrapping_key_fingerprint:\t{}",
+                    "<< RECOVERED WRAPPING KEY SUCCESSFULLY >>".green(),
+                    key_fingerprint(&wrapping_key).await?
+                ))
+            }
+            LocalKeysCommand::Rotate { force } => rotate::pipeline(&global, force).await,
+        }
+    }
+}
+
+async fn key_fingerprint(key: &EcEncryptionKey) -> Result<String, NativeError> {
+    Ok(hex_fingerprint(
+        key.public_key()?.fingerprint().await?.as_slice(),
+    ))
+}
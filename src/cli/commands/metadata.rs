@@ -1,8 +1,5 @@
-use std::collections::BTreeSet;
-
 use crate::{
     api::models::metadata::Metadata,
-    filesystem::FilesystemError,
     native::{configuration::globalconfig::GlobalConfig, sync::OmniBucket, NativeError},
     prelude::blockstore::RootedBlockStore,
 };
@@ -13,7 +10,6 @@ use super::{
 };
 use async_trait::async_trait;
 use clap::Subcommand;
-use wnfs::{libipld::Cid, private::PrivateNode};
 
 /// Subcommand for Bucket Metadata
 #[derive(Subcommand, Clone, Debug)]
@@ -30,12 +26,12 @@ pub enum MetadataCommand {
 
 #[async_trait(?Send)]
 impl RunnableCommand<NativeError> for MetadataCommand {
-    async fn run_internal(self) -> Result<String, NativeError> {
-        let mut client = GlobalConfig::from_disk().await?.get_client().await?;
+    async fn run_internal(self, global: GlobalConfig) -> Result<String, NativeError> {
+        let mut client = global.get_client().await?;
         match self {
             // List all Metadata for a Bucket
             MetadataCommand::Ls(drive_specifier) => {
-                let omni = OmniBucket::from_specifier(&drive_specifier).await;
+                let omni = OmniBucket::from_specifier(&global, &drive_specifier).await;
                 let bucket_id = omni.get_id()?;
                 Metadata::read_all(bucket_id, &mut client)
                     .await
@@ -49,7 +45,8 @@ impl RunnableCommand<NativeError> for MetadataCommand {
             // Read an existing metadata
             MetadataCommand::Read(metadata_specifier) => {
                 // Get Bucket config
-                let omni = OmniBucket::from_specifier(&metadata_specifier.drive_specifier).await;
+                let omni =
+                    OmniBucket::from_specifier(&global, &metadata_specifier.drive_specifier).await;
                 // If we can get the metadata
                 let remote_id = omni.get_id()?;
                 Metadata::read(remote_id, metadata_specifier.metadata_id, &mut client)
@@ -59,7 +56,7 @@ impl RunnableCommand<NativeError> for MetadataCommand {
             }
             // Read the current Metadata
             MetadataCommand::ReadCurrent(drive_specifier) => {
-                let omni = OmniBucket::from_specifier(&drive_specifier).await;
+                let omni = OmniBucket::from_specifier(&global, &drive_specifier).await;
                 let bucket_id = omni.get_id()?;
                 Metadata::read_current(bucket_id, &mut client)
                     .await
@@ -68,7 +65,8 @@ impl RunnableCommand<NativeError> for MetadataCommand {
             }
             // Take a Cold Snapshot of the remote metadata
             MetadataCommand::Snapshot(metadata_specifier) => {
-                let omni = OmniBucket::from_specifier(&metadata_specifier.drive_specifier).await;
+                let omni =
+                    OmniBucket::from_specifier(&global, &metadata_specifier.drive_specifier).await;
                 let bucket_id = omni.get_id().expect("no remote id");
                 let metadata =
                     Metadata::read(bucket_id, metadata_specifier.metadata_id, &mut client).await?;
@@ -84,27 +82,8 @@ impl RunnableCommand<NativeError> for MetadataCommand {
                 }
 
                 // Finish loading the filesystem
-                let fs = omni.unlock().await?;
-
-                // Start off by considering all CIDs in the metatadata CAR as 'active'
-                let index = local.metadata.car.car.index.borrow().clone();
-                let mut active_cids = index.buckets[0]
-                    .map
-                    .clone()
-                    .into_keys()
-                    .collect::<BTreeSet<Cid>>();
-
-                // For every node that is a PrivateFile
-                for (node, _) in fs.get_all_nodes(&local.metadata).await? {
-                    if let PrivateNode::File(file) = node {
-                        // Extend with all the cids in the file
-                        active_cids.extend(
-                            file.get_cids(&fs.forest, &local.content)
-                                .await
-                                .map_err(|err| FilesystemError::wnfs(Box::from(err)))?,
-                        )
-                    }
-                }
+                let fs = omni.unlock(&global).await?;
+                let active_cids = local.active_cids(&fs).await?;
 
                 metadata
                     .snapshot(active_cids, &mut client)
@@ -1,20 +1,28 @@
 mod account;
 mod api;
+mod devices;
 mod drives;
+mod fs;
 mod keys;
+mod local_keys;
 mod metadata;
+mod profile;
 mod runnable_command;
 
 use std::io::Read;
 
-use crate::native::NativeError;
+use crate::native::{configuration::globalconfig::GlobalConfig, NativeError};
 pub use account::AccountCommand;
 pub use api::ApiCommand;
 use async_trait::async_trait;
 use clap::Subcommand;
+pub use devices::DevicesCommand;
 pub use drives::DrivesCommand;
+pub use fs::{FsCommand, MetaCommand};
 pub use keys::KeyCommand;
+pub use local_keys::LocalKeysCommand;
 pub use metadata::MetadataCommand;
+pub use profile::ProfileCommand;
 pub use runnable_command::RunnableCommand;
 
 /// Prompt the user for a y/n answer
@@ -31,6 +39,31 @@ pub fn prompt_for_bool(msg: &str) -> bool {
     }
 }
 
+/// Prompt the user for a line of text
+pub fn prompt_for_string(msg: &str) -> Result<String, NativeError> {
+    info!("{msg}");
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input)?;
+    Ok(input.trim().to_string())
+}
+
+/// Prompt the user for a passphrase without echoing it
+pub fn prompt_for_passphrase(msg: &str) -> Result<String, NativeError> {
+    Ok(rpassword::prompt_password(msg)?)
+}
+
+/// Prompt the user for a new passphrase, making them type it twice
+pub fn prompt_for_new_passphrase() -> Result<String, NativeError> {
+    let passphrase = prompt_for_passphrase("New passphrase: ")?;
+    if passphrase.is_empty() {
+        return Err(NativeError::custom_error("Passphrase can't be empty"));
+    }
+    if passphrase != prompt_for_passphrase("Confirm passphrase: ")? {
+        return Err(NativeError::custom_error("Passphrases did not match"));
+    }
+    Ok(passphrase)
+}
+
 /// Defines the types of commands that can be executed from the CLI.
 #[derive(Debug, Subcommand, Clone)]
 pub enum TombCommand {
@@ -52,15 +85,29 @@ pub enum TombCommand {
         #[clap(subcommand)]
         command: DrivesCommand,
     },
+    /// Backup, restore and protect the keys on this device
+    Keys {
+        /// Subcommand
+        #[clap(subcommand)]
+        command: LocalKeysCommand,
+    },
+    /// Manage Profiles, each with its own account, endpoint, keys and Drives
+    Profile {
+        /// Subcommand
+        #[clap(subcommand)]
+        command: ProfileCommand,
+    },
 }
 
 #[async_trait(?Send)]
 impl RunnableCommand<NativeError> for TombCommand {
-    async fn run_internal(self) -> Result<String, NativeError> {
+    async fn run_internal(self, global: GlobalConfig) -> Result<String, NativeError> {
         match self {
-            TombCommand::Api { command } => Ok(command.run_internal().await?),
-            TombCommand::Account { command } => Ok(command.run_internal().await?),
-            TombCommand::Drives { command } => command.run_internal().await,
+            TombCommand::Api { command } => Ok(command.run_internal(global).await?),
+            TombCommand::Account { command } => Ok(command.run_internal(global).await?),
+            TombCommand::Drives { command } => command.run_internal(global).await,
+            TombCommand::Keys { command } => command.run_internal(global).await,
+            TombCommand::Profile { command } => command.run_internal(global).await,
         }
     }
 }
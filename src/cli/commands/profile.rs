@@ -0,0 +1,94 @@
+use super::{prompt_for_bool, RunnableCommand};
+use crate::native::{configuration::globalconfig::GlobalConfig, NativeError};
+use async_trait::async_trait;
+use clap::Subcommand;
+use colored::Colorize;
+use url::Url;
+
+/// Subcommand for Profiles
+#[derive(Subcommand, Clone, Debug)]
+pub enum ProfileCommand {
+    /// List all Profiles on this device
+    Ls,
+    /// Create a new Profile with its own keys
+    Add {
+        /// Profile name
+        name: String,
+        /// Remote endpoint, if not the default
+        #[arg(short, long)]
+        endpoint: Option<String>,
+    },
+    /// Use a Profile whenever none is selected with --profile or BANYAN_PROFILE
+    Use {
+        /// Profile name
+        name: String,
+    },
+    /// Remove a Profile, its local Drive data and its keys
+    Rm {
+        /// Profile name
+        name: String,
+    },
+}
+
+#[async_trait(?Send)]
+impl RunnableCommand<NativeError> for ProfileCommand {
+    async fn run_internal(self, global: GlobalConfig) -> Result<String, NativeError> {
+        match self {
+            ProfileCommand::Ls => {
+                let (profiles, selected) = global.profiles()?;
+                Ok(profiles.iter().fold(String::new(), |acc, profile| {
+                    format!("{}\n\n{}", acc, profile_fmt(profile, &selected))
+                }))
+            }
+            ProfileCommand::Add { name, endpoint } => {
+                let endpoint = endpoint
+                    .map(|endpoint| Url::parse(&endpoint))
+                    .transpose()
+                    .map_err(|_| NativeError::bad_data())?;
+                let profile = global.new_profile(&name, endpoint).await?;
+                Ok(format!(
+                    "{}\n{}",
+                    "<< CREATED PROFILE SUCCESSFULLY >>".green(),
+                    profile_fmt(&profile, "")
+                ))
+            }
+            ProfileCommand::Use { name } => {
+                global.use_profile(&name)?;
+                Ok(format!(
+                    "{}\nprofile:\t{}",
+                    "<< SWITCHED PROFILE SUCCESSFULLY >>".green(),
+                    name
+                ))
+            }
+            ProfileCommand::Rm { name } => {
+                if !prompt_for_bool(&format!(
+                    "Remove profile `{name}` along with its keys and local Drive data?"
+                )) {
+                    return Err(NativeError::custom_error("Removal cancelled"));
+                }
+                global.remove_profile(&name)?;
+                Ok(format!(
+                    "{}\nprofile:\t{}",
+                    "<< REMOVED PROFILE SUCCESSFULLY >>".green(),
+                    name
+                ))
+            }
+        }
+    }
+}
+
+/// Display a Profile, noting whether it is the one selected
+fn profile_fmt(profile: &GlobalConfig, selected: &str) -> String {
+    let info = format!(
+        "{}\nname:\t\t{}\nendpoint:\t{}\ndrives:\t\t{}",
+        "| PROFILE INFO |".yellow(),
+        profile.profile(),
+        profile.get_endpoint(),
+        profile.buckets.len()
+    );
+    if profile.profile() == selected {
+        format!("{}\n{}", "| THIS IS YOUR CURRENT PROFILE |".green(), info)
+    } else {
+        info
+    }
+}
@@ -1,7 +1,10 @@
 use std::fmt::Display;
 
 use crate::{
-    native::configuration::{globalconfig::GlobalConfig, xdg::config_path},
+    native::{
+        configuration::globalconfig::{GlobalConfig, Selection},
+        NativeError,
+    },
     WnfsError,
 };
 use async_trait::async_trait;
@@ -12,18 +15,21 @@ use colored::Colorize;
 #[async_trait(?Send)]
 pub trait RunnableCommand<ErrorType>: Subcommand
 where
-    ErrorType: Into<WnfsError> + std::fmt::Debug + Display,
+    ErrorType: Into<WnfsError> + From<NativeError> + std::fmt::Debug + Display,
 {
-    /// The internal running operation
-    async fn run_internal(self) -> Result<String, ErrorType>;
+    /// The internal running operation, given the configuration it was selected to run with
+    async fn run_internal(self, global: GlobalConfig) -> Result<String, ErrorType>;
 
-    /// Run the internal command, passing a reference to a global configuration which is saved after completion
-    async fn run(self) -> Result<(), ErrorType> {
-        if !config_path().exists() {
-            GlobalConfig::new().await.expect("new config");
+    /// Load the selected configuration, creating it if need be, and run the internal command with it
+    async fn run(self, selection: &Selection) -> Result<(), ErrorType> {
+        if let Err(err) = GlobalConfig::init_if_missing(&selection.homes).await {
+            error!("{}", format!("{}", err).red());
         }
 
-        let result = self.run_internal().await;
+        let result = match GlobalConfig::load(selection).await {
+            Ok(global) => self.run_internal(global).await,
+            Err(err) => Err(err.into()),
+        };
 
         // Provide output based on that
         match result {
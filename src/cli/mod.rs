@@ -2,6 +2,8 @@
 pub mod args;
 /// Commands to run
 pub mod commands;
+/// Showing the progress of pipelines on the terminal
+pub mod progress;
 /// Ways of specifying resources
 pub mod specifiers;
 /// Debug level
@@ -9,21 +11,35 @@ pub mod verbosity;
 
 #[cfg(test)]
 mod test {
-    #[cfg(feature = "integration-tests")]
-    use crate::cli::commands::AccountCommand;
+    use crate::cli::commands::{AccountCommand, DevicesCommand};
     use crate::{
+        api::{
+            mock::MockServer,
+            models::{
+                account::Account, bucket::BucketType, bucket_key::BucketKey,
+                device_api_key::DeviceApiKey,
+            },
+        },
         cli::{
-            commands::{DrivesCommand, RunnableCommand, TombCommand},
-            specifiers::DriveSpecifier,
+            commands::{DrivesCommand, KeyCommand, RunnableCommand, TombCommand},
+            specifiers::{DriveSpecifier, KeySpecifier},
+        },
+        native::{
+            configuration::globalconfig::{GlobalConfig, Selection},
+            operations::configure,
+            sync::OmniBucket,
+            NativeError,
         },
-        native::{configuration::globalconfig::GlobalConfig, NativeError},
         utils::{
-            testing::local_operations::{test_setup, test_teardown},
+            testing::local_operations::{test_config, test_homes, test_setup, test_teardown},
             UtilityError,
         },
     };
-    use serial_test::serial;
     use std::path::Path;
+    use tomb_crypt::{
+        hex_fingerprint,
+        prelude::{EcEncryptionKey, EcSignatureKey, PrivateKey, PublicKey},
+    };
 
     #[allow(dead_code)]
     #[cfg(feature = "integration-tests")]
@@ -38,12 +54,14 @@ mod test {
             command: DrivesCommand::Create {
                 name: "Bucket Name".to_string(),
                 origin: Some(origin.to_path_buf()),
+                drive_type: BucketType::Interactive,
+                storage_class: None,
             },
         }
     }
 
-    async fn cmd_delete(origin: &Path) -> Result<(), UtilityError> {
-        let mut global = GlobalConfig::from_disk().await?;
+    async fn cmd_delete(global: &GlobalConfig, origin: &Path) -> Result<(), UtilityError> {
+        let mut global = global.reload()?;
         let local = global
             .get_bucket(origin)
             .ok_or(NativeError::missing_local_drive())?;
@@ -70,88 +88,260 @@ mod test {
         }
     }
 
+    // Sync a Drive with the remote
+    fn cmd_sync(origin: &Path) -> TombCommand {
+        TombCommand::Drives {
+            command: DrivesCommand::Sync {
+                drive_specifier: DriveSpecifier::with_origin(origin),
+                snapshot: None,
+            },
+        }
+    }
+
+    // Approve a Key waiting for access to a Drive
+    fn cmd_approve(origin: &Path, fingerprint: &str) -> TombCommand {
+        TombCommand::Drives {
+            command: DrivesCommand::Keys {
+                subcommand: KeyCommand::Approve(KeySpecifier {
+                    drive_specifier: DriveSpecifier::with_origin(origin),
+                    fingerprint: fingerprint.to_string(),
+                }),
+            },
+        }
+    }
+
+    // Manage the Devices registered to this Account
+    fn cmd_devices(command: DevicesCommand) -> TombCommand {
+        TombCommand::Account {
+            command: AccountCommand::Devices { command },
+        }
+    }
+
+    // Revoke a Device Key
+    fn cmd_revoke_device(fingerprint: &str, force: bool, reregister: bool) -> TombCommand {
+        cmd_devices(DevicesCommand::Revoke {
+            fingerprint: fingerprint.to_string(),
+            force,
+            reregister,
+        })
+    }
+
+    /// Point this device at a mock server and log it into a new account there
+    async fn register(global: &GlobalConfig, server: &MockServer) -> Result<(), UtilityError> {
+        configure::remote_core(global, &server.core_url).await?;
+        let mut global = global.reload()?;
+        let mut client = global.get_client().await?;
+        Account::create_fake(&mut client)
+            .await
+            .map_err(NativeError::api)?;
+        global.save_client(client).await?;
+        Ok(())
+    }
+
+    /// A new wrapping key along with its PEM and fingerprint
+    async fn new_wrapping_key() -> Result<(EcEncryptionKey, String, String), NativeError> {
+        let key = EcEncryptionKey::generate().await?;
+        let public_key = key.public_key()?;
+        let pem = String::from_utf8(public_key.export().await?)?;
+        let fingerprint = hex_fingerprint(public_key.fingerprint().await?.as_slice());
+        Ok((key, pem, fingerprint))
+    }
+
     #[tokio::test]
-    #[serial]
     async fn init() -> Result<(), UtilityError> {
         let test_name = "cli_init";
         // Setup test
         let origin = &test_setup(test_name).await?;
+        let global = test_config(test_name).await?;
+        let selection = &Selection::new(test_homes(test_name)?);
         // Deinitialize for user
-        cmd_delete(origin).await.ok();
+        cmd_delete(&global, origin).await.ok();
         // Assert failure
-        assert!(cmd_prepare(origin).run().await.is_err());
+        assert!(cmd_prepare(origin).run(selection).await.is_err());
         // Initialization worked
-        cmd_create(origin).run().await?;
+        cmd_create(origin).run(selection).await?;
         // Assert the bucket exists now
-        assert!(GlobalConfig::from_disk()
-            .await?
-            .get_bucket(origin)
-            .is_some());
+        assert!(global.reload()?.get_bucket(origin).is_some());
         // Teardown test
         test_teardown(test_name).await
     }
 
     #[tokio::test]
-    #[serial]
     async fn init_deinit() -> Result<(), UtilityError> {
         let test_name = "cli_init_deinit";
         // Setup test
         let origin = &test_setup(test_name).await?;
+        let global = test_config(test_name).await?;
+        let selection = &Selection::new(test_homes(test_name)?);
         // Deinit if present
-        cmd_delete(origin).await.ok();
+        cmd_delete(&global, origin).await.ok();
         // Assert no bucket exists yet
-        assert!(GlobalConfig::from_disk()
-            .await?
-            .get_bucket(origin)
-            .is_none());
+        assert!(global.reload()?.get_bucket(origin).is_none());
         // Initialization worked
-        cmd_create(origin).run().await?;
+        cmd_create(origin).run(selection).await?;
         // Assert the bucket exists now
-        assert!(GlobalConfig::from_disk()
-            .await?
-            .get_bucket(origin)
-            .is_some());
+        assert!(global.reload()?.get_bucket(origin).is_some());
         // Deinitialize the directory
-        cmd_delete(origin).await?;
+        cmd_delete(&global, origin).await?;
         // Assert the bucket is gone again
-        assert!(GlobalConfig::from_disk()
-            .await?
-            .get_bucket(origin)
-            .is_none());
+        assert!(global.reload()?.get_bucket(origin).is_none());
         // Teardown test
         test_teardown(test_name).await
     }
 
     #[tokio::test]
-    #[serial]
     async fn prepare() -> Result<(), UtilityError> {
         let test_name = "cli_prepare";
         // Setup test
         let origin = &test_setup(test_name).await?;
+        let selection = &Selection::new(test_homes(test_name)?);
         // Initialize tomb
-        cmd_create(origin).run().await?;
+        cmd_create(origin).run(selection).await?;
         // Run prepare and assert success
-        cmd_prepare(origin).run().await?;
+        cmd_prepare(origin).run(selection).await?;
         // Teardown test
         test_teardown(test_name).await
     }
 
     #[tokio::test]
-    #[serial]
     async fn restore() -> Result<(), UtilityError> {
         let test_name = "cli_restore";
         // Setup test
         let origin = &test_setup(test_name).await?;
+        let selection = &Selection::new(test_homes(test_name)?);
         // Initialize tomb
-        cmd_create(origin).run().await?;
+        cmd_create(origin).run(selection).await?;
         // Run prepare and assert success
-        cmd_prepare(origin).run().await?;
+        cmd_prepare(origin).run(selection).await?;
         // Run restore and assert success
-        cmd_restore(origin).run().await?;
+        cmd_restore(origin).run(selection).await?;
         // Assert equality
         // let restored = GlobalConfig::from_disk().await?.get_bucket(origin)?.origin;
         // assert_paths(origin, rest÷ored).expect("restored dir does not match origin");
         // Teardown test
         test_teardown(test_name).await
     }
+
+    #[tokio::test]
+    async fn approve() -> Result<(), UtilityError> {
+        let test_name = "cli_approve";
+        let server = MockServer::start();
+        let origin = &test_setup(test_name).await?;
+        let global = test_config(test_name).await?;
+        let selection = &Selection::new(test_homes(test_name)?);
+        cmd_create(origin).run(selection).await?;
+        cmd_prepare(origin).run(selection).await?;
+        register(&global, &server).await?;
+        cmd_sync(origin).run(selection).await?;
+
+        // Another device asks for access to the Drive
+        let (requester, pem, fingerprint) = new_wrapping_key().await?;
+        let omni = OmniBucket::from_specifier(&global, &DriveSpecifier::with_origin(origin)).await;
+        let bucket_id = omni.get_id()?;
+        let mut client = global.reload()?.get_client().await?;
+        let pending = BucketKey::create(bucket_id, pem, &mut client)
+            .await
+            .map_err(NativeError::api)?;
+        assert!(!pending.approved);
+
+        // Approving it shares the Drive with the key and marks it approved remotely
+        cmd_approve(origin, &fingerprint).run(selection).await?;
+        let approved = BucketKey::read(bucket_id, pending.id, &mut client)
+            .await
+            .map_err(NativeError::api)?;
+        assert!(approved.approved);
+        let local = global
+            .reload()?
+            .get_bucket(origin)
+            .ok_or(NativeError::missing_local_drive())?;
+        assert!(local.unlock_fs(&requester).await.is_ok());
+
+        // It can't be approved twice
+        assert!(cmd_approve(origin, &fingerprint)
+            .run(selection)
+            .await
+            .is_err());
+
+        // Teardown test
+        test_teardown(test_name).await
+    }
+
+    #[tokio::test]
+    async fn devices() -> Result<(), UtilityError> {
+        let test_name = "cli_devices";
+        let server = MockServer::start();
+        test_setup(test_name).await?;
+        let global = test_config(test_name).await?;
+        let selection = &Selection::new(test_homes(test_name)?);
+        register(&global, &server).await?;
+        let global = global.reload()?;
+        let my_fingerprint = hex_fingerprint(
+            global
+                .api_key()
+                .await?
+                .public_key()
+                .map_err(NativeError::from)?
+                .fingerprint()
+                .await
+                .map_err(NativeError::from)?
+                .as_slice(),
+        );
+
+        // Register a second device
+        let other_key = EcSignatureKey::generate()
+            .await
+            .map_err(NativeError::from)?;
+        let other_pem = String::from_utf8(
+            other_key
+                .public_key()
+                .map_err(NativeError::from)?
+                .export()
+                .await
+                .map_err(NativeError::from)?,
+        )?;
+        let mut client = global.get_client().await?;
+        let other = DeviceApiKey::create(other_pem, &mut client)
+            .await
+            .map_err(NativeError::api)?;
+
+        // Both show up, and this device is pointed out
+        let listed = cmd_devices(DevicesCommand::Ls).run(selection).await?;
+        assert!(listed.contains(&my_fingerprint));
+        assert!(listed.contains(&other.fingerprint));
+        assert!(listed.contains("THIS IS YOUR DEVICE"));
+        let info = cmd_devices(DevicesCommand::Info {
+            fingerprint: other.fingerprint.clone(),
+        })
+        .run(selection)
+        .await?;
+        assert!(info.contains(&other.fingerprint));
+        assert!(!info.contains("THIS IS YOUR DEVICE"));
+
+        // Other devices can be revoked outright
+        cmd_revoke_device(&other.fingerprint, false, false)
+            .run(selection)
+            .await?;
+        let listed = cmd_devices(DevicesCommand::Ls).run(selection).await?;
+        assert!(!listed.contains(&other.fingerprint));
+
+        // Revoking this device's own key has to be forced
+        assert!(cmd_revoke_device(&my_fingerprint, false, false)
+            .run(selection)
+            .await
+            .is_err());
+        assert!(cmd_devices(DevicesCommand::Ls)
+            .run(selection)
+            .await?
+            .contains(&my_fingerprint));
+
+        // Reregistering swaps in a new key which keeps this device logged in
+        cmd_revoke_device(&my_fingerprint, true, true)
+            .run(selection)
+            .await?;
+        let listed = cmd_devices(DevicesCommand::Ls).run(selection).await?;
+        assert!(!listed.contains(&my_fingerprint));
+        assert!(listed.contains("THIS IS YOUR DEVICE"));
+
+        test_teardown(test_name).await
+    }
 }
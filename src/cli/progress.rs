@@ -0,0 +1,43 @@
+use crate::{
+    native::utils::get_progress_bar,
+    utils::progress::{ProgressEvent, ProgressSink},
+};
+use indicatif::ProgressBar;
+use std::cell::RefCell;
+
+/// Shows the progress of pipelines on bars in the terminal
+#[derive(Debug, Default)]
+pub struct TerminalProgress {
+    /// Bar for the current phase, if it covers a known number of files
+    bar: RefCell<Option<ProgressBar>>,
+}
+
+impl ProgressSink for TerminalProgress {
+    fn report(&self, event: ProgressEvent) {
+        let mut current = self.bar.borrow_mut();
+        match event {
+            ProgressEvent::PhaseChanged { total, .. } => {
+                if let Some(bar) = current.take() {
+                    bar.finish();
+                }
+                *current = total.map(get_progress_bar);
+            }
+            ProgressEvent::FileStarted { path } => {
+                if let Some(bar) = current.as_ref() {
+                    bar.set_message(path);
+                }
+            }
+            ProgressEvent::FileDone { .. } => {
+                if let Some(bar) = current.as_ref() {
+                    bar.inc(1);
+                }
+            }
+            // The Client already shows transfers on bars of their own
+            ProgressEvent::BytesUploaded { .. } => {}
+            ProgressEvent::Warning { message } => match current.as_ref() {
+                Some(bar) => bar.suspend(|| warn!("{message}")),
+                None => warn!("{message}"),
+            },
+        }
+    }
+}
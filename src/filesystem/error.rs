@@ -64,6 +64,10 @@ impl FilesystemError {
             kind: FilesystemErrorKind::Wnfs(err),
         }
     }
+
+    pub fn kind(&self) -> &FilesystemErrorKind {
+        &self.kind
+    }
 }
 
 #[derive(Debug)]
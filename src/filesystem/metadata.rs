@@ -2,19 +2,22 @@ use crate::{
     blockstore::{BanyanBlockStore, CarV2MemoryBlockStore, DoubleSplitStore, RootedBlockStore},
     filesystem::{
         serialize::{load_dir, load_forest, store_dir, store_forest, store_share_manager},
-        sharing::{manager::ShareManager, SharedFile},
+        sharing::{manager::ShareManager, SharedFile, SharingError},
         wnfsio::path_to_segments,
     },
 };
 use async_recursion::async_recursion;
 use chrono::Utc;
+use futures::io::AsyncRead;
 use futures_util::future::join_all;
 use rand::thread_rng;
 use serde::{Deserialize, Serialize};
 use std::{
     collections::BTreeMap,
     path::{Path, PathBuf},
+    pin::Pin,
     rc::Rc,
+    task::{Context, Poll},
 };
 use tomb_crypt::prelude::{EcEncryptionKey, EcPublicEncryptionKey, PrivateKey};
 use wnfs::{
@@ -22,7 +25,8 @@ use wnfs::{
     libipld::{Cid, Ipld},
     namefilter::Namefilter,
     private::{
-        share::SharePayload, PrivateDirectory, PrivateForest, PrivateNode, PrivateNodeOnPathHistory,
+        share::SharePayload, PrivateDirectory, PrivateFile, PrivateForest, PrivateNode,
+        PrivateNodeOnPathHistory,
     },
 };
 
@@ -33,6 +37,8 @@ const FOREST_LABEL: &str = "FOREST";
 const TOMB_BUILD_FEATURES_LABEL: &str = "TOMB_BUILD_FEATURES";
 const TOMB_BUILD_PROFILE_LABEL: &str = "TOMB_BUILD_PROFILE";
 const TOMB_REPO_VERSION_LABEL: &str = "TOMB_REPO_VERSION";
+/// Key in node metadata under which user tags are kept, apart from what we track ourselves
+const TAGS_KEY: &str = "tags";
 
 // TODO: Allow ser / de against a cbor file on disk -- that would be straight up easier to debug
 /// Describes how to serialize / deserialize metadata for a Wnfs Fs against
@@ -98,7 +104,8 @@ impl FsMetadata {
         match metadata_store.get_root() {
             None => self.share_manager.set_original_ref(&root_dir_ref).await?,
             Some(cid) => {
-                if cid == Cid::default() {
+                // The original ref is also missing after the root directory has been rotated
+                if cid == Cid::default() || self.share_manager.original_ref.is_none() {
                     self.share_manager.set_original_ref(&root_dir_ref).await?
                 }
             }
@@ -229,6 +236,31 @@ impl FsMetadata {
         })
     }
 
+    /// Read the fingerprints of every key the Fs is shared with, without unlocking it
+    pub async fn public_fingerprints(
+        store: &impl RootedBlockStore,
+    ) -> Result<Vec<String>, FilesystemError> {
+        // Get the map
+        let metadata_cid = store
+            .get_root()
+            .ok_or(FilesystemError::missing_metadata("root cid"))?;
+        let root_map = match store.get_deserializable::<Ipld>(&metadata_cid).await {
+            Ok(Ipld::Map(map)) => map,
+            _ => return Err(FilesystemError::missing_metadata("metadata map")),
+        };
+        // Get the share manager CID
+        let share_manager_cid = match root_map.get(SHARE_MANAGER_LABEL) {
+            Some(Ipld::Link(cid)) => cid,
+            _ => return Err(FilesystemError::missing_metadata(SHARE_MANAGER_LABEL)),
+        };
+        // Get the share manager
+        let share_manager = store
+            .get_deserializable::<ShareManager>(share_manager_cid)
+            .await
+            .map_err(Box::from)?;
+        Ok(share_manager.public_fingerprints())
+    }
+
     /// Share read write access with another key bearer
     pub async fn share_with(
         &mut self,
@@ -261,6 +293,44 @@ impl FsMetadata {
         Ok(())
     }
 
+    /// Revoke read write access from a key bearer.
+    /// The whole Fs is rewritten under a new root directory with a fresh ratchet, so the revoked
+    /// key can't follow it into future revisions. History restarts at the rotated root, and the
+    /// new refs are only encrypted for the remaining recipients on the next save.
+    pub async fn revoke(
+        &mut self,
+        fingerprint: &str,
+        metadata_store: &impl RootedBlockStore,
+        content_store: &impl RootedBlockStore,
+    ) -> Result<(), FilesystemError> {
+        // Stop sharing with the recipient
+        self.share_manager.revoke(fingerprint)?;
+
+        // Create a new root directory, which comes with a new ratchet
+        let mut new_root_dir = Rc::new(PrivateDirectory::new(
+            Namefilter::default(),
+            Utc::now(),
+            &mut thread_rng(),
+        ));
+        // Rewrite every node under the new root
+        rekey_children(
+            &self.root_dir.clone(),
+            &mut new_root_dir,
+            &[],
+            &mut self.forest,
+            metadata_store,
+            content_store,
+        )
+        .await?;
+        self.root_dir = new_root_dir;
+
+        // Neither ref is valid anymore, and the next save establishes both
+        self.share_manager.current_ref = None;
+        self.share_manager.original_ref = None;
+
+        Ok(())
+    }
+
     /// Share a single version of an individual node
     pub async fn share_file(
         &mut self,
@@ -320,7 +390,11 @@ impl FsMetadata {
 
         // Grab node using share label.
         match shared_file.payload {
-            SharePayload::Temporal(_) => todo!(),
+            // Files are only ever shared as snapshots
+            SharePayload::Temporal(_) => Err(SharingError::invalid_data(
+                "temporal share payloads can not be received as files",
+            )
+            .into()),
             SharePayload::Snapshot(snapshot) => {
                 let file = PrivateNode::load_from_snapshot(snapshot, &forest, store)
                     .await
@@ -360,6 +434,48 @@ impl FsMetadata {
         .map_err(FilesystemError::wnfs)
     }
 
+    /// List the revisions of the file at a path, starting with the current one and going back in time
+    pub async fn file_history(
+        &self,
+        path_segments: &[String],
+        store: &impl BlockStore,
+    ) -> Result<Vec<Rc<PrivateFile>>, FilesystemError> {
+        let current = self
+            .root_dir
+            .get_node(path_segments, true, &self.forest, store)
+            .await
+            .map_err(Box::from)?
+            .ok_or(FilesystemError::node_not_found(&path_segments.join("/")))?
+            .as_file()
+            .map_err(Box::from)?;
+        // Get the original private ref
+        let original_private_ref = self
+            .share_manager
+            .original_ref
+            .as_ref()
+            .ok_or(FilesystemError::missing_metadata("original private ref"))?;
+        // Get the original root directory
+        let original_root_dir = load_dir(store, original_private_ref, &self.forest).await?;
+        // Follow the file back through every revision of the root directory
+        let mut history = PrivateNodeOnPathHistory::of(
+            self.root_dir.clone(),
+            original_root_dir,
+            1_000_000,
+            path_segments,
+            true,
+            self.forest.clone(),
+            store,
+        )
+        .await
+        .map_err(Box::from)?;
+
+        let mut revisions = vec![current];
+        while let Some(previous) = history.get_previous(store).await.map_err(Box::from)? {
+            revisions.push(previous.as_file().map_err(Box::from)?);
+        }
+        Ok(revisions)
+    }
+
     /// Return the build details
     pub async fn build_details(
         &self,
@@ -453,12 +569,10 @@ impl FsMetadata {
                     .root_dir
                     .get_node(&node_path_segments, true, &self.forest, store)
                     .await
-                    .expect("node not found");
-                let entry = entry
+                    .map_err(Box::from)?
                     .ok_or(FilesystemError::node_not_found(
                         &node_path_segments.join("/"),
-                    ))
-                    .expect("node not found");
+                    ))?;
                 // Map the node to an FsMetadataEntry
                 let name = name.to_string();
                 let entry_type = match entry {
@@ -466,11 +580,11 @@ impl FsMetadata {
                     PrivateNode::File(_) => FsMetadataEntryType::File,
                 };
                 let metadata = metadata.clone();
-                FsMetadataEntry {
+                Ok::<_, FilesystemError>(FsMetadataEntry {
                     name,
                     entry_type,
                     metadata,
-                }
+                })
             };
 
             futures.push(future);
@@ -478,8 +592,7 @@ impl FsMetadata {
 
         // Since the transformation is async, await all futures
         for future in futures {
-            let entry = future.await;
-            transformed_entries.push(entry);
+            transformed_entries.push(future.await?);
         }
 
         Ok(transformed_entries)
@@ -609,11 +722,7 @@ impl FsMetadata {
         content_store: &impl BanyanBlockStore,
     ) -> Result<Vec<u8>, FilesystemError> {
         // Compress the data in the file
-        let result = self
-            .root_dir
-            .get_node(path_segments, true, &self.forest, metadata_store)
-            .await
-            .expect("node not found");
+        let result = self.get_node(path_segments, metadata_store).await?;
 
         // Split store for reading
         let split_store = DoubleSplitStore::new(content_store, metadata_store);
@@ -636,9 +745,26 @@ impl FsMetadata {
         metadata_store: &impl RootedBlockStore,
         content_store: &impl BanyanBlockStore,
         content: Vec<u8>,
+    ) -> Result<(), FilesystemError> {
+        self.write_stream(
+            path_segments,
+            metadata_store,
+            content_store,
+            content.as_slice(),
+        )
+        .await
+    }
+
+    /// Write data to a specific node as it is read, without holding all of it in memory at once
+    pub async fn write_stream(
+        &mut self,
+        path_segments: &[String],
+        metadata_store: &impl RootedBlockStore,
+        content_store: &impl BanyanBlockStore,
+        content: impl AsyncRead + Unpin,
     ) -> Result<(), FilesystemError> {
         let time = Utc::now();
-        let data_size = content.len();
+        let mut content = Counted::new(content);
         let mut rng = thread_rng();
 
         let ds_store = DoubleSplitStore::new(metadata_store, content_store);
@@ -658,7 +784,7 @@ impl FsMetadata {
         if let Ok(file) = result {
             file.set_content(
                 time,
-                content.as_slice(),
+                &mut content,
                 &mut self.forest,
                 content_store,
                 &mut rng,
@@ -675,7 +801,7 @@ impl FsMetadata {
 
             file.content
                 .metadata
-                .put("size", Ipld::Integer(data_size as i128));
+                .put("size", Ipld::Integer(content.count as i128));
 
             // Be extra sure to store the private file content back into the content store
             // This is important for retrieving snapshotted files later on
@@ -706,6 +832,60 @@ impl FsMetadata {
         }
     }
 
+    /// Get the metadata of a file or directory, including its tags
+    pub async fn get_metadata(
+        &self,
+        path_segments: &[String],
+        store: &impl BanyanBlockStore,
+    ) -> Result<Metadata, FilesystemError> {
+        match self.get_node(path_segments, store).await? {
+            Some(PrivateNode::File(file)) => Ok(file.get_metadata().clone()),
+            Some(PrivateNode::Dir(dir)) => Ok(dir.get_metadata().clone()),
+            None => Err(FilesystemError::node_not_found(&path_segments.join("/"))),
+        }
+    }
+
+    /// Tag a file with a value of the user's choosing, or remove the tag if the value is null
+    pub async fn set_metadata(
+        &mut self,
+        path_segments: &[String],
+        key: &str,
+        value: Ipld,
+        metadata_store: &impl RootedBlockStore,
+    ) -> Result<(), FilesystemError> {
+        // Don't create the file if it isn't there
+        match self.get_node(path_segments, metadata_store).await? {
+            Some(PrivateNode::File(_)) => {}
+            Some(PrivateNode::Dir(_)) => {
+                return Err(FilesystemError::wnfs(Box::from(format!(
+                    "{} is a directory, only files can be tagged",
+                    path_segments.join("/")
+                ))))
+            }
+            None => return Err(FilesystemError::node_not_found(&path_segments.join("/"))),
+        }
+
+        let file = self
+            .root_dir
+            .open_file_mut(
+                path_segments,
+                true,
+                Utc::now(),
+                &mut self.forest,
+                metadata_store,
+                &mut thread_rng(),
+            )
+            .await
+            .map_err(Box::from)?;
+        let mut tags = tags(&file.content.metadata);
+        match value {
+            Ipld::Null => tags.remove(key),
+            value => tags.insert(key.to_string(), value),
+        };
+        file.content.metadata.put(TAGS_KEY, Ipld::Map(tags));
+        Ok(())
+    }
+
     /// Get all nodes under the root directory
     pub async fn get_all_nodes(
         &self,
@@ -762,6 +942,95 @@ impl FsMetadata {
     }
 }
 
+/// Recursively copy the children of a directory in one root into another, creating every node anew
+#[async_recursion(?Send)]
+async fn rekey_children(
+    old_root_dir: &Rc<PrivateDirectory>,
+    new_root_dir: &mut Rc<PrivateDirectory>,
+    path_segments: &[String],
+    forest: &mut Rc<PrivateForest>,
+    metadata_store: &impl RootedBlockStore,
+    content_store: &impl RootedBlockStore,
+) -> Result<(), FilesystemError> {
+    let time = Utc::now();
+    let mut rng = thread_rng();
+    let ds_store = DoubleSplitStore::new(metadata_store, content_store);
+    let split_store = DoubleSplitStore::new(content_store, metadata_store);
+
+    let entries = old_root_dir
+        .ls(path_segments, true, forest, metadata_store)
+        .await
+        .map_err(Box::from)?;
+
+    for (name, _) in entries {
+        let node_path_segments = path_segments
+            .iter()
+            .chain(std::iter::once(&name))
+            .cloned()
+            .collect::<Vec<String>>();
+
+        let node = old_root_dir
+            .get_node(&node_path_segments, true, forest, metadata_store)
+            .await
+            .map_err(Box::from)?
+            .ok_or(FilesystemError::node_not_found(
+                &node_path_segments.join("/"),
+            ))?;
+
+        match node {
+            PrivateNode::Dir(_) => {
+                new_root_dir
+                    .mkdir(
+                        &node_path_segments,
+                        true,
+                        time,
+                        forest,
+                        metadata_store,
+                        &mut rng,
+                    )
+                    .await
+                    .map_err(Box::from)?;
+                rekey_children(
+                    old_root_dir,
+                    new_root_dir,
+                    &node_path_segments,
+                    forest,
+                    metadata_store,
+                    content_store,
+                )
+                .await?;
+            }
+            PrivateNode::File(old_file) => {
+                // Read the content out of the old file
+                let content = old_file
+                    .get_content(forest, &split_store)
+                    .await
+                    .map_err(Box::from)?;
+                // Write it into a new one
+                let new_file = new_root_dir
+                    .open_file_mut(&node_path_segments, true, time, forest, &ds_store, &mut rng)
+                    .await
+                    .map_err(Box::from)?;
+                new_file
+                    .set_content(time, content.as_slice(), forest, content_store, &mut rng)
+                    .await
+                    .map_err(Box::from)?;
+                // Carry over mime type, size and anything else we were tracking
+                for (key, value) in old_file.get_metadata().0.iter() {
+                    new_file.content.metadata.put(key, value.clone());
+                }
+                // Store the file content in the content store, just like a regular write
+                new_file
+                    .store(forest, &ds_store, &mut rng)
+                    .await
+                    .map_err(Box::from)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 /// Dirty enum describing the type of a FsMetadataEntry
 pub enum FsMetadataEntryType {
@@ -771,6 +1040,33 @@ pub enum FsMetadataEntryType {
     File,
 }
 
+/// Reader which keeps count of the bytes read through it
+struct Counted<R> {
+    inner: R,
+    count: u64,
+}
+
+impl<R> Counted<R> {
+    fn new(inner: R) -> Self {
+        Self { inner, count: 0 }
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for Counted<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = self.get_mut();
+        let poll = Pin::new(&mut this.inner).poll_read(cx, buf);
+        if let Poll::Ready(Ok(read)) = &poll {
+            this.count += *read as u64;
+        }
+        poll
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 /// Helper struct to return FsMetadataEntry
 pub struct FsMetadataEntry {
@@ -782,6 +1078,21 @@ pub struct FsMetadataEntry {
     pub metadata: Metadata,
 }
 
+impl FsMetadataEntry {
+    /// Tags the user has set on this entry
+    pub fn tags(&self) -> BTreeMap<String, Ipld> {
+        tags(&self.metadata)
+    }
+}
+
+/// Tags the user has set in node metadata
+pub fn tags(metadata: &Metadata) -> BTreeMap<String, Ipld> {
+    match metadata.0.get(TAGS_KEY) {
+        Some(Ipld::Map(tags)) => tags.clone(),
+        _ => BTreeMap::new(),
+    }
+}
+
 #[cfg(not(target_arch = "wasm32"))]
 #[cfg(test)]
 mod test {
@@ -790,8 +1101,11 @@ mod test {
         filesystem::{error::FilesystemError, metadata::FsMetadata},
         prelude::filesystem::sharing::SharedFile,
     };
-    use tomb_crypt::prelude::{EcEncryptionKey, PrivateKey};
-    use wnfs::private::PrivateNode;
+    use tomb_crypt::{
+        hex_fingerprint,
+        prelude::{EcEncryptionKey, PrivateKey, PublicKey},
+    };
+    use wnfs::{libipld::Ipld, private::PrivateNode};
 
     async fn _init_save_unlock(
         wrapping_key: &EcEncryptionKey,
@@ -825,6 +1139,111 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn file_history() -> Result<(), FilesystemError> {
+        let metadata_store = MemoryBlockStore::default();
+        let content_store = MemoryBlockStore::default();
+        let wrapping_key = &EcEncryptionKey::generate().await?;
+        let mut fs_metadata =
+            _init_save_unlock(wrapping_key, &metadata_store, &content_store).await?;
+
+        let cat_path = vec!["cat.txt".to_string()];
+        for content in ["hello kitty", "goodbye kitty!"] {
+            fs_metadata
+                .write(
+                    &cat_path,
+                    &metadata_store,
+                    &content_store,
+                    content.as_bytes().to_vec(),
+                )
+                .await?;
+            fs_metadata.save(&metadata_store, &content_store).await?;
+        }
+
+        let revisions = fs_metadata.file_history(&cat_path, &metadata_store).await?;
+        assert_eq!(revisions.len(), 2);
+        // Newest first, each with its own content
+        let sizes = revisions
+            .iter()
+            .map(|file| file.get_metadata().0.get("size").cloned())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            sizes,
+            vec![Some(Ipld::Integer(14)), Some(Ipld::Integer(11))]
+        );
+        let previous = revisions[1]
+            .get_content(&fs_metadata.forest, &content_store)
+            .await
+            .map_err(Box::from)?;
+        assert_eq!(previous, b"hello kitty".to_vec());
+
+        // Directories have no file history
+        assert!(fs_metadata
+            .file_history(&[], &metadata_store)
+            .await
+            .is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn tags() -> Result<(), FilesystemError> {
+        let metadata_store = MemoryBlockStore::default();
+        let content_store = MemoryBlockStore::default();
+        let wrapping_key = &EcEncryptionKey::generate().await?;
+        let mut fs_metadata =
+            _init_save_unlock(wrapping_key, &metadata_store, &content_store).await?;
+
+        let cat_path = vec!["cat.txt".to_string()];
+        fs_metadata
+            .write(
+                &cat_path,
+                &metadata_store,
+                &content_store,
+                b"hello kitty".to_vec(),
+            )
+            .await?;
+        fs_metadata
+            .set_metadata(
+                &cat_path,
+                "color",
+                Ipld::String("orange".to_string()),
+                &metadata_store,
+            )
+            .await?;
+        fs_metadata
+            .set_metadata(&cat_path, "lives", Ipld::Integer(9), &metadata_store)
+            .await?;
+        fs_metadata
+            .set_metadata(&cat_path, "lives", Ipld::Null, &metadata_store)
+            .await?;
+        fs_metadata.save(&metadata_store, &content_store).await?;
+
+        let mut fs_metadata = FsMetadata::unlock(wrapping_key, &metadata_store).await?;
+        let metadata = fs_metadata.get_metadata(&cat_path, &metadata_store).await?;
+        // Tags sit alongside the metadata we track ourselves
+        assert!(metadata.0.contains_key("size"));
+        let entries = fs_metadata.ls(&[], &metadata_store).await?;
+        let tags = entries[0].tags();
+        assert_eq!(tags.len(), 1);
+        assert_eq!(tags.get("color"), Some(&Ipld::String("orange".to_string())));
+
+        // Only files are tagged, and only ones that exist
+        assert!(fs_metadata
+            .set_metadata(&[], "color", Ipld::Integer(0), &metadata_store)
+            .await
+            .is_err());
+        assert!(fs_metadata
+            .set_metadata(
+                &["dog.txt".to_string()],
+                "color",
+                Ipld::Integer(0),
+                &metadata_store
+            )
+            .await
+            .is_err());
+        Ok(())
+    }
+
     #[tokio::test]
     async fn build_details() -> Result<(), FilesystemError> {
         let metadata_store = MemoryBlockStore::default();
@@ -863,6 +1282,39 @@ mod test {
         Ok(())
     }
 
+    #[tokio::test]
+    async fn add_stream_read() -> Result<(), FilesystemError> {
+        let metadata_store = MemoryBlockStore::default();
+        let content_store = MemoryBlockStore::default();
+        let wrapping_key = &EcEncryptionKey::generate().await?;
+        let mut fs_metadata =
+            _init_save_unlock(wrapping_key, &metadata_store, &content_store).await?;
+
+        let cat_path = vec!["cat.txt".to_string()];
+        // Enough to span several blocks
+        let kitty_bytes = "hello kitty".repeat(100_000).into_bytes();
+        fs_metadata
+            .write_stream(
+                &cat_path,
+                &metadata_store,
+                &content_store,
+                futures::io::Cursor::new(kitty_bytes.clone()),
+            )
+            .await?;
+
+        let new_kitty_bytes = fs_metadata
+            .read(&cat_path, &metadata_store, &content_store)
+            .await?;
+        assert_eq!(kitty_bytes, new_kitty_bytes);
+        let metadata = fs_metadata.get_metadata(&cat_path, &metadata_store).await?;
+        assert_eq!(
+            metadata.0.get("size"),
+            Some(&Ipld::Integer(kitty_bytes.len() as i128))
+        );
+
+        Ok(())
+    }
+
     #[tokio::test]
     async fn add_share_receive() -> Result<(), FilesystemError> {
         let metadata_store = MemoryBlockStore::default();
@@ -1140,4 +1592,66 @@ mod test {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn share_revoke() -> Result<(), FilesystemError> {
+        let metadata_store = MemoryBlockStore::default();
+        let content_store = MemoryBlockStore::default();
+        let wrapping_key = &EcEncryptionKey::generate().await?;
+        let mut fs_metadata =
+            _init_save_unlock(wrapping_key, &metadata_store, &content_store).await?;
+
+        let cat_path = vec!["dir".to_string(), "cat.txt".to_string()];
+        let kitty_bytes = "hello kitty".as_bytes().to_vec();
+        // Add a new file
+        fs_metadata
+            .write(
+                &cat_path,
+                &metadata_store,
+                &content_store,
+                kitty_bytes.clone(),
+            )
+            .await?;
+
+        // Share with a second key
+        let other_key = &EcEncryptionKey::generate().await?;
+        fs_metadata
+            .share_with(&other_key.public_key()?, &metadata_store)
+            .await?;
+        fs_metadata.save(&metadata_store, &content_store).await?;
+        assert!(FsMetadata::unlock(other_key, &metadata_store).await.is_ok());
+
+        // Revoke the second key
+        let other_fingerprint =
+            hex_fingerprint(other_key.public_key()?.fingerprint().await?.as_slice());
+        fs_metadata
+            .revoke(&other_fingerprint, &metadata_store, &content_store)
+            .await?;
+        fs_metadata.save(&metadata_store, &content_store).await?;
+        assert!(!fs_metadata
+            .share_manager
+            .public_fingerprints()
+            .contains(&other_fingerprint));
+
+        // The revoked key is locked out, the remaining one can still read everything
+        assert!(FsMetadata::unlock(other_key, &metadata_store)
+            .await
+            .is_err());
+        let fs_metadata = FsMetadata::unlock(wrapping_key, &metadata_store).await?;
+        let new_kitty_bytes = fs_metadata
+            .read(&cat_path, &metadata_store, &content_store)
+            .await?;
+        assert_eq!(kitty_bytes, new_kitty_bytes);
+
+        // The last key can't be revoked
+        let fingerprint =
+            hex_fingerprint(wrapping_key.public_key()?.fingerprint().await?.as_slice());
+        let mut fs_metadata = fs_metadata;
+        assert!(fs_metadata
+            .revoke(&fingerprint, &metadata_store, &content_store)
+            .await
+            .is_err());
+
+        Ok(())
+    }
 }
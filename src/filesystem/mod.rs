@@ -1,9 +1,11 @@
 mod error;
 mod metadata;
 #[allow(unused)]
-pub use metadata::{FsMetadata, FsMetadataEntry, FsMetadataEntryType};
+pub use metadata::{tags, FsMetadata, FsMetadataEntry, FsMetadataEntryType};
 pub mod serialize;
 pub mod sharing;
+mod usage;
 pub mod wnfsio;
 
-pub use error::FilesystemError;
+pub use error::{FilesystemError, FilesystemErrorKind};
+pub use usage::UsageBreakdown;
@@ -75,10 +75,8 @@ pub async fn load_forest<BS: BlockStore>(
     // Deserialize the IPLD DAG of the PrivateForest
     let forest_ipld: Ipld = store.get_deserializable(cid).await.map_err(Box::from)?;
     // Create a PrivateForest from that IPLD DAG
-    let forest: Rc<PrivateForest> = Rc::new(
-        ipld_serde::from_ipld::<PrivateForest>(forest_ipld)
-            .expect("failed to convert IPLD to PrivateForest"),
-    );
+    let forest: Rc<PrivateForest> =
+        Rc::new(ipld_serde::from_ipld::<PrivateForest>(forest_ipld).map_err(Box::from)?);
     // Return
     Ok(forest)
 }
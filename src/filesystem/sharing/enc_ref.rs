@@ -37,10 +37,7 @@ impl EncryptedPrivateRef {
         // Wrap the temporal key in a Symmetric Key
         let temporal_key = SymmetricKey::from(temporal_key.0.bytes());
         // Encrypt the symmetric key for the recipient key
-        let encrypted_temporal_key = temporal_key
-            .encrypt_for(recipient_key)
-            .await
-            .expect("could not encrypt symmetric key for recipient");
+        let encrypted_temporal_key = temporal_key.encrypt_for(recipient_key).await?;
         let encrypted_temporal_key_string = encrypted_temporal_key.export();
         // Return the EncryptedPrivateRef
         Ok(Self {
@@ -63,13 +60,14 @@ impl EncryptedPrivateRef {
         }
         // Get the encrypted temporal key from the string
         let encrypted_temporal_key =
-            EncryptedSymmetricKey::import(&self.encrypted_temporal_key_string)
-                .expect("could not import encrypted temporal key");
+            EncryptedSymmetricKey::import(&self.encrypted_temporal_key_string).map_err(|_| {
+                SharingError::invalid_data("could not import encrypted temporal key")
+            })?;
         // Decrypt the encrypted temporal key with the recipient key
         let temporal_key = encrypted_temporal_key
             .decrypt_with(recipient_key)
             .await
-            .expect("could not decrypt encrypted temporal key");
+            .map_err(|_| SharingError::unauthorized())?;
         let temporal_key_slice = temporal_key.as_ref();
         if temporal_key_slice.len() != 32 {
             return Err(SharingError::invalid_data("temporal key was not 32 bytes"));
@@ -32,6 +32,19 @@ impl SharingError {
             kind: SharingErrorKind::Cryptographic(err),
         }
     }
+
+    /// Whether the key in use has no access to what was shared
+    pub fn is_unauthorized(&self) -> bool {
+        matches!(
+            self.kind,
+            SharingErrorKind::UnauthorizedDecryption | SharingErrorKind::LostKey
+        )
+    }
+
+    /// Whether what was shared could not be read
+    pub fn is_invalid_data(&self) -> bool {
+        matches!(self.kind, SharingErrorKind::InvalidData(_))
+    }
 }
 
 impl std::error::Error for SharingError {}
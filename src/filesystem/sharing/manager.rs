@@ -51,6 +51,25 @@ impl ShareManager {
         Ok(())
     }
 
+    /// Stop sharing our references with a recipient.
+    /// This only removes the recipient from the maps, the refs themselves must be rotated
+    /// before the next save for the revocation to mean anything.
+    pub fn revoke(&mut self, fingerprint: &str) -> Result<(), SharingError> {
+        // Never lock every key out of the Fs
+        if self.public_fingerprints() == vec![fingerprint.to_string()] {
+            return Err(SharingError::invalid_data(
+                "refusing to revoke the last key with access",
+            ));
+        }
+
+        // Remove from original
+        self.original_map.remove_recipient(fingerprint)?;
+        // Remove from current
+        self.current_map.remove_recipient(fingerprint)?;
+
+        Ok(())
+    }
+
     /// Grab a list of the PEM strings for each Public Key recipient
     pub fn public_fingerprints(&self) -> Vec<String> {
         self.original_map.0.clone().into_keys().collect()
@@ -69,6 +69,14 @@ impl EncRefMapper {
         Ok(())
     }
 
+    /// Remove a recipient from the mapper by fingerprint
+    pub fn remove_recipient(&mut self, fingerprint: &str) -> Result<(), SharingError> {
+        self.0
+            .remove(fingerprint)
+            .map(|_| ())
+            .ok_or(SharingError::lost_key())
+    }
+
     /// Decrypt the TemporalKey using a recipient's PrivateKey
     pub async fn recover_ref(
         &self,
@@ -153,7 +161,7 @@ impl<'de> Deserialize<'de> for EncRefMapper {
         D: serde::Deserializer<'de>,
     {
         let ipld = Ipld::deserialize(deserializer)?;
-        Ok(Self::from_ipld(ipld).expect("failed to convert IPLD to EncRefMapper"))
+        Self::from_ipld(ipld).map_err(serde::de::Error::custom)
     }
 }
 
@@ -161,7 +169,10 @@ impl<'de> Deserialize<'de> for EncRefMapper {
 #[cfg(test)]
 mod test {
 
-    use tomb_crypt::prelude::{EcEncryptionKey, PrivateKey};
+    use tomb_crypt::{
+        hex_fingerprint,
+        prelude::{EcEncryptionKey, PrivateKey, PublicKey},
+    };
     use wnfs::{
         common::dagcbor,
         libipld::Cid,
@@ -251,4 +262,33 @@ mod test {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn add_remove_recipient() -> Result<(), SharingError> {
+        // Create new mapper
+        let mut mapper = EncRefMapper::default();
+        // Create a new EC encryption key intended to be used to encrypt/decrypt temporal keys
+        let wrapping_key = EcEncryptionKey::generate().await?;
+        // Public Key
+        let public_key = wrapping_key.public_key()?;
+        let private_ref = PrivateRef {
+            temporal_key: TemporalKey(AesKey::new([7u8; 32])),
+            saturated_name_hash: [0u8; 32],
+            content_cid: Cid::default(),
+        };
+        // Insert a public key
+        mapper
+            .add_recipient(&Some(private_ref), &public_key)
+            .await?;
+        assert!(mapper.recover_ref(&wrapping_key).await.is_ok());
+
+        // Remove it again
+        let fingerprint = hex_fingerprint(public_key.fingerprint().await?.as_slice());
+        mapper.remove_recipient(&fingerprint)?;
+        assert!(mapper.recover_ref(&wrapping_key).await.is_err());
+        // Removing twice is an error
+        assert!(mapper.remove_recipient(&fingerprint).is_err());
+
+        Ok(())
+    }
 }
@@ -18,7 +18,10 @@ mod test {
     use crate::filesystem::sharing::manager::ShareManager;
     use rand::Rng;
     use serial_test::serial;
-    use tomb_crypt::prelude::{EcEncryptionKey, PrivateKey};
+    use tomb_crypt::{
+        hex_fingerprint,
+        prelude::{EcEncryptionKey, PrivateKey, PublicKey},
+    };
     use wnfs::private::{AesKey, PrivateRef, TemporalKey};
 
     use super::SharingError;
@@ -160,4 +163,34 @@ mod test {
 
         Ok(())
     }
+
+    #[tokio::test]
+    #[serial]
+    async fn share_with_revoke() -> Result<(), SharingError> {
+        // Key manager
+        let mut key_manager = ShareManager::default();
+        // Two recipients
+        let wrapping_key = EcEncryptionKey::generate().await?;
+        let other_key = EcEncryptionKey::generate().await?;
+        key_manager.share_with(&wrapping_key.public_key()?).await?;
+        key_manager.share_with(&other_key.public_key()?).await?;
+        // Set both refs
+        key_manager.set_original_ref(&random_private_ref()).await?;
+        key_manager.set_current_ref(&random_private_ref()).await?;
+
+        // Revoke the second recipient
+        let other_fingerprint =
+            hex_fingerprint(other_key.public_key()?.fingerprint().await?.as_slice());
+        key_manager.revoke(&other_fingerprint)?;
+        assert_eq!(key_manager.public_fingerprints().len(), 1);
+        assert!(key_manager.load_refs(&other_key).await.is_err());
+        assert!(key_manager.load_refs(&wrapping_key).await.is_ok());
+
+        // The last recipient can't be revoked
+        let fingerprint =
+            hex_fingerprint(wrapping_key.public_key()?.fingerprint().await?.as_slice());
+        assert!(key_manager.revoke(&fingerprint).is_err());
+
+        Ok(())
+    }
 }
@@ -20,7 +20,7 @@ fn serialize_payload<S: Serializer>(
     payload: &SharePayload,
     serializer: S,
 ) -> Result<S::Ok, S::Error> {
-    let bytes = dagcbor::encode(&payload).expect("failed to serialize payload");
+    let bytes = dagcbor::encode(&payload).map_err(serde::ser::Error::custom)?;
     bytes.serialize(serializer)
 }
 
@@ -28,7 +28,7 @@ fn deserialize_payload<'de, D: Deserializer<'de>>(
     deserializer: D,
 ) -> Result<SharePayload, D::Error> {
     let bytes = <Vec<u8>>::deserialize(deserializer)?;
-    Ok(dagcbor::decode::<SharePayload>(&bytes).expect("failed to deserialize payload"))
+    dagcbor::decode::<SharePayload>(&bytes).map_err(serde::de::Error::custom)
 }
 
 impl SharedFile {
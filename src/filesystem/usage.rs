@@ -0,0 +1,146 @@
+use crate::{
+    blockstore::BanyanBlockStore,
+    filesystem::{wnfsio::path_to_segments, FilesystemError, FsMetadata},
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use wnfs::{
+    common::{BlockStore, Metadata},
+    libipld::Ipld,
+    private::PrivateNode,
+};
+
+/// What files without a known MIME type are counted under
+const UNKNOWN_MIME_TYPE: &str = "unknown";
+
+/// What the data in a Drive is made up of, worked out from the sizes recorded on its files
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct UsageBreakdown {
+    /// Bytes in the current version of every file under each directory, keyed by its path from "/"
+    pub by_directory: BTreeMap<String, u64>,
+    /// Bytes in the current version of every file of each MIME type
+    pub by_mime_type: BTreeMap<String, u64>,
+    /// Bytes in every file by how many versions old it is, with the current version at 0
+    pub by_version: BTreeMap<usize, u64>,
+    /// Number of distinct content blocks behind every version of every file
+    pub blocks: usize,
+    /// Bytes taken by the blocks held in the content store, counting blocks shared
+    /// between files or versions once. Blocks only held remotely aren't counted.
+    pub stored: u64,
+}
+
+impl FsMetadata {
+    /// Break down the size of everything in the Fs by directory, MIME type and version
+    pub async fn usage_breakdown(
+        &self,
+        metadata_store: &impl BlockStore,
+        content_store: &impl BanyanBlockStore,
+    ) -> Result<UsageBreakdown, FilesystemError> {
+        let mut breakdown = UsageBreakdown::default();
+        let mut cids = BTreeSet::new();
+
+        for (node, path) in self.get_all_nodes(metadata_store).await? {
+            let PrivateNode::File(file) = node else {
+                continue;
+            };
+            let path_segments = path_to_segments(&path)?;
+            let metadata = file.get_metadata();
+            let file_size = size(metadata);
+
+            // Count the file towards every directory it is in, up to the root
+            for depth in 0..path_segments.len() {
+                let directory = format!("/{}", path_segments[..depth].join("/"));
+                *breakdown.by_directory.entry(directory).or_default() += file_size;
+            }
+            let mime_type = match metadata.0.get("mime_type") {
+                Some(Ipld::String(mime_type)) => mime_type.clone(),
+                _ => UNKNOWN_MIME_TYPE.to_string(),
+            };
+            *breakdown.by_mime_type.entry(mime_type).or_default() += file_size;
+
+            // A Fs which has never been saved has no history to follow
+            let revisions = if self.share_manager.original_ref.is_some() {
+                self.file_history(&path_segments, metadata_store).await?
+            } else {
+                vec![file.clone()]
+            };
+            for (age, revision) in revisions.iter().enumerate() {
+                *breakdown.by_version.entry(age).or_default() += size(revision.get_metadata());
+                cids.extend(
+                    revision
+                        .get_cids(&self.forest, metadata_store)
+                        .await
+                        .map_err(Box::from)?,
+                );
+            }
+        }
+
+        breakdown.blocks = cids.len();
+        for cid in cids {
+            if let Ok(block) = content_store.get_block(&cid).await {
+                breakdown.stored += block.len() as u64;
+            }
+        }
+
+        Ok(breakdown)
+    }
+}
+
+/// The size recorded on a file when it was written
+fn size(metadata: &Metadata) -> u64 {
+    match metadata.0.get("size") {
+        Some(Ipld::Integer(size)) => u64::try_from(*size).unwrap_or(0),
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        blockstore::MemoryBlockStore,
+        filesystem::{FilesystemError, FsMetadata},
+    };
+    use tomb_crypt::prelude::{EcEncryptionKey, PrivateKey};
+
+    #[tokio::test]
+    async fn usage_breakdown() -> Result<(), FilesystemError> {
+        let metadata_store = MemoryBlockStore::default();
+        let content_store = MemoryBlockStore::default();
+        let wrapping_key = &EcEncryptionKey::generate().await?;
+        let mut fs_metadata = FsMetadata::init(wrapping_key).await?;
+        fs_metadata.save(&metadata_store, &content_store).await?;
+
+        let cat_path = vec!["cats".to_string(), "cat.txt".to_string()];
+        let dog_path = vec!["dog.png".to_string()];
+        for (path, content) in [
+            (&cat_path, "hello kitty"),
+            (&dog_path, "woof"),
+            (&cat_path, "goodbye kitty!"),
+        ] {
+            fs_metadata
+                .write(
+                    path,
+                    &metadata_store,
+                    &content_store,
+                    content.as_bytes().to_vec(),
+                )
+                .await?;
+            fs_metadata.save(&metadata_store, &content_store).await?;
+        }
+
+        let breakdown = fs_metadata
+            .usage_breakdown(&metadata_store, &content_store)
+            .await?;
+        assert_eq!(breakdown.by_directory.get("/"), Some(&18));
+        assert_eq!(breakdown.by_directory.get("/cats"), Some(&14));
+        assert_eq!(breakdown.by_mime_type.get("text/plain"), Some(&14));
+        assert_eq!(breakdown.by_mime_type.get("image/png"), Some(&4));
+        // Only the cat has been rewritten
+        assert_eq!(breakdown.by_version.get(&0), Some(&18));
+        assert_eq!(breakdown.by_version.get(&1), Some(&11));
+        assert_eq!(breakdown.blocks, 3);
+        assert!(breakdown.stored > 0);
+
+        Ok(())
+    }
+}
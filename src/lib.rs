@@ -28,12 +28,14 @@ pub(crate) mod wasm;
 
 pub mod prelude {
     pub mod api {
-        pub use crate::api::{client, models, requests};
+        pub use crate::api::{client, models, requests, retry, throttle};
     }
     pub mod blockstore {
+        #[cfg(target_arch = "wasm32")]
+        pub use crate::blockstore::IndexedDbBlockStore;
         pub use crate::blockstore::{
-            BanyanApiBlockStore, BanyanBlockStore, CarV2MemoryBlockStore, DoubleSplitStore,
-            MemoryBlockStore, RootedBlockStore,
+            BanyanApiBlockStore, BanyanBlockStore, BlockCache, CarV2MemoryBlockStore,
+            DoubleSplitStore, MemoryBlockStore, RootedBlockStore, DEFAULT_CACHE_CAPACITY,
         };
         #[cfg(not(target_arch = "wasm32"))]
         pub use crate::blockstore::{CarV2DiskBlockStore, MultiCarV2DiskBlockStore};
@@ -42,14 +44,19 @@ pub mod prelude {
         pub use crate::car::{v1, v2};
     }
     pub mod filesystem {
-        pub use crate::filesystem::{serialize, sharing, wnfsio, FilesystemError, FsMetadata};
+        pub use crate::filesystem::{
+            serialize, sharing, wnfsio, FilesystemError, FsMetadata, UsageBreakdown,
+        };
+    }
+    pub mod progress {
+        pub use crate::utils::progress::{LogProgress, Phase, ProgressEvent, ProgressSink};
     }
     #[cfg(target_arch = "wasm32")]
     pub mod wasm {
         pub use crate::wasm::{
-            register_log, TombResult, TombWasm, TombWasmError, WasmBucket, WasmBucketKey,
-            WasmBucketMetadata, WasmBucketMount, WasmFsMetadataEntry, WasmMount, WasmNodeMetadata,
-            WasmSnapshot,
+            register_log, TombResult, TombWasm, TombWasmError, TombWasmErrorCode, WasmBucket,
+            WasmBucketKey, WasmBucketMetadata, WasmBucketMount, WasmFsMetadataEntry, WasmMount,
+            WasmNodeMetadata, WasmSnapshot, WasmUsageBreakdown,
         };
     }
 }
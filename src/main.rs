@@ -38,8 +38,17 @@ async fn main() {
 
     tracing_subscriber::registry().with(stderr_layer).init();
 
+    // Work out which configuration to run with
+    let selection = match cli.selection() {
+        Ok(selection) => selection,
+        Err(err) => {
+            tracing::error!("{err}");
+            return;
+        }
+    };
+
     // Determine the command being executed run appropriate subcommand
-    let _ = cli.command.run().await;
+    let _ = cli.command.run(&selection).await;
 }
 
 #[cfg(not(target_arch = "wasm32"))]
@@ -0,0 +1,198 @@
+use crate::native::{configuration::globalconfig::GlobalConfig, NativeError};
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{engine::general_purpose, Engine as _};
+use chacha20poly1305::{
+    aead::{Aead, KeyInit},
+    Key, XChaCha20Poly1305, XNonce,
+};
+use rand::{thread_rng, Rng};
+use serde::{Deserialize, Serialize};
+use std::{fs::File, io::Read, path::Path};
+use tomb_crypt::prelude::PrivateKey;
+
+const ENVELOPE_VERSION: u8 = 1;
+const ENVELOPE_KDF: &str = "argon2id";
+const SALT_LENGTH: usize = 16;
+const NONCE_LENGTH: usize = 24;
+const KEY_LENGTH: usize = 32;
+
+/// Data encrypted using a key derived from a passphrase with Argon2id
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct PassphraseEnvelope {
+    /// Envelope format version
+    version: u8,
+    /// Key derivation function used
+    kdf: String,
+    /// Argon2 memory cost in KiB
+    m_cost: u32,
+    /// Argon2 iteration count
+    t_cost: u32,
+    /// Argon2 parallelism
+    p_cost: u32,
+    /// Base64 encoded salt
+    salt: String,
+    /// Base64 encoded XChaCha20Poly1305 nonce
+    nonce: String,
+    /// Base64 encoded ciphertext
+    ciphertext: String,
+}
+
+impl PassphraseEnvelope {
+    /// Encrypt some data with a passphrase
+    pub fn seal(plaintext: &[u8], passphrase: &str) -> Result<Self, NativeError> {
+        let mut rng = thread_rng();
+        let salt: [u8; SALT_LENGTH] = rng.gen();
+        let nonce: [u8; NONCE_LENGTH] = rng.gen();
+        let (m_cost, t_cost, p_cost) = (
+            Params::DEFAULT_M_COST,
+            Params::DEFAULT_T_COST,
+            Params::DEFAULT_P_COST,
+        );
+
+        // Derive the key and encrypt
+        let key = derive_key(passphrase, &salt, m_cost, t_cost, p_cost)?;
+        let ciphertext = XChaCha20Poly1305::new(Key::from_slice(&key))
+            .encrypt(XNonce::from_slice(&nonce), plaintext)
+            .map_err(|_| NativeError::custom_error("unable to encrypt with passphrase"))?;
+
+        Ok(Self {
+            version: ENVELOPE_VERSION,
+            kdf: ENVELOPE_KDF.to_string(),
+            m_cost,
+            t_cost,
+            p_cost,
+            salt: general_purpose::STANDARD.encode(salt),
+            nonce: general_purpose::STANDARD.encode(nonce),
+            ciphertext: general_purpose::STANDARD.encode(ciphertext),
+        })
+    }
+
+    /// Decrypt the data with a passphrase
+    pub fn open(&self, passphrase: &str) -> Result<Vec<u8>, NativeError> {
+        if self.version != ENVELOPE_VERSION || self.kdf != ENVELOPE_KDF {
+            return Err(NativeError::custom_error(&format!(
+                "unsupported key envelope: v{} {}",
+                self.version, self.kdf
+            )));
+        }
+
+        let salt = decode(&self.salt)?;
+        let nonce = decode(&self.nonce)?;
+        let ciphertext = decode(&self.ciphertext)?;
+        if nonce.len() != NONCE_LENGTH {
+            return Err(NativeError::bad_data());
+        }
+
+        // Derive the key and decrypt
+        let key = derive_key(passphrase, &salt, self.m_cost, self.t_cost, self.p_cost)?;
+        XChaCha20Poly1305::new(Key::from_slice(&key))
+            .decrypt(XNonce::from_slice(&nonce), ciphertext.as_slice())
+            .map_err(|_| NativeError::bad_passphrase())
+    }
+
+    /// Serialize the envelope
+    pub fn to_bytes(&self) -> Result<Vec<u8>, NativeError> {
+        serde_json::to_vec_pretty(self).map_err(|_| NativeError::bad_data())
+    }
+
+    /// Deserialize an envelope, if these bytes are one
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        serde_json::from_slice(bytes).ok()
+    }
+}
+
+fn derive_key(
+    passphrase: &str,
+    salt: &[u8],
+    m_cost: u32,
+    t_cost: u32,
+    p_cost: u32,
+) -> Result<[u8; KEY_LENGTH], NativeError> {
+    let params = Params::new(m_cost, t_cost, p_cost, Some(KEY_LENGTH))
+        .map_err(|err| NativeError::custom_error(&format!("bad argon2 parameters: {err}")))?;
+    let mut key = [0u8; KEY_LENGTH];
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|err| NativeError::custom_error(&format!("unable to derive key: {err}")))?;
+    Ok(key)
+}
+
+fn decode(value: &str) -> Result<Vec<u8>, NativeError> {
+    general_purpose::STANDARD
+        .decode(value)
+        .map_err(|_| NativeError::bad_data())
+}
+
+/// All of the private key material for this device, as PEM strings
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct KeyBundle {
+    /// Key used to wrap Drive keys
+    pub wrapping_key: String,
+    /// Key used to authenticate with the remote, if there is one
+    pub api_key: Option<String>,
+}
+
+impl KeyBundle {
+    /// Collect the keys a GlobalConfig is using
+    pub async fn from_config(global: &GlobalConfig) -> Result<Self, NativeError> {
+        let wrapping_key = String::from_utf8(global.wrapping_key().await?.export().await?)?;
+        let api_key = match global.api_key().await {
+            Ok(api_key) => Some(String::from_utf8(api_key.export().await?)?),
+            Err(_) => None,
+        };
+        Ok(Self {
+            wrapping_key,
+            api_key,
+        })
+    }
+
+    /// Encrypt the bundle with a passphrase
+    pub fn seal(&self, passphrase: &str) -> Result<PassphraseEnvelope, NativeError> {
+        let plaintext = serde_json::to_vec(self).map_err(|_| NativeError::bad_data())?;
+        PassphraseEnvelope::seal(&plaintext, passphrase)
+    }
+
+    /// Decrypt a bundle with a passphrase
+    pub fn open(envelope: &PassphraseEnvelope, passphrase: &str) -> Result<Self, NativeError> {
+        let plaintext = envelope.open(passphrase)?;
+        serde_json::from_slice(&plaintext).map_err(|_| NativeError::bad_data())
+    }
+
+    /// Read and decrypt a bundle from disk
+    pub fn from_file(path: &Path, passphrase: &str) -> Result<Self, NativeError> {
+        let mut bytes = Vec::new();
+        File::open(path)?.read_to_end(&mut bytes)?;
+        let envelope = PassphraseEnvelope::from_bytes(&bytes).ok_or(NativeError::bad_data())?;
+        Self::open(&envelope, passphrase)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{KeyBundle, PassphraseEnvelope};
+    use crate::native::NativeError;
+
+    #[test]
+    fn seal_open() -> Result<(), NativeError> {
+        let plaintext = "hello kitty".as_bytes();
+        let envelope = PassphraseEnvelope::seal(plaintext, "correct horse")?;
+        // Survives serialization
+        let envelope = PassphraseEnvelope::from_bytes(&envelope.to_bytes()?)
+            .expect("failed to parse envelope");
+        assert_eq!(envelope.open("correct horse")?, plaintext);
+        // Wrong passphrases are rejected
+        assert!(envelope.open("battery staple").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn bundle_seal_open() -> Result<(), NativeError> {
+        let bundle = KeyBundle {
+            wrapping_key: "wrapping".to_string(),
+            api_key: None,
+        };
+        let envelope = bundle.seal("correct horse")?;
+        assert_eq!(KeyBundle::open(&envelope, "correct horse")?, bundle);
+        Ok(())
+    }
+}
@@ -1,18 +1,29 @@
 use crate::{
-    api::client::{Client, Credentials},
+    api::{
+        client::{Client, Credentials},
+        throttle::{Bandwidth, Progress},
+    },
+    blockstore::{BlockCache, DEFAULT_CACHE_CAPACITY},
     native::{
         configuration::{
             keys::{load_api_key, new_api_key, new_wrapping_key, save_api_key, wrapping_key},
-            xdg::{config_path, default_api_key_path, default_wrapping_key_path},
+            lock::FileLock,
+            migrations::{migrate, CONFIG_SCHEMA_VERSION},
+            xdg::Homes,
         },
         sync::LocalBucket,
         NativeError,
     },
     utils::get_read,
 };
+use bytesize::ByteSize;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::{
-    fs::{remove_file, OpenOptions},
+    collections::BTreeMap,
+    env,
+    fs::{create_dir_all, remove_dir_all, rename, write, OpenOptions},
+    io::Read,
     path::{Path, PathBuf},
     str::FromStr,
 };
@@ -20,11 +31,199 @@ use tomb_crypt::prelude::{EcEncryptionKey, EcSignatureKey};
 use url::Url;
 use uuid::Uuid;
 
-/// Represents the Global contents of the tomb configuration file in a user's .config
+/// Name of the profile used when no other has been selected
+pub const DEFAULT_PROFILE: &str = "default";
+/// Environment variable which selects a profile, overriding the one in use
+pub const PROFILE_VAR: &str = "BANYAN_PROFILE";
+/// Profile names become directory names, so they're kept to characters which can't escape one
+pub fn validate_profile_name(name: &str) -> Result<(), NativeError> {
+    if !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        Ok(())
+    } else {
+        Err(NativeError::custom_error(&format!(
+            "`{name}` is not a valid profile name; use only letters, digits, `_` and `-`"
+        )))
+    }
+}
+
+/// Environment variable which limits upload speed, overriding the configured limit
+pub const LIMIT_UPLOAD_VAR: &str = "BANYAN_LIMIT_UPLOAD";
+/// Environment variable which limits download speed, overriding the configured limit
+pub const LIMIT_DOWNLOAD_VAR: &str = "BANYAN_LIMIT_DOWNLOAD";
+
+/// Which configuration to use: the directories it lives in, along with choices made for a single
+/// run which take precedence over the config file without being saved to it
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Selection {
+    /// Directories holding configuration and data
+    pub homes: Homes,
+    /// Profile to use instead of the one in use
+    pub profile: Option<String>,
+    /// Most bytes uploaded per second, where 0 lifts the configured limit
+    pub limit_upload: Option<u64>,
+    /// Most bytes downloaded per second, where 0 lifts the configured limit
+    pub limit_download: Option<u64>,
+}
+
+impl Selection {
+    /// Use these directories, without overriding anything in the config file
+    pub fn new(homes: Homes) -> Self {
+        Self {
+            homes,
+            ..Default::default()
+        }
+    }
+
+    /// Use these directories, with the profile and limits chosen in the environment
+    pub fn with_env(homes: Homes) -> Result<Self, NativeError> {
+        let profile = env::var(PROFILE_VAR)
+            .ok()
+            .filter(|profile| !profile.is_empty());
+        if let Some(profile) = &profile {
+            validate_profile_name(profile)?;
+        }
+        Ok(Self {
+            homes,
+            profile,
+            limit_upload: rate_from_env(LIMIT_UPLOAD_VAR)?,
+            limit_download: rate_from_env(LIMIT_DOWNLOAD_VAR)?,
+        })
+    }
+}
+
+/// Represents the contents of the tomb configuration file in a user's .config, which holds every profile
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
-pub struct GlobalConfig {
+struct ConfigFile {
     /// Tomb version
     version: String,
+    /// Version of the schema this file follows
+    schema_version: u64,
+    /// Profile in use when none is explicitly selected
+    current_profile: String,
+    /// Profile configurations by name
+    profiles: BTreeMap<String, GlobalConfig>,
+}
+
+impl Default for ConfigFile {
+    fn default() -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            schema_version: CONFIG_SCHEMA_VERSION,
+            current_profile: DEFAULT_PROFILE.to_string(),
+            profiles: BTreeMap::new(),
+        }
+    }
+}
+
+impl ConfigFile {
+    /// Write to disk atomically, so that readers never see a partially written file
+    fn to_disk(&self, homes: &Homes) -> Result<(), NativeError> {
+        let path = homes.config_path();
+        let temp_path = path.with_extension("json.tmp");
+        let mut writer = OpenOptions::new()
+            .create(true)
+            .append(false)
+            .truncate(true)
+            .write(true)
+            .open(&temp_path)?;
+
+        serde_json::to_writer_pretty(&mut writer, &self).map_err(|_| NativeError::bad_data())?;
+        writer.sync_all()?;
+        rename(temp_path, path)?;
+        Ok(())
+    }
+
+    /// Initialize from file on disk, migrating it to the current schema version if need be.
+    /// The config lock is taken to write the migration out, unless the caller already holds it.
+    fn from_disk(homes: &Homes, config_lock: Option<&FileLock>) -> Result<Self, NativeError> {
+        let (mut original_version, mut bytes, mut file) = Self::read_migrated(homes)?;
+        if original_version < CONFIG_SCHEMA_VERSION {
+            let _lock = match config_lock {
+                Some(_) => None,
+                None => {
+                    let lock = homes.lock_config()?;
+                    // Another process may have migrated it while waiting for the lock
+                    (original_version, bytes, file) = Self::read_migrated(homes)?;
+                    Some(lock)
+                }
+            };
+            if original_version < CONFIG_SCHEMA_VERSION {
+                // Keep the original around in case anything goes wrong
+                write(
+                    homes
+                        .config_path()
+                        .with_extension(format!("v{original_version}.json.bak")),
+                    &bytes,
+                )?;
+                file.to_disk(homes)?;
+            }
+        }
+        // Profiles know their own names and where they live
+        for (name, profile) in file.profiles.iter_mut() {
+            profile.profile = name.clone();
+            profile.selection = Selection::new(homes.clone());
+        }
+        Ok(file)
+    }
+
+    /// Read the file and migrate it in memory, returning the schema version it was written in
+    /// along with the original bytes
+    fn read_migrated(homes: &Homes) -> Result<(u64, Vec<u8>, Self), NativeError> {
+        let path = homes.config_path();
+        let mut bytes = Vec::new();
+        get_read(&path)?.read_to_end(&mut bytes)?;
+        let config: Value = serde_json::from_slice(&bytes).map_err(|err| {
+            NativeError::bad_config(&format!("{} is not valid JSON: {err}", path.display()))
+        })?;
+        let (original_version, config) = migrate(config)?;
+        let file: Self = serde_path_to_error::deserialize(config).map_err(|err| {
+            NativeError::bad_config(&format!("`{}` is invalid: {}", err.path(), err.inner()))
+        })?;
+        Ok((original_version, bytes, file))
+    }
+
+    /// Load the file if there is one, otherwise start a new one
+    fn from_disk_or_default(
+        homes: &Homes,
+        config_lock: Option<&FileLock>,
+    ) -> Result<Self, NativeError> {
+        if homes.config_path().exists() {
+            Self::from_disk(homes, config_lock)
+        } else {
+            Ok(Self::default())
+        }
+    }
+
+    /// The profile selected for this run, or the one in use otherwise
+    fn selected_profile(&self, selection: &Selection) -> String {
+        selection
+            .profile
+            .clone()
+            .unwrap_or(self.current_profile.clone())
+    }
+
+    /// Get a profile by name
+    fn get_profile(&self, name: &str) -> Result<GlobalConfig, NativeError> {
+        self.profiles
+            .get(name)
+            .cloned()
+            .ok_or_else(|| NativeError::custom_error(&format!("No profile named `{name}` exists")))
+    }
+}
+
+/// Represents the configuration of a single profile: its remote, keys and Drives
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct GlobalConfig {
+    /// Name of this profile
+    #[serde(skip)]
+    profile: String,
+    /// Directories this profile's configuration and data live in, and what overrides it
+    #[serde(skip)]
+    selection: Selection,
     /// Location of wrapping key on disk in PEM format
     pub wrapping_key_path: PathBuf,
     /// Location of api key on disk in PEM format
@@ -33,12 +232,17 @@ pub struct GlobalConfig {
     endpoint: Url,
     /// Remote account id
     remote_user_id: Option<Uuid>,
+    /// Limits on how fast data is transferred
+    #[serde(default)]
+    bandwidth: Bandwidth,
     /// Bucket Configurations
     pub(crate) buckets: Vec<LocalBucket>,
 }
 
-impl Default for GlobalConfig {
-    fn default() -> Self {
+// Self
+impl GlobalConfig {
+    /// The default profile within a set of directories
+    fn default_in(homes: Homes) -> Self {
         let endpoint = Url::parse(if option_env!("DEV_ENDPOINTS").is_some() {
             "http://127.0.0.1:3001"
         } else {
@@ -47,48 +251,151 @@ impl Default for GlobalConfig {
         .expect("unable to parse known URLs");
 
         Self {
-            version: env!("CARGO_PKG_VERSION").to_string(),
+            profile: DEFAULT_PROFILE.to_string(),
             endpoint,
-            wrapping_key_path: default_wrapping_key_path(),
-            api_key_path: default_api_key_path(),
+            wrapping_key_path: homes.default_wrapping_key_path(),
+            api_key_path: homes.default_api_key_path(),
+            selection: Selection::new(homes),
             remote_user_id: None,
+            bandwidth: Bandwidth::default(),
             buckets: Vec::new(),
         }
     }
-}
 
-// Self
-impl GlobalConfig {
-    /// Create a new Global Configuration, keys, and save them all
-    pub async fn new() -> Result<Self, NativeError> {
+    /// Create a new Global Configuration, keys, and save them all in the given directories
+    pub async fn new_in(homes: Homes) -> Result<Self, NativeError> {
         // Create a default config
-        let config = Self::default();
+        let config = Self::default_in(homes);
         config.to_disk()?;
+        config.create_keys().await?;
+
+        // Ok
+        Ok(config)
+    }
 
+    /// Create a new named profile with its own keys, and save it
+    pub async fn new_profile(
+        &self,
+        name: &str,
+        endpoint: Option<Url>,
+    ) -> Result<Self, NativeError> {
+        validate_profile_name(name)?;
+        if ConfigFile::from_disk_or_default(self.homes(), None)?
+            .profiles
+            .contains_key(name)
+        {
+            return Err(NativeError::custom_error(&format!(
+                "A profile named `{name}` already exists"
+            )));
+        }
+
+        create_dir_all(self.homes().profile_config_home(name))?;
+        let default = Self::default_in(self.homes().clone());
+        let config = Self {
+            profile: name.to_string(),
+            endpoint: endpoint.unwrap_or(default.endpoint),
+            wrapping_key_path: self.homes().profile_wrapping_key_path(name),
+            api_key_path: self.homes().profile_api_key_path(name),
+            ..default
+        };
+        config.to_disk()?;
+        config.create_keys().await?;
+        Ok(config)
+    }
+
+    /// Create the keys for this profile
+    async fn create_keys(&self) -> Result<(), NativeError> {
         // Do not blindly overwrite key files if they exist
-        if !config.wrapping_key_path.exists() {
-            let _wrapping_key = new_wrapping_key(&config.wrapping_key_path).await?;
+        if !self.wrapping_key_path.exists() {
+            let _wrapping_key = new_wrapping_key(&self.wrapping_key_path).await?;
         }
-        if !config.api_key_path.exists() {
-            let _api_key = new_api_key(&config.api_key_path).await?;
+        if !self.api_key_path.exists() {
+            let _api_key = new_api_key(&self.api_key_path).await?;
         }
+        Ok(())
+    }
 
-        // Ok
-        Ok(config)
+    /// Get the name of this profile
+    pub fn profile(&self) -> &str {
+        &self.profile
+    }
+
+    /// Get the directories this profile lives in
+    pub fn homes(&self) -> &Homes {
+        &self.selection.homes
+    }
+
+    /// Open the cache of blocks fetched from remote storage
+    pub fn block_cache(&self) -> Result<BlockCache, NativeError> {
+        Ok(BlockCache::new(
+            &self.homes().block_cache_path(),
+            DEFAULT_CACHE_CAPACITY,
+        )?)
+    }
+
+    /// List every profile, along with the name of the one selected
+    pub fn profiles(&self) -> Result<(Vec<Self>, String), NativeError> {
+        let file = ConfigFile::from_disk(self.homes(), None)?;
+        let selected = file.selected_profile(&self.selection);
+        Ok((file.profiles.into_values().collect(), selected))
+    }
+
+    /// Make a profile the one in use when none is explicitly selected
+    pub fn use_profile(&self, name: &str) -> Result<(), NativeError> {
+        validate_profile_name(name)?;
+        let lock = self.homes().lock_config()?;
+        let mut file = ConfigFile::from_disk(self.homes(), Some(&lock))?;
+        file.get_profile(name)?;
+        file.current_profile = name.to_string();
+        file.to_disk(self.homes())
+    }
+
+    /// Remove a profile, along with its Drive data and any keys that belong to it alone
+    pub fn remove_profile(&self, name: &str) -> Result<(), NativeError> {
+        validate_profile_name(name)?;
+        let lock = self.homes().lock_config()?;
+        let mut file = ConfigFile::from_disk(self.homes(), Some(&lock))?;
+        if file.selected_profile(&self.selection) == name || self.profile == name {
+            return Err(NativeError::custom_error(
+                "Refusing to remove the profile in use; switch to another one first",
+            ));
+        }
+        let profile = file.get_profile(name)?;
+        for bucket in &profile.buckets {
+            bucket.remove_data(&self.homes().data)?;
+        }
+        // Only named profiles have a key directory of their own
+        let profile_home = self.homes().profile_config_home(name);
+        if profile.wrapping_key_path.starts_with(&profile_home) && profile_home.exists() {
+            // Never remove anything but a directory directly within the profiles directory
+            let profile_home = profile_home.canonicalize()?;
+            let profiles_home = self.homes().profiles_home().canonicalize()?;
+            if profile_home.parent() != Some(profiles_home.as_path()) {
+                return Err(NativeError::custom_error(&format!(
+                    "Refusing to remove {}, which is outside the profiles directory",
+                    profile_home.display()
+                )));
+            }
+            remove_dir_all(profile_home)?;
+        }
+        file.profiles.remove(name);
+        file.to_disk(self.homes())
     }
 
     /// Get the wrapping key
     pub async fn wrapping_key(&self) -> Result<EcEncryptionKey, NativeError> {
-        wrapping_key(&self.wrapping_key_path)
-            .await
-            .map_err(|_| NativeError::missing_wrapping_key())
+        if !self.wrapping_key_path.exists() {
+            return Err(NativeError::missing_wrapping_key());
+        }
+        wrapping_key(&self.wrapping_key_path).await
     }
 
     /// Get the api key
     pub async fn api_key(&self) -> Result<EcSignatureKey, NativeError> {
-        load_api_key(&self.api_key_path)
-            .await
-            .map_err(|_| NativeError::missing_api_key())
+        if !self.api_key_path.exists() {
+            return Err(NativeError::missing_api_key());
+        }
+        load_api_key(&self.api_key_path).await
     }
 
     // Get the Gredentials
@@ -103,6 +410,8 @@ impl GlobalConfig {
     pub async fn get_client(&self) -> Result<Client, NativeError> {
         // Create a new Client
         let mut client = Client::new(self.endpoint.as_ref())?;
+        client.with_bandwidth(self.bandwidth()?);
+        client.with_progress(Progress::PerTransfer);
         // If there are already credentials
         if let Ok(credentials) = self.get_credentials().await {
             // Set the credentials
@@ -115,14 +424,11 @@ impl GlobalConfig {
     #[allow(unused)]
     /// Save the Client data to the config
     pub async fn save_client(&mut self, client: Client) -> Result<(), NativeError> {
-        // Update the Remote endpoints
-        self.endpoint = client.remote_core;
-        // If there is a Claim
-        if let Some(token) = client.claims {
-            // Update the remote account ID
-            self.remote_user_id =
-                Some(Uuid::from_str(token.sub()?).map_err(|_| NativeError::bad_data())?);
-        }
+        // If there is a Claim, grab the remote account ID
+        let remote_user_id = match &client.claims {
+            Some(token) => Some(Uuid::from_str(token.sub()?).map_err(|_| NativeError::bad_data())?),
+            None => None,
+        };
 
         // If the Client has an API key
         if let Some(api_key) = client.signing_key {
@@ -130,7 +436,15 @@ impl GlobalConfig {
             save_api_key(&self.api_key_path, api_key).await?;
         }
 
-        self.to_disk()
+        self.modify(|config| {
+            // Update the Remote endpoints
+            config.endpoint = client.remote_core;
+            // Update the remote account ID
+            if remote_user_id.is_some() {
+                config.remote_user_id = remote_user_id;
+            }
+            Ok(())
+        })
     }
 
     #[allow(unused)]
@@ -139,69 +453,154 @@ impl GlobalConfig {
     }
 
     pub fn set_endpoint(&mut self, endpoint: Url) -> Result<(), NativeError> {
-        self.endpoint = endpoint;
-        self.to_disk()
+        self.modify(|config| {
+            config.endpoint = endpoint;
+            Ok(())
+        })
+    }
+
+    /// Get the limits on how fast data is transferred, letting the selection override those configured
+    pub fn bandwidth(&self) -> Result<Bandwidth, NativeError> {
+        let limit = |selected: Option<u64>, configured: Option<u64>| match selected {
+            Some(rate) => Some(rate).filter(|rate| *rate > 0),
+            None => configured,
+        };
+        Ok(Bandwidth {
+            upload: limit(self.selection.limit_upload, self.bandwidth.upload),
+            download: limit(self.selection.limit_download, self.bandwidth.download),
+        })
     }
 
-    /// Write to disk
+    /// Set the limits on how fast data is transferred, in bytes per second.
+    /// Limits which aren't given are left as they are, and a limit of 0 is lifted.
+    pub fn set_bandwidth(
+        &mut self,
+        upload: Option<u64>,
+        download: Option<u64>,
+    ) -> Result<(), NativeError> {
+        let unlimited_if_zero = |rate: u64| Some(rate).filter(|rate| *rate > 0);
+        self.modify(|config| {
+            if let Some(upload) = upload {
+                config.bandwidth.upload = unlimited_if_zero(upload);
+            }
+            if let Some(download) = download {
+                config.bandwidth.download = unlimited_if_zero(download);
+            }
+            Ok(())
+        })
+    }
+
+    /// Write to disk, leaving the other profiles untouched
     fn to_disk(&self) -> Result<(), NativeError> {
-        let writer = OpenOptions::new()
-            .create(true)
-            .append(false)
-            .truncate(true)
-            .write(true)
-            .open(config_path())?;
+        let lock = self.homes().lock_config()?;
+        let mut file = ConfigFile::from_disk_or_default(self.homes(), Some(&lock))?;
+        file.profiles.insert(self.profile.clone(), self.clone());
+        file.to_disk(self.homes())
+    }
 
-        serde_json::to_writer_pretty(writer, &self).map_err(|_| NativeError::bad_data())
+    /// Apply a change to the latest version of this profile on disk while holding the config lock,
+    /// so that changes made by other processes in the meantime aren't lost
+    fn modify(
+        &mut self,
+        change: impl FnOnce(&mut Self) -> Result<(), NativeError>,
+    ) -> Result<(), NativeError> {
+        let lock = self.homes().lock_config()?;
+        let mut file = ConfigFile::from_disk_or_default(self.homes(), Some(&lock))?;
+        let mut latest = file
+            .profiles
+            .get(&self.profile)
+            .cloned()
+            .unwrap_or_else(|| self.clone());
+        latest.selection = self.selection.clone();
+        change(&mut latest)?;
+        file.profiles.insert(self.profile.clone(), latest.clone());
+        file.to_disk(self.homes())?;
+        *self = latest;
+        Ok(())
     }
 
-    /// Initialize from file on disk
+    /// Initialize the selected profile from file on disk, finding it through the environment
     pub async fn from_disk() -> Result<Self, NativeError> {
-        let file = get_read(&config_path())?;
-        let config = serde_json::from_reader(file).map_err(|_| NativeError::bad_data())?;
+        Self::load(&Selection::with_env(Homes::from_env()?)?).await
+    }
+
+    /// Initialize the profile in use from file on disk in the given directories
+    pub async fn from_disk_in(homes: Homes) -> Result<Self, NativeError> {
+        Self::load(&Selection::new(homes)).await
+    }
+
+    /// Initialize the selected profile from file on disk
+    pub async fn load(selection: &Selection) -> Result<Self, NativeError> {
+        let file = ConfigFile::from_disk(&selection.homes, None)?;
+        let mut config = file.get_profile(&file.selected_profile(selection))?;
+        config.selection = selection.clone();
+        Ok(config)
+    }
+
+    /// Read this profile from disk again, picking up changes made since it was loaded
+    pub fn reload(&self) -> Result<Self, NativeError> {
+        let mut config = ConfigFile::from_disk(self.homes(), None)?.get_profile(&self.profile)?;
+        config.selection = self.selection.clone();
         Ok(config)
     }
 
+    /// Create a new Global Configuration in these directories if there isn't one already
+    pub async fn init_if_missing(homes: &Homes) -> Result<(), NativeError> {
+        if !homes.config_path().exists() {
+            Self::new_in(homes.clone()).await?;
+        }
+        Ok(())
+    }
+
     /// Remove a BucketConfig for an origin
     pub fn remove_bucket(&mut self, bucket: &LocalBucket) -> Result<(), NativeError> {
         // Remove bucket data
-        bucket.remove_data()?;
-        // Find index of bucket
-        let index = self
-            .buckets
-            .iter()
-            .position(|b| b == bucket)
-            .expect("cannot find index in buckets");
+        bucket.remove_data(&self.homes().data)?;
         // Remove bucket config from global config
-        self.buckets.remove(index);
-        self.to_disk()
+        self.modify(|config| {
+            config.buckets.retain(|b| b.origin != bucket.origin);
+            Ok(())
+        })
     }
 
     /// Remove Config data associated with each Bucket
     pub fn remove_all_data(&self) -> Result<(), NativeError> {
         // Remove bucket data
         for bucket in &self.buckets {
-            bucket.remove_data()?;
-        }
-        // Remove global
-        let path = config_path();
-        if path.exists() {
-            remove_file(path)?;
+            bucket.remove_data(&self.homes().data)?;
         }
         self.to_disk()
     }
 
     /// Update a given BucketConfig
     pub fn update_config(&mut self, bucket: &LocalBucket) -> Result<(), NativeError> {
-        // Find index
-        let index = self
+        self.modify(|config| {
+            // Find index
+            let index = config
+                .buckets
+                .iter()
+                .position(|b| b.origin == bucket.origin)
+                .ok_or(NativeError::missing_local_drive())?;
+            // Update bucket at index
+            config.buckets[index] = bucket.clone();
+            Ok(())
+        })
+    }
+
+    /// Take exclusive access to a Drive, then read its configuration and stores again so that
+    /// nothing written by another process before the lock was taken is lost
+    pub fn lock_bucket(
+        &self,
+        bucket: &LocalBucket,
+    ) -> Result<(FileLock, LocalBucket), NativeError> {
+        let drive_lock = bucket.lock()?;
+        let latest = self
+            .reload()?
             .buckets
-            .iter()
-            .position(|b| b.origin == bucket.origin)
+            .into_iter()
+            .find(|b| b.local_id == bucket.local_id)
             .ok_or(NativeError::missing_local_drive())?;
-        // Update bucket at index
-        self.buckets[index] = bucket.clone();
-        self.to_disk()
+        Ok((drive_lock, latest))
     }
 
     /// Create a new bucket
@@ -211,10 +610,12 @@ impl GlobalConfig {
         origin: &Path,
     ) -> Result<LocalBucket, NativeError> {
         let wrapping_key = self.wrapping_key().await?;
-        let mut bucket = LocalBucket::new(origin, &wrapping_key).await?;
+        let mut bucket = LocalBucket::new(origin, &wrapping_key, &self.homes().data).await?;
         bucket.name = name.to_string();
-        self.buckets.push(bucket.clone());
-        self.to_disk()?;
+        self.modify(|config| {
+            config.buckets.push(bucket.clone());
+            Ok(())
+        })?;
         Ok(bucket)
     }
 
@@ -240,109 +641,230 @@ impl GlobalConfig {
     }
 }
 
+/// Read a rate limit in bytes per second from the environment
+fn rate_from_env(var: &str) -> Result<Option<u64>, NativeError> {
+    match env::var(var) {
+        Ok(rate) if !rate.is_empty() => {
+            let rate = ByteSize::from_str(&rate).map_err(|err| {
+                NativeError::custom_error(&format!("Invalid rate in {var}: {err}"))
+            })?;
+            Ok(Some(rate.as_u64()))
+        }
+        _ => Ok(None),
+    }
+}
+
 #[cfg(test)]
 mod test {
 
-    use serial_test::serial;
-    use std::{fs::remove_file, path::Path};
-
-    use crate::native::{
-        configuration::{
-            globalconfig::GlobalConfig,
-            xdg::{config_path, default_api_key_path, default_wrapping_key_path},
+    use std::{
+        fs::{remove_dir_all, File},
+        path::Path,
+    };
+    use url::Url;
+
+    use crate::{
+        api::throttle::Bandwidth,
+        native::{
+            configuration::{
+                globalconfig::{GlobalConfig, Selection, DEFAULT_PROFILE},
+                xdg::Homes,
+            },
+            NativeError,
         },
-        NativeError,
     };
 
+    /// Fresh directories for each test, so that they can run in parallel
+    fn test_homes(test_name: &str) -> Result<Homes, NativeError> {
+        let home = Path::new("test").join("homes").join(test_name);
+        if home.exists() {
+            remove_dir_all(&home)?;
+        }
+        Homes::within(&home)
+    }
+
     #[tokio::test]
-    #[serial]
     async fn to_from_disk() -> Result<(), NativeError> {
-        // The known path of the global config file
-        let known_path = config_path();
-        // Remove it if it exists
-        if known_path.exists() {
-            remove_file(&known_path)?;
-        }
-        let known_path = default_wrapping_key_path();
-        // Remove it if it exists
-        if known_path.exists() {
-            remove_file(&known_path)?;
-        }
-        let known_path = default_api_key_path();
-        // Remove it if it exists
-        if known_path.exists() {
-            remove_file(&known_path)?;
-        }
+        let homes = test_homes("to_from_disk")?;
         // Create default
-        let original = GlobalConfig::new().await?;
+        let original = GlobalConfig::new_in(homes.clone()).await?;
         // Load from disk
-        let reconstructed = GlobalConfig::from_disk().await?;
+        let reconstructed = GlobalConfig::from_disk_in(homes).await?;
         assert_eq!(original, reconstructed);
         Ok(())
     }
 
     #[tokio::test]
-    #[serial]
     async fn from_disk_direct() -> Result<(), NativeError> {
-        // The known path of the global config file
-        let known_path = config_path();
-        // Remove it if it exists
-        if known_path.exists() {
-            remove_file(&known_path)?;
-        }
-        let known_path = default_wrapping_key_path();
-        // Remove it if it exists
-        if known_path.exists() {
-            remove_file(&known_path)?;
-        }
-        let known_path = default_api_key_path();
-        // Remove it if it exists
-        if known_path.exists() {
-            remove_file(&known_path)?;
-        }
+        let homes = test_homes("from_disk_direct")?;
         // Load from disk
-        let reconstructed = GlobalConfig::new().await?;
+        let reconstructed = GlobalConfig::new_in(homes.clone()).await?;
+        // Keys aren't needed to load the config
+        std::fs::remove_file(homes.default_wrapping_key_path())?;
+        std::fs::remove_file(homes.default_api_key_path())?;
         // Assert that it is just the default config
-        let known_path = default_wrapping_key_path();
-        // Remove it if it exists
-        if known_path.exists() {
-            remove_file(&known_path)?;
-        }
-        let known_path = default_api_key_path();
-        // Remove it if it exists
-        if known_path.exists() {
-            remove_file(&known_path)?;
-        }
-        assert_eq!(GlobalConfig::from_disk().await?, reconstructed);
+        assert_eq!(GlobalConfig::from_disk_in(homes).await?, reconstructed);
         Ok(())
     }
 
     #[tokio::test]
-    #[serial]
     async fn add_bucket() -> Result<(), NativeError> {
-        // The known path of the global config file
-        let known_path = config_path();
-        // Remove it if it exists
-        if known_path.exists() {
-            remove_file(&known_path)?;
-        }
-
-        let origin = Path::new("test");
+        let homes = test_homes("add_bucket")?;
+        let origin = Path::new("test").join("add_bucket_origin");
 
         // Create
-        let mut original = GlobalConfig::new().await?;
-        let original_bucket = original.get_or_init_bucket("new", origin).await?;
+        let mut original = GlobalConfig::new_in(homes.clone()).await?;
+        let original_bucket = original.get_or_init_bucket("new", &origin).await?;
         // Save
         original.to_disk()?;
-        let reconstructed = GlobalConfig::from_disk().await?;
+        let reconstructed = GlobalConfig::from_disk_in(homes.clone()).await?;
         let reconstructed_bucket = reconstructed
-            .get_bucket(origin)
+            .get_bucket(&origin)
             .expect("bucket config does not exist for this origin");
 
         // Assert equality
         assert_eq!(original_bucket.metadata, reconstructed_bucket.metadata);
         assert_eq!(original_bucket.content, reconstructed_bucket.content);
+        // Drive data lives in the data home
+        assert!(reconstructed_bucket.metadata.path.starts_with(&homes.data));
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn bandwidth() -> Result<(), NativeError> {
+        let homes = test_homes("bandwidth")?;
+        let mut config = GlobalConfig::new_in(homes.clone()).await?;
+        config.set_bandwidth(Some(1 << 20), Some(4 << 20))?;
+        // Limits which aren't given are left alone, and 0 lifts a limit
+        config.set_bandwidth(Some(0), None)?;
+        let reconstructed = GlobalConfig::from_disk_in(homes.clone()).await?;
+        assert_eq!(
+            reconstructed.bandwidth()?,
+            Bandwidth {
+                upload: None,
+                download: Some(4 << 20),
+            }
+        );
+
+        // The selection overrides the configured limits, and can lift them
+        let mut selection = Selection::new(homes);
+        selection.limit_download = Some(8 << 20);
+        let selected = GlobalConfig::load(&selection).await?;
+        assert_eq!(selected.bandwidth()?.download, Some(8 << 20));
+        selection.limit_download = Some(0);
+        let selected = GlobalConfig::load(&selection).await?;
+        assert_eq!(selected.bandwidth()?.download, None);
+        // Without being saved
+        selected.reload()?.set_bandwidth(None, None)?;
+        assert_eq!(
+            GlobalConfig::from_disk_in(selection.homes)
+                .await?
+                .bandwidth()?,
+            reconstructed.bandwidth()?
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn stale_updates() -> Result<(), NativeError> {
+        let homes = test_homes("stale_updates")?;
+        let mut first = GlobalConfig::new_in(homes.clone()).await?;
+        let mut second = GlobalConfig::from_disk_in(homes.clone()).await?;
+
+        // Neither handle knows about the other's Drive
+        let first_origin = Path::new("test").join("stale_updates_first");
+        let second_origin = Path::new("test").join("stale_updates_second");
+        first.get_or_init_bucket("first", &first_origin).await?;
+        second.get_or_init_bucket("second", &second_origin).await?;
+
+        // But both Drives are saved
+        let reconstructed = GlobalConfig::from_disk_in(homes).await?;
+        assert!(reconstructed.get_bucket(&first_origin).is_some());
+        assert!(reconstructed.get_bucket(&second_origin).is_some());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn migrate_legacy() -> Result<(), NativeError> {
+        let homes = test_homes("migrate_legacy")?;
+        // Write a config file from before profiles existed
+        let legacy = GlobalConfig::default_in(homes.clone());
+        serde_json::to_writer_pretty(File::create(homes.config_path())?, &legacy)
+            .map_err(|_| NativeError::bad_data())?;
+
+        // It is loaded as the default profile
+        let migrated = GlobalConfig::from_disk_in(homes.clone()).await?;
+        assert_eq!(migrated, legacy);
+        assert_eq!(migrated.profile(), DEFAULT_PROFILE);
+        // And rewritten in the new format, keeping a backup of the original
+        let (profiles, selected) = migrated.profiles()?;
+        assert_eq!(profiles, vec![legacy]);
+        assert_eq!(selected, DEFAULT_PROFILE);
+        assert!(homes.config_path().with_extension("v0.json.bak").exists());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn bad_field() -> Result<(), NativeError> {
+        let homes = test_homes("bad_field")?;
+        // Write a config with an unusable endpoint
+        let mut config = serde_json::to_value(GlobalConfig::default_in(homes.clone())).unwrap();
+        config["endpoint"] = serde_json::json!(42);
+        serde_json::to_writer_pretty(File::create(homes.config_path())?, &config)
+            .map_err(|_| NativeError::bad_data())?;
+
+        // The error names the field
+        let err = GlobalConfig::from_disk_in(homes)
+            .await
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("profiles.default.endpoint"));
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn add_use_remove_profile() -> Result<(), NativeError> {
+        let homes = test_homes("add_use_remove_profile")?;
+        // Start from just the default profile
+        let default = GlobalConfig::new_in(homes.clone()).await?;
+
+        // Named profiles get their own endpoint and keys
+        let endpoint = Url::parse("http://127.0.0.1:3001").unwrap();
+        let work = default.new_profile("work", Some(endpoint.clone())).await?;
+        assert_eq!(work.get_endpoint(), endpoint);
+        assert_ne!(work.wrapping_key_path, default.wrapping_key_path);
+        assert!(work.wrapping_key_path.exists());
+        assert!(default.new_profile("work", None).await.is_err());
+
+        // Switching profiles changes what is loaded
+        default.use_profile("work")?;
+        assert_eq!(GlobalConfig::from_disk_in(homes.clone()).await?, work);
+        assert!(default.remove_profile("work").is_err());
+        default.use_profile(DEFAULT_PROFILE)?;
+        assert_eq!(GlobalConfig::from_disk_in(homes).await?, default);
+        assert!(default.use_profile("missing").is_err());
+
+        // Removing a profile removes its keys
+        default.remove_profile("work")?;
+        assert!(!work.wrapping_key_path.exists());
+        assert_eq!(default.profiles()?.0, vec![default]);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn invalid_profile_names() -> Result<(), NativeError> {
+        let homes = test_homes("invalid_profile_names")?;
+        let default = GlobalConfig::new_in(homes.clone()).await?;
+
+        // Names which would lead out of the profiles directory are refused outright
+        for name in ["..", "/x", "", "work/../.."] {
+            assert!(default.new_profile(name, None).await.is_err());
+            assert!(default.use_profile(name).is_err());
+            assert!(default.remove_profile(name).is_err());
+        }
+        assert!(default.wrapping_key_path.exists());
+        assert!(homes.config_path().exists());
+        Ok(())
+    }
 }
@@ -1,11 +1,15 @@
-use crate::native::NativeError;
+use crate::native::{configuration::bundle::PassphraseEnvelope, NativeError};
 use std::{
-    fs::File,
+    env,
+    fs::{rename, File},
     io::{Read, Write},
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 use tomb_crypt::prelude::{EcEncryptionKey, EcSignatureKey, PrivateKey};
 
+/// Environment variable which can hold the passphrase for keys encrypted at rest
+const KEY_PASSPHRASE_VAR: &str = "BANYAN_KEY_PASSPHRASE";
+
 /// Generate a new Ecdsa key to use for authentication
 /// Writes the key to the config path
 pub async fn new_api_key(path: &PathBuf) -> Result<EcSignatureKey, NativeError> {
@@ -14,16 +18,13 @@ pub async fn new_api_key(path: &PathBuf) -> Result<EcSignatureKey, NativeError>
     }
     let key = EcSignatureKey::generate().await?;
     let pem_bytes = key.export().await?;
-    let mut f = File::create(path)?;
-    f.write_all(&pem_bytes)?;
+    write_key_file(path, &pem_bytes)?;
     Ok(key)
 }
 
 /// Read the API key from disk
 pub async fn load_api_key(path: &PathBuf) -> Result<EcSignatureKey, NativeError> {
-    let mut reader = File::open(path)?;
-    let mut pem_bytes = Vec::new();
-    reader.read_to_end(&mut pem_bytes)?;
+    let pem_bytes = read_key_file(path)?;
     let key = EcSignatureKey::import(&pem_bytes).await?;
     Ok(key)
 }
@@ -31,10 +32,8 @@ pub async fn load_api_key(path: &PathBuf) -> Result<EcSignatureKey, NativeError>
 #[allow(dead_code)]
 /// Save the API key to disk
 pub async fn save_api_key(path: &PathBuf, key: EcSignatureKey) -> Result<(), NativeError> {
-    let mut writer = File::create(path)?;
     // Write the PEM bytes
-    writer.write_all(&key.export().await?)?;
-    Ok(())
+    write_key_file(path, &key.export().await?)
 }
 
 /// Generate a new Ecdh key to use for key wrapping
@@ -45,16 +44,122 @@ pub async fn new_wrapping_key(path: &PathBuf) -> Result<EcEncryptionKey, NativeE
     }
     let key = EcEncryptionKey::generate().await?;
     let pem_bytes = key.export().await?;
-    let mut f = File::create(path)?;
-    f.write_all(&pem_bytes)?;
+    write_key_file(path, &pem_bytes)?;
     Ok(key)
 }
 
 /// Read the Wrapping key from disk
 pub async fn wrapping_key(path: &PathBuf) -> Result<EcEncryptionKey, NativeError> {
-    let mut reader = File::open(path)?;
-    let mut pem_bytes = Vec::new();
-    reader.read_to_end(&mut pem_bytes)?;
+    let pem_bytes = read_key_file(path)?;
     let key = EcEncryptionKey::import(&pem_bytes).await?;
     Ok(key)
 }
+
+/// Save the Wrapping key to disk
+pub async fn save_wrapping_key(path: &PathBuf, key: &EcEncryptionKey) -> Result<(), NativeError> {
+    write_key_file(path, &key.export().await?)
+}
+
+/// Whether or not the key file at this path is encrypted at rest
+pub fn is_encrypted(path: &Path) -> Result<bool, NativeError> {
+    Ok(PassphraseEnvelope::from_bytes(&read_raw(path)?).is_some())
+}
+
+/// Encrypt a plaintext key file in place
+pub fn encrypt_key_file(path: &Path, passphrase: &str) -> Result<(), NativeError> {
+    let raw = read_raw(path)?;
+    if PassphraseEnvelope::from_bytes(&raw).is_some() {
+        return Ok(());
+    }
+    let envelope = PassphraseEnvelope::seal(&raw, passphrase)?;
+    write_raw(path, &envelope.to_bytes()?)
+}
+
+/// Decrypt an encrypted key file in place
+pub fn decrypt_key_file(path: &Path, passphrase: &str) -> Result<(), NativeError> {
+    let raw = read_raw(path)?;
+    if let Some(envelope) = PassphraseEnvelope::from_bytes(&raw) {
+        write_raw(path, &envelope.open(passphrase)?)?;
+    }
+    Ok(())
+}
+
+/// Grab the passphrase for keys encrypted at rest, prompting for it if it isn't in the environment
+pub fn key_passphrase() -> Result<String, NativeError> {
+    match env::var(KEY_PASSPHRASE_VAR) {
+        Ok(passphrase) => Ok(passphrase),
+        Err(_) => Ok(rpassword::prompt_password("Key passphrase: ")?),
+    }
+}
+
+fn read_raw(path: &Path) -> Result<Vec<u8>, NativeError> {
+    let mut reader = File::open(path)?;
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Replace a key file atomically, as it may hold the only copy of the key
+fn write_raw(path: &Path, bytes: &[u8]) -> Result<(), NativeError> {
+    let temp_path = path.with_extension("tmp");
+    let mut writer = File::create(&temp_path)?;
+    writer.write_all(bytes)?;
+    writer.sync_all()?;
+    rename(temp_path, path)?;
+    Ok(())
+}
+
+/// Read the PEM bytes of a key file, decrypting them if need be
+fn read_key_file(path: &Path) -> Result<Vec<u8>, NativeError> {
+    let raw = read_raw(path)?;
+    match PassphraseEnvelope::from_bytes(&raw) {
+        Some(envelope) => envelope.open(&key_passphrase()?),
+        None => Ok(raw),
+    }
+}
+
+/// Write the PEM bytes of a key file, keeping it encrypted if it already was
+fn write_key_file(path: &Path, pem_bytes: &[u8]) -> Result<(), NativeError> {
+    let bytes = if path.exists() && is_encrypted(path)? {
+        PassphraseEnvelope::seal(pem_bytes, &key_passphrase()?)?.to_bytes()?
+    } else {
+        pem_bytes.to_vec()
+    };
+    write_raw(path, &bytes)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{decrypt_key_file, encrypt_key_file, is_encrypted, wrapping_key};
+    use crate::native::{configuration::keys::new_wrapping_key, NativeError};
+    use serial_test::serial;
+    use std::{env, fs::create_dir_all, path::Path};
+    use tomb_crypt::prelude::{PrivateKey, PublicKey};
+
+    #[tokio::test]
+    #[serial]
+    async fn encrypt_decrypt_at_rest() -> Result<(), NativeError> {
+        let test_path = Path::new("test").join("keys_at_rest");
+        create_dir_all(&test_path)?;
+        let key_path = test_path.join("wrapping_key.pem");
+        let key = new_wrapping_key(&key_path).await?;
+        let fingerprint = key.public_key()?.fingerprint().await?;
+
+        // Encrypt the file in place
+        encrypt_key_file(&key_path, "correct horse")?;
+        assert!(is_encrypted(&key_path)?);
+        // Loading uses the passphrase in the environment
+        env::set_var(super::KEY_PASSPHRASE_VAR, "correct horse");
+        let loaded_key = wrapping_key(&key_path).await?;
+        env::remove_var(super::KEY_PASSPHRASE_VAR);
+        assert_eq!(fingerprint, loaded_key.public_key()?.fingerprint().await?);
+
+        // Decrypt it again
+        assert!(decrypt_key_file(&key_path, "battery staple").is_err());
+        decrypt_key_file(&key_path, "correct horse")?;
+        assert!(!is_encrypted(&key_path)?);
+
+        std::fs::remove_dir_all(test_path)?;
+        Ok(())
+    }
+}
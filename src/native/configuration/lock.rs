@@ -0,0 +1,75 @@
+use crate::native::NativeError;
+use fs2::FileExt;
+use std::{
+    fs::{File, OpenOptions},
+    path::Path,
+};
+
+/// An advisory lock on a file which keeps every other holder out until it is dropped.
+/// Locks aren't reentrant, not even within a process, so whoever holds one has to pass it down
+/// to anything else which needs it.
+#[derive(Debug)]
+pub struct FileLock {
+    /// The locked file
+    file: File,
+}
+
+impl FileLock {
+    /// Wait until an exclusive lock can be taken on the file at this path
+    pub fn exclusive(path: &Path) -> Result<Self, NativeError> {
+        let file = Self::open(path)?;
+        file.lock_exclusive()?;
+        Ok(Self { file })
+    }
+
+    /// Take an exclusive lock on the file at this path, returning None if it is already held
+    pub fn try_exclusive(path: &Path) -> Result<Option<Self>, NativeError> {
+        let file = Self::open(path)?;
+        match file.try_lock_exclusive() {
+            Ok(()) => Ok(Some(Self { file })),
+            Err(err) if err.kind() == fs2::lock_contended_error().kind() => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    fn open(path: &Path) -> Result<File, NativeError> {
+        Ok(OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path)?)
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = self.file.unlock();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::FileLock;
+    use crate::native::NativeError;
+    use std::{
+        fs::{create_dir_all, remove_dir_all},
+        path::Path,
+    };
+
+    #[test]
+    fn exclusive() -> Result<(), NativeError> {
+        let test_path = Path::new("test").join("file_lock");
+        create_dir_all(&test_path)?;
+        let lock_path = test_path.join("test.lock");
+
+        let held = FileLock::exclusive(&lock_path)?;
+        // Nobody else gets in while it is held, not even this process
+        assert!(FileLock::try_exclusive(&lock_path)?.is_none());
+        drop(held);
+        // The lock is free again once its holder is gone
+        assert!(FileLock::try_exclusive(&lock_path)?.is_some());
+
+        remove_dir_all(test_path)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,98 @@
+use crate::native::{configuration::globalconfig::DEFAULT_PROFILE, NativeError};
+use serde_json::{json, Value};
+
+/// Version of the configuration file schema written by this build
+pub const CONFIG_SCHEMA_VERSION: u64 = 1;
+
+/// Upgrades a configuration from one schema version to the next
+type Migration = fn(Value) -> Result<Value, NativeError>;
+
+/// Every migration in order, such that `MIGRATIONS[n]` upgrades schema version `n` to `n + 1`
+const MIGRATIONS: [Migration; CONFIG_SCHEMA_VERSION as usize] = [v0_to_v1];
+
+/// Determine the schema version of a configuration
+pub fn schema_version(config: &Value) -> Result<u64, NativeError> {
+    match config.get("schema_version") {
+        Some(version) => version
+            .as_u64()
+            .ok_or_else(|| NativeError::bad_config("`schema_version` is not a number")),
+        // Profiles were introduced before the schema was versioned
+        None if config.get("profiles").is_some() => Ok(1),
+        None => Ok(0),
+    }
+}
+
+/// Run every migration needed to bring a configuration up to the current schema version,
+/// returning the version it started at along with the upgraded configuration
+pub fn migrate(mut config: Value) -> Result<(u64, Value), NativeError> {
+    let original_version = schema_version(&config)?;
+    if original_version > CONFIG_SCHEMA_VERSION {
+        return Err(NativeError::bad_config(&format!(
+            "schema version {original_version} was written by a newer version of this tool, which understands up to version {CONFIG_SCHEMA_VERSION}"
+        )));
+    }
+    for (version, migration) in MIGRATIONS
+        .iter()
+        .enumerate()
+        .skip(original_version as usize)
+    {
+        info!("Migrating configuration from schema version {version}...");
+        config = migration(config)?;
+    }
+    // Files from before the schema was versioned may not say so yet
+    if let Some(fields) = config.as_object_mut() {
+        fields.insert("schema_version".to_string(), json!(CONFIG_SCHEMA_VERSION));
+    }
+    Ok((original_version, config))
+}
+
+/// Version 0 held a single configuration, which becomes the default profile
+fn v0_to_v1(mut config: Value) -> Result<Value, NativeError> {
+    let fields = config
+        .as_object_mut()
+        .ok_or_else(|| NativeError::bad_config("expected an object at the top level"))?;
+    let version = fields
+        .remove("version")
+        .unwrap_or(json!(env!("CARGO_PKG_VERSION")));
+    Ok(json!({
+        "version": version,
+        "schema_version": 1,
+        "current_profile": DEFAULT_PROFILE,
+        "profiles": { DEFAULT_PROFILE: config },
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{migrate, schema_version, CONFIG_SCHEMA_VERSION};
+    use crate::native::{configuration::globalconfig::DEFAULT_PROFILE, NativeError};
+    use serde_json::json;
+
+    #[test]
+    fn migrate_v0() -> Result<(), NativeError> {
+        let legacy = json!({
+            "version": "1.0.0",
+            "endpoint": "http://127.0.0.1:3001/",
+            "buckets": [],
+        });
+        let (original_version, migrated) = migrate(legacy)?;
+        assert_eq!(original_version, 0);
+        assert_eq!(schema_version(&migrated)?, CONFIG_SCHEMA_VERSION);
+        assert_eq!(migrated["current_profile"], DEFAULT_PROFILE);
+        assert_eq!(
+            migrated["profiles"][DEFAULT_PROFILE]["endpoint"],
+            "http://127.0.0.1:3001/"
+        );
+        // Running again changes nothing
+        let (version, remigrated) = migrate(migrated.clone())?;
+        assert_eq!(version, CONFIG_SCHEMA_VERSION);
+        assert_eq!(remigrated, migrated);
+        Ok(())
+    }
+
+    #[test]
+    fn reject_newer() {
+        let future = json!({ "schema_version": CONFIG_SCHEMA_VERSION + 1 });
+        assert!(migrate(future).is_err());
+    }
+}
@@ -1,6 +1,14 @@
+/// Passphrase encrypted key bundles
+pub mod bundle;
 /// Global level configurations
 pub mod globalconfig;
 /// Key config
 pub mod keys;
+/// Advisory file locks
+pub mod lock;
+/// Configuration schema migrations
+pub mod migrations;
+/// Recovery phrases for wrapping keys
+pub mod recovery;
 /// XDG config
 pub mod xdg;
@@ -0,0 +1,99 @@
+use crate::native::NativeError;
+use bip39::Mnemonic;
+use pem::Pem;
+use tomb_crypt::prelude::{EcEncryptionKey, PrivateKey};
+
+/// Length of a P-384 private scalar
+const SCALAR_LENGTH: usize = 48;
+/// Words encoding the first 32 bytes of the scalar
+const FIRST_PHRASE_WORDS: usize = 24;
+/// ECPrivateKey version and the start of the private key octet string
+const EC_PRIVATE_KEY_PREFIX: [u8; 5] = [0x02, 0x01, 0x01, 0x04, 0x30];
+/// PKCS8 wrapper for an ECPrivateKey on secp384r1 without the optional public key
+const PKCS8_PREFIX: [u8; 30] = [
+    0x30, 0x4e, 0x02, 0x01, 0x00, 0x30, 0x10, 0x06, 0x07, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x02, 0x01,
+    0x06, 0x05, 0x2b, 0x81, 0x04, 0x00, 0x22, 0x04, 0x37, 0x30, 0x35, 0x02, 0x01, 0x01,
+];
+
+/// Represent a wrapping key as a printable phrase of 36 BIP39 words.
+/// BIP39 tops out at 32 bytes of entropy, so the 48 byte scalar is split across two mnemonics.
+pub async fn to_recovery_phrase(wrapping_key: &EcEncryptionKey) -> Result<String, NativeError> {
+    // Find the private scalar in the DER
+    let der = pem::parse(wrapping_key.export().await?)
+        .map_err(|_| NativeError::bad_data())?
+        .into_contents();
+    let start = der
+        .windows(EC_PRIVATE_KEY_PREFIX.len())
+        .position(|window| window == EC_PRIVATE_KEY_PREFIX)
+        .ok_or(NativeError::bad_data())?
+        + EC_PRIVATE_KEY_PREFIX.len();
+    let scalar = der
+        .get(start..start + SCALAR_LENGTH)
+        .ok_or(NativeError::bad_data())?;
+
+    // Encode it as words
+    let first = Mnemonic::from_entropy(&scalar[..32]).map_err(|_| NativeError::bad_data())?;
+    let second = Mnemonic::from_entropy(&scalar[32..]).map_err(|_| NativeError::bad_data())?;
+    Ok(format!("{first} {second}"))
+}
+
+/// Reconstruct a wrapping key from its recovery phrase
+pub async fn from_recovery_phrase(phrase: &str) -> Result<EcEncryptionKey, NativeError> {
+    let words = phrase.split_whitespace().collect::<Vec<&str>>();
+    if words.len() != FIRST_PHRASE_WORDS + FIRST_PHRASE_WORDS / 2 {
+        return Err(NativeError::custom_error(
+            "recovery phrases are exactly 36 words long",
+        ));
+    }
+
+    // Decode and verify the checksum of both halves
+    let invalid_phrase = |_| NativeError::custom_error("invalid recovery phrase");
+    let first = Mnemonic::parse(words[..FIRST_PHRASE_WORDS].join(" ")).map_err(invalid_phrase)?;
+    let second = Mnemonic::parse(words[FIRST_PHRASE_WORDS..].join(" ")).map_err(invalid_phrase)?;
+    let mut scalar = first.to_entropy();
+    scalar.extend(second.to_entropy());
+    if scalar.len() != SCALAR_LENGTH {
+        return Err(NativeError::custom_error("invalid recovery phrase"));
+    }
+
+    // Rebuild the PKCS8 DER and let the public key be derived on import
+    let mut der = PKCS8_PREFIX.to_vec();
+    der.extend([0x04, SCALAR_LENGTH as u8]);
+    der.extend(scalar);
+    let pem = pem::encode(&Pem::new("PRIVATE KEY", der));
+    Ok(EcEncryptionKey::import(pem.as_bytes()).await?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{from_recovery_phrase, to_recovery_phrase};
+    use crate::native::NativeError;
+    use tomb_crypt::prelude::{EcEncryptionKey, PrivateKey, PublicKey};
+
+    #[tokio::test]
+    async fn phrase_round_trip() -> Result<(), NativeError> {
+        let wrapping_key = EcEncryptionKey::generate().await?;
+        let phrase = to_recovery_phrase(&wrapping_key).await?;
+        assert_eq!(phrase.split_whitespace().count(), 36);
+        let recovered_key = from_recovery_phrase(&phrase).await?;
+        assert_eq!(
+            wrapping_key.public_key()?.fingerprint().await?,
+            recovered_key.public_key()?.fingerprint().await?
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn bad_phrase() -> Result<(), NativeError> {
+        let wrapping_key = EcEncryptionKey::generate().await?;
+        let phrase = to_recovery_phrase(&wrapping_key).await?;
+        // Drop a word
+        let short_phrase = phrase
+            .split_whitespace()
+            .skip(1)
+            .collect::<Vec<&str>>()
+            .join(" ");
+        assert!(from_recovery_phrase(&short_phrase).await.is_err());
+        Ok(())
+    }
+}
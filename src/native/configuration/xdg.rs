@@ -1,50 +1,144 @@
-use std::{env, fs::create_dir_all, path::PathBuf};
-const HOME_ERROR: &str = "cant find home directory";
+use crate::native::{configuration::lock::FileLock, NativeError};
+use std::{
+    env,
+    fs::create_dir_all,
+    path::{Path, PathBuf},
+};
 const GLOBAL_CONFIG_FILE_NAME: &str = "config.json";
+const GLOBAL_CONFIG_LOCK_FILE_NAME: &str = "config.lock";
 const DEVICE_API_KEY_FILE_NAME: &str = "device_api_key.pem";
 const DEVICE_WRAPPING_KEY_FILE_NAME: &str = "wrapping_key.pem";
+const PROFILES_DIR_NAME: &str = "profiles";
+const BLOCK_CACHE_DIR_NAME: &str = "blocks";
+const APP_DIR_NAME: &str = "banyan";
 
-/// Grab config path
-pub fn xdg_config_home() -> PathBuf {
-    // Construct
-    let path = PathBuf::from(format!(
-        "{}/.config/banyan",
-        env::var("HOME").expect(HOME_ERROR)
-    ));
-    // If the directory doesnt exist yet, make it!
-    if !path.exists() {
-        create_dir_all(&path).expect("failed to create XDG config home");
-    }
-    // Return
-    path
-}
+/// Environment variable which keeps all configuration and data in a single directory
+pub const BANYAN_HOME_VAR: &str = "BANYAN_HOME";
 
-/// Grab data path
-pub fn xdg_data_home() -> PathBuf {
-    // Construct
-    let path = PathBuf::from(format!(
-        "{}/.local/share/banyan",
-        env::var("HOME").expect(HOME_ERROR)
-    ));
-    // If the directory doesnt exist yet, make it!
-    if !path.exists() {
-        create_dir_all(&path).expect("failed to create XDG data home");
-    }
-    // Return
-    path
+/// The directories holding configuration and data on this device
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Homes {
+    /// Configuration and keys
+    pub config: PathBuf,
+    /// Drive metadata and content
+    pub data: PathBuf,
+    /// Data which can always be fetched again
+    pub cache: PathBuf,
 }
 
-/// Grab path to config.json File
-pub fn config_path() -> PathBuf {
-    xdg_config_home().join(GLOBAL_CONFIG_FILE_NAME)
+impl Homes {
+    /// Use BANYAN_HOME if it is set, otherwise follow the XDG base directory specification
+    pub fn from_env() -> Result<Self, NativeError> {
+        match env::var_os(BANYAN_HOME_VAR).filter(|home| !home.is_empty()) {
+            Some(home) => Self::within(Path::new(&home)),
+            None => {
+                let config = xdg_dir("XDG_CONFIG_HOME", ".config")?.join(APP_DIR_NAME);
+                let data = xdg_dir("XDG_DATA_HOME", ".local/share")?.join(APP_DIR_NAME);
+                let cache = xdg_dir("XDG_CACHE_HOME", ".cache")?.join(APP_DIR_NAME);
+                Self::new(config, data, cache)
+            }
+        }
+    }
+
+    /// Keep both configuration and data in a single directory
+    pub fn within(home: &Path) -> Result<Self, NativeError> {
+        Self::new(home.join("config"), home.join("data"), home.join("cache"))
+    }
+
+    /// Use these directories, creating them if they don't exist yet
+    fn new(config: PathBuf, data: PathBuf, cache: PathBuf) -> Result<Self, NativeError> {
+        create_dir_all(&config)?;
+        create_dir_all(&data)?;
+        create_dir_all(&cache)?;
+        Ok(Self {
+            config,
+            data,
+            cache,
+        })
+    }
+
+    /// Grab path to config.json File
+    pub fn config_path(&self) -> PathBuf {
+        self.config.join(GLOBAL_CONFIG_FILE_NAME)
+    }
+
+    /// Wait for exclusive access to config.json, for the duration of a read-modify-write
+    pub fn lock_config(&self) -> Result<FileLock, NativeError> {
+        FileLock::exclusive(&self.config.join(GLOBAL_CONFIG_LOCK_FILE_NAME))
+    }
+
+    /// Grab the directory caching blocks fetched from remote storage
+    pub fn block_cache_path(&self) -> PathBuf {
+        self.cache.join(BLOCK_CACHE_DIR_NAME)
+    }
+
+    /// Grab path to API Key
+    pub fn default_api_key_path(&self) -> PathBuf {
+        self.config.join(DEVICE_API_KEY_FILE_NAME)
+    }
+
+    /// Grab path to Wrapping Key
+    pub fn default_wrapping_key_path(&self) -> PathBuf {
+        self.config.join(DEVICE_WRAPPING_KEY_FILE_NAME)
+    }
+
+    /// Grab the directory holding the key directories of every named profile
+    pub fn profiles_home(&self) -> PathBuf {
+        self.config.join(PROFILES_DIR_NAME)
+    }
+
+    /// Grab the directory holding the keys of a named profile
+    pub fn profile_config_home(&self, profile: &str) -> PathBuf {
+        self.profiles_home().join(profile)
+    }
+
+    /// Grab path to the API Key of a named profile
+    pub fn profile_api_key_path(&self, profile: &str) -> PathBuf {
+        self.profile_config_home(profile)
+            .join(DEVICE_API_KEY_FILE_NAME)
+    }
+
+    /// Grab path to the Wrapping Key of a named profile
+    pub fn profile_wrapping_key_path(&self, profile: &str) -> PathBuf {
+        self.profile_config_home(profile)
+            .join(DEVICE_WRAPPING_KEY_FILE_NAME)
+    }
 }
 
-/// Grab path to API Key
-pub fn default_api_key_path() -> PathBuf {
-    xdg_config_home().join(DEVICE_API_KEY_FILE_NAME)
+/// Grab an XDG base directory, falling back on its default location within HOME.
+/// Relative paths are ignored, as the specification requires.
+fn xdg_dir(var: &str, default: &str) -> Result<PathBuf, NativeError> {
+    if let Some(dir) = env::var_os(var).map(PathBuf::from) {
+        if dir.is_absolute() {
+            return Ok(dir);
+        }
+    }
+    env::var_os("HOME")
+        .filter(|home| !home.is_empty())
+        .map(|home| PathBuf::from(home).join(default))
+        .ok_or_else(|| {
+            NativeError::custom_error(&format!(
+                "Unable to find a home directory; set HOME, {var} or {BANYAN_HOME_VAR}"
+            ))
+        })
 }
 
-/// Grab path to Wrapping Key
-pub fn default_wrapping_key_path() -> PathBuf {
-    xdg_config_home().join(DEVICE_WRAPPING_KEY_FILE_NAME)
+#[cfg(test)]
+mod test {
+    use super::Homes;
+    use crate::native::NativeError;
+    use std::{fs::remove_dir_all, path::Path};
+
+    #[test]
+    fn within() -> Result<(), NativeError> {
+        let home = Path::new("test").join("homes").join("within");
+        let homes = Homes::within(&home)?;
+        assert!(homes.config.exists() && homes.data.exists() && homes.cache.exists());
+        assert!(homes.config_path().starts_with(&home));
+        assert!(homes
+            .profile_wrapping_key_path("work")
+            .starts_with(&homes.config));
+        remove_dir_all(home)?;
+        Ok(())
+    }
 }
@@ -0,0 +1,558 @@
+use crate::{
+    api::{
+        client::Client,
+        models::{bucket::Bucket, metadata::Metadata, storage_ticket::StorageTicket},
+        requests::{
+            core::buckets::metadata::push::PushMetadata, staging::upload::content::UploadContent,
+        },
+    },
+    blockstore::{
+        BanyanApiBlockStore, BlockCache, CarV2DiskBlockStore, CarV2MemoryBlockStore,
+        DoubleSplitStore, RootedBlockStore,
+    },
+    filesystem::{wnfsio::path_to_segments, FilesystemError, FsMetadata, FsMetadataEntry},
+    native::{
+        configuration::{globalconfig::GlobalConfig, lock::FileLock},
+        operations::{prepare, restore},
+        sync::LocalBucket,
+        NativeError,
+    },
+    utils::progress::{Phase, ProgressEvent, ProgressSink},
+};
+use futures_util::StreamExt;
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+};
+use tokio::io::AsyncWriteExt;
+use tomb_crypt::prelude::{EcEncryptionKey, PrivateKey, PublicKey};
+
+/// Everything needed to work with Drives, given explicitly rather than read from a user's configuration
+#[derive(Clone)]
+pub struct Drive {
+    /// Key which unlocks the Drives
+    wrapping_key: EcEncryptionKey,
+    /// Directory the data of new Drives is kept in
+    data_home: PathBuf,
+    /// Means of connecting to the server
+    client: Client,
+    /// Cache for content fetched when restoring interactive Drives
+    block_cache: Option<BlockCache>,
+}
+
+impl Drive {
+    /// Work with Drives using this key, keeping their data in the data home
+    pub fn new(wrapping_key: EcEncryptionKey, data_home: &Path, client: Client) -> Self {
+        Self {
+            wrapping_key,
+            data_home: data_home.to_path_buf(),
+            client,
+            block_cache: None,
+        }
+    }
+
+    /// Keep content fetched while restoring interactive Drives in this cache
+    pub fn with_block_cache(mut self, block_cache: BlockCache) -> Self {
+        self.block_cache = Some(block_cache);
+        self
+    }
+
+    /// Everything the profile in a configuration uses
+    pub(crate) async fn from_config(global: &GlobalConfig) -> Result<Self, NativeError> {
+        let drive = Self::new(
+            global.wrapping_key().await?,
+            &global.homes().data,
+            global.get_client().await?,
+        );
+        match global.block_cache() {
+            Ok(block_cache) => Ok(drive.with_block_cache(block_cache)),
+            Err(err) => {
+                warn!("Unable to open the block cache, fetched content won't be kept: {err}");
+                Ok(drive)
+            }
+        }
+    }
+
+    /// Start a new Drive backed by the origin directory
+    pub async fn create(&self, name: &str, origin: &Path) -> Result<DriveHandle, NativeError> {
+        let mut bucket = LocalBucket::new(origin, &self.wrapping_key, &self.data_home).await?;
+        bucket.name = name.to_string();
+        let lock = bucket.lock()?;
+        self.open(bucket, lock).await
+    }
+
+    /// Unlock a Drive, given the lock which keeps everyone else out of it
+    pub async fn open(
+        &self,
+        bucket: LocalBucket,
+        lock: FileLock,
+    ) -> Result<DriveHandle, NativeError> {
+        let fs = bucket.unlock_fs(&self.wrapping_key).await?;
+        Ok(DriveHandle {
+            drive: self.clone(),
+            client: self.client.clone(),
+            bucket,
+            fs,
+            _lock: lock,
+        })
+    }
+}
+
+/// How much of a Drive made it to the server when it was pushed
+#[derive(Debug)]
+pub enum Pushed {
+    /// There was no new content, so only metadata was pushed
+    Metadata(Metadata),
+    /// Metadata and content were both pushed
+    Content(Metadata),
+    /// Metadata was pushed, but its content failed to upload
+    ContentFailed(Metadata),
+}
+
+/// An unlocked Drive, which no other process can modify until it is dropped.
+/// Changes stay in memory until they are committed.
+pub struct DriveHandle {
+    /// Drive this was opened with
+    drive: Drive,
+    /// Means of connecting to the server, kept so that authentication carries across calls
+    client: Client,
+    /// The Drive's data on disk
+    bucket: LocalBucket,
+    /// The unlocked filesystem
+    fs: FsMetadata,
+    /// Held for as long as the handle is
+    _lock: FileLock,
+}
+
+impl DriveHandle {
+    /// The Drive's data on disk, which has to be kept to open it again
+    pub fn bucket(&self) -> &LocalBucket {
+        &self.bucket
+    }
+
+    /// Give up access to the Drive, keeping its data
+    pub fn into_bucket(self) -> LocalBucket {
+        self.bucket
+    }
+
+    /// The unlocked filesystem
+    pub fn fs(&self) -> &FsMetadata {
+        &self.fs
+    }
+
+    /// List the entries in the directory at a path
+    pub async fn ls(&self, path: &Path) -> Result<Vec<FsMetadataEntry>, NativeError> {
+        Ok(self
+            .fs
+            .ls(&path_to_segments(path)?, &self.bucket.metadata)
+            .await?)
+    }
+
+    /// Read the file at a path, fetching content which isn't held locally from the server
+    pub async fn read(&self, path: &Path) -> Result<Vec<u8>, NativeError> {
+        let api_store = BanyanApiBlockStore::from(self.client.clone());
+        let content_store = DoubleSplitStore::new(&self.bucket.content, &api_store);
+        Ok(self
+            .fs
+            .read(
+                &path_to_segments(path)?,
+                &self.bucket.metadata,
+                &content_store,
+            )
+            .await?)
+    }
+
+    /// Write a file at a path, creating any directories above it
+    pub async fn write(&mut self, path: &Path, content: Vec<u8>) -> Result<(), NativeError> {
+        Ok(self
+            .fs
+            .write(
+                &path_to_segments(path)?,
+                &self.bucket.metadata,
+                &self.bucket.content,
+                content,
+            )
+            .await?)
+    }
+
+    /// Remove the file or directory at a path
+    pub async fn rm(&mut self, path: &Path) -> Result<(), NativeError> {
+        Ok(self
+            .fs
+            .rm(&path_to_segments(path)?, &self.bucket.metadata)
+            .await?)
+    }
+
+    /// Bring the filesystem in line with the Drive's origin directory
+    pub async fn prepare(
+        &mut self,
+        follow_links: bool,
+        progress: &dyn ProgressSink,
+    ) -> Result<(), NativeError> {
+        prepare::prepare_bucket(
+            &mut self.bucket,
+            &mut self.fs,
+            &mut self.client,
+            follow_links,
+            progress,
+        )
+        .await
+    }
+
+    /// Write out every file in the filesystem under the target directory
+    pub async fn restore(
+        &mut self,
+        target: &Path,
+        progress: &dyn ProgressSink,
+    ) -> Result<(), NativeError> {
+        restore::restore_bucket(
+            &self.bucket,
+            &self.fs,
+            target,
+            &mut self.client,
+            self.drive.block_cache.clone(),
+            progress,
+        )
+        .await
+    }
+
+    /// Save the changes made so far as a new version of the Drive
+    pub async fn commit(&mut self) -> Result<(), NativeError> {
+        Ok(self.bucket.save_fs(&mut self.fs).await?)
+    }
+
+    /// Push committed metadata and content to the server, creating the remote Drive if there isn't one yet
+    pub async fn push(&mut self, progress: &dyn ProgressSink) -> Result<Pushed, NativeError> {
+        progress.report(ProgressEvent::PhaseChanged {
+            phase: Phase::Pushing,
+            total: None,
+        });
+        // The share manager lists valid keys in plaintext, so there is no need to unlock
+        let valid_keys = FsMetadata::public_fingerprints(&self.bucket.metadata).await?;
+
+        // If there is still no ID, the remote Drive was never created
+        let bucket_id = match self.bucket.remote_id {
+            Some(bucket_id) => bucket_id,
+            None => {
+                let public_key = self.drive.wrapping_key.public_key()?;
+                let pem = String::from_utf8(public_key.export().await?)?;
+                let (remote, _) = Bucket::create(
+                    self.bucket.name.clone(),
+                    pem,
+                    self.bucket.r#type,
+                    self.bucket.storage_class.clone(),
+                    &mut self.client,
+                )
+                .await?;
+                self.bucket.remote_id = Some(remote.id);
+                remote.id
+            }
+        };
+
+        let content_cid = self
+            .bucket
+            .content
+            .get_root()
+            .ok_or(FilesystemError::missing_metadata("root cid"))?;
+        let metadata_cid = self
+            .bucket
+            .metadata
+            .get_root()
+            .ok_or(FilesystemError::missing_metadata("metdata cid"))?;
+        let delta = self.bucket.content.get_delta()?;
+
+        // Push the metadata
+        let (metadata, host, authorization) = Metadata::push(
+            PushMetadata {
+                bucket_id,
+                expected_data_size: delta.data_size(),
+                root_cid: content_cid.to_string(),
+                metadata_cid: metadata_cid.to_string(),
+                previous_cid: self.bucket.previous_cid.map(|cid| cid.to_string()),
+                valid_keys,
+                deleted_block_cids: self
+                    .bucket
+                    .deleted_block_cids
+                    .iter()
+                    .map(|v| v.to_string())
+                    .collect(),
+                metadata_stream: tokio::fs::File::open(&self.bucket.metadata.path)
+                    .await?
+                    .into(),
+            },
+            &mut self.client,
+        )
+        .await?;
+
+        // Empty the list of deleted blocks, now that it's the server's problem
+        self.bucket.deleted_block_cids = BTreeSet::new();
+
+        if host.is_none() && authorization.is_none() {
+            self.bucket.storage_ticket = None;
+        }
+
+        info!("Uploading your new data now...");
+        progress.report(ProgressEvent::PhaseChanged {
+            phase: Phase::Uploading,
+            total: None,
+        });
+
+        let upload_result = match (host, authorization) {
+            // New storage ticket
+            (Some(host), Some(authorization)) => {
+                // Update the storage ticket locally and create grant
+                let storage_ticket = StorageTicket {
+                    host,
+                    authorization,
+                };
+                storage_ticket.create_grant(&mut self.client).await?;
+                self.bucket.storage_ticket = Some(storage_ticket.clone());
+                self.bucket
+                    .content
+                    .upload_with_progress(
+                        storage_ticket.host,
+                        metadata.id,
+                        &mut self.client,
+                        progress,
+                    )
+                    .await
+            }
+            // Already granted, still upload
+            (Some(host), None) => {
+                self.bucket
+                    .content
+                    .upload_with_progress(host, metadata.id, &mut self.client, progress)
+                    .await
+            }
+            // No uploading required
+            _ => {
+                self.bucket.previous_cid = Some(metadata_cid);
+                return Ok(Pushed::Metadata(metadata));
+            }
+        };
+
+        // Until its content arrives, the version pushed stays pending and the server stays on
+        // the one before, which the next push builds on again
+        Ok(match upload_result {
+            Ok(()) => {
+                self.bucket.previous_cid = Some(metadata_cid);
+                Pushed::Content(metadata)
+            }
+            Err(err) => {
+                progress.warn(&format!("Unable to upload content: {err}"));
+                Pushed::ContentFailed(metadata)
+            }
+        })
+    }
+
+    /// Snapshot the current version of the remote Drive, keeping every block it needs
+    pub async fn snapshot(&mut self) -> Result<Metadata, NativeError> {
+        let bucket_id = self
+            .bucket
+            .remote_id
+            .ok_or(NativeError::missing_identifier())?;
+        let mut metadata = Metadata::read_current(bucket_id, &mut self.client)
+            .await
+            .map_err(NativeError::api)?;
+        let active_cids = self.bucket.active_cids(&self.fs).await?;
+        metadata.snapshot_id = Some(
+            metadata
+                .snapshot(active_cids, &mut self.client)
+                .await
+                .map_err(NativeError::api)?,
+        );
+        Ok(metadata)
+    }
+
+    /// Replace the Drive's metadata with the current version on the server.
+    /// Content is fetched as it is read or restored.
+    /// Commits which were never pushed are only overwritten if asked to.
+    pub async fn pull(
+        &mut self,
+        overwrite: bool,
+        progress: &dyn ProgressSink,
+    ) -> Result<Metadata, NativeError> {
+        let bucket_id = self
+            .bucket
+            .remote_id
+            .ok_or(NativeError::missing_identifier())?;
+        if !overwrite && self.bucket.metadata.get_root() != self.bucket.previous_cid {
+            return Err(NativeError::custom_error(&format!(
+                "Drive `{}` has commits which haven't been pushed, pulling would overwrite them",
+                self.bucket.name
+            )));
+        }
+        progress.report(ProgressEvent::PhaseChanged {
+            phase: Phase::Pulling,
+            total: None,
+        });
+        let current = Metadata::read_current(bucket_id, &mut self.client).await?;
+        let mut byte_stream = current.pull(&mut self.client).await?;
+
+        let mut buffer = <Vec<u8>>::new();
+        // Write every chunk to it
+        while let Some(chunk) = byte_stream.next().await {
+            tokio::io::copy(&mut chunk?.as_ref(), &mut buffer).await?;
+        }
+        // Attempt to create a CARv2 BlockStore from the data
+        let metadata = CarV2MemoryBlockStore::try_from(buffer)?;
+        let metadata_cid = metadata
+            .get_root()
+            .ok_or(FilesystemError::missing_metadata("metadata cid"))?;
+        // Write it out beside the metadata, so that a failure part way leaves the old version intact
+        let metadata_path = self.bucket.metadata.path.clone();
+        let pulled_path = metadata_path.with_extension("pulled");
+        let mut pulled_file = tokio::fs::File::create(&pulled_path).await?;
+        pulled_file.write_all(&metadata.get_data()).await?;
+        pulled_file.sync_all().await?;
+        drop(pulled_file);
+        tokio::fs::rename(&pulled_path, &metadata_path).await?;
+
+        // Pick up the new metadata
+        self.bucket.metadata = CarV2DiskBlockStore::load(&metadata_path)?;
+        self.bucket.previous_cid = Some(metadata_cid);
+        self.fs = self.bucket.unlock_fs(&self.drive.wrapping_key).await?;
+        Ok(current)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        api::{client::Client, mock::MockServer},
+        native::{
+            drive::{Drive, Pushed},
+            NativeError,
+        },
+        utils::progress::LogProgress,
+    };
+    use serial_test::serial;
+    use std::{
+        fs::{create_dir_all, remove_dir_all},
+        path::Path,
+    };
+    use tomb_crypt::prelude::{EcEncryptionKey, PrivateKey};
+
+    #[tokio::test]
+    #[serial]
+    async fn write_commit_reopen() -> Result<(), NativeError> {
+        let root = Path::new("test").join("drive_write_commit_reopen");
+        if root.exists() {
+            remove_dir_all(&root)?;
+        }
+        let origin = root.join("origin");
+        let data_home = root.join("data");
+        create_dir_all(&data_home)?;
+
+        // Nothing here is read from a user's configuration
+        let drive = Drive::new(
+            EcEncryptionKey::generate().await?,
+            &data_home,
+            Client::new("http://127.0.0.1:3001")?,
+        );
+        let mut handle = drive.create("embedded", &origin).await?;
+        let cat_path = Path::new("cats/cat.txt");
+        handle.write(cat_path, b"hello kitty".to_vec()).await?;
+        handle.commit().await?;
+        let bucket = handle.into_bucket();
+
+        let lock = bucket.lock()?;
+        let mut handle = drive.open(bucket, lock).await?;
+        // Nobody else can open it in the meantime, not even this process
+        assert!(handle.bucket().lock().is_err());
+        let entries = handle.ls(Path::new("cats")).await?;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].name, "cat.txt");
+        assert_eq!(handle.read(cat_path).await?, b"hello kitty".to_vec());
+
+        handle.rm(cat_path).await?;
+        handle.commit().await?;
+        assert!(handle.ls(Path::new("cats")).await?.is_empty());
+
+        remove_dir_all(&root)?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    #[serial]
+    async fn push_pull() -> Result<(), NativeError> {
+        let root = Path::new("test").join("drive_push_pull");
+        if root.exists() {
+            remove_dir_all(&root)?;
+        }
+        let server = MockServer::start();
+        let client = server.authenticated_client().await;
+        let wrapping_key = EcEncryptionKey::generate().await?;
+        let pusher = Drive::new(wrapping_key.clone(), &root.join("pusher"), client.clone());
+        let puller = Drive::new(wrapping_key, &root.join("puller"), client);
+        create_dir_all(root.join("pusher"))?;
+        create_dir_all(root.join("puller"))?;
+
+        let cat_path = Path::new("cats/cat.txt");
+        let mut pushing = pusher.create("pushed", &root.join("origin")).await?;
+        pushing.write(cat_path, b"hello kitty".to_vec()).await?;
+        pushing.commit().await?;
+        assert!(matches!(
+            pushing.push(&LogProgress).await?,
+            Pushed::Content(_)
+        ));
+        let remote_id = pushing.bucket().remote_id;
+
+        // A Drive which has never been in step with the remote one is only overwritten if asked
+        let mut bucket = puller
+            .create("pulled", &root.join("elsewhere"))
+            .await?
+            .into_bucket();
+        bucket.remote_id = remote_id;
+        let lock = bucket.lock()?;
+        let mut pulling = puller.open(bucket, lock).await?;
+        assert!(pulling.pull(false, &LogProgress).await.is_err());
+        pulling.pull(true, &LogProgress).await?;
+        let entries = pulling.ls(Path::new("cats")).await?;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].name, "cat.txt");
+        assert!(!pulling
+            .bucket()
+            .metadata
+            .path
+            .with_extension("pulled")
+            .exists());
+
+        // Once in step, newer versions are pulled without asking
+        pushing.rm(cat_path).await?;
+        pushing.commit().await?;
+        pushing.push(&LogProgress).await?;
+        pulling.pull(false, &LogProgress).await?;
+        assert!(pulling.ls(Path::new("cats")).await?.is_empty());
+
+        // A version whose content never arrived is pushed again rather than pulled over
+        pushing.write(cat_path, b"hello again".to_vec()).await?;
+        pushing.commit().await?;
+        server.fail_uploads(1);
+        assert!(matches!(
+            pushing.push(&LogProgress).await?,
+            Pushed::ContentFailed(_)
+        ));
+        assert!(pushing.pull(false, &LogProgress).await.is_err());
+        assert!(matches!(
+            pushing.push(&LogProgress).await?,
+            Pushed::Content(_)
+        ));
+        pushing.pull(false, &LogProgress).await?;
+        assert_eq!(pushing.read(cat_path).await?, b"hello again".to_vec());
+
+        // Until something is committed here that hasn't been pushed
+        pulling
+            .write(Path::new("dog.txt"), b"hello puppy".to_vec())
+            .await?;
+        pulling.commit().await?;
+        assert!(pulling.pull(false, &LogProgress).await.is_err());
+        assert_eq!(
+            pulling.read(Path::new("dog.txt")).await?,
+            b"hello puppy".to_vec()
+        );
+
+        remove_dir_all(&root)?;
+        Ok(())
+    }
+}
@@ -37,6 +37,12 @@ impl Display for NativeError {
                 "There is already a unique Drive with these specs".to_owned()
             }
             NativeErrorKind::BadData => "bad data".to_owned(),
+            NativeErrorKind::BadConfig(msg) => {
+                format!("{} {msg}", "CONFIGURATION ERROR:".underline())
+            }
+            NativeErrorKind::BadPassphrase => {
+                "Incorrect passphrase or corrupted key data".to_owned()
+            }
             NativeErrorKind::Custom(msg) => msg.to_owned(),
             NativeErrorKind::Cryptographic(err) => {
                 format!("{} {err}", "CRYPTOGRAPHIC ERROR:".underline())
@@ -103,6 +109,18 @@ impl NativeError {
         }
     }
 
+    pub fn bad_config(msg: &str) -> Self {
+        Self {
+            kind: NativeErrorKind::BadConfig(msg.to_owned()),
+        }
+    }
+
+    pub fn bad_passphrase() -> Self {
+        Self {
+            kind: NativeErrorKind::BadPassphrase,
+        }
+    }
+
     pub fn custom_error(msg: &str) -> Self {
         Self {
             kind: NativeErrorKind::Custom(msg.to_owned()),
@@ -160,6 +178,8 @@ enum NativeErrorKind {
     MissingRemoteDrive,
     UniqueDriveError,
     BadData,
+    BadConfig(String),
+    BadPassphrase,
     Custom(String),
     Cryptographic(TombCryptError),
     Filesystem(Box<FilesystemError>),
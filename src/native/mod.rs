@@ -23,6 +23,8 @@
 extern crate core;
 /// Local configurations
 pub(crate) mod configuration;
+/// Working with Drives from other programs, without a user's configuration
+pub mod drive;
 /// Scanning local filesystems
 pub(crate) mod file_scanning;
 /// Operations which can be performed
@@ -33,3 +35,4 @@ pub mod utils;
 
 mod error;
 pub use error::NativeError;
+pub use sync::LocalBucket;
@@ -8,12 +8,15 @@ use std::path::Path;
 
 /// The pipeline for adding an individual file to a WNFS
 pub async fn pipeline(
-    mut local: LocalBucket,
+    global: &GlobalConfig,
+    local: LocalBucket,
     input_file: &Path,
     wnfs_path: &Path,
 ) -> Result<String, NativeError> {
+    // Keep other processes out of this Drive, picking up anything they wrote before we got in
+    let (_drive_lock, mut local) = global.lock_bucket(&local)?;
     // Global config
-    let mut global = GlobalConfig::from_disk().await?;
+    let mut global = global.reload()?;
     let wrapping_key = global.clone().wrapping_key().await?;
     // Get structs
     let mut fs = local.unlock_fs(&wrapping_key).await?;
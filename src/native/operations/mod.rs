@@ -8,22 +8,43 @@ pub mod prepare;
 pub mod remove;
 /// This module contains the decryption pipeline function, which is the main entry point for restoring previously prepared data.
 pub mod restore;
+/// This module contains the key rotation pipeline function, which moves every Drive onto a new wrapping key.
+pub mod rotate;
+/// This module contains the tagging functions, which read and write user tags on files in existing WNFS filesystems.
+pub mod tag;
+/// This module contains the usage breakdown function, which works out what the files in a Drive take up.
+pub mod usage;
+/// This module contains the verification pipeline function, which checks that remote storage serves a Drive's content intact.
+pub mod verify;
 
 #[cfg(test)]
 #[cfg(feature = "cli")]
 mod test {
     use crate::{
+        api::{
+            mock::MockServer,
+            models::{
+                account::Account,
+                bucket::{Bucket, BucketType, StorageClass},
+                bucket_key::BucketKey,
+                metadata::Metadata,
+            },
+        },
+        blockstore::{CarV2MemoryBlockStore, RootedBlockStore},
         cli::specifiers::DriveSpecifier,
         filesystem::wnfsio::{decompress_bytes, path_to_segments},
         native::{
             configuration::globalconfig::GlobalConfig,
-            operations::{add, configure, prepare, remove, restore},
-            sync::OmniBucket,
+            operations::{add, configure, prepare, remove, restore, rotate, tag, usage},
+            sync::{OmniBucket, SyncState},
             NativeError,
         },
         utils::{
             compute_directory_size,
-            testing::local_operations::{test_setup, test_setup_structured, test_teardown},
+            progress::{LogProgress, Phase, ProgressEvent, ProgressSink},
+            testing::local_operations::{
+                test_config, test_setup, test_setup_structured, test_teardown,
+            },
             UtilityError,
         },
     };
@@ -31,8 +52,8 @@ mod test {
     use dir_assert::assert_paths;
     use fake_file::{utils::ensure_path_exists_and_is_empty_dir, Strategy, Structure};
     use fs_extra::dir;
-    use serial_test::serial;
     use std::{
+        cell::RefCell,
         fs::{
             create_dir_all, read_link, remove_dir_all, remove_file, rename, symlink_metadata, File,
         },
@@ -40,89 +61,154 @@ mod test {
         os::unix::fs::symlink,
         path::{Path, PathBuf},
     };
+    use tomb_crypt::{
+        hex_fingerprint,
+        prelude::{EcEncryptionKey, PrivateKey, PublicKey},
+    };
 
     /// Simplified Prepare call function
-    async fn prepare_pipeline(origin: &Path) -> Result<String, NativeError> {
+    async fn prepare_pipeline(global: &GlobalConfig, origin: &Path) -> Result<String, NativeError> {
         let name = origin.file_name().unwrap().to_string_lossy().to_string();
-        let omni = OmniBucket::create(&name, origin).await?;
-        prepare::pipeline(omni, true).await
+        let omni = OmniBucket::create(
+            global,
+            &name,
+            origin,
+            BucketType::Interactive,
+            StorageClass::Hot,
+        )
+        .await?;
+        prepare::pipeline(global, omni, true, &LogProgress).await
     }
 
     /// Simplified Restore call function
-    async fn restore_pipeline(origin: &Path, restored: &Path) -> Result<String, NativeError> {
-        let omni = OmniBucket::from_specifier(&DriveSpecifier::with_origin(origin)).await;
+    async fn restore_pipeline(
+        global: &GlobalConfig,
+        origin: &Path,
+        restored: &Path,
+    ) -> Result<String, NativeError> {
+        let omni = OmniBucket::from_specifier(global, &DriveSpecifier::with_origin(origin)).await;
         let tmp = origin.parent().unwrap().join("tmp");
         rename(origin, &tmp)?;
-        let result = restore::pipeline(omni).await;
+        let result = restore::pipeline(global, omni, &LogProgress).await;
         rename(origin, restored)?;
         rename(tmp, origin)?;
         result
     }
 
     #[tokio::test]
-    #[serial]
     async fn init() -> Result<(), UtilityError> {
         let test_name = "init";
         // Create the setup conditions
         let origin = &test_setup(test_name).await?;
+        let global = test_config(test_name).await?;
         // Deinitialize for user
-        configure::deinit(origin).await?;
+        configure::deinit(&global, origin).await?;
         // Assert that a config exists for this bucket now
-        assert!(GlobalConfig::from_disk()
-            .await?
-            .get_bucket(origin)
-            .is_none());
+        assert!(global.reload()?.get_bucket(origin).is_none());
         // Initialize for this user
-        configure::init("init", origin).await?;
+        configure::init(&global, "init", origin).await?;
         // Assert that a config exists for this bucket now
-        assert!(GlobalConfig::from_disk()
-            .await?
-            .get_bucket(origin)
-            .is_some());
+        assert!(global.reload()?.get_bucket(origin).is_some());
         // Teardown
         test_teardown(test_name).await
     }
 
     #[tokio::test]
-    #[serial]
     async fn configure_remote() -> Result<(), UtilityError> {
+        let test_name = "configure_remote";
         let address = "http://app.tomb.com.net.org:5423/";
-        configure::deinit_all().await?;
-        let _ = GlobalConfig::new().await?;
+        let global = test_config(test_name).await?;
         // Configure the remote endpoint
-        configure::remote_core(address).await?;
+        configure::remote_core(&global, address).await?;
         // Assert it was actually modified
         assert_eq!(
-            GlobalConfig::from_disk().await?.get_endpoint().to_string(),
+            global.reload()?.get_endpoint().to_string(),
             address.to_string()
         );
-        Ok(())
+        test_teardown(test_name).await
     }
 
     #[tokio::test]
-    #[serial]
     async fn prepare() -> Result<(), UtilityError> {
         let test_name = "prepare";
         // Create the setup conditions
         let origin = &test_setup(test_name).await?;
+        let global = test_config(test_name).await?;
         // Initialize
-        configure::init(test_name, origin).await?;
+        configure::init(&global, test_name, origin).await?;
         // Prepare
-        prepare_pipeline(origin).await?;
+        prepare_pipeline(&global, origin).await?;
+        // Teardown
+        test_teardown(test_name).await
+    }
+
+    /// Keeps every event it is sent
+    #[derive(Default)]
+    struct RecordedProgress(RefCell<Vec<ProgressEvent>>);
+
+    impl ProgressSink for RecordedProgress {
+        fn report(&self, event: ProgressEvent) {
+            self.0.borrow_mut().push(event);
+        }
+    }
+
+    #[tokio::test]
+    async fn prepare_progress() -> Result<(), UtilityError> {
+        let test_name = "prepare_progress";
+        // Create the setup conditions
+        let origin = &test_setup(test_name).await?;
+        let global = test_config(test_name).await?;
+        // Initialize
+        configure::init(&global, test_name, origin).await?;
+        let omni = OmniBucket::create(
+            &global,
+            test_name,
+            origin,
+            BucketType::Interactive,
+            StorageClass::Hot,
+        )
+        .await?;
+        // Prepare, keeping track of progress
+        let progress = RecordedProgress::default();
+        prepare::pipeline(&global, omni, true, &progress).await?;
+        let events = progress.0.into_inner();
+        // Every file announced when preparing starts is started and finished
+        let total = events
+            .iter()
+            .find_map(|event| match event {
+                ProgressEvent::PhaseChanged {
+                    phase: Phase::Preparing,
+                    total,
+                } => *total,
+                _ => None,
+            })
+            .expect("no preparing phase");
+        let count = |matches: fn(&ProgressEvent) -> bool| {
+            events.iter().filter(|event| matches(event)).count() as u64
+        };
+        assert!(total > 0);
+        assert_eq!(
+            count(|event| matches!(event, ProgressEvent::FileStarted { .. })),
+            total
+        );
+        assert_eq!(
+            count(|event| matches!(event, ProgressEvent::FileDone { .. })),
+            total
+        );
         // Teardown
         test_teardown(test_name).await
     }
 
     #[tokio::test]
-    #[serial]
     async fn restore() -> Result<(), UtilityError> {
         let test_name = "restore";
         // Create the setup conditions
         let origin = &test_setup(test_name).await?;
+        let global = test_config(test_name).await?;
         // Initialize
-        configure::init(test_name, origin).await?;
+        configure::init(&global, test_name, origin).await?;
         // Prepare locally
-        prepare_pipeline(origin).await?;
+        prepare_pipeline(&global, origin).await?;
         // Create a new dir to restore in
         let restored_dir = &origin
             .parent()
@@ -130,7 +216,7 @@ mod test {
             .join(format!("{}_restored", test_name));
         create_dir_all(restored_dir)?;
         // Run the restoring pipeline
-        restore_pipeline(origin, restored_dir).await?;
+        restore_pipeline(&global, origin, restored_dir).await?;
         // Assert the pre-prepared and restored directories are identical
         assert_paths(origin, restored_dir).expect("restored dir does not match origin");
         // Teardown
@@ -138,15 +224,15 @@ mod test {
     }
 
     #[tokio::test]
-    #[serial]
     async fn add() -> Result<(), UtilityError> {
         let test_name = "add";
         // Create the setup conditions
         let origin = &test_setup(test_name).await?;
+        let global = test_config(test_name).await?;
         // Initialize tomb
-        configure::init(test_name, origin).await?;
+        configure::init(&global, test_name, origin).await?;
         // Run the prepare pipeline
-        prepare_pipeline(origin).await?;
+        prepare_pipeline(&global, origin).await?;
         // This is still in the input dir. Technically we could just
         let input_file = &origin.join("hello.txt");
         // Content to be written to the file
@@ -156,11 +242,17 @@ mod test {
         // Create and write to the file
         File::create(input_file)?.write_all(&file_content)?;
         // Add the input file to the WNFS
-        let global = GlobalConfig::from_disk().await?;
-        add::pipeline(global.get_bucket(origin).unwrap(), input_file, input_file).await?;
+        let global = global.reload()?;
+        add::pipeline(
+            &global,
+            global.get_bucket(origin).unwrap(),
+            input_file,
+            input_file,
+        )
+        .await?;
 
         // Now that the pipeline has run, grab all metadata
-        let global = GlobalConfig::from_disk().await?;
+        let global = global.reload()?;
         let wrapping_key = global.clone().wrapping_key().await?;
         let config = global
             .get_bucket(origin)
@@ -197,20 +289,87 @@ mod test {
     }
 
     #[tokio::test]
-    #[serial]
+    async fn tag() -> Result<(), UtilityError> {
+        let test_name = "tag";
+        // Create the setup conditions
+        let origin = &test_setup(test_name).await?;
+        let global = test_config(test_name).await?;
+        // Initialize tomb
+        configure::init(&global, test_name, origin).await?;
+        // Run the prepare pipeline
+        prepare_pipeline(&global, origin).await?;
+        let wnfs_path = &PathBuf::from("").join("0").join("0");
+        // Tag the file, and tag it again, removing one of the tags
+        let global = global.reload()?;
+        for (key, value) in [
+            ("color", Some("orange")),
+            ("lives", Some("9")),
+            ("lives", None),
+        ] {
+            tag::set(
+                &global,
+                global.get_bucket(origin).unwrap(),
+                wnfs_path,
+                key,
+                value.map(String::from),
+            )
+            .await?;
+        }
+
+        // The tags survive being saved
+        let global = global.reload()?;
+        let local = global.get_bucket(origin).unwrap();
+        assert_eq!(
+            tag::get(&global, &local, wnfs_path, None).await?,
+            "color:\torange"
+        );
+        assert_eq!(
+            tag::get(&global, &local, wnfs_path, Some("lives".into())).await?,
+            ""
+        );
+        // Teardown
+        test_teardown(test_name).await
+    }
+
+    #[tokio::test]
+    async fn usage_breakdown() -> Result<(), UtilityError> {
+        let test_name = "usage_breakdown";
+        // Create the setup conditions
+        let origin = &test_setup(test_name).await?;
+        let global = test_config(test_name).await?;
+        // Initialize tomb
+        configure::init(&global, test_name, origin).await?;
+        // Run the prepare pipeline
+        prepare_pipeline(&global, origin).await?;
+        let global = global.reload()?;
+        let breakdown = usage::breakdown(&global, &global.get_bucket(origin).unwrap()).await?;
+        // Everything is under the root, and nothing has been rewritten yet
+        let total = breakdown.by_directory["/"];
+        assert!(total > 0);
+        assert_eq!(breakdown.by_mime_type.values().sum::<u64>(), total);
+        assert_eq!(breakdown.by_version.get(&0), Some(&total));
+        assert_eq!(breakdown.by_version.len(), 1);
+        assert!(breakdown.blocks > 0);
+        assert!(breakdown.stored > 0);
+        // Teardown
+        test_teardown(test_name).await
+    }
+
+    #[tokio::test]
     async fn remove() -> Result<(), UtilityError> {
         let test_name = "remove";
         // Create the setup conditions
         let origin = &test_setup(test_name).await?;
+        let global = test_config(test_name).await?;
         // Initialize tomb
-        configure::init(test_name, origin).await?;
+        configure::init(&global, test_name, origin).await?;
         // Run the prepare pipeline
-        prepare_pipeline(origin).await?;
+        prepare_pipeline(&global, origin).await?;
         // Write out a reference to where we expect to find this file
         let wnfs_path = &PathBuf::from("").join("0").join("0");
         let wnfs_segments = path_to_segments(wnfs_path)?;
         // Load metadata
-        let global = GlobalConfig::from_disk().await?;
+        let global = global.reload()?;
         let wrapping_key = global.clone().wrapping_key().await?;
         let config = global
             .get_bucket(origin)
@@ -224,9 +383,9 @@ mod test {
         // Assert the node exists presently
         assert!(result.is_some());
         // Remove the PrivateFile at this Path
-        remove::pipeline(global.get_bucket(origin).unwrap(), wnfs_path).await?;
+        remove::pipeline(&global, global.get_bucket(origin).unwrap(), wnfs_path).await?;
         // Reload metadata
-        let global = GlobalConfig::from_disk().await?;
+        let global = global.reload()?;
         let wrapping_key = global.clone().wrapping_key().await?;
         let config = global
             .get_bucket(origin)
@@ -250,10 +409,11 @@ mod test {
         // Grab directories
         let root_path = PathBuf::from("test").join(test_name);
         let origin = &root_path.join("input");
+        let global = test_config(test_name).await?;
         // Initialize
-        configure::init(test_name, origin).await?;
+        configure::init(&global, test_name, origin).await?;
         // Prepare locally
-        prepare_pipeline(origin).await?;
+        prepare_pipeline(&global, origin).await?;
         // Create a new dir to restore in
         let restored_dir = &origin
             .parent()
@@ -264,7 +424,7 @@ mod test {
         }
         create_dir_all(restored_dir)?;
         // Run the restoring pipeline
-        restore_pipeline(origin, restored_dir).await?;
+        restore_pipeline(&global, origin, restored_dir).await?;
         // Assert the pre-prepared and restored directories are identical
         assert_paths(origin, restored_dir).expect("restored dir does not match origin");
         Ok(())
@@ -273,7 +433,6 @@ mod test {
     const TEST_INPUT_SIZE: usize = 1024;
 
     #[tokio::test]
-    #[serial]
     async fn structure_simple() -> Result<(), UtilityError> {
         let test_name = "structure_simple";
         let structure = Structure::new(4, 4, TEST_INPUT_SIZE, Strategy::Simple);
@@ -283,7 +442,6 @@ mod test {
     }
 
     #[tokio::test]
-    #[serial]
     async fn structure_deep() -> Result<(), UtilityError> {
         let test_name = "structure_deep";
         let structure = Structure::new(2, 8, TEST_INPUT_SIZE, Strategy::Simple);
@@ -293,9 +451,8 @@ mod test {
     }
 
     #[tokio::test]
-    #[serial]
     async fn structure_wide() -> Result<(), UtilityError> {
-        let test_name = "structure_deep";
+        let test_name = "structure_wide";
         let structure = Structure::new(16, 1, TEST_INPUT_SIZE, Strategy::Simple);
         test_setup_structured(test_name, structure).await?;
         assert_prepare_restore(test_name).await?;
@@ -303,7 +460,6 @@ mod test {
     }
 
     #[tokio::test]
-    #[serial]
     async fn big_file() -> Result<(), UtilityError> {
         let test_name = "big_file";
         let structure = Structure::new(1, 1, 1024 * 1024 * 10, Strategy::Simple);
@@ -314,7 +470,6 @@ mod test {
 
     /// Ensure that the pipeline can recover duplicate files
     #[tokio::test]
-    #[serial]
     async fn deduplication_integrity() -> Result<(), UtilityError> {
         let test_name = "deduplication_integrity";
         // Setup the test
@@ -342,7 +497,6 @@ mod test {
     // / Ensure that the duplicate data occupies a smaller footprint when prepared
     //TODO (organizedgrime) - This test is a bit longer than I would like, might modify it to be more modular / reusable
     #[tokio::test]
-    #[serial]
     #[ignore = "refactor for new pipeline structure"]
     async fn deduplication_size() -> Result<(), UtilityError> {
         let test_name = "deduplication_size";
@@ -350,9 +504,6 @@ mod test {
         let test_name_unique = &format!("{}_unique", test_name);
         // Use bigger files such that metadata comprises a minority of the content CARs
         let structure = Structure::new(2, 2, 1024 * 1024, Strategy::Simple);
-        // Deinit all
-        configure::deinit_all().await?;
-
         // Base of the test directory
         let root_path_dup = PathBuf::from("test").join(test_name_dup);
         let root_path_unique = PathBuf::from("test").join(test_name_unique);
@@ -386,18 +537,18 @@ mod test {
         assert_prepare_restore(test_name_dup).await?;
         assert_prepare_restore(test_name_unique).await?;
 
-        // Get configs
-        let global = GlobalConfig::from_disk().await?;
         // Compute the sizes of these directories
         let prepared_dups_size = compute_directory_size(
-            &global
+            &test_config(test_name_dup)
+                .await?
                 .get_bucket(origin_dup)
                 .expect("bucket config does not exist for this origin")
                 .content
                 .path,
         )? as f64;
         let prepared_unique_size = compute_directory_size(
-            &global
+            &test_config(test_name_unique)
+                .await?
                 .get_bucket(origin_unique)
                 .expect("bucket config does not exist for this origin")
                 .content
@@ -413,7 +564,6 @@ mod test {
     }
 
     #[tokio::test]
-    #[serial]
     async fn double_bundling() -> Result<(), UtilityError> {
         let test_name = "double_bundling";
         // Setup the test once
@@ -426,11 +576,11 @@ mod test {
     }
 
     #[tokio::test]
-    #[serial]
     async fn block_tracking() -> Result<(), UtilityError> {
         let test_name = "block_tracking";
         // Setup the test once
         let origin = test_setup(test_name).await?;
+        let global = test_config(test_name).await?;
         // Run the test twice
         assert_prepare_restore(test_name).await?;
 
@@ -440,8 +590,8 @@ mod test {
 
         assert_prepare_restore(test_name).await?;
 
-        let config = GlobalConfig::from_disk()
-            .await?
+        let config = global
+            .reload()?
             .get_bucket(&origin)
             .expect("no bucket at origin");
 
@@ -452,13 +602,13 @@ mod test {
     }
 
     #[tokio::test]
-    #[serial]
     #[ignore]
     async fn versioning_complex() -> Result<(), UtilityError> {
         let test_name = "versioning_complex";
         let structure = Structure::new(2, 2, 2000, Strategy::Simple);
         // Setup the test once
         let origin = &test_setup_structured(test_name, structure).await?;
+        let global = test_config(test_name).await?;
 
         // Path for the actual file on disk that we'll be writing
         let versioned_file_path = origin.join("0").join("0");
@@ -486,7 +636,7 @@ mod test {
         // Run the test again
         assert_prepare_restore(test_name).await?;
 
-        let global = GlobalConfig::from_disk().await?;
+        let global = global.reload()?;
         let wrapping_key = global.clone().wrapping_key().await?;
         let config = global
             .get_bucket(origin)
@@ -591,12 +741,12 @@ mod test {
     }
 
     #[tokio::test]
-    #[serial]
     async fn versioning_simple() -> Result<(), UtilityError> {
         let test_name = "versioning_simple";
         let structure = Structure::new(1, 1, 2000, Strategy::Simple);
         // Setup the test once
         let origin = &test_setup_structured(test_name, structure).await?;
+        let global = test_config(test_name).await?;
 
         // Path for the actual file on disk that we'll be writing
         let versioned_file_path = origin.join("0");
@@ -616,7 +766,7 @@ mod test {
         // Run the test again
         assert_prepare_restore(test_name).await?;
 
-        let global = GlobalConfig::from_disk().await?;
+        let global = global.reload()?;
         let wrapping_key = global.clone().wrapping_key().await?;
         let config = global
             .get_bucket(origin)
@@ -689,7 +839,6 @@ mod test {
     }
 
     #[tokio::test]
-    #[serial]
     async fn symlinks() -> Result<(), UtilityError> {
         let test_name = "symlinks";
 
@@ -725,4 +874,188 @@ mod test {
         // Teardown
         test_teardown(test_name).await
     }
+
+    #[tokio::test]
+    async fn sync() -> Result<(), UtilityError> {
+        let test_name = "sync";
+        let server = MockServer::start();
+        // Create the setup conditions
+        let origin = &test_setup(test_name).await?;
+        let global = test_config(test_name).await?;
+        prepare_pipeline(&global, origin).await?;
+        // Register this device with the mock server
+        configure::remote_core(&global, &server.core_url).await?;
+        let mut global = global.reload()?;
+        let mut client = global.get_client().await?;
+        Account::create_fake(&mut client)
+            .await
+            .map_err(NativeError::api)?;
+        global.save_client(client).await?;
+
+        // Publish the Drive along with its content
+        let mut omni =
+            OmniBucket::from_specifier(&global, &DriveSpecifier::with_origin(origin)).await;
+        assert_eq!(omni.sync_state, SyncState::Unpublished);
+        omni.sync_bucket(&global, None, &LogProgress).await?;
+        assert_eq!(omni.sync_state, SyncState::AllSynced);
+        let remote = omni.get_remote()?;
+        let omni = OmniBucket::from_specifier(&global, &DriveSpecifier::with_origin(origin)).await;
+        assert_eq!(omni.sync_state, SyncState::AllSynced);
+
+        // Forget the Drive locally, then download its metadata again
+        configure::deinit(&global, origin).await?;
+        let mut omni = OmniBucket::from_remote(&remote);
+        omni.sync_bucket(&global, None, &LogProgress).await?;
+        assert_eq!(omni.sync_state, SyncState::MetadataSynced);
+        let local = omni.get_local()?;
+        let metadata = CarV2MemoryBlockStore::try_from(std::fs::read(&local.metadata.path)?)
+            .map_err(NativeError::from)?;
+        let mut client = global.reload()?.get_client().await?;
+        let current = Metadata::read_current(remote.id, &mut client)
+            .await
+            .map_err(NativeError::api)?;
+        assert_eq!(
+            metadata.get_root().map(|cid| cid.to_string()),
+            Some(current.metadata_cid)
+        );
+        let downloaded = local.origin;
+
+        // Teardown
+        configure::deinit(&global, &downloaded).await?;
+        remove_dir_all(&downloaded)?;
+        test_teardown(test_name).await
+    }
+
+    /// Hex fingerprint of a wrapping key
+    async fn fingerprint(key: &EcEncryptionKey) -> Result<String, NativeError> {
+        Ok(hex_fingerprint(
+            key.public_key()?.fingerprint().await?.as_slice(),
+        ))
+    }
+
+    #[tokio::test]
+    async fn rotate() -> Result<(), UtilityError> {
+        let test_name = "rotate";
+        let server = MockServer::start();
+        let origin = &test_setup(test_name).await?;
+        let global = test_config(test_name).await?;
+        prepare_pipeline(&global, origin).await?;
+        configure::remote_core(&global, &server.core_url).await?;
+        let mut global = global.reload()?;
+        let mut client = global.get_client().await?;
+        Account::create_fake(&mut client)
+            .await
+            .map_err(NativeError::api)?;
+        global.save_client(client).await?;
+        let old_key = global.wrapping_key().await?;
+        let old_fingerprint = fingerprint(&old_key).await?;
+
+        // Publish the Drive, and create another which only exists remotely
+        let mut omni =
+            OmniBucket::from_specifier(&global, &DriveSpecifier::with_origin(origin)).await;
+        omni.sync_bucket(&global, None, &LogProgress).await?;
+        let remote = omni.get_remote()?;
+        let mut client = global.reload()?.get_client().await?;
+        let pem = String::from_utf8(
+            old_key
+                .public_key()
+                .map_err(NativeError::from)?
+                .export()
+                .await
+                .map_err(NativeError::from)?,
+        )?;
+        Bucket::create(
+            "rotate_remote".to_string(),
+            pem,
+            BucketType::Interactive,
+            StorageClass::Hot,
+            &mut client,
+        )
+        .await
+        .map_err(NativeError::api)?;
+
+        // The remote only Drive keeps the old key from being retired, unless forced
+        assert!(rotate::pipeline(&global, false).await.is_err());
+        assert_eq!(
+            fingerprint(&global.reload()?.wrapping_key().await?).await?,
+            old_fingerprint
+        );
+        let report = rotate::pipeline(&global, true).await?;
+        assert!(report.contains("SKIPPED"));
+        assert!(report.contains("rotate_remote\tonly readable with the retired key"));
+        let global = global.reload()?;
+        let new_key = global.wrapping_key().await?;
+        let new_fingerprint = fingerprint(&new_key).await?;
+        assert_ne!(old_fingerprint, new_fingerprint);
+
+        // Only the new key opens the local Drive, locally and remotely
+        let local = global.get_bucket(origin).expect("drive config missing");
+        assert!(local.unlock_fs(&new_key).await.is_ok());
+        assert!(local.unlock_fs(&old_key).await.is_err());
+        let mut client = global.get_client().await?;
+        let fingerprints: Vec<String> = BucketKey::read_all(remote.id, &mut client)
+            .await
+            .map_err(NativeError::api)?
+            .into_iter()
+            .map(|key| key.fingerprint)
+            .collect();
+        assert!(fingerprints.contains(&new_fingerprint));
+        assert!(!fingerprints.contains(&old_fingerprint));
+
+        // Teardown
+        test_teardown(test_name).await
+    }
+
+    #[tokio::test]
+    async fn sync_backup() -> Result<(), UtilityError> {
+        let test_name = "sync_backup";
+        let server = MockServer::start();
+        let origin = &test_setup(test_name).await?;
+        let global = test_config(test_name).await?;
+        // Created offline, so the remote Drive waits until the first sync
+        let omni = OmniBucket::create(
+            &global,
+            test_name,
+            origin,
+            BucketType::Backup,
+            BucketType::Backup.default_storage_class(),
+        )
+        .await?;
+        prepare::pipeline(&global, omni, true, &LogProgress).await?;
+        configure::remote_core(&global, &server.core_url).await?;
+        let mut global = global.reload()?;
+        let mut client = global.get_client().await?;
+        Account::create_fake(&mut client)
+            .await
+            .map_err(NativeError::api)?;
+        global.save_client(client).await?;
+
+        // Backups are snapshotted as they are synced
+        let mut omni =
+            OmniBucket::from_specifier(&global, &DriveSpecifier::with_origin(origin)).await;
+        omni.sync_bucket(&global, None, &LogProgress).await?;
+        assert_eq!(omni.sync_state, SyncState::AllSynced);
+        let mut client = global.reload()?.get_client().await?;
+        let remote = Bucket::read(&mut client, omni.get_id()?)
+            .await
+            .map_err(NativeError::api)?;
+        assert_eq!(remote.r#type, BucketType::Backup);
+        assert_eq!(remote.storage_class, StorageClass::Cold);
+        let snapshots = remote
+            .list_snapshots(&mut client)
+            .await
+            .map_err(NativeError::api)?;
+        assert_eq!(snapshots.len(), 1);
+
+        // Moving it to another storage class is remembered locally too
+        let mut omni =
+            OmniBucket::from_specifier(&global, &DriveSpecifier::with_origin(origin)).await;
+        omni.update_storage_class(&global, StorageClass::Warm)
+            .await?;
+        assert_eq!(omni.get_remote()?.storage_class, StorageClass::Warm);
+        assert_eq!(omni.get_local()?.storage_class, StorageClass::Warm);
+
+        // Teardown
+        test_teardown(test_name).await
+    }
 }
@@ -1,14 +1,15 @@
 use crate::{
-    api::models::metadata::Metadata,
+    api::{client::Client, models::metadata::Metadata},
     blockstore::{BanyanApiBlockStore, DoubleSplitStore, RootedBlockStore},
     filesystem::{wnfsio::path_to_segments, FsMetadata},
     native::{
         configuration::globalconfig::GlobalConfig,
+        drive::Drive,
         file_scanning::{grouper, spider, spider_plans::PreparePipelinePlan},
-        sync::OmniBucket,
-        utils::get_progress_bar,
+        sync::{LocalBucket, OmniBucket},
         NativeError,
     },
+    utils::progress::{Phase, ProgressEvent, ProgressSink},
 };
 use std::{
     collections::HashSet,
@@ -23,36 +24,69 @@ use wnfs::private::PrivateNode;
 ///
 /// # Arguments
 ///
-/// * `fs` - FileSystem to modify
 /// * `omni` - Context aware online / offline Drive
-/// * `client` - Means of connecting to the server if need be
 /// * `follow_links` - Whether or not to follow symlinks when bundling.
+/// * `progress` - Where to report progress as files are prepared
 ///
 /// # Return Type
 /// Returns `Ok(())` on success, otherwise returns an error.
-pub async fn pipeline(mut omni: OmniBucket, follow_links: bool) -> Result<String, NativeError> {
-    let mut fs = omni.unlock().await?;
-    let mut local = omni.get_local()?;
-    let mut global = GlobalConfig::from_disk().await?;
-    let mut client = global.get_client().await?;
+pub async fn pipeline(
+    global: &GlobalConfig,
+    omni: OmniBucket,
+    follow_links: bool,
+    progress: &dyn ProgressSink,
+) -> Result<String, NativeError> {
+    let mut global = global.reload()?;
+    let drive = Drive::from_config(&global).await?;
+    // Keep other processes out of this Drive until it is prepared
+    let (drive_lock, local) = global.lock_bucket(&omni.get_local()?)?;
+    let mut handle = drive.open(local, drive_lock).await?;
+    handle.prepare(follow_links, progress).await?;
+    handle.commit().await?;
+    global.update_config(handle.bucket())?;
+
+    Ok(format!(
+        "Prepared data successfully; Encrypted in {}",
+        handle.bucket().content.path.display()
+    ))
+}
 
+/// Bring an unlocked Fs in line with the origin of its Drive, without saving it
+///
+/// # Arguments
+///
+/// * `local` - Drive whose origin is prepared
+/// * `fs` - FileSystem to modify
+/// * `client` - Means of connecting to the server if need be
+/// * `follow_links` - Whether or not to follow symlinks when bundling.
+/// * `progress` - Where to report progress as files are prepared
+pub async fn prepare_bucket(
+    local: &mut LocalBucket,
+    fs: &mut FsMetadata,
+    client: &mut Client,
+    follow_links: bool,
+    progress: &dyn ProgressSink,
+) -> Result<(), NativeError> {
     // If there is a remote Bucket with metadatas that include a content root cid which has already been persisted
     if client.is_authenticated().await {
-        if let Ok(remote) = omni.get_remote() {
-            if let Ok(metadatas) = Metadata::read_all(remote.id, &mut client).await {
+        if let Some(remote_id) = local.remote_id {
+            if let Ok(metadatas) = Metadata::read_all(remote_id, client).await {
                 if metadatas.iter().any(|metadata| {
                     Some(metadata.root_cid.clone())
                         == local.content.get_root().map(|cid| cid.to_string())
                 }) {
                     info!("Starting a new delta...");
                     local.content.add_delta()?;
-                    omni.set_local(local.clone());
                 }
             }
         }
     }
 
     // Create bundling plan
+    progress.report(ProgressEvent::PhaseChanged {
+        phase: Phase::Scanning,
+        total: None,
+    });
     let bundling_plan = create_plans(&local.origin, follow_links).await?;
 
     // Get all the paths present on disk
@@ -94,27 +128,19 @@ pub async fn pipeline(mut omni: OmniBucket, follow_links: bool) -> Result<String
         }
     }
 
+    // Use the server as a backup for getting content
     let split_store_local = DoubleSplitStore::new(&local.content, &local.metadata);
-
-    // If we're online, let's also spin up a BanyanApiBlockStore for getting content
-    if let Ok(client) = GlobalConfig::from_disk().await?.get_client().await {
-        let api_blockstore = BanyanApiBlockStore::from(client);
-        let split_store_remote = DoubleSplitStore::new(&split_store_local, &api_blockstore);
-        info!("Using online server as backup to check for file differences...");
-        process_plans(&mut fs, bundling_plan, &local.metadata, &split_store_remote).await?;
-    } else {
-        warn!("We notice you're offline or unauthenticated, preparing may fail to detect content changes and require repreparation of old files.");
-        process_plans(&mut fs, bundling_plan, &local.metadata, &split_store_local).await?;
-    }
-
-    local.save_fs(&mut fs).await?;
-    global.update_config(&local)?;
-    omni.set_local(local);
-
-    Ok(format!(
-        "Prepared data successfully; Encrypted in {}",
-        omni.get_local()?.content.path.display()
-    ))
+    let api_blockstore = BanyanApiBlockStore::from(client.clone());
+    let split_store_remote = DoubleSplitStore::new(&split_store_local, &api_blockstore);
+    info!("Using online server as backup to check for file differences...");
+    process_plans(
+        fs,
+        bundling_plan,
+        &local.metadata,
+        &split_store_remote,
+        progress,
+    )
+    .await
 }
 
 /// Create PreparePipelinePlans from an origin dir
@@ -158,9 +184,13 @@ pub async fn process_plans(
     bundling_plan: Vec<PreparePipelinePlan>,
     metadata_store: &impl RootedBlockStore,
     content_store: &impl RootedBlockStore,
+    progress: &dyn ProgressSink,
 ) -> Result<(), NativeError> {
-    // Initialize the progress bar using the number of Nodes to process
-    let progress_bar = get_progress_bar(bundling_plan.len() as u64);
+    // Announce the number of Nodes to process
+    progress.report(ProgressEvent::PhaseChanged {
+        phase: Phase::Preparing,
+        total: Some(bundling_plan.len() as u64),
+    });
     // Create vectors of direct and indirect plans
     let mut direct_plans: Vec<PreparePipelinePlan> = Vec::new();
     let mut symlink_plans: Vec<PreparePipelinePlan> = Vec::new();
@@ -179,6 +209,8 @@ pub async fn process_plans(
 
     // First, write data which corresponds to real data
     for direct_plan in direct_plans {
+        let path = plan_path(&direct_plan);
+        progress.report(ProgressEvent::FileStarted { path: path.clone() });
         match direct_plan {
             PreparePipelinePlan::FileGroup(metadatas) => {
                 // Grab the metadata for the first occurrence of this file
@@ -226,11 +258,13 @@ pub async fn process_plans(
         }
 
         // Denote progress for each loop iteration
-        progress_bar.inc(1);
+        progress.report(ProgressEvent::FileDone { path });
     }
 
     // Now that the data exists, we can symlink to it
     for symlink_plan in symlink_plans {
+        let path = plan_path(&symlink_plan);
+        progress.report(ProgressEvent::FileStarted { path: path.clone() });
         match symlink_plan {
             PreparePipelinePlan::Symlink(meta, symlink_target) => {
                 // The path where the symlink will be placed
@@ -245,9 +279,22 @@ pub async fn process_plans(
         }
 
         // Denote progress for each loop iteration
-        progress_bar.inc(1);
+        progress.report(ProgressEvent::FileDone { path });
     }
 
     // Return Ok
     Ok(())
 }
+
+/// Where in the Drive a plan puts its node
+fn plan_path(plan: &PreparePipelinePlan) -> String {
+    let metadata = match plan {
+        PreparePipelinePlan::FileGroup(metadatas) => {
+            metadatas.first().expect("no metadatas present")
+        }
+        PreparePipelinePlan::Directory(metadata) | PreparePipelinePlan::Symlink(metadata, _) => {
+            metadata
+        }
+    };
+    metadata.original_location.display().to_string()
+}
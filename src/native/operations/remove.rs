@@ -5,9 +5,15 @@ use crate::{
 use std::path::Path;
 
 /// The pipeline for removing an individual file from a WNFS
-pub async fn pipeline(mut local: LocalBucket, wnfs_path: &Path) -> Result<(), NativeError> {
+pub async fn pipeline(
+    global: &GlobalConfig,
+    local: LocalBucket,
+    wnfs_path: &Path,
+) -> Result<(), NativeError> {
+    // Keep other processes out of this Drive, picking up anything they wrote before we got in
+    let (_drive_lock, mut local) = global.lock_bucket(&local)?;
     // Global config
-    let mut global = GlobalConfig::from_disk().await?;
+    let mut global = global.reload()?;
     let wrapping_key = global.clone().wrapping_key().await?;
 
     let mut fs = local.unlock_fs(&wrapping_key).await?;
@@ -1,33 +1,72 @@
 use crate::{
-    blockstore::{BanyanApiBlockStore, DoubleSplitStore, RootedBlockStore},
+    api::{client::Client, models::bucket::BucketType},
+    blockstore::{
+        BanyanApiBlockStore, BanyanBlockStore, BlockCache, DoubleSplitStore, RootedBlockStore,
+    },
     filesystem::{wnfsio::path_to_segments, FsMetadata},
     native::{
-        configuration::globalconfig::GlobalConfig, sync::OmniBucket, utils::get_progress_bar,
+        configuration::globalconfig::GlobalConfig,
+        drive::Drive,
+        sync::{LocalBucket, OmniBucket},
         NativeError,
     },
+    utils::progress::{Phase, ProgressEvent, ProgressSink},
+};
+use std::{
+    collections::BTreeSet,
+    fs::File,
+    io::Write,
+    os::unix::fs::symlink,
+    path::{Path, PathBuf},
 };
-use std::{fs::File, io::Write, os::unix::fs::symlink, path::PathBuf};
-use wnfs::private::PrivateNode;
+use wnfs::private::{PrivateFile, PrivateNode};
 
 /// Given the manifest file and a destination for our restored data, run the restoring pipeline
 /// on the data referenced in the manifest.
 ///
 /// # Arguments
 ///
-/// * `fs` - FileSystem to modify
 /// * `omni` - Context aware online / offline Drive
-/// * `client` - Means of connecting to the server if need be
+/// * `progress` - Where to report progress as files are restored
 ///
 /// # Return Type
 /// Returns `Ok(())` on success, otherwise returns an error.
-pub async fn pipeline(mut omni: OmniBucket) -> Result<String, NativeError> {
-    let fs = omni.unlock().await?;
-    let local = omni.get_local()?;
-    let mut global = GlobalConfig::from_disk().await?;
-    let mut client = global.get_client().await?;
+pub async fn pipeline(
+    global: &GlobalConfig,
+    mut omni: OmniBucket,
+    progress: &dyn ProgressSink,
+) -> Result<String, NativeError> {
+    let restored = omni.get_or_init_origin(global).await?;
+    let mut global = global.reload()?;
+    let drive = Drive::from_config(&global).await?;
+    let (drive_lock, local) = global.lock_bucket(&omni.get_local()?)?;
+    let mut handle = drive.open(local, drive_lock).await?;
+    handle.restore(&restored, progress).await?;
+    global.update_config(handle.bucket())?;
+
+    Ok("🎉 Data has been successfully reconstructed!".to_string())
+}
+
+/// Restore every node of an unlocked Fs into a directory
+///
+/// # Arguments
+///
+/// * `local` - Drive being restored
+/// * `fs` - FileSystem to restore
+/// * `restored` - Directory to restore into
+/// * `client` - Means of connecting to the server if need be
+/// * `block_cache` - Where to keep content fetched for interactive Drives, if anywhere
+/// * `progress` - Where to report progress as files are restored
+pub async fn restore_bucket(
+    local: &LocalBucket,
+    fs: &FsMetadata,
+    restored: &Path,
+    client: &mut Client,
+    block_cache: Option<BlockCache>,
+    progress: &dyn ProgressSink,
+) -> Result<(), NativeError> {
     // Announce that we're starting
     info!("🚀 Starting restoration pipeline...");
-    let restored = omni.get_or_init_origin().await?;
 
     let metadata_store = &local.metadata;
     // Get all the nodes in the FileSystem
@@ -39,44 +78,77 @@ pub async fn pipeline(mut omni: OmniBucket) -> Result<String, NativeError> {
     );
 
     if client.is_authenticated().await {
-        let api_store = BanyanApiBlockStore::from(client.to_owned());
-        let split_store = DoubleSplitStore::new(&local.content, &api_store);
+        let mut api_store = BanyanApiBlockStore::from(client.to_owned());
+        // Backups are restored in bulk and rarely read again, so keep them from evicting interactive content
+        if local.r#type == BucketType::Interactive {
+            if let Some(cache) = block_cache {
+                api_store = api_store.with_cache(cache);
+            }
+        }
         info!("Using online server as backup to grab file content...");
-        restore_nodes(&fs, all_nodes, restored, metadata_store, &split_store).await?;
+        restore_nodes(
+            fs,
+            all_nodes,
+            restored.to_path_buf(),
+            metadata_store,
+            &local.content,
+            Some(&api_store),
+            progress,
+        )
+        .await
     } else {
-        warn!("We notice you're offline or unauthenticated, reconstructing may fail if encrypted data is not already present on disk.");
-        restore_nodes(&fs, all_nodes, restored, metadata_store, &local.content).await?;
+        progress.warn("We notice you're offline or unauthenticated, reconstructing may fail if encrypted data is not already present on disk.");
+        restore_nodes(
+            fs,
+            all_nodes,
+            restored.to_path_buf(),
+            metadata_store,
+            &local.content,
+            None,
+            progress,
+        )
+        .await
     }
-
-    global.update_config(&local)?;
-
-    Ok("🎉 Data has been successfully reconstructed!".to_string())
 }
 
-/// Restore all nodes
+/// Restore all nodes, fetching content missing from the content store from the API store if there is one
 pub async fn restore_nodes(
     fs: &FsMetadata,
     all_nodes: Vec<(PrivateNode, PathBuf)>,
     restored: PathBuf,
     metadata_store: &impl RootedBlockStore,
     content_store: &impl RootedBlockStore,
+    api_store: Option<&BanyanApiBlockStore>,
+    progress: &dyn ProgressSink,
 ) -> Result<(), NativeError> {
-    // Initialize the progress bar using the number of Nodes to process
-    let progress_bar = get_progress_bar(all_nodes.len() as u64);
+    // Announce the number of Nodes to process
+    progress.report(ProgressEvent::PhaseChanged {
+        phase: Phase::Restoring,
+        total: Some(all_nodes.len() as u64),
+    });
     // For each node path tuple in the FS Metadata
     for (node, path) in all_nodes {
+        let progress_path = path.display().to_string();
+        progress.report(ProgressEvent::FileStarted {
+            path: progress_path.clone(),
+        });
         match node {
             PrivateNode::Dir(_) => {
                 // Create the directory
                 std::fs::create_dir_all(restored.join(path))?;
-                progress_bar.inc(1);
             }
             PrivateNode::File(file) => {
                 let built_path = restored.join(path.clone());
 
-                let content = fs
-                    .read(&path_to_segments(&path)?, metadata_store, content_store)
-                    .await?;
+                let segments = path_to_segments(&path)?;
+                let content = match api_store {
+                    Some(api_store) => {
+                        prefetch(fs, &file, metadata_store, content_store, api_store).await?;
+                        let split_store = DoubleSplitStore::new(content_store, api_store);
+                        fs.read(&segments, metadata_store, &split_store).await?
+                    }
+                    None => fs.read(&segments, metadata_store, content_store).await?,
+                };
 
                 // If this file is a symlink
                 if let Some(origin) = file.symlink_origin() {
@@ -96,10 +168,36 @@ pub async fn restore_nodes(
                     // Write out the content to disk
                     output_file.write_all(&content)?;
                 }
-
-                progress_bar.inc(1);
             }
         }
+        progress.report(ProgressEvent::FileDone {
+            path: progress_path,
+        });
+    }
+    Ok(())
+}
+
+/// Fetch all the blocks of a file which aren't already in the content store, several at a time
+async fn prefetch(
+    fs: &FsMetadata,
+    file: &PrivateFile,
+    metadata_store: &impl RootedBlockStore,
+    content_store: &impl RootedBlockStore,
+    api_store: &BanyanApiBlockStore,
+) -> Result<(), NativeError> {
+    let mut missing = BTreeSet::new();
+    for cid in file
+        .get_cids(&fs.forest, metadata_store)
+        .await
+        .map_err(Box::from)?
+    {
+        if BanyanBlockStore::get_block(content_store, &cid)
+            .await
+            .is_err()
+        {
+            missing.insert(cid);
+        }
     }
+    api_store.prefetch(missing).await?;
     Ok(())
 }
@@ -0,0 +1,231 @@
+use crate::{
+    api::{client::Client, models::bucket_key::BucketKey},
+    native::{
+        configuration::{
+            globalconfig::GlobalConfig,
+            keys::{
+                encrypt_key_file, is_encrypted, key_passphrase, new_wrapping_key, wrapping_key,
+            },
+        },
+        sync::{OmniBucket, SyncState},
+        NativeError,
+    },
+    utils::progress::LogProgress,
+};
+use chrono::Utc;
+use colored::Colorize;
+use std::{
+    fs::{copy, rename},
+    path::{Path, PathBuf},
+};
+use tomb_crypt::{
+    hex_fingerprint,
+    prelude::{EcEncryptionKey, EcPublicEncryptionKey, PrivateKey, PublicKey},
+};
+
+/// Where the replacement wrapping key lives while a rotation is in progress
+fn next_wrapping_key_path(wrapping_key_path: &Path) -> PathBuf {
+    wrapping_key_path.with_extension("next.pem")
+}
+
+/// Where the old wrapping key is kept once a rotation completes
+fn retired_wrapping_key_path(wrapping_key_path: &Path) -> PathBuf {
+    wrapping_key_path.with_extension(format!("retired-{}.pem", Utc::now().timestamp()))
+}
+
+/// Everything needed to move a Drive from one wrapping key to another
+struct Rotation {
+    old_key: EcEncryptionKey,
+    old_fingerprint: String,
+    new_key: EcEncryptionKey,
+    new_public_key: EcPublicEncryptionKey,
+    new_fingerprint: String,
+    new_pem: String,
+}
+
+/// Replace the wrapping key, moving every local Drive over to the new key before retiring the old one.
+/// The new key is persisted before any Drive is touched, and every step checks whether it has
+/// already been performed, so an interrupted rotation is finished by running this again.
+/// Drives which only exist remotely can't be moved over, so the old key is only retired
+/// while any remain if forced to, leaving them unreadable with the new key.
+pub async fn pipeline(global: &GlobalConfig, force: bool) -> Result<String, NativeError> {
+    let next_path = next_wrapping_key_path(&global.wrapping_key_path);
+
+    // Resume with the pending key if a previous rotation was interrupted
+    let old_key = global.wrapping_key().await?;
+    let new_key = if next_path.exists() {
+        info!("Resuming a previous wrapping key rotation...");
+        wrapping_key(&next_path).await?
+    } else {
+        let new_key = new_wrapping_key(&next_path).await?;
+        // Keep the new key as well protected as the old one
+        if is_encrypted(&global.wrapping_key_path)? {
+            encrypt_key_file(&next_path, &key_passphrase()?)?;
+        }
+        new_key
+    };
+
+    let new_public_key = new_key.public_key()?;
+    let rotation = Rotation {
+        old_fingerprint: hex_fingerprint(old_key.public_key()?.fingerprint().await?.as_slice()),
+        new_fingerprint: hex_fingerprint(new_public_key.fingerprint().await?.as_slice()),
+        new_pem: String::from_utf8(new_public_key.export().await?)?,
+        old_key,
+        new_key,
+        new_public_key,
+    };
+
+    // Move every Drive over, noting failures instead of stopping at the first one
+    let mut client = global.get_client().await?;
+    let mut report = String::new();
+    let mut failures = 0;
+    let mut skipped = Vec::new();
+    for mut omni in OmniBucket::ls(global).await? {
+        let name = match (omni.get_local(), omni.get_remote()) {
+            (Ok(local), _) => local.name,
+            // Without local metadata there is nothing this device can re-share
+            (_, Ok(remote)) => {
+                report = format!(
+                    "{report}\n{}\t{}\tnot available locally",
+                    "SKIPPED".yellow(),
+                    remote.name
+                );
+                skipped.push(remote.name);
+                continue;
+            }
+            _ => continue,
+        };
+        match rotate_drive(global, &mut omni, &rotation, &mut client).await {
+            Ok(()) => report = format!("{report}\n{}\t{name}", "ROTATED".green()),
+            Err(err) => {
+                failures += 1;
+                report = format!("{report}\n{}\t{name}\t{err}", "FAILED".red());
+            }
+        }
+    }
+
+    // The old key can't be retired while any Drive still depends on it
+    if failures > 0 {
+        return Err(NativeError::custom_error(&format!(
+            "{}{report}\n{}",
+            "<< WRAPPING KEY ROTATION INCOMPLETE >>".red(),
+            "Address the failures above and run the rotation again.".yellow()
+        )));
+    }
+    if !skipped.is_empty() {
+        if !force {
+            return Err(NativeError::custom_error(&format!(
+                "{}{report}
+{}",
+                "<< WRAPPING KEY ROTATION INCOMPLETE >>".red(),
+                "The skipped Drives are only shared with the old key. Sync them and run the rotation again, or force it to retire the old key regardless.".yellow()
+            )));
+        }
+        for name in &skipped {
+            warn!(
+                "Retiring the old wrapping key leaves Drive `{name}` unreadable with the new one"
+            );
+            report = format!(
+                "{report}
+{}	{name}	only readable with the retired key",
+                "WARNING".yellow()
+            );
+        }
+    }
+
+    // Retire the old key
+    copy(
+        &global.wrapping_key_path,
+        retired_wrapping_key_path(&global.wrapping_key_path),
+    )?;
+    rename(&next_path, &global.wrapping_key_path)?;
+
+    Ok(format!(
+        "{}{report}\nold_fingerprint:\t{}\nnew_fingerprint:\t{}",
+        "<< WRAPPING KEY ROTATED SUCCESSFULLY >>".green(),
+        rotation.old_fingerprint,
+        rotation.new_fingerprint
+    ))
+}
+
+/// Move a single Drive from the old wrapping key to the new one
+async fn rotate_drive(
+    global: &GlobalConfig,
+    omni: &mut OmniBucket,
+    rotation: &Rotation,
+    client: &mut Client,
+) -> Result<(), NativeError> {
+    let local = omni
+        .get_local()
+        .map_err(|_| NativeError::custom_error("Drive is not available locally; sync it first"))?;
+    let (drive_lock, mut local) = global.lock_bucket(&local)?;
+
+    // If the old key can't open the Drive anymore, it may have been rotated already
+    let mut fs = match local.unlock_fs(&rotation.old_key).await {
+        Ok(fs) => fs,
+        Err(_) => local.unlock_fs(&rotation.new_key).await?,
+    };
+    let fingerprints = fs.share_manager.public_fingerprints();
+    let needs_new_key = !fingerprints.contains(&rotation.new_fingerprint);
+    let has_old_key = fingerprints.contains(&rotation.old_fingerprint);
+
+    // Register the new key remotely so that pushing the metadata approves it
+    if let Ok(remote) = omni.get_remote() {
+        let existing_keys = BucketKey::read_all(remote.id, client).await?;
+        if !existing_keys
+            .iter()
+            .any(|key| key.fingerprint == rotation.new_fingerprint)
+        {
+            BucketKey::create(remote.id, rotation.new_pem.clone(), client).await?;
+        }
+    }
+
+    if needs_new_key || has_old_key {
+        match omni.sync_state {
+            // Everything is already persisted remotely, so write to a fresh delta
+            SyncState::AllSynced => local.content.add_delta()?,
+            // Nothing to conflict with
+            SyncState::Ahead | SyncState::Unpublished => {}
+            _ => {
+                return Err(NativeError::custom_error(
+                    "Drive must be synced before rotating keys",
+                ))
+            }
+        }
+
+        if needs_new_key {
+            fs.share_with(&rotation.new_public_key, &local.metadata)
+                .await?;
+        }
+        // Rotate the ratchets as well, so that the old key can't follow future revisions
+        if has_old_key {
+            fs.revoke(&rotation.old_fingerprint, &local.metadata, &local.content)
+                .await?;
+        }
+        local.save_fs(&mut fs).await?;
+        global.reload()?.update_config(&local)?;
+        omni.set_local(local);
+        if omni.get_remote().is_ok() {
+            omni.sync_state = SyncState::Ahead;
+        }
+    }
+
+    if let Ok(remote) = omni.get_remote() {
+        // Push any metadata that hasn't made it to the remote yet, including from interrupted runs
+        if omni.sync_state == SyncState::Ahead {
+            omni.sync_bucket_locked(global, Some(drive_lock), None, &LogProgress)
+                .await?;
+        }
+
+        // Retire the old key remotely as well
+        let existing_keys = BucketKey::read_all(remote.id, client).await?;
+        if let Some(old_key) = existing_keys
+            .iter()
+            .find(|key| key.fingerprint == rotation.old_fingerprint)
+        {
+            BucketKey::delete_by_id(remote.id, old_key.id, client).await?;
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,65 @@
+use crate::{
+    filesystem::{tags, wnfsio::path_to_segments},
+    native::{configuration::globalconfig::GlobalConfig, sync::LocalBucket, NativeError},
+};
+use std::path::Path;
+use wnfs::libipld::Ipld;
+
+/// Read the tags on a file or directory in a WNFS, or just the one under `key`
+pub async fn get(
+    global: &GlobalConfig,
+    local: &LocalBucket,
+    wnfs_path: &Path,
+    key: Option<String>,
+) -> Result<String, NativeError> {
+    let wrapping_key = global.wrapping_key().await?;
+    let fs = local.unlock_fs(&wrapping_key).await?;
+
+    let metadata = fs
+        .get_metadata(&path_to_segments(wnfs_path)?, &local.metadata)
+        .await?;
+    let mut tags = tags(&metadata);
+    if let Some(key) = key {
+        tags.retain(|tag, _| *tag == key);
+    }
+    Ok(tags
+        .iter()
+        .map(|(key, value)| format!("{key}:\t{}", display(value)))
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+/// The pipeline for tagging an individual file in a WNFS, removing the tag if there is no value
+pub async fn set(
+    global: &GlobalConfig,
+    local: LocalBucket,
+    wnfs_path: &Path,
+    key: &str,
+    value: Option<String>,
+) -> Result<(), NativeError> {
+    // Keep other processes out of this Drive, picking up anything they wrote before we got in
+    let (_drive_lock, mut local) = global.lock_bucket(&local)?;
+    // Global config
+    let mut global = global.reload()?;
+    let wrapping_key = global.clone().wrapping_key().await?;
+
+    let mut fs = local.unlock_fs(&wrapping_key).await?;
+    let value = value.map(Ipld::String).unwrap_or(Ipld::Null);
+    fs.set_metadata(&path_to_segments(wnfs_path)?, key, value, &local.metadata)
+        .await?;
+
+    // Store all the updated information, now that we've tagged the file
+    local.save_fs(&mut fs).await?;
+
+    // Update global
+    global.update_config(&local)?;
+    Ok(())
+}
+
+/// Strings are shown as they are, everything else as it would be in a debugger
+fn display(value: &Ipld) -> String {
+    match value {
+        Ipld::String(s) => s.clone(),
+        value => format!("{value:?}"),
+    }
+}
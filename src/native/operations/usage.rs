@@ -0,0 +1,15 @@
+use crate::{
+    filesystem::UsageBreakdown,
+    native::{configuration::globalconfig::GlobalConfig, sync::LocalBucket, NativeError},
+};
+
+/// Break down what the files in a local Drive take up, from its metadata
+pub async fn breakdown(
+    global: &GlobalConfig,
+    local: &LocalBucket,
+) -> Result<UsageBreakdown, NativeError> {
+    let wrapping_key = global.wrapping_key().await?;
+    let fs = local.unlock_fs(&wrapping_key).await?;
+
+    Ok(fs.usage_breakdown(&local.metadata, &local.content).await?)
+}
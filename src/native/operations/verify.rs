@@ -0,0 +1,65 @@
+use crate::{
+    blockstore::{BanyanApiBlockStore, BanyanBlockStore},
+    native::{
+        configuration::globalconfig::GlobalConfig, sync::OmniBucket, utils::get_progress_bar,
+        NativeError,
+    },
+};
+use colored::Colorize;
+use wnfs::private::PrivateNode;
+
+/// Fetch every content block of a Drive from remote storage, checking that each one arrives
+/// and matches its CID, and report on those which don't.
+///
+/// # Arguments
+///
+/// * `omni` - Context aware online / offline Drive
+///
+/// # Return Type
+/// Returns a report on the blocks which failed verification, or an error if they couldn't be checked.
+pub async fn pipeline(global: &GlobalConfig, omni: OmniBucket) -> Result<String, NativeError> {
+    let fs = omni.unlock(global).await?;
+    let local = omni.get_local()?;
+    let client = global.get_client().await?;
+    // Steer clear of the block cache, the point is to check what storage serves now
+    let api_store = BanyanApiBlockStore::from(client);
+
+    info!(
+        "🔍 Verifying the content of {} in remote storage...",
+        local.name
+    );
+    let all_nodes = fs.get_all_nodes(&local.metadata).await?;
+    let progress_bar = get_progress_bar(all_nodes.len() as u64);
+    let mut verified = 0;
+    let mut failures = Vec::new();
+    for (node, path) in all_nodes {
+        if let PrivateNode::File(file) = node {
+            let cids = file
+                .get_cids(&fs.forest, &local.metadata)
+                .await
+                .map_err(Box::from)?;
+            api_store.find_cids(cids.clone()).await?;
+            for cid in cids {
+                match api_store.get_block(&cid).await {
+                    Ok(_) => verified += 1,
+                    Err(err) => failures.push(format!("{}\t{cid}\n\t{err}", path.display())),
+                }
+            }
+        }
+        progress_bar.inc(1);
+    }
+
+    if failures.is_empty() {
+        Ok(format!(
+            "{}\n{verified} blocks verified",
+            "<< ALL BLOCKS VERIFIED >>".green()
+        ))
+    } else {
+        Ok(format!(
+            "{}\n{verified} blocks verified, {} failed:\n{}",
+            "<< BLOCKS FAILED VERIFICATION >>".red(),
+            failures.len(),
+            failures.join("\n")
+        ))
+    }
+}
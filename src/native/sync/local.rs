@@ -1,8 +1,11 @@
 use crate::{
-    api::models::storage_ticket::StorageTicket,
+    api::models::{
+        bucket::{BucketType, StorageClass},
+        storage_ticket::StorageTicket,
+    },
     blockstore::{CarV2DiskBlockStore, MultiCarV2DiskBlockStore},
     filesystem::{FilesystemError, FsMetadata},
-    native::configuration::xdg::xdg_data_home,
+    native::{configuration::lock::FileLock, NativeError},
     prelude::blockstore::RootedBlockStore,
 };
 use colored::Colorize;
@@ -16,21 +19,25 @@ use std::{
 };
 use tomb_crypt::prelude::EcEncryptionKey;
 use uuid::Uuid;
-use wnfs::{libipld::Cid, private::PrivateNodeOnPathHistory};
+use wnfs::{
+    libipld::Cid,
+    private::{PrivateNode, PrivateNodeOnPathHistory},
+};
 
 const BUCKET_METADATA_FILE_NAME: &str = "metadata.car";
 const BUCKET_CONTENT_DIR_NAME: &str = "content";
+const BUCKET_LOCK_FILE_NAME: &str = "drive.lock";
 
-fn bucket_data_home(local_id: &str) -> PathBuf {
-    xdg_data_home().join(local_id)
+fn bucket_data_home(data_home: &Path, local_id: &str) -> PathBuf {
+    data_home.join(local_id)
 }
 
-fn bucket_metadata_path(name: &str) -> PathBuf {
-    xdg_data_home().join(name).join(BUCKET_METADATA_FILE_NAME)
+fn bucket_metadata_path(data_home: &Path, name: &str) -> PathBuf {
+    data_home.join(name).join(BUCKET_METADATA_FILE_NAME)
 }
 
-fn bucket_content_path(name: &str) -> PathBuf {
-    xdg_data_home().join(name).join(BUCKET_CONTENT_DIR_NAME)
+fn bucket_content_path(data_home: &Path, name: &str) -> PathBuf {
+    data_home.join(name).join(BUCKET_CONTENT_DIR_NAME)
 }
 
 // TODO: This is maybe better concieved of as a Bucket
@@ -45,6 +52,12 @@ pub struct LocalBucket {
     local_id: String,
     /// Bucket Uuid on the remote server
     pub(crate) remote_id: Option<Uuid>,
+    /// What this bucket is used for, deciding how it is synced
+    #[serde(default)]
+    pub(crate) r#type: BucketType,
+    /// Storage class requested for the bucket when it is created remotely
+    #[serde(default)]
+    pub(crate) storage_class: StorageClass,
     /// Storage ticket in case we lose track of non-metadata components
     pub(crate) storage_ticket: Option<StorageTicket>,
     /// Locally deleted blocks the server needs to be notified of
@@ -60,7 +73,7 @@ pub struct LocalBucket {
 impl Display for LocalBucket {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.write_fmt(format_args!(
-            "name:\t\t\t{}\ndrive_id:\t\t{}\norigin:\t\t\t{}\nstorage_ticket:\t\t{}",
+            "name:\t\t\t{}\ndrive_id:\t\t{}\norigin:\t\t\t{}\ntype:\t\t\t{}\nstorage_class:\t\t{}\nstorage_ticket:\t\t{}",
             self.name,
             if let Some(remote_id) = self.remote_id {
                 remote_id.to_string()
@@ -68,6 +81,8 @@ impl Display for LocalBucket {
                 format!("{}", "Unknown".red())
             },
             self.origin.display(),
+            self.r#type,
+            self.storage_class,
             if let Some(storage_ticket) = self.storage_ticket.clone() {
                 storage_ticket.host
             } else {
@@ -78,10 +93,11 @@ impl Display for LocalBucket {
 }
 
 impl LocalBucket {
-    /// Given a directory, initialize a configuration for it
+    /// Given a directory, initialize a configuration for it, keeping its data in the data home
     pub async fn new(
         origin: &Path,
         wrapping_key: &EcEncryptionKey,
+        data_home: &Path,
     ) -> Result<Self, FilesystemError> {
         create_dir_all(origin).expect("already exists");
         let name = origin
@@ -97,10 +113,10 @@ impl LocalBucket {
             .map(char::from)
             .collect();
         // Compose the generated directory
-        let bucket_home = bucket_data_home(&local_id);
+        let bucket_home = bucket_data_home(data_home, &local_id);
         create_dir_all(bucket_home).expect("already exists");
-        let metadata_path = bucket_metadata_path(&local_id);
-        let content_path = bucket_content_path(&local_id);
+        let metadata_path = bucket_metadata_path(data_home, &local_id);
+        let content_path = bucket_content_path(data_home, &local_id);
         let metadata = CarV2DiskBlockStore::new(&metadata_path)?;
         let mut content = MultiCarV2DiskBlockStore::new(&content_path)?;
         content.add_delta()?;
@@ -114,6 +130,8 @@ impl LocalBucket {
             origin: origin.to_path_buf(),
             local_id,
             remote_id: None,
+            r#type: BucketType::default(),
+            storage_class: StorageClass::default(),
             storage_ticket: None,
             deleted_block_cids: BTreeSet::new(),
             metadata,
@@ -122,14 +140,30 @@ impl LocalBucket {
         })
     }
 
-    pub(crate) fn remove_data(&self) -> Result<(), std::io::Error> {
+    pub(crate) fn remove_data(&self, data_home: &Path) -> Result<(), std::io::Error> {
         // Remove dir if it exists
-        if bucket_data_home(&self.local_id).exists() {
-            remove_dir_all(bucket_data_home(&self.local_id))?;
+        let bucket_home = bucket_data_home(data_home, &self.local_id);
+        if bucket_home.exists() {
+            remove_dir_all(bucket_home)?;
         }
         Ok(())
     }
 
+    /// Take exclusive access to this Drive's data, so that no other process modifies it until the lock is dropped
+    pub fn lock(&self) -> Result<FileLock, NativeError> {
+        let bucket_home = self
+            .metadata
+            .path
+            .parent()
+            .ok_or(NativeError::custom_error("Drive data has no home"))?;
+        FileLock::try_exclusive(&bucket_home.join(BUCKET_LOCK_FILE_NAME))?.ok_or_else(|| {
+            NativeError::custom_error(&format!(
+                "Drive `{}` is being modified by another process",
+                self.name
+            ))
+        })
+    }
+
     /// Shortcut for unlocking a filesystem
     pub async fn unlock_fs(
         &self,
@@ -144,6 +178,30 @@ impl LocalBucket {
         fs.save(&self.metadata, &self.content).await
     }
 
+    /// Every CID a snapshot of this bucket has to keep: all the blocks of its metadata and of its files
+    pub async fn active_cids(&self, fs: &FsMetadata) -> Result<BTreeSet<Cid>, FilesystemError> {
+        // Start off by considering all CIDs in the metatadata CAR as 'active'
+        let index = self.metadata.car.car.index.borrow().clone();
+        let mut active_cids = index.buckets[0]
+            .map
+            .clone()
+            .into_keys()
+            .collect::<BTreeSet<Cid>>();
+
+        // For every node that is a PrivateFile
+        for (node, _) in fs.get_all_nodes(&self.metadata).await? {
+            if let PrivateNode::File(file) = node {
+                // Extend with all the cids in the file
+                active_cids.extend(
+                    file.get_cids(&fs.forest, &self.content)
+                        .await
+                        .map_err(|err| FilesystemError::wnfs(Box::from(err)))?,
+                )
+            }
+        }
+        Ok(active_cids)
+    }
+
     /// Shortcut for serialize::load_history
     pub async fn get_history(
         &self,
@@ -156,17 +214,15 @@ impl LocalBucket {
 
 #[cfg(test)]
 mod test {
-    use crate::native::{configuration::globalconfig::GlobalConfig, NativeError};
+    use crate::{native::NativeError, utils::testing::local_operations::test_config};
     use chrono::Utc;
     use rand::thread_rng;
-    use serial_test::serial;
     use std::{
         fs::{create_dir_all, remove_dir_all},
         path::Path,
     };
 
     #[tokio::test]
-    #[serial]
     async fn get_set_get_all() -> Result<(), NativeError> {
         let test_name = "config_set_get_all";
         let origin = Path::new("test").join(test_name);
@@ -174,7 +230,7 @@ mod test {
             remove_dir_all(&origin)?;
         }
         create_dir_all(&origin)?;
-        let mut global = GlobalConfig::from_disk().await?;
+        let mut global = test_config(test_name).await?;
         let wrapping_key = global.clone().wrapping_key().await?;
         let mut config = global.get_or_init_bucket("test", &origin).await?;
         let mut rng = thread_rng();
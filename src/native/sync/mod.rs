@@ -1,6 +1,5 @@
 mod local;
 mod omni;
-use crate::prelude::api::requests::core::buckets::metadata::push::PushMetadata;
 // mod sync;
 // mod error;
 // pub(crate) use error::SyncError;
@@ -11,24 +10,21 @@ use crate::{
     api::{
         client::Client,
         error::ApiError,
-        models::{
-            bucket::{Bucket, BucketType, StorageClass},
-            metadata::Metadata,
-            storage_ticket::StorageTicket,
-        },
-        requests::staging::upload::content::UploadContent,
+        models::{bucket::BucketType, metadata::Metadata, storage_ticket::StorageTicket},
     },
-    blockstore::{BanyanApiBlockStore, CarV2MemoryBlockStore, RootedBlockStore},
-    filesystem::{FilesystemError, FsMetadata},
-    native::{configuration::globalconfig::GlobalConfig, operations::restore, NativeError},
+    blockstore::{BanyanApiBlockStore, RootedBlockStore},
+    filesystem::FilesystemError,
+    native::{
+        configuration::{globalconfig::GlobalConfig, lock::FileLock},
+        drive::{Drive, Pushed},
+        NativeError,
+    },
+    utils::progress::ProgressSink,
 };
 use colored::Colorize;
-use futures_util::StreamExt;
 pub use local::LocalBucket;
 pub use omni::OmniBucket;
 use std::{collections::BTreeSet, fmt::Display};
-use tokio::io::AsyncWriteExt;
-use tomb_crypt::prelude::{PrivateKey, PublicKey};
 use wnfs::{common::BlockStore, libipld::Ipld};
 
 /// Sync State
@@ -68,7 +64,7 @@ impl Display for SyncState {
 
 impl OmniBucket {
     /// Determine the Sync State of an omni bucket
-    pub async fn determine_sync_state(&mut self) -> Result<(), NativeError> {
+    pub async fn determine_sync_state(&mut self, global: &GlobalConfig) -> Result<(), NativeError> {
         let bucket_id = match self.get_id() {
             Ok(bucket_id) => bucket_id,
             Err(err) => {
@@ -79,7 +75,7 @@ impl OmniBucket {
         };
 
         // Grab the current remote Metadata, or return Unpublished if that operation fails
-        let mut client = GlobalConfig::from_disk().await?.get_client().await?;
+        let mut client = global.get_client().await?;
         let Ok(current_remote) = Metadata::read_current(bucket_id, &mut client).await else {
             self.sync_state = SyncState::Unpublished;
             return Ok(());
@@ -117,32 +113,52 @@ impl OmniBucket {
         }
     }
 
-    /// Sync
+    /// Sync, snapshotting newly uploaded content if asked to. Backup Drives snapshot by default.
     #[allow(unused)]
-    pub async fn sync_bucket(&mut self) -> Result<String, NativeError> {
-        let mut global = GlobalConfig::from_disk().await?;
+    pub async fn sync_bucket(
+        &mut self,
+        global: &GlobalConfig,
+        snapshot: Option<bool>,
+        progress: &dyn ProgressSink,
+    ) -> Result<String, NativeError> {
+        // Keep other processes out of this Drive while syncing, if it exists locally yet
+        let drive_lock = match self.get_local() {
+            Ok(local) => {
+                let (drive_lock, local) = global.lock_bucket(&local)?;
+                self.set_local(local);
+                Some(drive_lock)
+            }
+            Err(_) => None,
+        };
+        self.sync_bucket_locked(global, drive_lock, snapshot, progress)
+            .await
+    }
+
+    /// Sync while already holding the lock on the local Drive, if there is one yet
+    pub async fn sync_bucket_locked(
+        &mut self,
+        global: &GlobalConfig,
+        drive_lock: Option<FileLock>,
+        snapshot: Option<bool>,
+        progress: &dyn ProgressSink,
+    ) -> Result<String, NativeError> {
+        let snapshot = snapshot.unwrap_or(self.get_type() == BucketType::Backup);
+        let mut global = global.reload()?;
         let mut client = global.get_client().await?;
+        let drive = Drive::from_config(&global).await?;
         match &self.sync_state {
             // Download the Bucket
             SyncState::Unlocalized | SyncState::Behind => {
-                let current = Metadata::read_current(self.get_id()?, &mut client).await?;
-                let mut byte_stream = current.pull(&mut client).await?;
-
-                self.get_or_init_origin().await.ok();
-
-                let mut buffer = <Vec<u8>>::new();
-                // Write every chunk to it
-                while let Some(chunk) = byte_stream.next().await {
-                    tokio::io::copy(&mut chunk.map_err(ApiError::http)?.as_ref(), &mut buffer)
-                        .await?;
-                }
-                // Attempt to create a CARv2 BlockStore from the data
-                let metadata = CarV2MemoryBlockStore::try_from(buffer)?;
-                // Grab the metadata file
-                let mut metadata_file =
-                    tokio::fs::File::create(&self.get_local()?.metadata.path).await?;
-                metadata_file.write_all(&metadata.get_data()).await?;
-                // Write that data out to the metadatas
+                self.get_or_init_origin(&global).await.ok();
+                let local = self.get_local()?;
+                let drive_lock = drive_lock.map_or_else(|| local.lock(), Ok)?;
+                let mut handle = drive.open(local, drive_lock).await?;
+                // A Drive only just made here has nothing to lose, but commits to any other which
+                // haven't been pushed, or whose content never arrived, are kept rather than pulled over
+                let overwrite = matches!(self.sync_state, SyncState::Unlocalized);
+                handle.pull(overwrite, progress).await?;
+                global.update_config(handle.bucket())?;
+                self.set_local(handle.into_bucket());
 
                 info!("{}", "<< METADATA RECONSTRUCTED >>".green());
                 self.sync_state = SyncState::MetadataSynced;
@@ -153,118 +169,34 @@ impl OmniBucket {
             }
             // Upload the Bucket
             SyncState::Unpublished | SyncState::Ahead => {
-                let mut local = self.get_local()?;
-                let wrapping_key = global.wrapping_key().await?;
-                let fs = local.unlock_fs(&wrapping_key).await?;
-
-                // If there is still no ID, that means the remote Bucket was never created
-                if self.get_id().is_err() {
-                    let public_key = wrapping_key.public_key()?;
-                    let pem = String::from_utf8(public_key.export().await?)?;
-                    let (remote, _) = Bucket::create(
-                        local.name.clone(),
-                        pem,
-                        BucketType::Interactive,
-                        StorageClass::Hot,
-                        &mut client,
-                    )
-                    .await?;
-
-                    self.set_remote(remote.clone());
-                    local.remote_id = Some(remote.id);
-                    global.update_config(&local)?;
-                    self.set_local(local.clone());
-                }
-
-                // Extract variables or error
-                let bucket_id = self.get_id()?;
-                let local_content_cid = local
-                    .content
-                    .get_root()
-                    .ok_or(FilesystemError::missing_metadata("root cid"))?;
-                let local_metadata_cid = local
-                    .metadata
-                    .get_root()
-                    .ok_or(FilesystemError::missing_metadata("metdata cid"))?;
-                let delta = local.content.get_delta()?;
-
-                // Push the metadata
-                let (metadata, host, authorization) = Metadata::push(
-                    PushMetadata {
-                        bucket_id,
-                        expected_data_size: delta.data_size(),
-                        root_cid: local_content_cid.to_string(),
-                        metadata_cid: local_metadata_cid.to_string(),
-                        previous_cid: local.previous_cid.map(|cid| cid.to_string()),
-                        valid_keys: fs.share_manager.public_fingerprints(),
-                        deleted_block_cids: local
-                            .deleted_block_cids
-                            .clone()
-                            .iter()
-                            .map(|v| v.to_string())
-                            .collect(),
-                        metadata_stream: tokio::fs::File::open(&local.metadata.path).await?.into(),
-                    },
-                    &mut client,
-                )
-                .await?;
-
-                // Empty the list of deleted blocks, now that it's the server's problem
-                local.deleted_block_cids = BTreeSet::new();
-
-                if host.is_none() && authorization.is_none() {
-                    local.storage_ticket = None;
-                }
-
-                info!("Uploading your new data now...");
+                let local = self.get_local()?;
+                let drive_lock = drive_lock.map_or_else(|| local.lock(), Ok)?;
+                let mut handle = drive.open(local, drive_lock).await?;
+                let pushed = handle.push(progress).await;
+                // Keep whatever made it to the server, even if the push failed partway
+                global.update_config(handle.bucket())?;
+                self.set_local(handle.bucket().clone());
 
-                let upload_result = match (host, authorization) {
-                    // New storage ticket
-                    (Some(host), Some(authorization)) => {
-                        // Update the storage ticket locally and create grant
-                        let storage_ticket = StorageTicket {
-                            host,
-                            authorization,
-                        };
-                        storage_ticket.create_grant(&mut client).await?;
-                        local.storage_ticket = Some(storage_ticket.clone());
-                        local
-                            .content
-                            .upload(storage_ticket.host, metadata.id, &mut client)
-                            .await
-                    }
-                    // Already granted, still upload
-                    (Some(host), None) => {
-                        local.content.upload(host, metadata.id, &mut client).await
-                    }
-                    // No uploading required
-                    _ => {
-                        global.update_config(&local)?;
-                        self.set_local(local);
-                        return Ok("METADATA PUSHED; NO CONTENT PUSH NEEDED".to_string());
+                match pushed? {
+                    Pushed::Metadata(_) => {
+                        Ok("METADATA PUSHED; NO CONTENT PUSH NEEDED".to_string())
                     }
-                };
-
-                global.update_config(&local)?;
-                self.set_local(local);
-
-                match upload_result {
-                    // Upload succeeded
-                    Ok(()) => {
+                    Pushed::Content(_) => {
                         self.sync_state = SyncState::AllSynced;
-                        Metadata::read_current(bucket_id, &mut client)
-                            .await
-                            .map(|new_metadata| {
-                                format!(
-                                    "{}\n{}",
-                                    "<< SUCCESSFULLY UPLOADED METADATA & CONTENT >>".green(),
-                                    new_metadata
-                                )
-                            })
-                            .map_err(NativeError::api)
+                        let new_metadata = if snapshot {
+                            handle.snapshot().await?
+                        } else {
+                            Metadata::read_current(self.get_id()?, &mut client)
+                                .await
+                                .map_err(NativeError::api)?
+                        };
+                        Ok(format!(
+                            "{}\n{}",
+                            "<< SUCCESSFULLY UPLOADED METADATA & CONTENT >>".green(),
+                            new_metadata
+                        ))
                     }
-                    // Upload failed
-                    Err(_) => Ok(format!(
+                    Pushed::ContentFailed(metadata) => Ok(format!(
                         "{}\n{}\n{}\n",
                         "<< FAILED TO PUSH CONTENT >>".red(),
                         "<< SUCCESSFULLY PUSHED PENDING METADATA >>".green(),
@@ -304,10 +236,11 @@ impl OmniBucket {
                     storage_ticket.create_grant(&mut client).await?;
                 }
 
-                // Open the FileSystem
-                let fs = FsMetadata::unlock(&global.wrapping_key().await?, &local.metadata).await?;
                 // Reconstruct the data on disk
-                let restoration_result = restore::pipeline(self.clone()).await;
+                let drive_lock = drive_lock.map_or_else(|| local.lock(), Ok)?;
+                let mut handle = drive.open(local.clone(), drive_lock).await?;
+                let restoration_result = handle.restore(&local.origin, progress).await;
+                global.update_config(handle.bucket())?;
                 // If we succeed at reconstructing
                 if restoration_result.is_ok() {
                     // Save the metadata in the content store as well
@@ -330,13 +263,14 @@ impl OmniBucket {
 
                 info!("{self}");
                 restoration_result
+                    .map(|()| "🎉 Data has been successfully reconstructed!".to_string())
             }
             SyncState::AllSynced => Ok(format!(
                 "{}",
                 "This Bucket data is already synced :)".green()
             )),
             SyncState::Unknown => {
-                self.determine_sync_state().await?;
+                self.determine_sync_state(&global).await?;
                 Ok(format!(
                     "{}",
                     format!("<< SYNC STATE UPDATED TO {:?} >>", self.sync_state).blue()
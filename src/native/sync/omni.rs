@@ -33,14 +33,14 @@ pub struct OmniBucket {
 impl OmniBucket {
     /// Use local and remote to find
     #[cfg(feature = "cli")]
-    pub async fn from_specifier(drive_specifier: &DriveSpecifier) -> Self {
+    pub async fn from_specifier(global: &GlobalConfig, drive_specifier: &DriveSpecifier) -> Self {
         let mut omni = Self {
             local: None,
             remote: None,
             sync_state: SyncState::Unknown,
         };
 
-        if let Ok(global) = GlobalConfig::from_disk().await {
+        if let Ok(global) = global.reload() {
             let local_result = global.buckets.clone().into_iter().find(|bucket| {
                 let check_remote = bucket.remote_id == drive_specifier.drive_id;
                 let check_origin = Some(bucket.origin.clone()) == drive_specifier.origin;
@@ -67,7 +67,7 @@ impl OmniBucket {
                 }
 
                 // Determine the sync state
-                let _ = omni.determine_sync_state().await;
+                let _ = omni.determine_sync_state(&global).await;
             }
         }
 
@@ -125,9 +125,26 @@ impl OmniBucket {
         self.remote = Some(remote);
     }
 
+    /// Get the type from wherever it might be found
+    pub fn get_type(&self) -> BucketType {
+        if let Some(remote) = &self.remote {
+            remote.r#type
+        } else if let Some(local) = &self.local {
+            local.r#type
+        } else {
+            BucketType::default()
+        }
+    }
+
     /// Create a new bucket
-    pub async fn create(name: &str, origin: &Path) -> Result<OmniBucket, NativeError> {
-        let mut global = GlobalConfig::from_disk().await?;
+    pub async fn create(
+        global: &GlobalConfig,
+        name: &str,
+        origin: &Path,
+        r#type: BucketType,
+        storage_class: StorageClass,
+    ) -> Result<OmniBucket, NativeError> {
+        let mut global = global.reload()?;
 
         let mut omni = OmniBucket {
             local: None,
@@ -152,8 +169,8 @@ impl OmniBucket {
         if let Ok((remote, _)) = RemoteBucket::create(
             name.to_string(),
             pem,
-            BucketType::Interactive,
-            StorageClass::Hot,
+            r#type,
+            storage_class.clone(),
             &mut global.get_client().await?,
         )
         .await
@@ -164,6 +181,9 @@ impl OmniBucket {
 
         // Initialize locally
         if let Ok(mut local) = global.get_or_init_bucket(name, origin).await {
+            // Remember how to create the remote bucket if that has to wait until sync
+            local.r#type = r#type;
+            local.storage_class = storage_class;
             // If a remote bucket was made successfully
             if let Ok(remote) = omni.get_remote() {
                 // Also save that in the local obj
@@ -177,13 +197,38 @@ impl OmniBucket {
         Ok(omni)
     }
 
+    /// Move the Bucket to another storage class, remotely and locally
+    pub async fn update_storage_class(
+        &mut self,
+        global: &GlobalConfig,
+        storage_class: StorageClass,
+    ) -> Result<String, NativeError> {
+        let mut global = global.reload()?;
+        let mut remote = self.get_remote()?;
+        remote.storage_class = storage_class.clone();
+        remote
+            .update(&mut global.get_client().await?)
+            .await
+            .map_err(NativeError::api)?;
+        self.set_remote(remote);
+
+        if let Ok(mut local) = self.get_local() {
+            local.storage_class = storage_class;
+            global.update_config(&local)?;
+            self.set_local(local);
+        }
+
+        Ok(format!("{}\n{}", "<< DRIVE UPDATED >>".green(), self))
+    }
+
     /// Delete an individual Bucket
     pub async fn delete(
         &self,
+        global: &GlobalConfig,
         local_deletion: bool,
         mut remote_deletion: bool,
     ) -> Result<String, NativeError> {
-        let mut global = GlobalConfig::from_disk().await?;
+        let mut global = global.reload()?;
         if local_deletion {
             global.remove_bucket(&self.get_local()?)?;
         }
@@ -204,13 +249,10 @@ impl OmniBucket {
     }
 
     /// List all available Buckets
-    pub async fn ls() -> Result<Vec<OmniBucket>, NativeError> {
-        let mut client = GlobalConfig::from_disk().await?.get_client().await?;
-        let local_buckets = if let Ok(global) = GlobalConfig::from_disk().await {
-            global.buckets
-        } else {
-            Vec::new()
-        };
+    pub async fn ls(global: &GlobalConfig) -> Result<Vec<OmniBucket>, NativeError> {
+        let global = global.reload()?;
+        let mut client = global.get_client().await?;
+        let local_buckets = global.buckets.clone();
         let remote_buckets = match RemoteBucket::read_all(&mut client).await {
             Ok(buckets) => buckets,
             Err(_) => {
@@ -237,7 +279,7 @@ impl OmniBucket {
                     sync_state: SyncState::Unknown,
                 };
 
-                omni.determine_sync_state().await?;
+                omni.determine_sync_state(&global).await?;
 
                 map.insert(key, omni);
             } else {
@@ -249,7 +291,10 @@ impl OmniBucket {
     }
 
     /// Get the origin for this bucket or create one in the default tomb directory if a local bucket does not yet exist
-    pub async fn get_or_init_origin(&mut self) -> Result<PathBuf, NativeError> {
+    pub async fn get_or_init_origin(
+        &mut self,
+        global: &GlobalConfig,
+    ) -> Result<PathBuf, NativeError> {
         if let Ok(local) = self.get_local() {
             Ok(local.origin)
         } else {
@@ -262,11 +307,14 @@ impl OmniBucket {
 
             // Create a new local bucket
             self.set_local({
-                let mut value = GlobalConfig::from_disk()
-                    .await?
+                let mut value = global
+                    .reload()?
                     .get_or_init_bucket(&self.get_remote()?.name, &new_local_origin)
                     .await?;
-                value.remote_id = Some(self.get_remote()?.id);
+                let remote = self.get_remote()?;
+                value.remote_id = Some(remote.id);
+                value.r#type = remote.r#type;
+                value.storage_class = remote.storage_class;
                 value
             });
 
@@ -275,9 +323,8 @@ impl OmniBucket {
     }
 
     /// Unlock FsMetadata
-    pub async fn unlock(&self) -> Result<FsMetadata, NativeError> {
+    pub async fn unlock(&self, global: &GlobalConfig) -> Result<FsMetadata, NativeError> {
         let local = self.get_local()?;
-        let global = GlobalConfig::from_disk().await?;
         let wrapping_key = global.wrapping_key().await?;
         FsMetadata::unlock(&wrapping_key, &local.metadata)
             .await
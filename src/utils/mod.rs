@@ -13,6 +13,7 @@ pub use io::{get_read, get_read_write, get_write};
 pub use io::compute_directory_size;
 
 mod cast;
+pub mod progress;
 pub mod varint;
 
 mod error;
@@ -0,0 +1,75 @@
+use std::fmt::Display;
+use tracing::warn;
+
+/// A stage of a long-running pipeline
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    /// Working out what is on disk
+    Scanning,
+    /// Encrypting files into a Drive
+    Preparing,
+    /// Writing the files in a Drive out to disk
+    Restoring,
+    /// Fetching the current version of a Drive's metadata
+    Pulling,
+    /// Pushing a Drive's metadata
+    Pushing,
+    /// Replaying changes on top of a version pushed elsewhere
+    Merging,
+    /// Uploading a Drive's content
+    Uploading,
+}
+
+impl Display for Phase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Phase::Scanning => "scanning",
+            Phase::Preparing => "preparing",
+            Phase::Restoring => "restoring",
+            Phase::Pulling => "pulling",
+            Phase::Pushing => "pushing",
+            Phase::Merging => "merging",
+            Phase::Uploading => "uploading",
+        })
+    }
+}
+
+/// Something which happened while a pipeline ran
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProgressEvent {
+    /// A new phase began, covering this many files if the number is known
+    PhaseChanged { phase: Phase, total: Option<u64> },
+    /// Work began on the file at this path
+    FileStarted { path: String },
+    /// Work finished on the file at this path
+    FileDone { path: String },
+    /// This many bytes of content have been uploaded so far, out of the total
+    BytesUploaded { uploaded: u64, total: u64 },
+    /// Something went wrong which the pipeline carried on from
+    Warning { message: String },
+}
+
+/// Somewhere the progress of long-running pipelines is reported as they run
+pub trait ProgressSink {
+    /// Report that something happened
+    fn report(&self, event: ProgressEvent);
+
+    /// Report that something went wrong which the pipeline carried on from
+    fn warn(&self, message: &str) {
+        self.report(ProgressEvent::Warning {
+            message: message.to_string(),
+        });
+    }
+}
+
+/// Reports nothing but warnings, which go to the log
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LogProgress;
+
+impl ProgressSink for LogProgress {
+    fn report(&self, event: ProgressEvent) {
+        if let ProgressEvent::Warning { message } = event {
+            warn!("{message}");
+        }
+    }
+}
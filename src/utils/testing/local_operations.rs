@@ -1,7 +1,26 @@
-use crate::{native::operations::configure, utils::UtilityError};
+use crate::{
+    native::{
+        configuration::{globalconfig::GlobalConfig, xdg::Homes},
+        operations::configure,
+        NativeError,
+    },
+    utils::UtilityError,
+};
 use fake_file::{utils::ensure_path_exists_and_is_empty_dir, Strategy, Structure};
 use std::{fs::remove_dir_all, path::PathBuf};
 
+/// Configuration and data directories belonging to a single test, so tests don't share any state
+pub fn test_homes(test_name: &str) -> Result<Homes, NativeError> {
+    Homes::within(&PathBuf::from("test").join(test_name).join("home"))
+}
+
+/// Load the configuration belonging to a single test, creating it if it doesn't exist yet
+pub async fn test_config(test_name: &str) -> Result<GlobalConfig, NativeError> {
+    let homes = test_homes(test_name)?;
+    GlobalConfig::init_if_missing(&homes).await?;
+    GlobalConfig::from_disk_in(homes).await
+}
+
 /// Set up temporary filesystem for test cases
 pub async fn test_setup(test_name: &str) -> Result<PathBuf, UtilityError> {
     // Run the structured test setup with a default Structure
@@ -13,8 +32,6 @@ pub async fn test_setup_structured(
     test_name: &str,
     structure: Structure,
 ) -> Result<PathBuf, UtilityError> {
-    // Deinit all
-    configure::deinit_all().await?;
     // Base of the test directory
     let root_path = PathBuf::from("test").join(test_name);
     // Remove anything that might already be there
@@ -27,9 +44,9 @@ pub async fn test_setup_structured(
     let input_path = root_path.join("input");
     // Generate file structure
     structure.generate(&input_path).map_err(Box::from)?;
-    // Deinitialize existing data / metadata
-    configure::deinit(&input_path).await?;
-    configure::init(test_name, &input_path).await?;
+    // Configure the Drive in a home of its own
+    let global = test_config(test_name).await?;
+    configure::init(&global, test_name, &input_path).await?;
     // Return all paths
     Ok(input_path.clone())
 }
@@ -17,9 +17,9 @@ use std::{
 use tomb_crypt::prelude::{EcEncryptionKey, EcSignatureKey, PrivateKey, PublicKey};
 use tracing::{error, info};
 pub use types::{
-    to_js_error_with_msg, to_wasm_error_with_msg, TombWasmError, WasmBucket, WasmBucketKey,
-    WasmBucketMetadata, WasmBucketMount, WasmFsMetadataEntry, WasmMount, WasmNodeMetadata,
-    WasmSharedFile, WasmSnapshot,
+    to_js_error_with_msg, to_wasm_error_with_msg, TombWasmError, TombWasmErrorCode, WasmBucket,
+    WasmBucketKey, WasmBucketMetadata, WasmBucketMount, WasmFsMetadataEntry, WasmMount,
+    WasmNodeMetadata, WasmSharedFile, WasmSnapshot, WasmUsageBreakdown,
 };
 use uuid::Uuid;
 use wasm_bindgen::prelude::{wasm_bindgen, JsValue};
@@ -53,22 +53,28 @@ impl TombWasm {
     /// A new TombWasm instance
     ///
     /// Don't call it from multiple threads in parallel!
-    pub async fn new(signing_key_pem: String, user_id: String, core_endpoint: String) -> Self {
+    pub async fn new(
+        signing_key_pem: String,
+        user_id: String,
+        core_endpoint: String,
+    ) -> TombResult<TombWasm> {
         info!("tomb-wasm: new()");
 
-        let mut client = Client::new(&core_endpoint).unwrap();
+        let mut client =
+            Client::new(&core_endpoint).map_err(to_wasm_error_with_msg("create client"))?;
         let signing_key = EcSignatureKey::import(signing_key_pem.as_bytes())
             .await
-            .map_err(to_wasm_error_with_msg("signature key from pem"))
-            .unwrap();
+            .map_err(|err| {
+                TombWasmError::invalid_input(&format!("signature key from pem | {err}"))
+            })?;
 
-        let user_id = Uuid::parse_str(&user_id).unwrap();
+        let user_id = Uuid::parse_str(&user_id).map_err(to_wasm_error_with_msg("parse user id"))?;
         let banyan_credentials = Credentials {
             user_id,
             signing_key,
         };
         client.with_credentials(banyan_credentials);
-        Self(client)
+        Ok(Self(client))
     }
 }
 
@@ -342,6 +348,7 @@ impl TombWasm {
     /// * bucket_id - The id of the bucket to mount
     /// * key - The key to use to mount the bucket. This should be the crypto key pair that was used to create the bucket
     ///         or that has access to the bucket
+    /// * persistent - Keep the mount in IndexedDB, so that it survives page loads and works offline (optional, defaults to false)
     /// # Returns
     /// A WasmMount instance
     #[wasm_bindgen(js_name = mount)]
@@ -349,6 +356,7 @@ impl TombWasm {
         &mut self,
         bucket_id: String,
         encryption_key_pem: String,
+        persistent: Option<bool>,
     ) -> TombResult<WasmMount> {
         info!("mount()/{}", &bucket_id);
 
@@ -361,6 +369,12 @@ impl TombWasm {
         let key = EcEncryptionKey::import(encryption_key_pem.as_bytes())
             .await
             .map_err(to_wasm_error_with_msg("import encryption key"))?;
+
+        if persistent.unwrap_or(false) {
+            info!("mount()/{}/opening persistent mount", &bucket_id);
+            return Ok(WasmMount::open_persistent(bucket_id_uuid, self.client(), &key).await?);
+        }
+
         info!("mount()/{}/reading bucket", &bucket_id);
 
         // Load the bucket
@@ -411,11 +425,12 @@ impl TombWasm {
     ) -> TombResult<Vec<u8>> {
         use crate::prelude::filesystem::{sharing::SharedFile, FsMetadata};
 
-        let shared_file = SharedFile::import_b64_url(shared_file_payload).unwrap();
+        let shared_file = SharedFile::import_b64_url(shared_file_payload)
+            .map_err(to_wasm_error_with_msg("import shared file"))?;
 
         let data = FsMetadata::receive_file_content(shared_file, bs)
             .await
-            .unwrap();
+            .map_err(to_wasm_error_with_msg("receive shared file"))?;
 
         Ok(data)
     }
@@ -429,11 +444,12 @@ impl TombWasm {
         let api_blockstore_client = self.client().clone();
         let api_blockstore = BanyanApiBlockStore::from(api_blockstore_client);
 
-        let shared_file = SharedFile::import_b64_url(shared_file_payload).unwrap();
+        let shared_file = SharedFile::import_b64_url(shared_file_payload)
+            .map_err(to_wasm_error_with_msg("import shared file"))?;
 
         let data = FsMetadata::receive_file_content(shared_file, &api_blockstore)
             .await
-            .unwrap();
+            .map_err(to_wasm_error_with_msg("receive shared file"))?;
 
         Ok(data)
     }
@@ -1,4 +1,7 @@
-use crate::{api::models::metadata::Metadata, wasm::TombWasmError};
+use crate::{
+    api::models::metadata::Metadata,
+    wasm::{TombResult, TombWasmError},
+};
 use wasm_bindgen::prelude::wasm_bindgen;
 
 #[wasm_bindgen]
@@ -17,11 +20,11 @@ impl WasmBucketMetadata {
     }
 
     #[wasm_bindgen(getter = snapshotId)]
-    pub fn snapshot_id(&self) -> String {
-        self.0
+    pub fn snapshot_id(&self) -> TombResult<String> {
+        Ok(self
+            .0
             .snapshot_id
-            .ok_or(TombWasmError::new("missing Snapshot id"))
-            .unwrap()
-            .to_string()
+            .ok_or(TombWasmError::not_found("missing Snapshot id"))?
+            .to_string())
     }
 }
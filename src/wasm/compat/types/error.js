@@ -0,0 +1,33 @@
+// Errors thrown by tomb-wasm, one class per error code so that callers can tell them apart
+export class TombError extends Error {
+  constructor(message, code) {
+    super(message);
+    this.name = new.target.name;
+    this.code = code;
+  }
+}
+
+export class LockedError extends TombError {}
+export class NotFoundError extends TombError {}
+export class AuthError extends TombError {}
+export class NetworkError extends TombError {}
+export class QuotaError extends TombError {}
+export class CorruptDataError extends TombError {}
+export class ConflictError extends TombError {}
+export class InvalidInputError extends TombError {}
+
+const classes = {
+  locked: LockedError,
+  not_found: NotFoundError,
+  auth: AuthError,
+  network: NetworkError,
+  quota: QuotaError,
+  corrupt: CorruptDataError,
+  conflict: ConflictError,
+  invalid_input: InvalidInputError,
+};
+
+export function tombError(code, message) {
+  const Class = classes[code] || TombError;
+  return new Class(message, code);
+}
@@ -1,34 +1,186 @@
+use crate::{
+    api::error::ApiError,
+    blockstore::BlockStoreError,
+    car::error::CarError,
+    filesystem::{sharing::SharingError, FilesystemError, FilesystemErrorKind},
+};
+use reqwest::StatusCode;
 use std::error::Error;
 use std::fmt::{self, Display, Formatter};
-use wasm_bindgen::JsValue;
+use wasm_bindgen::prelude::wasm_bindgen;
+
+#[wasm_bindgen(module = "/src/wasm/compat/types/error.js")]
+extern "C" {
+    #[wasm_bindgen(js_name = tombError)]
+    fn tomb_error(code: &str, message: &str) -> js_sys::Error;
+}
+
+/// What went wrong, thrown to JS as the `code` of the error and by its class
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TombWasmErrorCode {
+    /// The bucket has to be unlocked first
+    Locked,
+    /// There is nothing at the path, or with the id, asked for
+    NotFound,
+    /// The credentials or key in use have no access
+    Auth,
+    /// The remote could not be reached
+    Network,
+    /// The account is out of storage
+    Quota,
+    /// Data could not be read, or was not what it claimed to be
+    Corrupt,
+    /// The same path was changed both locally and remotely
+    Conflict,
+    /// The arguments passed in from JS were not usable
+    InvalidInput,
+    /// Anything else
+    Unknown,
+}
+
+impl TombWasmErrorCode {
+    fn as_str(&self) -> &'static str {
+        match self {
+            TombWasmErrorCode::Locked => "locked",
+            TombWasmErrorCode::NotFound => "not_found",
+            TombWasmErrorCode::Auth => "auth",
+            TombWasmErrorCode::Network => "network",
+            TombWasmErrorCode::Quota => "quota",
+            TombWasmErrorCode::Corrupt => "corrupt",
+            TombWasmErrorCode::Conflict => "conflict",
+            TombWasmErrorCode::InvalidInput => "invalid_input",
+            TombWasmErrorCode::Unknown => "unknown",
+        }
+    }
+
+    /// Work out what went wrong from the error which caused it
+    fn of(err: &(dyn Error + 'static)) -> Self {
+        if let Some(err) = err.downcast_ref::<TombWasmError>() {
+            return err.code;
+        }
+        if let Some(err) = err.downcast_ref::<ApiError>() {
+            if err.is_auth_unavailable() {
+                return TombWasmErrorCode::Auth;
+            }
+            if err.is_unreachable() {
+                return TombWasmErrorCode::Network;
+            }
+            return match err.status() {
+                Some(StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN) => TombWasmErrorCode::Auth,
+                Some(StatusCode::NOT_FOUND) => TombWasmErrorCode::NotFound,
+                Some(StatusCode::CONFLICT) => TombWasmErrorCode::Conflict,
+                Some(
+                    StatusCode::PAYMENT_REQUIRED
+                    | StatusCode::PAYLOAD_TOO_LARGE
+                    | StatusCode::INSUFFICIENT_STORAGE,
+                ) => TombWasmErrorCode::Quota,
+                _ => TombWasmErrorCode::Unknown,
+            };
+        }
+        if let Some(err) = err.downcast_ref::<FilesystemError>() {
+            return match err.kind() {
+                FilesystemErrorKind::NodeNotFound(_) => TombWasmErrorCode::NotFound,
+                FilesystemErrorKind::MissingMetadata(_) => TombWasmErrorCode::Corrupt,
+                FilesystemErrorKind::Sharing(err) => Self::of(err),
+                FilesystemErrorKind::Blockstore(err) => Self::of(err),
+                FilesystemErrorKind::Wnfs(_) => TombWasmErrorCode::Unknown,
+            };
+        }
+        if let Some(err) = err.downcast_ref::<SharingError>() {
+            if err.is_unauthorized() {
+                return TombWasmErrorCode::Auth;
+            }
+            if err.is_invalid_data() {
+                return TombWasmErrorCode::Corrupt;
+            }
+        }
+        if let Some(err) = err.downcast_ref::<BlockStoreError>() {
+            if err.is_hash_mismatch() || err.is_car() {
+                return TombWasmErrorCode::Corrupt;
+            }
+        }
+        if err.is::<CarError>() || err.is::<serde_json::Error>() {
+            return TombWasmErrorCode::Corrupt;
+        }
+        if err.is::<uuid::Error>() {
+            return TombWasmErrorCode::InvalidInput;
+        }
+        TombWasmErrorCode::Unknown
+    }
+}
+
+impl Display for TombWasmErrorCode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
 
 #[derive(Debug)]
-pub struct TombWasmError(String);
+pub struct TombWasmError {
+    code: TombWasmErrorCode,
+    message: String,
+}
 
 impl TombWasmError {
     pub fn new(message: &str) -> TombWasmError {
-        TombWasmError(String::from(message))
+        Self::with_code(TombWasmErrorCode::Unknown, message)
+    }
+
+    pub fn with_code(code: TombWasmErrorCode, message: &str) -> TombWasmError {
+        TombWasmError {
+            code,
+            message: String::from(message),
+        }
+    }
+
+    pub fn locked(message: &str) -> TombWasmError {
+        Self::with_code(TombWasmErrorCode::Locked, message)
+    }
+
+    pub fn not_found(message: &str) -> TombWasmError {
+        Self::with_code(TombWasmErrorCode::NotFound, message)
+    }
+
+    pub fn corrupt(message: &str) -> TombWasmError {
+        Self::with_code(TombWasmErrorCode::Corrupt, message)
+    }
+
+    pub fn conflict(message: &str) -> TombWasmError {
+        Self::with_code(TombWasmErrorCode::Conflict, message)
+    }
+
+    pub fn invalid_input(message: &str) -> TombWasmError {
+        Self::with_code(TombWasmErrorCode::InvalidInput, message)
+    }
+
+    pub fn code(&self) -> TombWasmErrorCode {
+        self.code
     }
 }
 
 impl Display for TombWasmError {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        write!(f, "tomm-wasm (unexpected error): {}", self.0)
+        write!(f, "tomb-wasm ({}): {}", self.code, self.message)
     }
 }
 
 impl From<TombWasmError> for js_sys::Error {
     fn from(err: TombWasmError) -> Self {
-        JsValue::from(err.0).into()
+        tomb_error(err.code.as_str(), &err.message)
     }
 }
 
 impl Error for TombWasmError {}
 
-pub fn to_js_error_with_msg<E: Error>(message: &str) -> impl Fn(E) -> js_sys::Error + '_ {
-    move |err| js_sys::Error::from(TombWasmError(format!("{} | {}", message, err)))
+pub fn to_js_error_with_msg<E: Error + 'static>(message: &str) -> impl Fn(E) -> js_sys::Error + '_ {
+    move |err| js_sys::Error::from(to_wasm_error_with_msg(message)(err))
 }
 
-pub fn to_wasm_error_with_msg<E: Error>(message: &str) -> impl Fn(E) -> TombWasmError + '_ {
-    move |err| TombWasmError(format!("{} | {}", message, err))
+pub fn to_wasm_error_with_msg<E: Error + 'static>(
+    message: &str,
+) -> impl Fn(E) -> TombWasmError + '_ {
+    move |err| TombWasmError {
+        code: TombWasmErrorCode::of(&err),
+        message: format!("{} | {}", message, err),
+    }
 }
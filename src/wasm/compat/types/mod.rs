@@ -6,16 +6,19 @@ mod error;
 mod fs_metadata_entry;
 mod mount;
 mod node_metadata;
+mod progress;
 mod shared_file;
 mod snapshot;
+mod usage_breakdown;
 
 pub use bucket::WasmBucket;
 pub use bucket_key::WasmBucketKey;
 pub use bucket_metadata::WasmBucketMetadata;
 pub use bucketmount::WasmBucketMount;
-pub use error::{to_js_error_with_msg, to_wasm_error_with_msg, TombWasmError};
+pub use error::{to_js_error_with_msg, to_wasm_error_with_msg, TombWasmError, TombWasmErrorCode};
 pub use fs_metadata_entry::WasmFsMetadataEntry;
 pub use mount::WasmMount;
 pub use node_metadata::WasmNodeMetadata;
 pub use shared_file::WasmSharedFile;
 pub use snapshot::WasmSnapshot;
+pub use usage_breakdown::WasmUsageBreakdown;
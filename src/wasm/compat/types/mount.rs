@@ -1,12 +1,27 @@
-use futures_util::StreamExt;
-use js_sys::{Array, ArrayBuffer, Uint8Array};
-use std::collections::BTreeSet;
+use async_trait::async_trait;
+use futures_util::{StreamExt, TryStreamExt};
+use js_sys::{Array, ArrayBuffer, Function, Uint8Array};
+use js_sys::{Object, Reflect};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, BTreeSet};
 use std::convert::TryFrom;
+use std::fmt::{self, Debug, Formatter};
 use std::io::Cursor;
+use std::rc::Rc;
+use std::str::FromStr;
 use tomb_crypt::prelude::{EcEncryptionKey, EcPublicEncryptionKey, PrivateKey, PublicKey};
 use tracing::info;
-use wasm_bindgen::prelude::{wasm_bindgen, JsValue};
-use wnfs::private::PrivateNode;
+use uuid::Uuid;
+use wasm_bindgen::prelude::{wasm_bindgen, JsCast, JsValue};
+use wasm_streams::{readable::sys, ReadableStream};
+use wnfs::{
+    libipld::{Cid, Ipld, IpldCodec},
+    private::PrivateNode,
+};
+
+use super::{node_metadata::js_to_ipld, progress::JsProgress};
 
 use crate::{
     api::{
@@ -17,20 +32,34 @@ use crate::{
         },
         requests::staging::upload::content::UploadContent,
     },
-    blockstore::{BanyanApiBlockStore, CarV2MemoryBlockStore as BlockStore, RootedBlockStore},
+    blockstore::{
+        BanyanApiBlockStore, BanyanBlockStore, BlockCache, BlockStoreError, CarV2Mark,
+        CarV2MemoryBlockStore as BlockStore, IndexedDbBlockStore, RootedBlockStore,
+        DEFAULT_CACHE_CAPACITY,
+    },
     filesystem::FsMetadata,
     prelude::{
         api::requests::core::buckets::metadata::push::PushMetadata, blockstore::DoubleSplitStore,
     },
+    utils::progress::{LogProgress, Phase, ProgressEvent, ProgressSink},
+    value,
     wasm::{
-        to_wasm_error_with_msg, TombResult, TombWasmError, WasmBucket, WasmBucketMetadata,
-        WasmFsMetadataEntry, WasmSharedFile, WasmSnapshot,
+        to_wasm_error_with_msg, TombResult, TombWasmError, TombWasmErrorCode, WasmBucket,
+        WasmBucketMetadata, WasmFsMetadataEntry, WasmNodeMetadata, WasmSharedFile, WasmSnapshot,
+        WasmUsageBreakdown,
     },
+    LibipldError,
 };
 
+/// Name of the IndexedDB database caching blocks fetched from remote storage
+const BLOCK_CACHE_NAME: &str = "banyan-blocks";
+/// Key of the state of a persistent mount, kept alongside its metadata blocks
+const MOUNT_STATE_KEY: &str = "mount";
+
 /// Mount point for a Bucket in WASM
 ///
-/// Enables to call Fs methods on a Bucket, pulling metadata from a remote
+/// Enables to call Fs methods on a Bucket, pulling metadata from a remote.
+/// Every change is pushed as it is made, unless a transaction batches them until commit.
 #[derive(Debug, Clone)]
 #[wasm_bindgen]
 pub struct WasmMount {
@@ -52,8 +81,370 @@ pub struct WasmMount {
     /// Previous root CID of the Metadata BlockStore
     previous_cid: Option<String>,
 
+    /// Changes made on top of the previous version, replayed over a newer one if another
+    /// device pushed in the meantime
+    journal: Vec<Operation>,
+
     metadata_blockstore: BlockStore,
     content_blockstore: BlockStore,
+    /// Content streamed in by `writeStream`, kept in IndexedDB rather than memory until it is
+    /// pushed. Opened on the first streamed write.
+    spool: Option<Spool>,
+
+    /// State to return to if the open transaction is rolled back.
+    /// Changes are only pushed when it is committed.
+    transaction: Option<Checkpoint>,
+
+    /// Key the mount was unlocked with, kept to unlock past versions of its metadata
+    key: Option<UnlockKey>,
+
+    /// Where the mount is kept between page loads, if it is persistent
+    persistence: Option<Persistence>,
+
+    /// Where the progress of syncs is reported, if anywhere
+    progress: Option<JsProgress>,
+}
+
+#[derive(Clone)]
+struct UnlockKey(Rc<EcEncryptionKey>);
+
+impl Debug for UnlockKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("UnlockKey(..)")
+    }
+}
+
+/// IndexedDB databases keeping a mount between page loads
+#[derive(Debug, Clone)]
+struct Persistence {
+    /// Every metadata block, along with the state of the mount
+    metadata: IndexedDbBlockStore,
+    /// Content written since the last sync
+    content: IndexedDbBlockStore,
+}
+
+impl Persistence {
+    async fn open(bucket_id: &Uuid) -> Result<Self, TombWasmError> {
+        let metadata = IndexedDbBlockStore::open(&format!("banyan-mount-{bucket_id}-metadata"))
+            .await
+            .map_err(to_wasm_error_with_msg("open metadata database"))?;
+        let content = IndexedDbBlockStore::open(&format!("banyan-mount-{bucket_id}-content"))
+            .await
+            .map_err(to_wasm_error_with_msg("open content database"))?;
+        Ok(Self { metadata, content })
+    }
+
+    async fn load_state(&self) -> Result<Option<MountState>, TombWasmError> {
+        let state = self
+            .metadata
+            .get_state(MOUNT_STATE_KEY)
+            .await
+            .map_err(to_wasm_error_with_msg("read mount state"))?;
+        state
+            .map(|state| serde_json::from_str(&state))
+            .transpose()
+            .map_err(to_wasm_error_with_msg("parse mount state"))
+    }
+}
+
+/// Everything about a persistent mount that isn't held in its blockstores
+#[derive(Debug, Serialize, Deserialize)]
+struct MountState {
+    bucket: Bucket,
+    metadata: Option<Metadata>,
+    previous_cid: Option<String>,
+    /// Whether there are changes queued for the next sync
+    dirty: bool,
+    append: bool,
+    deleted_block_cids: BTreeSet<String>,
+    #[serde(default)]
+    journal: Vec<Operation>,
+    /// Blocks in the spool which belong to the queued changes
+    #[serde(default)]
+    spooled: BTreeSet<String>,
+}
+
+/// Everything a transaction can change about a mount before it is committed
+#[derive(Debug, Clone)]
+struct Checkpoint {
+    metadata: Option<Metadata>,
+    previous_cid: Option<String>,
+    fs_metadata: Option<FsMetadata>,
+    dirty: bool,
+    append: bool,
+    deleted_block_cids: BTreeSet<String>,
+    journal: Vec<Operation>,
+    /// Blocks are only ever added, so the stores are truncated rather than copied
+    metadata_mark: CarV2Mark,
+    content_mark: CarV2Mark,
+    /// Blocks in the spool which belonged to changes made before the transaction
+    spooled: Option<BTreeSet<Cid>>,
+    /// The metadata blockstore the transaction began on, if a merge has replaced it since
+    metadata_blockstore: Option<BlockStore>,
+}
+
+/// Content kept in IndexedDB until it is pushed, so that files larger than memory can be written.
+/// The blocks of changes which were rolled back are left behind until the spool is next opened
+/// or cleared, but only the blocks recorded here are ever read back or uploaded.
+#[derive(Debug, Clone)]
+struct Spool {
+    store: IndexedDbBlockStore,
+    cids: Rc<RefCell<BTreeSet<Cid>>>,
+}
+
+impl Spool {
+    /// Open the spool, keeping only the blocks given
+    async fn open(name: &str, cids: BTreeSet<Cid>) -> Result<Self, TombWasmError> {
+        let store = IndexedDbBlockStore::open(name)
+            .await
+            .map_err(to_wasm_error_with_msg("open spool"))?;
+        store
+            .retain(&cids)
+            .await
+            .map_err(to_wasm_error_with_msg("clear spool"))?;
+        Ok(Self {
+            store,
+            cids: Rc::new(RefCell::new(cids)),
+        })
+    }
+
+    fn cids(&self) -> BTreeSet<Cid> {
+        self.cids.borrow().clone()
+    }
+
+    /// Forget every block added since `cids` were recorded
+    fn reset(&self, cids: BTreeSet<Cid>) {
+        *self.cids.borrow_mut() = cids;
+    }
+
+    /// Copy the spooled blocks and those of `content` into a new CAR with the root of `content`.
+    /// This is only held for as long as it takes to upload it.
+    async fn with_content(&self, content: &BlockStore) -> Result<BlockStore, TombWasmError> {
+        let car = BlockStore::new().map_err(to_wasm_error_with_msg("create blockstore"))?;
+        let held = content.car.car.index.borrow().get_all_cids();
+        let cids: BTreeSet<Cid> = self.cids().into_iter().chain(held).collect();
+        for cid in cids {
+            let block = match BanyanBlockStore::get_block(content, &cid).await {
+                Ok(block) => block.into_owned(),
+                Err(_) => BanyanBlockStore::get_block(&self.store, &cid)
+                    .await
+                    .map_err(to_wasm_error_with_msg("read spooled block"))?
+                    .into_owned(),
+            };
+            let codec = IpldCodec::try_from(cid.codec())
+                .map_err(to_wasm_error_with_msg("codec of spooled block"))?;
+            BanyanBlockStore::put_block(&car, block, codec)
+                .await
+                .map_err(to_wasm_error_with_msg("copy spooled block"))?;
+        }
+        if let Some(root) = content.get_root() {
+            car.set_root(&root);
+        }
+        Ok(car)
+    }
+
+    async fn clear(&self) -> Result<(), TombWasmError> {
+        self.cids.borrow_mut().clear();
+        self.store
+            .clear()
+            .await
+            .map_err(to_wasm_error_with_msg("clear spool"))
+    }
+}
+
+#[async_trait(?Send)]
+impl BanyanBlockStore for Spool {
+    async fn get_block(&self, cid: &Cid) -> Result<Cow<'_, Vec<u8>>, BlockStoreError> {
+        if !self.cids.borrow().contains(cid) {
+            return Err(BlockStoreError::wnfs(Box::from(format!(
+                "no block {cid} in the spool"
+            ))));
+        }
+        BanyanBlockStore::get_block(&self.store, cid).await
+    }
+
+    async fn put_block(&self, content: Vec<u8>, codec: IpldCodec) -> Result<Cid, BlockStoreError> {
+        let cid = BanyanBlockStore::put_block(&self.store, content, codec).await?;
+        self.cids.borrow_mut().insert(cid);
+        Ok(cid)
+    }
+}
+
+#[async_trait(?Send)]
+impl wnfs::common::BlockStore for Spool {
+    async fn put_block(&self, bytes: Vec<u8>, codec: IpldCodec) -> Result<Cid, LibipldError> {
+        BanyanBlockStore::put_block(self, bytes, codec)
+            .await
+            .map_err(|err| LibipldError::msg(err.to_string()))
+    }
+
+    async fn get_block(&self, cid: &Cid) -> Result<Cow<'_, Vec<u8>>, LibipldError> {
+        BanyanBlockStore::get_block(self, cid)
+            .await
+            .map_err(|err| LibipldError::msg(err.to_string()))
+    }
+}
+
+/// Every block of content the mount holds locally, for reading.
+/// Anything written through it is held in memory.
+struct MountContent<'a> {
+    memory: &'a BlockStore,
+    spool: Option<&'a Spool>,
+}
+
+impl<'a> MountContent<'a> {
+    fn new(memory: &'a BlockStore, spool: Option<&'a Spool>) -> Self {
+        Self { memory, spool }
+    }
+}
+
+#[async_trait(?Send)]
+impl BanyanBlockStore for MountContent<'_> {
+    async fn get_block(&self, cid: &Cid) -> Result<Cow<'_, Vec<u8>>, BlockStoreError> {
+        match (
+            BanyanBlockStore::get_block(self.memory, cid).await,
+            self.spool,
+        ) {
+            (Err(_), Some(spool)) => BanyanBlockStore::get_block(spool, cid).await,
+            (result, _) => result,
+        }
+    }
+
+    async fn put_block(&self, content: Vec<u8>, codec: IpldCodec) -> Result<Cid, BlockStoreError> {
+        BanyanBlockStore::put_block(self.memory, content, codec).await
+    }
+}
+
+#[async_trait(?Send)]
+impl wnfs::common::BlockStore for MountContent<'_> {
+    async fn put_block(&self, bytes: Vec<u8>, codec: IpldCodec) -> Result<Cid, LibipldError> {
+        BanyanBlockStore::put_block(self, bytes, codec)
+            .await
+            .map_err(|err| LibipldError::msg(err.to_string()))
+    }
+
+    async fn get_block(&self, cid: &Cid) -> Result<Cow<'_, Vec<u8>>, LibipldError> {
+        BanyanBlockStore::get_block(self, cid)
+            .await
+            .map_err(|err| LibipldError::msg(err.to_string()))
+    }
+}
+
+/// A change made to the mount, kept until it is pushed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum Operation {
+    Mkdir {
+        path: Vec<String>,
+    },
+    /// The content is read back from the mount when the write is replayed
+    Write {
+        path: Vec<String>,
+    },
+    Mv {
+        from: Vec<String>,
+        to: Vec<String>,
+    },
+    Rm {
+        path: Vec<String>,
+    },
+    SetMetadata {
+        path: Vec<String>,
+        key: String,
+        value: Ipld,
+    },
+    ShareWith {
+        recipient_pem: String,
+    },
+}
+
+impl Operation {
+    /// The paths this changes, which conflict with changes to the same paths made remotely.
+    /// Making a directory that already exists changes nothing, so it never conflicts.
+    fn paths(&self) -> Vec<&Vec<String>> {
+        match self {
+            Operation::Write { path }
+            | Operation::Rm { path }
+            | Operation::SetMetadata { path, .. } => vec![path],
+            Operation::Mv { from, to } => vec![from, to],
+            Operation::Mkdir { .. } | Operation::ShareWith { .. } => vec![],
+        }
+    }
+}
+
+/// Where a file written at `path` ended up after the operations that followed,
+/// or None if it was removed
+fn final_location(path: &[String], later: &[Operation]) -> Option<Vec<String>> {
+    let mut location = path.to_vec();
+    for operation in later {
+        match operation {
+            Operation::Mv { from, to } if location.starts_with(from) => {
+                location = to.iter().chain(&location[from.len()..]).cloned().collect();
+            }
+            Operation::Rm { path } if location.starts_with(path) => return None,
+            _ => {}
+        }
+    }
+    Some(location)
+}
+
+/// What is at a path, to tell whether it differs between two versions
+#[derive(PartialEq)]
+enum NodeState {
+    Missing,
+    File(BTreeMap<String, Ipld>),
+    Dir(
+        BTreeMap<String, Ipld>,
+        Vec<(String, BTreeMap<String, Ipld>)>,
+    ),
+}
+
+impl NodeState {
+    async fn of(
+        fs: &FsMetadata,
+        path: &[String],
+        store: &BlockStore,
+    ) -> Result<Self, TombWasmError> {
+        match fs.get_node(path, store).await {
+            Ok(Some(PrivateNode::File(file))) => Ok(Self::File(file.get_metadata().0.clone())),
+            Ok(Some(PrivateNode::Dir(dir))) => {
+                let entries = fs
+                    .ls(path, store)
+                    .await
+                    .map_err(to_wasm_error_with_msg("list directory entries"))?
+                    .into_iter()
+                    .map(|entry| (entry.name, entry.metadata.0))
+                    .collect();
+                Ok(Self::Dir(dir.get_metadata().0.clone(), entries))
+            }
+            Ok(None) | Err(_) => Ok(Self::Missing),
+        }
+    }
+}
+
+/// Name of the IndexedDB database spooling the streamed writes of a mount. Persistent mounts
+/// keep their own, so that mounting the same bucket without persistence never clears them.
+fn spool_name(bucket_id: &Uuid, persistent: bool) -> String {
+    if persistent {
+        format!("banyan-mount-{bucket_id}-spool")
+    } else {
+        format!("banyan-spool-{bucket_id}")
+    }
+}
+
+/// Pull the metadata blocks of a version into memory
+async fn pull_metadata(
+    metadata: &Metadata,
+    client: &mut Client,
+) -> Result<BlockStore, TombWasmError> {
+    let mut stream = metadata
+        .pull(client)
+        .await
+        .map_err(to_wasm_error_with_msg("pull metadata"))?;
+    let mut data = Vec::new();
+    while let Some(chunk) = stream.next().await {
+        data.extend_from_slice(&chunk.map_err(to_wasm_error_with_msg("chunk from stream"))?);
+    }
+    BlockStore::try_from(data).map_err(to_wasm_error_with_msg("metadata to blockstore"))
 }
 
 impl WasmMount {
@@ -92,7 +483,13 @@ impl WasmMount {
             deleted_block_cids: BTreeSet::new(),
             metadata_blockstore,
             content_blockstore,
+            spool: None,
             previous_cid: None,
+            journal: Vec::new(),
+            transaction: None,
+            key: Some(UnlockKey(Rc::new(key))),
+            persistence: None,
+            progress: None,
         };
 
         info!("new()/{} - syncing", wasm_bucket.id());
@@ -148,11 +545,194 @@ impl WasmMount {
 
             metadata_blockstore,
             content_blockstore,
+            spool: None,
             previous_cid: Some(metadata_cid),
+            journal: Vec::new(),
             fs_metadata: None,
+            transaction: None,
+            key: None,
+            persistence: None,
+            progress: None,
+        })
+    }
+
+    /// Mount a bucket which is kept in IndexedDB between page loads, so that it can be read
+    /// without pulling its metadata again, and used offline. Changes which couldn't be pushed
+    /// are queued for the next sync.
+    pub async fn open_persistent(
+        bucket_id: Uuid,
+        client: &mut Client,
+        key: &EcEncryptionKey,
+    ) -> Result<Self, TombWasmError> {
+        info!("open_persistent()/{}", bucket_id);
+        let persistence = Persistence::open(&bucket_id).await?;
+        let saved = persistence.load_state().await?;
+
+        let remote = match Bucket::read(client, bucket_id).await {
+            Ok(bucket) => Metadata::read_current(bucket.id, client)
+                .await
+                .ok()
+                .map(|metadata| (bucket, metadata)),
+            Err(err) => {
+                info!(
+                    "open_persistent()/{} - unable to reach remote: {err}",
+                    bucket_id
+                );
+                None
+            }
+        };
+
+        let mut mount = match (remote, saved) {
+            // Queued changes are kept, and anything we already have isn't pulled again
+            (Some((bucket, metadata)), Some(mut saved))
+                if saved.dirty || saved.previous_cid.as_ref() == Some(&metadata.metadata_cid) =>
+            {
+                if saved.previous_cid.as_ref() != Some(&metadata.metadata_cid) {
+                    info!(
+                        "open_persistent()/{} - changes were queued on top of an older version, merging on the next sync",
+                        bucket_id
+                    );
+                }
+                saved.bucket = bucket;
+                Self::restore_state(client, saved, persistence, key).await?
+            }
+            (Some((bucket, _)), _) => {
+                info!("open_persistent()/{} - pulling", bucket_id);
+                let mut mount = Self::pull(WasmBucket::from(bucket), client).await?;
+                mount.persistence = Some(persistence);
+                if let Err(err) = mount.unlock(key).await {
+                    info!(
+                        "open_persistent()/{} - could not unlock mount: {err}",
+                        bucket_id
+                    );
+                }
+                mount.persist().await?;
+                mount
+            }
+            (None, Some(saved)) => {
+                info!("open_persistent()/{} - mounting offline", bucket_id);
+                Self::restore_state(client, saved, persistence, key).await?
+            }
+            (None, None) => {
+                return Err(TombWasmError::new(
+                    "unable to reach a bucket which has not been mounted on this device before",
+                ))
+            }
+        };
+        mount.client = client.to_owned();
+        Ok(mount)
+    }
+
+    /// Rebuild a persistent mount from what was kept in IndexedDB, and unlock it
+    async fn restore_state(
+        client: &Client,
+        state: MountState,
+        persistence: Persistence,
+        key: &EcEncryptionKey,
+    ) -> Result<Self, TombWasmError> {
+        info!("restore_state()/{}", state.bucket.id);
+        let metadata_blockstore = persistence
+            .metadata
+            .to_car()
+            .await
+            .map_err(to_wasm_error_with_msg("load metadata blocks"))?;
+        let content_blockstore = persistence
+            .content
+            .to_car()
+            .await
+            .map_err(to_wasm_error_with_msg("load content blocks"))?;
+        // Only streamed writes which were queued are kept, anything else left behind is stale
+        let spooled = state
+            .spooled
+            .iter()
+            .map(|cid| Cid::from_str(cid))
+            .collect::<Result<BTreeSet<Cid>, _>>()
+            .map_err(to_wasm_error_with_msg("parse spooled CIDs"))?;
+        let spool = Spool::open(&spool_name(&state.bucket.id, true), spooled).await?;
+        // Queued changes have moved the metadata on from the last version pushed,
+        // so this is unlocked without checking it against that version
+        let fs_metadata = FsMetadata::unlock(key, &metadata_blockstore)
+            .await
+            .map_err(to_wasm_error_with_msg("unlock FsMetadata"))?;
+
+        Ok(Self {
+            client: client.to_owned(),
+            bucket: state.bucket,
+            metadata: state.metadata,
+            fs_metadata: Some(fs_metadata),
+            locked: false,
+            dirty: state.dirty,
+            append: state.append,
+            deleted_block_cids: state.deleted_block_cids,
+            previous_cid: state.previous_cid,
+            journal: state.journal,
+            metadata_blockstore,
+            content_blockstore,
+            spool: Some(spool),
+            transaction: None,
+            key: Some(UnlockKey(Rc::new(key.clone()))),
+            persistence: Some(persistence),
+            progress: None,
         })
     }
 
+    /// Keep the mount in IndexedDB, if it is persistent. Content is only kept until it is pushed.
+    async fn persist(&self) -> Result<(), TombWasmError> {
+        let Some(persistence) = &self.persistence else {
+            return Ok(());
+        };
+        info!("persist()/{} - dirty: {}", self.bucket.id, self.dirty);
+        persistence
+            .metadata
+            .save_car(&self.metadata_blockstore)
+            .await
+            .map_err(to_wasm_error_with_msg("persist metadata blocks"))?;
+        if self.dirty {
+            persistence
+                .content
+                .save_car(&self.content_blockstore)
+                .await
+                .map_err(to_wasm_error_with_msg("persist content blocks"))?;
+        } else {
+            // Everything has been pushed, so only the root is needed to push again
+            persistence
+                .content
+                .clear()
+                .await
+                .map_err(to_wasm_error_with_msg("clear pushed content blocks"))?;
+            if let Some(root) = self.content_blockstore.get_root() {
+                persistence.content.set_root(&root);
+            }
+            persistence
+                .content
+                .save()
+                .await
+                .map_err(to_wasm_error_with_msg("persist content root"))?;
+        }
+
+        let state = MountState {
+            bucket: self.bucket.clone(),
+            metadata: self.metadata.clone(),
+            previous_cid: self.previous_cid.clone(),
+            dirty: self.dirty,
+            append: self.append,
+            deleted_block_cids: self.deleted_block_cids.clone(),
+            journal: self.journal.clone(),
+            spooled: self
+                .spool
+                .as_ref()
+                .map(|spool| spool.cids().iter().map(Cid::to_string).collect())
+                .unwrap_or_default(),
+        };
+        let state =
+            serde_json::to_string(&state).map_err(to_wasm_error_with_msg("serialize mount"))?;
+        persistence
+            .metadata
+            .put_state(MOUNT_STATE_KEY, &state)
+            .await
+            .map_err(to_wasm_error_with_msg("persist mount state"))
+    }
+
     /// Refresh the current fs_metadata with the remote
     pub async fn refresh(&mut self, key: &EcEncryptionKey) -> Result<(), TombWasmError> {
         info!(bucket_id = ?self.bucket.id, "refresh");
@@ -201,26 +781,35 @@ impl WasmMount {
         self.metadata = Some(metadata.to_owned());
         self.metadata_blockstore = metadata_blockstore;
         self.content_blockstore = content_blockstore;
+        if let Some(spool) = &self.spool {
+            spool.clear().await?;
+        }
+        // Nothing made before the refresh is left to roll back to
+        self.transaction = None;
         self.locked = true;
         self.dirty = false;
         self.append = false;
         self.fs_metadata = None;
         self.previous_cid = Some(metadata_cid);
+        self.journal.clear();
 
         info!("refresh()/{} - pulled", self.bucket.id.to_string());
         self.unlock(key).await?;
         info!("refresh()/{} - unlocked", self.bucket.id.to_string());
+        self.persist().await?;
 
         Ok(())
     }
 
-    /// Sync the current fs_metadata with the remote
+    /// Sync the current fs_metadata with the remote.
+    /// If another device pushed since the mount was last in step, the changes made here are
+    /// replayed on top of what it pushed.
     pub async fn sync(&mut self) -> Result<(), TombWasmError> {
         info!("sync()/{}", self.bucket.id.to_string());
         // Check if the bucket is locked
         if self.locked() {
             info!("sync()/{} - bucket is locked", self.bucket.id.to_string());
-            panic!("Bucket is locked");
+            return Err(TombWasmError::locked("unable to sync a locked bucket"));
         };
         info!(
             "sync()/{} - saving changes; dirty: {}",
@@ -236,14 +825,37 @@ impl WasmMount {
             let _ = self
                 .fs_metadata
                 .as_mut()
-                .ok_or(TombWasmError::new("missing FsMetadata"))?
+                .ok_or(TombWasmError::locked("bucket is locked"))?
                 .save(&self.metadata_blockstore, &self.content_blockstore)
                 .await;
         } else {
             info!("sync()/{} - no changes to fs", self.bucket.id.to_string());
         }
 
+        let progress: Box<dyn ProgressSink> = match self.progress.clone() {
+            Some(progress) => Box::new(progress),
+            None => Box::new(LogProgress),
+        };
+
+        // Pushing on top of anything but the current version would fork the bucket
+        if let Some(previous_cid) = self.previous_cid.clone() {
+            let current = Metadata::read_current(self.bucket.id, &mut self.client)
+                .await
+                .map_err(to_wasm_error_with_msg("read current metadata"))?;
+            if current.metadata_cid != previous_cid {
+                progress.report(ProgressEvent::PhaseChanged {
+                    phase: Phase::Merging,
+                    total: Some(self.journal.len() as u64),
+                });
+                self.merge(&previous_cid, current).await?;
+            }
+        }
+
         info!("sync()/{} - pushing changes", self.bucket.id.to_string());
+        progress.report(ProgressEvent::PhaseChanged {
+            phase: Phase::Pushing,
+            total: None,
+        });
 
         let root_cid = self
             .content_blockstore
@@ -262,10 +874,16 @@ impl WasmMount {
             "sync()/{} - pushing root at version {}",
             self.bucket.id, root_cid,
         );
+        // Streamed writes are only brought into memory for as long as it takes to upload them
+        let spooled = match self.spool.as_ref().filter(|spool| !spool.cids().is_empty()) {
+            Some(spool) => Some(spool.with_content(&self.content_blockstore).await?),
+            None => None,
+        };
+        let content = spooled.as_ref().unwrap_or(&self.content_blockstore);
         // Assume that the metadata is always at least as big as the content
         let mut data_size = 0;
         if self.append {
-            data_size = self.content_blockstore.data_size();
+            data_size = content.data_size();
         }
         info!(
             "sync()/{} - metadata cid {} ; content size difference {}",
@@ -283,7 +901,7 @@ impl WasmMount {
                 valid_keys: self
                     .fs_metadata
                     .as_ref()
-                    .ok_or(TombWasmError::new("missing FsMetadata"))?
+                    .ok_or(TombWasmError::locked("bucket is locked"))?
                     .share_manager
                     .public_fingerprints(),
                 deleted_block_cids: self.deleted_block_cids.clone(),
@@ -294,15 +912,30 @@ impl WasmMount {
         .await
         .map_err(to_wasm_error_with_msg("push metadata"))?;
 
-        assert_eq!(metadata.root_cid, root_cid.to_string());
-        assert_eq!(metadata.metadata_cid, metadata_cid.to_string());
+        if metadata.root_cid != root_cid.to_string()
+            || metadata.metadata_cid != metadata_cid.to_string()
+        {
+            return Err(TombWasmError::corrupt(
+                "pushed metadata does not match the local CIDs",
+            ));
+        }
         let metadata_id = metadata.id;
-        self.metadata = Some(metadata);
-        self.previous_cid = Some(metadata_cid.to_string());
 
+        // Browsers send the content whole, so its progress can only be reported once it is sent
+        let content_len = content
+            .get_length()
+            .map_err(to_wasm_error_with_msg("measure content"))?;
+        let uploaded = ProgressEvent::BytesUploaded {
+            uploaded: content_len,
+            total: content_len,
+        };
         match (host, authorization) {
             // New storage ticket
             (Some(host), Some(authorization)) => {
+                progress.report(ProgressEvent::PhaseChanged {
+                    phase: Phase::Uploading,
+                    total: None,
+                });
                 // First create a grant
                 StorageTicket {
                     host: host.clone(),
@@ -313,17 +946,23 @@ impl WasmMount {
                 .map_err(to_wasm_error_with_msg("register storage grant"))?;
 
                 // Then perform upload
-                self.content_blockstore
+                content
                     .upload(host, metadata_id, &mut self.client)
                     .await
                     .map_err(to_wasm_error_with_msg("created grant; failed upload"))?;
+                progress.report(uploaded);
             }
             // Already granted, still upload
             (Some(host), None) => {
-                self.content_blockstore
+                progress.report(ProgressEvent::PhaseChanged {
+                    phase: Phase::Uploading,
+                    total: None,
+                });
+                content
                     .upload(host, metadata_id, &mut self.client)
                     .await
                     .map_err(to_wasm_error_with_msg("no grant; failed upload"))?;
+                progress.report(uploaded);
             }
             // No uploading required
             _ => {
@@ -331,14 +970,199 @@ impl WasmMount {
             }
         }
 
+        // Only once its content has arrived does the version pushed become the one to build on.
+        // Until then the remote stays on the previous one, which the next sync pushes over again.
+        self.metadata = Some(metadata);
+        self.previous_cid = Some(metadata_cid.to_string());
+
+        // The content has arrived, so neither the copy uploaded nor the spool is needed any more
+        drop(spooled);
+        if let Some(spool) = &self.spool {
+            if let Err(err) = spool.clear().await {
+                progress.warn(&format!(
+                    "sync()/{} - unable to clear spool: {err}",
+                    self.bucket.id
+                ));
+            }
+        }
         self.dirty = false;
         self.append = false;
+        self.journal.clear();
+        // What was pushed is already safe, so failing to keep it locally is no reason to fail
+        if let Err(err) = self.persist().await {
+            progress.warn(&format!(
+                "sync()/{} - unable to persist mount: {err}",
+                self.bucket.id
+            ));
+        }
 
         info!("sync()/{} - synced", self.bucket.id.to_string());
 
         Ok(())
     }
 
+    /// Make the changes in the journal again on top of a version pushed by someone else since
+    /// `previous_cid`, failing without changing anything if both changed the same path
+    async fn merge(&mut self, previous_cid: &str, current: Metadata) -> Result<(), TombWasmError> {
+        info!(
+            "merge()/{} - {} was pushed since {}, replaying {} changes",
+            self.bucket.id,
+            current.metadata_cid,
+            previous_cid,
+            self.journal.len()
+        );
+        let key = self.key.clone().ok_or(TombWasmError::locked(
+            "unable to merge into a locked bucket",
+        ))?;
+
+        // The version the changes were made on is still held in the metadata blockstore
+        let base_store = self.metadata_blockstore.clone();
+        let previous_cid =
+            Cid::from_str(previous_cid).map_err(to_wasm_error_with_msg("parse previous CID"))?;
+        base_store.set_root(&previous_cid);
+        let base = FsMetadata::unlock(&key.0, &base_store)
+            .await
+            .map_err(to_wasm_error_with_msg("unlock previous FsMetadata"))?;
+
+        let remote_store = pull_metadata(&current, &mut self.client).await?;
+        let mut remote = FsMetadata::unlock(&key.0, &remote_store)
+            .await
+            .map_err(to_wasm_error_with_msg("unlock current FsMetadata"))?;
+
+        let paths: BTreeSet<&Vec<String>> =
+            self.journal.iter().flat_map(Operation::paths).collect();
+        for path in paths {
+            if NodeState::of(&base, path, &base_store).await?
+                != NodeState::of(&remote, path, &remote_store).await?
+            {
+                return Err(TombWasmError::conflict(&format!(
+                    "{} was changed both here and remotely",
+                    path.join("/")
+                )));
+            }
+        }
+
+        let local = self
+            .fs_metadata
+            .as_ref()
+            .ok_or(TombWasmError::locked("bucket is locked"))?;
+        for (index, operation) in self.journal.iter().enumerate() {
+            let replayed = match operation {
+                Operation::Mkdir { path } => remote.mkdir(path, &remote_store).await,
+                Operation::Write { path } => {
+                    // Later operations may have moved or removed the file, in which case
+                    // they are replayed as well
+                    let content = match final_location(path, &self.journal[index + 1..]) {
+                        Some(location) => local
+                            .read(
+                                &location,
+                                &self.metadata_blockstore,
+                                &MountContent::new(&self.content_blockstore, self.spool.as_ref()),
+                            )
+                            .await
+                            .map_err(to_wasm_error_with_msg("read local changes"))?,
+                        None => Vec::new(),
+                    };
+                    remote
+                        .write(path, &remote_store, &self.content_blockstore, content)
+                        .await
+                }
+                Operation::Mv { from, to } => {
+                    remote
+                        .mv(from, to, &remote_store, &self.content_blockstore)
+                        .await
+                }
+                Operation::Rm { path } => remote.rm(path, &remote_store).await,
+                Operation::SetMetadata { path, key, value } => {
+                    remote
+                        .set_metadata(path, key, value.clone(), &remote_store)
+                        .await
+                }
+                Operation::ShareWith { recipient_pem } => {
+                    let recipient_key = EcPublicEncryptionKey::import(recipient_pem.as_bytes())
+                        .await
+                        .map_err(to_wasm_error_with_msg("import recipient key"))?;
+                    remote.share_with(&recipient_key, &remote_store).await
+                }
+            };
+            replayed.map_err(to_wasm_error_with_msg("replay local changes"))?;
+        }
+        remote
+            .save(&remote_store, &self.content_blockstore)
+            .await
+            .map_err(to_wasm_error_with_msg("save merged FsMetadata"))?;
+
+        info!("merge()/{} - merged", self.bucket.id);
+        self.previous_cid = Some(current.metadata_cid.clone());
+        self.metadata = Some(current);
+        self.fs_metadata = Some(remote);
+        let base_store = std::mem::replace(&mut self.metadata_blockstore, remote_store);
+        // Rolling back still returns to the version the transaction began on
+        if let Some(checkpoint) = self.transaction.as_mut() {
+            checkpoint.metadata_blockstore.get_or_insert(base_store);
+        }
+        Ok(())
+    }
+
+    /// Whether a version, given by metadata id or CID, is the one currently mounted
+    fn is_current(&self, version: &str) -> bool {
+        self.metadata.as_ref().map_or(false, |metadata| {
+            metadata.id.to_string() == version || metadata.metadata_cid == version
+        })
+    }
+
+    /// Pull and unlock a past version of the metadata, given its id or CID
+    async fn pull_version(
+        &mut self,
+        version: &str,
+    ) -> Result<(FsMetadata, BlockStore), TombWasmError> {
+        info!("pull_version()/{}/{}", self.bucket.id, version);
+        let key = self.key.clone().ok_or(TombWasmError::locked(
+            "unable to read past versions of a locked bucket",
+        ))?;
+        let metadata = Metadata::read_all(self.bucket.id, &mut self.client)
+            .await
+            .map_err(to_wasm_error_with_msg("read metadata"))?
+            .into_iter()
+            .find(|metadata| metadata.id.to_string() == version || metadata.metadata_cid == version)
+            .ok_or(TombWasmError::not_found(&format!("no version {version}")))?;
+
+        let metadata_blockstore = pull_metadata(&metadata, &mut self.client).await?;
+        let fs_metadata = FsMetadata::unlock(&key.0, &metadata_blockstore)
+            .await
+            .map_err(to_wasm_error_with_msg("unlock FsMetadata"))?;
+        Ok((fs_metadata, metadata_blockstore))
+    }
+
+    /// Push changes now, unless a transaction is holding them back until it is committed.
+    /// Persistent mounts queue changes which couldn't be pushed for want of a connection
+    /// or credentials, but anything else is the caller's to deal with.
+    async fn sync_changes(&mut self) -> Result<(), TombWasmError> {
+        if self.transaction.is_some() {
+            info!(
+                "sync_changes()/{} - deferred until commit",
+                self.bucket.id.to_string()
+            );
+            return Ok(());
+        }
+        match self.sync().await {
+            Err(err)
+                if self.persistence.is_some()
+                    && matches!(
+                        err.code(),
+                        TombWasmErrorCode::Network | TombWasmErrorCode::Auth
+                    ) =>
+            {
+                info!(
+                    "sync_changes()/{} - queued for the next sync: {err}",
+                    self.bucket.id.to_string()
+                );
+                self.persist().await
+            }
+            result => result,
+        }
+    }
+
     /// Unlock the current fs_metadata
     pub async fn unlock(&mut self, key: &EcEncryptionKey) -> Result<(), TombWasmError> {
         info!("unlock()/{}", self.bucket.id);
@@ -364,8 +1188,13 @@ impl WasmMount {
             .as_ref()
             .ok_or(TombWasmError::new("missing FsMetadata"))?;
 
-        assert_eq!(metadata_cid.to_string(), metadata.metadata_cid);
-        assert_eq!(root_cid.to_string(), metadata.root_cid);
+        if metadata_cid.to_string() != metadata.metadata_cid
+            || root_cid.to_string() != metadata.root_cid
+        {
+            return Err(
+                TombWasmError::corrupt("pulled metadata does not match the bucket's CIDs").into(),
+            );
+        }
 
         // Now try unlocking the metadata
         let fs_metadata = FsMetadata::unlock(key, &self.metadata_blockstore)
@@ -376,6 +1205,7 @@ impl WasmMount {
 
         self.locked = false;
         self.fs_metadata = Some(fs_metadata);
+        self.key = Some(UnlockKey(Rc::new(key.clone())));
 
         Ok(())
     }
@@ -402,6 +1232,143 @@ impl WasmMount {
         self.locked
     }
 
+    /// Returns whether or not a transaction is open
+    #[wasm_bindgen(js_name = inTransaction)]
+    pub fn in_transaction(&self) -> bool {
+        self.transaction.is_some()
+    }
+
+    /// Begin a transaction. Until it is committed, changes are only made locally,
+    /// so that any number of them can be pushed together as a single new version.
+    /// # Errors
+    /// * `Bucket is locked` - If the bucket is locked
+    /// * `Transaction already open` - If a transaction has already begun
+    pub fn begin(&mut self) -> TombResult<()> {
+        info!("begin()/{}", self.bucket.id.to_string());
+        if self.locked() {
+            return Err(
+                TombWasmError::locked("unable to begin a transaction on a locked bucket").into(),
+            );
+        }
+        if self.transaction.is_some() {
+            return Err(TombWasmError::new("a transaction is already open").into());
+        }
+        self.transaction = Some(Checkpoint {
+            metadata: self.metadata.clone(),
+            previous_cid: self.previous_cid.clone(),
+            fs_metadata: self.fs_metadata.clone(),
+            dirty: self.dirty,
+            append: self.append,
+            deleted_block_cids: self.deleted_block_cids.clone(),
+            journal: self.journal.clone(),
+            metadata_mark: self
+                .metadata_blockstore
+                .mark()
+                .map_err(to_wasm_error_with_msg("mark metadata blocks"))?,
+            content_mark: self
+                .content_blockstore
+                .mark()
+                .map_err(to_wasm_error_with_msg("mark content blocks"))?,
+            spooled: self.spool.as_ref().map(Spool::cids),
+            metadata_blockstore: None,
+        });
+        Ok(())
+    }
+
+    /// Commit the open transaction, saving and pushing every change made since it began at once
+    /// # Returns
+    /// Promise<void> in js speak
+    /// # Errors
+    /// * `No transaction open` - If no transaction has begun
+    /// * `Could not sync` - If the sync fails, in which case the transaction stays open
+    pub async fn commit(&mut self) -> TombResult<()> {
+        info!("commit()/{}", self.bucket.id.to_string());
+        if self.transaction.is_none() {
+            return Err(TombWasmError::new("no transaction is open").into());
+        }
+        if !self.dirty() {
+            info!("commit()/{} - no changes", self.bucket.id.to_string());
+            self.transaction = None;
+            return Ok(());
+        }
+        // On failure, leave it to the caller to try again or roll back
+        self.sync().await?;
+        self.transaction = None;
+        Ok(())
+    }
+
+    /// Roll back the open transaction, discarding every change made since it began
+    /// # Errors
+    /// * `No transaction open` - If no transaction has begun
+    pub fn rollback(&mut self) -> TombResult<()> {
+        info!("rollback()/{}", self.bucket.id.to_string());
+        let checkpoint = self
+            .transaction
+            .take()
+            .ok_or(TombWasmError::new("no transaction is open"))?;
+        // A failed commit may have pushed metadata whose content never arrived,
+        // so changes are made on top of the version the transaction began on again
+        self.metadata = checkpoint.metadata;
+        self.previous_cid = checkpoint.previous_cid;
+        self.fs_metadata = checkpoint.fs_metadata;
+        self.dirty = checkpoint.dirty;
+        self.append = checkpoint.append;
+        self.deleted_block_cids = checkpoint.deleted_block_cids;
+        self.journal = checkpoint.journal;
+        if let Some(metadata_blockstore) = checkpoint.metadata_blockstore {
+            self.metadata_blockstore = metadata_blockstore;
+        }
+        self.metadata_blockstore
+            .rollback(checkpoint.metadata_mark)
+            .map_err(to_wasm_error_with_msg("roll back metadata blocks"))?;
+        self.content_blockstore
+            .rollback(checkpoint.content_mark)
+            .map_err(to_wasm_error_with_msg("roll back content blocks"))?;
+        if let Some(spool) = &self.spool {
+            spool.reset(checkpoint.spooled.unwrap_or_default());
+        }
+        Ok(())
+    }
+
+    /// Returns whether or not the mount is kept in IndexedDB between page loads
+    pub fn persistent(&self) -> bool {
+        self.persistence.is_some()
+    }
+
+    /// Report the progress of every sync to a callback, including those made by other calls
+    /// # Arguments
+    /// * `on_progress` - Called with an object describing each event, whose `type` is one of
+    ///   `phaseChanged`, `fileStarted`, `fileDone`, `bytesUploaded` or `warning`.
+    ///   Leave it out to stop reporting progress.
+    #[wasm_bindgen(js_name = onProgress)]
+    pub fn on_progress(&mut self, on_progress: Option<Function>) {
+        self.progress = on_progress.map(JsProgress);
+    }
+
+    /// Push changes which were queued while they couldn't be, such as when offline
+    /// # Returns
+    /// Promise<void> in js speak
+    /// # Errors
+    /// * `Bucket is locked` - If the bucket is locked
+    /// * `Transaction open` - If a transaction has begun, which `commit` pushes instead
+    /// * `Could not sync` - If the sync fails, in which case the changes stay queued
+    /// * `Conflict` - If a path changed here was also changed remotely, which a remount resolves
+    #[wasm_bindgen(js_name = sync)]
+    pub async fn sync_queued(&mut self) -> TombResult<()> {
+        info!("sync_queued()/{}", self.bucket.id.to_string());
+        if self.locked() {
+            return Err(TombWasmError::locked("unable to sync a locked bucket").into());
+        }
+        if self.transaction.is_some() {
+            return Err(TombWasmError::new("commit the open transaction to sync it").into());
+        }
+        if !self.dirty() {
+            return Ok(());
+        }
+        self.sync().await?;
+        Ok(())
+    }
+
     /// Returns the Bucket behind the mount
     pub fn bucket(&self) -> WasmBucket {
         WasmBucket::from(self.bucket.clone())
@@ -449,7 +1416,10 @@ impl WasmMount {
         // Read the array as a Vec<String>
         let path_segments = path_segments
             .iter()
-            .map(|s| s.as_string().ok_or(TombWasmError::new("JsValue as string")))
+            .map(|s| {
+                s.as_string()
+                    .ok_or(TombWasmError::invalid_input("JsValue as string"))
+            })
             .collect::<Result<Vec<String>, TombWasmError>>()?;
 
         info!(
@@ -459,9 +1429,10 @@ impl WasmMount {
         );
 
         if self.locked() {
-            return Err(
-                TombWasmError::new("unable to list directory contents of a locked bucket").into(),
-            );
+            return Err(TombWasmError::locked(
+                "unable to list directory contents of a locked bucket",
+            )
+            .into());
         };
 
         info!(
@@ -474,7 +1445,7 @@ impl WasmMount {
         let fs_metadata_entries = self
             .fs_metadata
             .as_ref()
-            .ok_or(TombWasmError::new("missing FsMetadata"))?
+            .ok_or(TombWasmError::locked("bucket is locked"))?
             .ls(&path_segments, &self.metadata_blockstore)
             .await
             .map_err(to_wasm_error_with_msg("list directory entries"))?;
@@ -509,7 +1480,10 @@ impl WasmMount {
         // Read the array as a Vec<String>
         let path_segments = path_segments
             .iter()
-            .map(|s| s.as_string().ok_or(TombWasmError::new("JsValue as string")))
+            .map(|s| {
+                s.as_string()
+                    .ok_or(TombWasmError::invalid_input("JsValue as string"))
+            })
             .collect::<Result<Vec<String>, TombWasmError>>()?;
 
         info!(
@@ -519,7 +1493,9 @@ impl WasmMount {
         );
 
         if self.locked() {
-            panic!("Bucket is locked");
+            return Err(
+                TombWasmError::locked("unable to make a directory in a locked bucket").into(),
+            );
         };
 
         info!(
@@ -529,10 +1505,13 @@ impl WasmMount {
         );
         self.fs_metadata
             .as_mut()
-            .ok_or(TombWasmError::new("missing FsMetadata"))?
+            .ok_or(TombWasmError::locked("bucket is locked"))?
             .mkdir(&path_segments, &self.metadata_blockstore)
             .await
             .map_err(to_wasm_error_with_msg("mkdir"))?;
+        self.journal.push(Operation::Mkdir {
+            path: path_segments.clone(),
+        });
 
         info!(
             "mkdir()/{}/{} - dirty, syncing changes",
@@ -540,7 +1519,7 @@ impl WasmMount {
             &path_segments.join("/")
         );
         self.dirty = true;
-        self.sync().await?;
+        self.sync_changes().await?;
 
         // Ok
         Ok(())
@@ -549,6 +1528,12 @@ impl WasmMount {
     /// Refreshes the bucket to ensure its up to date against what the server is aware of
     pub async fn remount(&mut self, encryption_key_pem: String) -> TombResult<()> {
         info!(bucket_id = ?self.bucket.id, "remount");
+        if self.transaction.is_some() {
+            return Err(TombWasmError::new(
+                "commit or roll back the open transaction before remounting",
+            )
+            .into());
+        }
 
         let key = EcEncryptionKey::import(encryption_key_pem.as_bytes())
             .await
@@ -579,7 +1564,10 @@ impl WasmMount {
         // Read the array as a Vec<String>
         let path_segments = path_segments
             .iter()
-            .map(|s| s.as_string().ok_or(TombWasmError::new("JsValue as string")))
+            .map(|s| {
+                s.as_string()
+                    .ok_or(TombWasmError::invalid_input("JsValue as string"))
+            })
             .collect::<Result<Vec<String>, TombWasmError>>()?;
 
         info!(
@@ -589,14 +1577,14 @@ impl WasmMount {
         );
 
         if self.locked() {
-            panic!("Bucket is locked");
+            return Err(TombWasmError::locked("unable to write to a locked bucket").into());
         };
 
         let content = Uint8Array::new(&content_buffer).to_vec();
 
         self.fs_metadata
             .as_mut()
-            .ok_or(TombWasmError::new("missing FsMetadata"))?
+            .ok_or(TombWasmError::locked("bucket is locked"))?
             .write(
                 &path_segments,
                 &self.metadata_blockstore,
@@ -605,6 +1593,9 @@ impl WasmMount {
             )
             .await
             .map_err(to_wasm_error_with_msg("fs add"))?;
+        self.journal.push(Operation::Write {
+            path: path_segments,
+        });
         info!(
             "add()/{} - dirty, syncing changes",
             self.bucket.id.to_string()
@@ -612,30 +1603,161 @@ impl WasmMount {
         self.dirty = true;
         self.append = true;
 
-        self.sync().await?;
+        self.sync_changes().await?;
 
         // Ok
         Ok(())
     }
 
+    /// Write a file to a mounted bucket as its content arrives, rather than from a single buffer.
+    /// Its encrypted blocks are kept in IndexedDB until they are pushed, so only the upload itself
+    /// holds the whole file in memory. Where IndexedDB is unavailable they are held in memory.
+    /// # Arguments
+    /// * `path_segments` - The path to write to (as an Array)
+    /// * `content` - A ReadableStream of Uint8Array chunks, or a File or Blob to stream
+    /// * `on_progress` - Called with the number of bytes written so far after every chunk (optional)
+    /// # Errors
+    /// * `Bucket is locked` - If the bucket is locked
+    /// * `expected a ReadableStream` - If the content can't be streamed
+    /// * `fs write stream` - If the stream fails part way
+    #[wasm_bindgen(js_name = writeStream)]
+    pub async fn write_stream(
+        &mut self,
+        path_segments: Array,
+        content: JsValue,
+        on_progress: Option<Function>,
+    ) -> TombResult<()> {
+        // Read the array as a Vec<String>
+        let path_segments = path_segments
+            .iter()
+            .map(|s| {
+                s.as_string()
+                    .ok_or(TombWasmError::invalid_input("JsValue as string"))
+            })
+            .collect::<Result<Vec<String>, TombWasmError>>()?;
+
+        info!(
+            "write_stream()/{}/{}",
+            self.bucket.id.to_string(),
+            &path_segments.join("/")
+        );
+
+        if self.locked() {
+            return Err(TombWasmError::locked("unable to write to a locked bucket").into());
+        };
+
+        // Files and Blobs hand out streams of their own
+        let stream = Reflect::get(&content, &value!("stream"))
+            .map_err(|_| TombWasmError::new("stream property on object"))?;
+        let content = match stream.dyn_ref::<Function>() {
+            Some(stream) => stream
+                .call0(&content)
+                .map_err(|_| TombWasmError::new("unable to stream the content"))?,
+            None => content,
+        };
+        let raw = content
+            .dyn_into::<sys::ReadableStream>()
+            .map_err(|_| TombWasmError::new("expected a ReadableStream, File or Blob"))?;
+
+        if self.spool.is_none() {
+            let name = spool_name(&self.bucket.id, self.persistence.is_some());
+            // A spool opened only now can hold nothing but stale blocks, so none are kept
+            match Spool::open(&name, BTreeSet::new()).await {
+                Ok(spool) => self.spool = Some(spool),
+                Err(err) => info!(
+                    "write_stream()/{} - holding content in memory, unable to open spool: {err}",
+                    self.bucket.id
+                ),
+            }
+        }
+
+        let mut written = 0u64;
+        let reader = Box::pin(ReadableStream::from_raw(raw).into_stream())
+            .map(|chunk| {
+                let chunk = chunk
+                    .map_err(|err| {
+                        std::io::Error::new(std::io::ErrorKind::Other, format!("{err:?}"))
+                    })?
+                    .dyn_into::<Uint8Array>()
+                    .map_err(|_| {
+                        std::io::Error::new(
+                            std::io::ErrorKind::InvalidData,
+                            "expected Uint8Array chunks",
+                        )
+                    })?
+                    .to_vec();
+                written += chunk.len() as u64;
+                if let Some(on_progress) = &on_progress {
+                    // Progress is only reported, it's no reason to stop writing
+                    let _ = on_progress.call1(&JsValue::NULL, &value!(written as f64));
+                }
+                Ok::<_, std::io::Error>(chunk)
+            })
+            .into_async_read();
+
+        let fs_metadata = self
+            .fs_metadata
+            .as_mut()
+            .ok_or(TombWasmError::locked("bucket is locked"))?;
+        let written = match self.spool.as_ref() {
+            Some(spool) => {
+                fs_metadata
+                    .write_stream(&path_segments, &self.metadata_blockstore, spool, reader)
+                    .await
+            }
+            None => {
+                fs_metadata
+                    .write_stream(
+                        &path_segments,
+                        &self.metadata_blockstore,
+                        &self.content_blockstore,
+                        reader,
+                    )
+                    .await
+            }
+        };
+        written.map_err(to_wasm_error_with_msg("fs write stream"))?;
+        self.journal.push(Operation::Write {
+            path: path_segments,
+        });
+        info!(
+            "write_stream()/{} - dirty, syncing changes",
+            self.bucket.id.to_string()
+        );
+        self.dirty = true;
+        self.append = true;
+
+        self.sync_changes().await?;
+
+        Ok(())
+    }
+
     /// Read a file from a mounted bucket
     ///     Read / Download a File (takes a path to a file inside the bucket, not available for cold only buckets)
-    ///     Allows reading at a version
+    ///     Allows reading at a version, and at any past revision of the file
     /// # Arguments
     /// * `path_segments` - The path to read from (as an Array)
-    /// * `version` - The version to read from (optional)
+    /// * `version` - The id or metadata CID of the version to read from (optional, defaults to the mounted one)
+    /// * `revision` - The `revision` of the file to read, as listed by `fileHistory` (optional, defaults to 0, the current one)
     /// # Returns
     /// A Promise<ArrayBuffer> in js speak
+    /// # Errors
+    /// * `no version` - If the bucket has no version with that id or CID
+    /// * `no revision` - If the file has no revision that far back
     #[wasm_bindgen(js_name = readBytes)]
     pub async fn read_bytes(
         &mut self,
         path_segments: Array,
-        _version: Option<String>,
+        version: Option<String>,
+        revision: Option<u32>,
     ) -> TombResult<Uint8Array> {
         // Read the array as a Vec<String>
         let path_segments = path_segments
             .iter()
-            .map(|s| s.as_string().ok_or(TombWasmError::new("JsValue as string")))
+            .map(|s| {
+                s.as_string()
+                    .ok_or(TombWasmError::invalid_input("JsValue as string"))
+            })
             .collect::<Result<Vec<String>, TombWasmError>>()?;
 
         info!(
@@ -645,49 +1767,298 @@ impl WasmMount {
         );
 
         if self.locked() {
-            panic!("Bucket is locked");
+            return Err(TombWasmError::locked("unable to read from a locked bucket").into());
         };
 
         let api_blockstore_client = self.client.clone();
-        let api_blockstore = BanyanApiBlockStore::from(api_blockstore_client);
-
-        let fs = self
-            .fs_metadata
-            .as_mut()
-            .ok_or(TombWasmError::new("missing FsMetadata"))?;
+        let mut api_blockstore = BanyanApiBlockStore::from(api_blockstore_client);
+        // Keep what we fetch across reads and page loads, wherever IndexedDB is available
+        match BlockCache::open(BLOCK_CACHE_NAME, DEFAULT_CACHE_CAPACITY).await {
+            Ok(cache) => api_blockstore = api_blockstore.with_cache(cache),
+            Err(err) => info!("read_bytes() continuing without a block cache: {err}"),
+        }
 
-        let node = fs
-            .get_node(&path_segments, &self.metadata_blockstore)
-            .await
-            .map_err(to_wasm_error_with_msg("access FsMetadata"))?
-            .ok_or(TombWasmError::new("no node at path"))?;
+        // Past versions are read from their own metadata, their content from remote storage
+        let past = match version.filter(|version| !version.is_empty() && !self.is_current(version))
+        {
+            Some(version) => Some(self.pull_version(&version).await?),
+            None => None,
+        };
+        let (fs, metadata_blockstore) = match &past {
+            Some((fs, metadata_blockstore)) => (fs, metadata_blockstore),
+            None => (
+                self.fs_metadata
+                    .as_ref()
+                    .ok_or(TombWasmError::locked("bucket is locked"))?,
+                &self.metadata_blockstore,
+            ),
+        };
 
-        if let PrivateNode::File(file) = node {
-            let cids = file
-                .get_cids(&fs.forest, &self.metadata_blockstore)
+        // Past revisions of a file are all still held in the metadata of the version
+        let file = match revision.filter(|revision| *revision > 0) {
+            Some(revision) => fs
+                .file_history(&path_segments, metadata_blockstore)
                 .await
-                .map_err(|_| TombWasmError::new("retrieve CIDs"))?;
-            api_blockstore
-                .find_cids(cids)
+                .map_err(to_wasm_error_with_msg("file history"))?
+                .get(revision as usize)
+                .cloned()
+                .ok_or(TombWasmError::not_found(&format!("no revision {revision}")))?,
+            None => match fs
+                .get_node(&path_segments, metadata_blockstore)
                 .await
-                .map_err(to_wasm_error_with_msg("find_cids"))?;
+                .map_err(to_wasm_error_with_msg("access FsMetadata"))?
+            {
+                Some(PrivateNode::File(file)) => file,
+                _ => return Err(TombWasmError::not_found("no file at path").into()),
+            },
+        };
+
+        let cids = file
+            .get_cids(&fs.forest, metadata_blockstore)
+            .await
+            .map_err(|_| TombWasmError::new("retrieve CIDs"))?;
+        // Fetch whatever isn't held locally, several blocks at a time
+        let content = MountContent::new(&self.content_blockstore, self.spool.as_ref());
+        let mut missing = BTreeSet::new();
+        for cid in cids {
+            if BanyanBlockStore::get_block(&content, &cid).await.is_err() {
+                missing.insert(cid);
+            }
         }
+        api_blockstore
+            .prefetch(missing)
+            .await
+            .map_err(to_wasm_error_with_msg("prefetch"))?;
 
-        info!("read_bytes() running fs.read @ {:?}", path_segments);
+        info!("read_bytes() reading @ {:?}", path_segments);
 
         // Attempt to fetch from local first, remote second
-        let split_store = DoubleSplitStore::new(&self.content_blockstore, &api_blockstore);
-        let vec = fs
-            .read(&path_segments, &self.metadata_blockstore, &split_store)
+        let split_store = DoubleSplitStore::new(&content, &api_blockstore);
+        let vec = file
+            .get_content(
+                &fs.forest,
+                &DoubleSplitStore::new(&split_store, metadata_blockstore),
+            )
             .await
-            .map_err(to_wasm_error_with_msg("read node bytes"))?;
+            .map_err(|err| TombWasmError::new(&format!("read node bytes | {err}")))?;
 
         let bytes = vec.into_boxed_slice();
         let array = Uint8Array::from(&bytes[..]);
         Ok(array)
     }
 
-    // TODO: Get metadata on node
+    /// List the revisions of a file, starting with the current one and going back in time
+    /// # Arguments
+    /// * `path_segments` - The path of the file (as an Array)
+    /// # Returns
+    /// An Array of objects in the form of:
+    ///
+    /// ```json
+    /// [
+    ///   {
+    ///     "revision": 0,
+    ///     "metadata": {
+    ///       "created": 0,
+    ///       "modified": 0,
+    ///       "size": 0
+    ///     }
+    ///   }
+    /// ]
+    /// ```
+    ///
+    /// where `revision` counts back from the current revision, 0, and can be passed to `readBytes` to read that revision.
+    /// # Errors
+    /// * `Bucket is locked` - If the bucket is locked
+    /// * `file history` - If there is no file at the path
+    #[wasm_bindgen(js_name = fileHistory)]
+    pub async fn file_history(&self, path_segments: Array) -> TombResult<Array> {
+        let path_segments = path_segments
+            .iter()
+            .map(|s| {
+                s.as_string()
+                    .ok_or(TombWasmError::invalid_input("JsValue as string"))
+            })
+            .collect::<Result<Vec<String>, TombWasmError>>()?;
+
+        info!(
+            "file_history()/{}/{}",
+            self.bucket.id.to_string(),
+            &path_segments.join("/")
+        );
+
+        if self.locked() {
+            return Err(
+                TombWasmError::locked("unable to read the history of a locked bucket").into(),
+            );
+        };
+
+        let revisions = self
+            .fs_metadata
+            .as_ref()
+            .ok_or(TombWasmError::locked("bucket is locked"))?
+            .file_history(&path_segments, &self.metadata_blockstore)
+            .await
+            .map_err(to_wasm_error_with_msg("file history"))?;
+
+        revisions
+            .iter()
+            .enumerate()
+            .map(|(revision, file)| {
+                let object = Object::new();
+                Reflect::set(&object, &value!("revision"), &value!(revision as f64))
+                    .map_err(|_| TombWasmError::new("revision property on object"))?;
+                Reflect::set(
+                    &object,
+                    &value!("metadata"),
+                    &JsValue::try_from(WasmNodeMetadata(file.get_metadata().clone()))?,
+                )
+                .map_err(|_| TombWasmError::new("metadata property on object"))?;
+                Ok(value!(object))
+            })
+            .collect()
+    }
+
+    /// Break down what the files in the bucket take up
+    /// # Returns
+    /// An object in the form of:
+    ///
+    /// ```json
+    /// {
+    ///   "byDirectory": { "/": 0, "/cats": 0 },
+    ///   "byMimeType": { "image/png": 0 },
+    ///   "byVersion": [0, 0],
+    ///   "blocks": 0,
+    ///   "stored": 0
+    /// }
+    /// ```
+    ///
+    /// where `byVersion` counts back from the current version of every file, 0,
+    /// and `stored` only counts blocks held locally that have yet to be pushed.
+    /// # Errors
+    /// * `Bucket is locked` - If the bucket is locked
+    #[wasm_bindgen(js_name = usageBreakdown)]
+    pub async fn usage_breakdown(&self) -> TombResult<JsValue> {
+        info!("usage_breakdown()/{}", self.bucket.id.to_string());
+
+        if self.locked() {
+            return Err(
+                TombWasmError::locked("unable to break down usage of a locked bucket").into(),
+            );
+        };
+
+        let breakdown = self
+            .fs_metadata
+            .as_ref()
+            .ok_or(TombWasmError::locked("bucket is locked"))?
+            .usage_breakdown(
+                &self.metadata_blockstore,
+                &MountContent::new(&self.content_blockstore, self.spool.as_ref()),
+            )
+            .await
+            .map_err(to_wasm_error_with_msg("usage breakdown"))?;
+
+        JsValue::try_from(WasmUsageBreakdown::from(breakdown))
+    }
+
+    /// Get the metadata of a file or directory
+    /// # Arguments
+    /// * `path_segments` - The path to the node (as an Array)
+    /// # Returns
+    /// The metadata of the node, with any tags under `tags`
+    /// # Errors
+    /// * `Bucket is locked` - If the bucket is locked
+    /// * `get metadata` - If there is no node at the path
+    #[wasm_bindgen(js_name = getMetadata)]
+    pub async fn get_metadata(&self, path_segments: Array) -> TombResult<JsValue> {
+        let path_segments = path_segments
+            .iter()
+            .map(|s| {
+                s.as_string()
+                    .ok_or(TombWasmError::invalid_input("JsValue as string"))
+            })
+            .collect::<Result<Vec<String>, TombWasmError>>()?;
+
+        info!(
+            "get_metadata()/{}/{}",
+            self.bucket.id.to_string(),
+            &path_segments.join("/")
+        );
+
+        if self.locked() {
+            return Err(
+                TombWasmError::locked("unable to read the metadata of a locked bucket").into(),
+            );
+        };
+
+        let metadata = self
+            .fs_metadata
+            .as_ref()
+            .ok_or(TombWasmError::locked("bucket is locked"))?
+            .get_metadata(&path_segments, &self.metadata_blockstore)
+            .await
+            .map_err(to_wasm_error_with_msg("get metadata"))?;
+
+        JsValue::try_from(WasmNodeMetadata(metadata))
+    }
+
+    /// Tag a file with a value of your choosing
+    /// # Arguments
+    /// * `path_segments` - The path to the file (as an Array)
+    /// * `key` - The name of the tag
+    /// * `value` - Any value made of objects, arrays, strings, numbers, booleans and Uint8Arrays.
+    ///   Null or undefined removes the tag.
+    /// # Errors
+    /// * `Bucket is locked` - If the bucket is locked
+    /// * `set metadata` - If there is no file at the path
+    #[wasm_bindgen(js_name = setMetadata)]
+    pub async fn set_metadata(
+        &mut self,
+        path_segments: Array,
+        key: String,
+        value: JsValue,
+    ) -> TombResult<()> {
+        let path_segments = path_segments
+            .iter()
+            .map(|s| {
+                s.as_string()
+                    .ok_or(TombWasmError::invalid_input("JsValue as string"))
+            })
+            .collect::<Result<Vec<String>, TombWasmError>>()?;
+
+        info!(
+            "set_metadata()/{}/{}/{}",
+            self.bucket.id.to_string(),
+            &path_segments.join("/"),
+            key
+        );
+
+        if self.locked() {
+            return Err(TombWasmError::locked("unable to tag a file in a locked bucket").into());
+        };
+
+        let value = js_to_ipld(&value)?;
+        self.fs_metadata
+            .as_mut()
+            .ok_or(TombWasmError::locked("bucket is locked"))?
+            .set_metadata(
+                &path_segments,
+                &key,
+                value.clone(),
+                &self.metadata_blockstore,
+            )
+            .await
+            .map_err(to_wasm_error_with_msg("set metadata"))?;
+        self.journal.push(Operation::SetMetadata {
+            path: path_segments,
+            key,
+            value,
+        });
+
+        self.dirty = true;
+        self.sync_changes().await?;
+
+        Ok(())
+    }
 
     /// Mv a file or directory
     /// # Arguments
@@ -706,11 +2077,17 @@ impl WasmMount {
     ) -> TombResult<()> {
         let from_path_segments = from_path_segments
             .iter()
-            .map(|s| s.as_string().ok_or(TombWasmError::new("JsValue as string")))
+            .map(|s| {
+                s.as_string()
+                    .ok_or(TombWasmError::invalid_input("JsValue as string"))
+            })
             .collect::<Result<Vec<String>, TombWasmError>>()?;
         let to_path_segments = to_path_segments
             .iter()
-            .map(|s| s.as_string().ok_or(TombWasmError::new("JsValue as string")))
+            .map(|s| {
+                s.as_string()
+                    .ok_or(TombWasmError::invalid_input("JsValue as string"))
+            })
             .collect::<Result<Vec<String>, TombWasmError>>()?;
 
         info!(
@@ -721,12 +2098,12 @@ impl WasmMount {
         );
 
         if self.locked() {
-            panic!("Bucket is locked");
+            return Err(TombWasmError::locked("unable to move within a locked bucket").into());
         };
 
         self.fs_metadata
             .as_mut()
-            .ok_or(TombWasmError::new("missing FsMetadata"))?
+            .ok_or(TombWasmError::locked("bucket is locked"))?
             .mv(
                 &from_path_segments,
                 &to_path_segments,
@@ -735,6 +2112,10 @@ impl WasmMount {
             )
             .await
             .map_err(to_wasm_error_with_msg("fs mv"))?;
+        self.journal.push(Operation::Mv {
+            from: from_path_segments,
+            to: to_path_segments,
+        });
 
         info!(
             "mv()/{} - dirty, syncing changes",
@@ -746,7 +2127,7 @@ impl WasmMount {
         // Not ideal but it works.
         self.append = true;
 
-        self.sync().await?;
+        self.sync_changes().await?;
 
         // Ok
         Ok(())
@@ -764,7 +2145,10 @@ impl WasmMount {
     pub async fn rm(&mut self, path_segments: Array) -> TombResult<()> {
         let path_segments = path_segments
             .iter()
-            .map(|s| s.as_string().ok_or(TombWasmError::new("JsValue as string")))
+            .map(|s| {
+                s.as_string()
+                    .ok_or(TombWasmError::invalid_input("JsValue as string"))
+            })
             .collect::<Result<Vec<String>, TombWasmError>>()?;
 
         info!(
@@ -774,13 +2158,13 @@ impl WasmMount {
         );
 
         if self.locked() {
-            panic!("Bucket is locked");
+            return Err(TombWasmError::locked("unable to remove from a locked bucket").into());
         };
 
         let fs = self
             .fs_metadata
             .as_mut()
-            .ok_or(TombWasmError::new("missing FsMetadata"))?;
+            .ok_or(TombWasmError::locked("bucket is locked"))?;
 
         let node = fs
             .get_node(&path_segments, &self.metadata_blockstore)
@@ -801,13 +2185,16 @@ impl WasmMount {
         fs.rm(&path_segments, &self.metadata_blockstore)
             .await
             .map_err(to_wasm_error_with_msg("fs rm"))?;
+        self.journal.push(Operation::Rm {
+            path: path_segments,
+        });
 
         info!(
             "rm()/{} - dirty, syncing changes",
             self.bucket.id.to_string()
         );
         self.dirty = true;
-        self.sync().await?;
+        self.sync_changes().await?;
 
         // Ok
         Ok(())
@@ -840,22 +2227,23 @@ impl WasmMount {
             .await
             .map_err(to_wasm_error_with_msg("read drive key"))?;
 
-        let recipient_key = bucket_key.pem;
-        info!("share_with/{} - importing key", recipient_key.clone());
-        let recipient_key = EcPublicEncryptionKey::import(recipient_key.as_bytes())
+        let recipient_pem = bucket_key.pem;
+        info!("share_with/{} - importing key", recipient_pem.clone());
+        let recipient_key = EcPublicEncryptionKey::import(recipient_pem.as_bytes())
             .await
             .map_err(to_wasm_error_with_msg("import recipient key"))?;
 
         if self.locked() {
-            panic!("Bucket is locked");
+            return Err(TombWasmError::locked("unable to share a locked bucket").into());
         };
 
         self.fs_metadata
             .as_mut()
-            .ok_or(TombWasmError::new("missing FsMetadata"))?
+            .ok_or(TombWasmError::locked("bucket is locked"))?
             .share_with(&recipient_key, &self.metadata_blockstore)
             .await
             .map_err(to_wasm_error_with_msg("fs share_with"))?;
+        self.journal.push(Operation::ShareWith { recipient_pem });
 
         // Mark as dirty so fs is saved with new key info
         self.dirty = true;
@@ -865,7 +2253,7 @@ impl WasmMount {
             self.bucket.id.to_string()
         );
 
-        self.sync().await?;
+        self.sync_changes().await?;
 
         // Ok
         Ok(())
@@ -883,17 +2271,22 @@ impl WasmMount {
         // Read the array as a Vec<String>
         let path_segments = path_segments
             .iter()
-            .map(|s| s.as_string().ok_or(TombWasmError::new("JsValue as string")))
+            .map(|s| {
+                s.as_string()
+                    .ok_or(TombWasmError::invalid_input("JsValue as string"))
+            })
             .collect::<Result<Vec<String>, TombWasmError>>()?;
 
         if self.locked() {
-            return Err(TombWasmError::new("unable to share a file from a locked bucket").into());
+            return Err(
+                TombWasmError::locked("unable to share a file from a locked bucket").into(),
+            );
         };
 
         let shared_file = self
             .fs_metadata
             .as_mut()
-            .ok_or(TombWasmError::new("missing FsMetadata"))?
+            .ok_or(TombWasmError::locked("bucket is locked"))?
             .share_file(
                 &path_segments,
                 &self.metadata_blockstore,
@@ -912,7 +2305,7 @@ impl WasmMount {
             self.bucket.id.to_string()
         );
 
-        self.sync().await?;
+        self.sync_changes().await?;
 
         let shared_file = WasmSharedFile(shared_file);
         Ok(shared_file.export_b64_url()?)
@@ -924,15 +2317,14 @@ impl WasmMount {
     /// # Errors
     /// * "missing metadata" - If the metadata is missing
     #[wasm_bindgen(js_name = hasSnapshot)]
-    pub fn has_snapshot(&self) -> bool {
+    pub fn has_snapshot(&self) -> TombResult<bool> {
         info!("has_snapshot()/{}", self.bucket.id.to_string());
         let metadata = self
             .metadata
             .as_ref()
-            .ok_or(TombWasmError::new("missing FsMetadata"))
-            .unwrap();
+            .ok_or(TombWasmError::new("missing FsMetadata"))?;
 
-        metadata.snapshot_id.is_some()
+        Ok(metadata.snapshot_id.is_some())
     }
 
     /// Snapshot a mounted bucket
@@ -951,7 +2343,7 @@ impl WasmMount {
         let fs = self
             .fs_metadata
             .as_mut()
-            .ok_or(TombWasmError::new("missing FsMetadata"))?;
+            .ok_or(TombWasmError::locked("bucket is locked"))?;
 
         let all_nodes = fs
             .get_all_nodes(&self.metadata_blockstore)
@@ -1,5 +1,5 @@
 use crate::{value, wasm::TombWasmError};
-use js_sys::{Object, Reflect};
+use js_sys::{Array, Object, Reflect, Uint8Array};
 use std::collections::BTreeMap;
 use tracing::info;
 use wasm_bindgen::prelude::{JsCast, JsValue};
@@ -36,6 +36,12 @@ impl TryFrom<JsValue> for WasmNodeMetadata {
             info!("WARNING: WasmNodeMetadata did not contain a 'modified' timestamp");
         }
 
+        let tags_ref = Reflect::get(&object, &JsValue::from_str("tags"))
+            .map_err(|_| TombWasmError::new("tags property on object"))?;
+        if tags_ref.is_object() {
+            map.insert("tags".into(), js_to_ipld(&tags_ref)?);
+        }
+
         Ok(Self(NodeMetadata(map)))
     }
 }
@@ -62,6 +68,10 @@ impl TryFrom<WasmNodeMetadata> for JsValue {
             Reflect::set(&object, &JsValue::from_str("size"), &value!(*i as f64))?;
         }
 
+        if let Some(tags @ Ipld::Map(_)) = fs_entry.0 .0.get("tags") {
+            Reflect::set(&object, &JsValue::from_str("tags"), &ipld_to_js(tags)?)?;
+        }
+
         Reflect::set(
             &object,
             &JsValue::from_str("cid"),
@@ -71,3 +81,67 @@ impl TryFrom<WasmNodeMetadata> for JsValue {
         Ok(value!(object))
     }
 }
+
+/// Convert a tag value into its JS equivalent
+pub(crate) fn ipld_to_js(ipld: &Ipld) -> Result<JsValue, js_sys::Error> {
+    Ok(match ipld {
+        Ipld::Null => JsValue::NULL,
+        Ipld::Bool(b) => value!(*b),
+        Ipld::Integer(i) => value!(*i as f64),
+        Ipld::Float(f) => value!(*f),
+        Ipld::String(s) => value!(s),
+        Ipld::Bytes(bytes) => value!(Uint8Array::from(bytes.as_slice())),
+        Ipld::Link(cid) => value!(cid.to_string()),
+        Ipld::List(list) => value!(list
+            .iter()
+            .map(ipld_to_js)
+            .collect::<Result<Array, js_sys::Error>>()?),
+        Ipld::Map(map) => {
+            let object = Object::new();
+            for (key, value) in map {
+                Reflect::set(&object, &JsValue::from_str(key), &ipld_to_js(value)?)?;
+            }
+            value!(object)
+        }
+    })
+}
+
+/// Convert a JS value into a tag value, failing on things like functions which can't be stored
+pub(crate) fn js_to_ipld(js_value: &JsValue) -> Result<Ipld, TombWasmError> {
+    if js_value.is_null() || js_value.is_undefined() {
+        Ok(Ipld::Null)
+    } else if let Some(b) = js_value.as_bool() {
+        Ok(Ipld::Bool(b))
+    } else if let Some(f) = js_value.as_f64() {
+        if f.fract() == 0.0 && f.abs() < 2f64.powi(53) {
+            Ok(Ipld::Integer(f as i128))
+        } else {
+            Ok(Ipld::Float(f))
+        }
+    } else if let Some(s) = js_value.as_string() {
+        Ok(Ipld::String(s))
+    } else if let Some(bytes) = js_value.dyn_ref::<Uint8Array>() {
+        Ok(Ipld::Bytes(bytes.to_vec()))
+    } else if let Some(array) = js_value.dyn_ref::<Array>() {
+        array
+            .iter()
+            .map(|value| js_to_ipld(&value))
+            .collect::<Result<Vec<Ipld>, TombWasmError>>()
+            .map(Ipld::List)
+    } else if let Some(object) = js_value.dyn_ref::<Object>() {
+        let mut map = BTreeMap::new();
+        for entry in Object::entries(object).iter() {
+            let entry = Array::from(&entry);
+            let key = entry
+                .get(0)
+                .as_string()
+                .ok_or(TombWasmError::new("tag key was not a string"))?;
+            map.insert(key, js_to_ipld(&entry.get(1))?);
+        }
+        Ok(Ipld::Map(map))
+    } else {
+        Err(TombWasmError::new(&format!(
+            "unable to store {js_value:?} as a tag"
+        )))
+    }
+}
@@ -0,0 +1,54 @@
+use crate::{
+    utils::progress::{ProgressEvent, ProgressSink},
+    value,
+};
+use js_sys::{Function, Object, Reflect};
+use std::fmt::{self, Debug, Formatter};
+use tracing::warn;
+use wasm_bindgen::prelude::JsValue;
+
+/// Forwards progress to a JS callback, as objects with a `type` and the details of the event
+#[derive(Clone)]
+pub struct JsProgress(pub(crate) Function);
+
+impl Debug for JsProgress {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str("JsProgress(..)")
+    }
+}
+
+impl ProgressSink for JsProgress {
+    fn report(&self, event: ProgressEvent) {
+        let object = Object::new();
+        let fields = match &event {
+            ProgressEvent::PhaseChanged { phase, total } => vec![
+                ("type", value!("phaseChanged")),
+                ("phase", value!(phase.to_string())),
+                (
+                    "total",
+                    total.map_or(JsValue::NULL, |total| value!(total as f64)),
+                ),
+            ],
+            ProgressEvent::FileStarted { path } => {
+                vec![("type", value!("fileStarted")), ("path", value!(path))]
+            }
+            ProgressEvent::FileDone { path } => {
+                vec![("type", value!("fileDone")), ("path", value!(path))]
+            }
+            ProgressEvent::BytesUploaded { uploaded, total } => vec![
+                ("type", value!("bytesUploaded")),
+                ("uploaded", value!(*uploaded as f64)),
+                ("total", value!(*total as f64)),
+            ],
+            ProgressEvent::Warning { message } => {
+                warn!("{message}");
+                vec![("type", value!("warning")), ("message", value!(message))]
+            }
+        };
+        for (key, field) in fields {
+            let _ = Reflect::set(&object, &value!(key), &field);
+        }
+        // Progress is only reported, it's no reason to stop what is being reported on
+        let _ = self.0.call1(&JsValue::NULL, &value!(object));
+    }
+}
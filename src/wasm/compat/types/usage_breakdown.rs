@@ -0,0 +1,50 @@
+use crate::{filesystem::UsageBreakdown, value, wasm::TombWasmError};
+use js_sys::{Array, Object, Reflect};
+use std::collections::BTreeMap;
+use wasm_bindgen::prelude::JsValue;
+
+pub struct WasmUsageBreakdown(pub(crate) UsageBreakdown);
+
+impl From<UsageBreakdown> for WasmUsageBreakdown {
+    fn from(breakdown: UsageBreakdown) -> Self {
+        Self(breakdown)
+    }
+}
+
+impl TryFrom<WasmUsageBreakdown> for JsValue {
+    type Error = js_sys::Error;
+
+    fn try_from(breakdown: WasmUsageBreakdown) -> Result<Self, Self::Error> {
+        let breakdown = breakdown.0;
+        let object = Object::new();
+
+        set(&object, "byDirectory", &sizes(&breakdown.by_directory)?)?;
+        set(&object, "byMimeType", &sizes(&breakdown.by_mime_type)?)?;
+        // Indexed by how many versions old the files are
+        let by_version: Array = breakdown
+            .by_version
+            .values()
+            .map(|size| value!(*size as f64))
+            .collect();
+        set(&object, "byVersion", &value!(by_version))?;
+        set(&object, "blocks", &value!(breakdown.blocks as f64))?;
+        set(&object, "stored", &value!(breakdown.stored as f64))?;
+
+        Ok(value!(object))
+    }
+}
+
+/// Sizes keyed by name as a plain JS object
+fn sizes(sizes: &BTreeMap<String, u64>) -> Result<JsValue, TombWasmError> {
+    let object = Object::new();
+    for (name, size) in sizes {
+        set(&object, name, &value!(*size as f64))?;
+    }
+    Ok(value!(object))
+}
+
+fn set(object: &Object, key: &str, value: &JsValue) -> Result<(), TombWasmError> {
+    Reflect::set(object, &value!(key), value)
+        .map(|_| ())
+        .map_err(|_| TombWasmError::new(&format!("{key} property on object")))
+}
@@ -4,19 +4,24 @@ mod test {
     use {
         banyan_cli::prelude::{
             api::{client::Client, models::account::Account},
-            blockstore::MemoryBlockStore,
+            blockstore::{
+                BanyanBlockStore, CarV2MemoryBlockStore, IndexedDbBlockStore, MemoryBlockStore,
+                RootedBlockStore,
+            },
             filesystem::{sharing::SharedFile, FilesystemError, FsMetadata},
             wasm::{
-                register_log, TombResult, TombWasm, WasmBucket, WasmBucketKey, WasmBucketMount,
-                WasmFsMetadataEntry,
+                register_log, TombResult, TombWasm, TombWasmError, WasmBucket, WasmBucketKey,
+                WasmBucketMount, WasmFsMetadataEntry,
             },
         },
-        js_sys::{Array, Uint8Array},
-        std::convert::TryFrom,
+        js_sys::{Array, Function, Reflect, Uint8Array},
+        std::{cell::RefCell, convert::TryFrom, rc::Rc},
         tomb_crypt::prelude::{EcEncryptionKey, PrivateKey, PublicKey},
         tracing::info,
-        wasm_bindgen::{convert::TryFromJsValue, JsValue},
+        wasm_bindgen::{closure::Closure, convert::TryFromJsValue, JsCast, JsValue},
         wasm_bindgen_test::{wasm_bindgen_test, wasm_bindgen_test_configure},
+        wasm_streams::ReadableStream,
+        wnfs::libipld::IpldCodec,
     };
     wasm_bindgen_test_configure!(run_in_browser);
     const USAGE_LIMIT: u64 = 53_687_091_200;
@@ -77,6 +82,20 @@ mod test {
         String::from_utf8(bytes).unwrap()
     }
 
+    /// Make every content upload fail, until the function returned is called
+    fn fail_uploads() -> Result<Function, JsValue> {
+        Function::new_no_args(
+            "const fetch = globalThis.fetch;
+            globalThis.fetch = (request, init) =>
+                String(request.url ?? request).includes('/api/v1/upload')
+                    ? Promise.reject(new TypeError('upload failed'))
+                    : fetch.call(globalThis, request, init);
+            return () => { globalThis.fetch = fetch; };",
+        )
+        .call0(&JsValue::NULL)?
+        .dyn_into::<Function>()
+    }
+
     async fn ecencryption_key_pair() -> (String, String) {
         let private_key = EcEncryptionKey::generate()
             .await
@@ -142,7 +161,7 @@ mod test {
         mount.rename("new_name".to_string()).await?;
         assert_eq!(mount.bucket().name(), "new_name");
         let mount = client
-            .mount(bucket_mount.bucket().id().to_string(), private_pem)
+            .mount(bucket_mount.bucket().id().to_string(), private_pem, None)
             .await?;
         assert_eq!(mount.bucket().name(), "new_name");
         Ok(())
@@ -160,12 +179,114 @@ mod test {
             client.create_bucket_key(bucket.id().to_string()).await?;
         assert_eq!(wasm_bucket_key.bucket_id(), bucket.id().to_string());
         assert!(!wasm_bucket_key.approved());
-        let mut mount = client.mount(bucket.id().to_string(), private_pem).await?;
+        let mut mount = client
+            .mount(bucket.id().to_string(), private_pem, None)
+            .await?;
         assert!(!mount.locked());
         mount.share_with(wasm_bucket_key.id()).await?;
         Ok(())
     }
 
+    #[wasm_bindgen_test]
+    async fn indexeddb_blockstore() -> TombResult<()> {
+        let name = "banyan-test-blockstore";
+        let store = IndexedDbBlockStore::open(name)
+            .await
+            .map_err(|err| TombWasmError::new(&err.to_string()))?;
+        let kitty_bytes = b"Hello Kitty!".to_vec();
+        let kitty_cid = store
+            .put_block(kitty_bytes.clone(), IpldCodec::Raw)
+            .await
+            .map_err(|err| TombWasmError::new(&err.to_string()))?;
+        store.set_root(&kitty_cid);
+        store
+            .save()
+            .await
+            .map_err(|err| TombWasmError::new(&err.to_string()))?;
+
+        // Everything is still there once reopened, and can be copied into memory
+        let reopened = IndexedDbBlockStore::open(name)
+            .await
+            .map_err(|err| TombWasmError::new(&err.to_string()))?;
+        assert_eq!(reopened.get_root(), Some(kitty_cid));
+        let car = reopened
+            .to_car()
+            .await
+            .map_err(|err| TombWasmError::new(&err.to_string()))?;
+        assert_eq!(car.get_root(), Some(kitty_cid));
+        assert_eq!(
+            car.get_block(&kitty_cid)
+                .await
+                .map_err(|err| TombWasmError::new(&err.to_string()))?
+                .to_vec(),
+            kitty_bytes
+        );
+
+        // Saving a CAR leaves exactly its blocks behind
+        let puppy_bytes = b"Hello Puppy!".to_vec();
+        let puppy_car =
+            CarV2MemoryBlockStore::new().map_err(|err| TombWasmError::new(&err.to_string()))?;
+        let puppy_cid = puppy_car
+            .put_block(puppy_bytes.clone(), IpldCodec::Raw)
+            .await
+            .map_err(|err| TombWasmError::new(&err.to_string()))?;
+        puppy_car.set_root(&puppy_cid);
+        reopened
+            .save_car(&puppy_car)
+            .await
+            .map_err(|err| TombWasmError::new(&err.to_string()))?;
+        assert_eq!(reopened.get_root(), Some(puppy_cid));
+        assert!(reopened.get_block(&kitty_cid).await.is_err());
+        assert_eq!(
+            reopened
+                .get_block(&puppy_cid)
+                .await
+                .map_err(|err| TombWasmError::new(&err.to_string()))?
+                .to_vec(),
+            puppy_bytes
+        );
+
+        reopened
+            .clear()
+            .await
+            .map_err(|err| TombWasmError::new(&err.to_string()))?;
+        assert!(reopened.get_block(&puppy_cid).await.is_err());
+        Ok(())
+    }
+
+    #[wasm_bindgen_test]
+    async fn persistent_mount() -> TombResult<()> {
+        let mut client = authenticated_client().await?;
+        info!("tomb_wasm_test: persistent_mount()");
+        let (private_pem, public_pem) = ecencryption_key_pair().await;
+        let bucket_mount =
+            create_bucket_and_mount(&mut client, private_pem.clone(), public_pem).await?;
+        let bucket_id = bucket_mount.bucket().id().to_string();
+
+        let mut mount = client
+            .mount(bucket_id.clone(), private_pem.clone(), Some(true))
+            .await?;
+        assert!(mount.persistent());
+        let write_path_array: Array = js_array(&["cat.txt"]).into();
+        let content = Uint8Array::from(&b"hello kitty"[..]);
+        mount
+            .write(write_path_array.clone(), content.buffer())
+            .await?;
+        assert!(!mount.dirty());
+        let metadata_id = mount.metadata()?.id();
+
+        // Mounting again picks up where the last mount left off
+        let mut mount = client.mount(bucket_id, private_pem, Some(true)).await?;
+        assert!(!mount.locked());
+        assert_eq!(mount.metadata()?.id(), metadata_id);
+        let read = mount.read_bytes(write_path_array, None, None).await?;
+        assert_eq!(read.to_vec(), content.to_vec());
+        // Nothing is queued, so there's nothing to sync
+        mount.sync_queued().await?;
+        assert_eq!(mount.metadata()?.id(), metadata_id);
+        Ok(())
+    }
+
     #[wasm_bindgen_test]
     async fn write_share_receive_local() -> Result<(), FilesystemError> {
         let metadata_store = MemoryBlockStore::default();
@@ -287,15 +408,15 @@ mod test {
             create_bucket_and_mount(&mut client, private_pem.clone(), public_pem).await?;
         let mut mount = bucket_mount.mount();
         assert!(!mount.locked());
-        assert!(!mount.has_snapshot());
+        assert!(!mount.has_snapshot()?);
         let _snapshot_id = mount.snapshot().await?;
-        assert!(mount.has_snapshot());
+        assert!(mount.has_snapshot()?);
 
         let mount = client
-            .mount(mount.bucket().id().to_string(), private_pem)
+            .mount(mount.bucket().id().to_string(), private_pem, None)
             .await?;
         assert!(!mount.locked());
-        assert!(mount.has_snapshot());
+        assert!(mount.has_snapshot()?);
         Ok(())
     }
 
@@ -344,7 +465,7 @@ mod test {
         assert_eq!(ls.length(), 1);
         info!("tomb_wasm_test: create_bucket_mount_mkdir_remount_ls(): remount() and ls()");
         let mut mount = client
-            .mount(bucket_mount.bucket().id().to_string(), private_pem)
+            .mount(bucket_mount.bucket().id().to_string(), private_pem, None)
             .await?;
         assert!(!mount.locked());
         let ls: Array = mount.ls(ls_path_array).await?;
@@ -401,13 +522,35 @@ mod test {
         let fs_entry = WasmFsMetadataEntry::try_from(ls_0).unwrap();
         assert_eq!(fs_entry.name(), "zero.bin");
         assert_eq!(fs_entry.entry_type(), "file");
-        let new_bytes = mount.read_bytes(write_path_array, None).await?.to_vec();
+        let new_bytes = mount
+            .read_bytes(write_path_array, None, None)
+            .await?
+            .to_vec();
         // Assert successful reconstruction
         assert_eq!(new_bytes, zero_content_buffer.to_vec());
 
         Ok(())
     }
 
+    #[wasm_bindgen_test]
+    async fn read_missing() -> TombResult<()> {
+        let mut client = authenticated_client().await?;
+        info!("tomb_wasm_test: read_missing()");
+        let (private_pem, public_pem) = ecencryption_key_pair().await;
+        let bucket_mount = create_bucket_and_mount(&mut client, private_pem, public_pem).await?;
+        let mut mount = bucket_mount.mount();
+        let read_path_array: Array = js_array(&["missing.bin"]).into();
+        let err = mount
+            .read_bytes(read_path_array, None, None)
+            .await
+            .expect_err("read a file which was never written");
+        // Thrown to JS as a NotFoundError with a matching code
+        assert_eq!(Reflect::get(&err, &"code".into())?, "not_found");
+        assert_eq!(Reflect::get(&err, &"name".into())?, "NotFoundError");
+
+        Ok(())
+    }
+
     #[wasm_bindgen_test]
     async fn write_remount() -> TombResult<()> {
         let mut client = authenticated_client().await?;
@@ -436,7 +579,7 @@ mod test {
 
         info!("tomb_wasm_test: create_bucket_mount_write_ls_remount_ls(): remount() and ls()");
         let mut mount = client
-            .mount(bucket_mount.bucket().id().to_string(), private_pem)
+            .mount(bucket_mount.bucket().id().to_string(), private_pem, None)
             .await?;
         assert!(!mount.locked());
         let ls: Array = mount.ls(ls_path_array).await?;
@@ -448,6 +591,70 @@ mod test {
         Ok(())
     }
 
+    #[wasm_bindgen_test]
+    async fn write_merge() -> TombResult<()> {
+        let mut client = authenticated_client().await?;
+        info!("tomb_wasm_test: write_merge()");
+        let (private_pem, public_pem) = ecencryption_key_pair().await;
+        let bucket_mount =
+            create_bucket_and_mount(&mut client, private_pem.clone(), public_pem).await?;
+        let bucket_id = bucket_mount.bucket().id().to_string();
+        let mut mount = bucket_mount.mount();
+        // Another tab on the same drive
+        let mut other = client
+            .mount(bucket_id.clone(), private_pem.clone(), None)
+            .await?;
+
+        let content = Uint8Array::new_with_length(10).buffer();
+        mount
+            .write(js_array(&["here.bin"]).into(), content.clone())
+            .await?;
+        // Pushed on top of the version written above, rather than forking from it
+        other
+            .write(js_array(&["there.bin"]).into(), content)
+            .await?;
+        let ls_path_array: Array = js_array(&[]).into();
+        assert_eq!(other.ls(ls_path_array.clone()).await?.length(), 2);
+
+        let mut mount = client.mount(bucket_id, private_pem, None).await?;
+        assert_eq!(mount.ls(ls_path_array).await?.length(), 2);
+        Ok(())
+    }
+
+    #[wasm_bindgen_test]
+    async fn write_merge_conflict() -> TombResult<()> {
+        let mut client = authenticated_client().await?;
+        info!("tomb_wasm_test: write_merge_conflict()");
+        let (private_pem, public_pem) = ecencryption_key_pair().await;
+        let bucket_mount =
+            create_bucket_and_mount(&mut client, private_pem.clone(), public_pem).await?;
+        let mut mount = bucket_mount.mount();
+        let write_path_array: Array = js_array(&["zero.bin"]).into();
+        mount
+            .write(
+                write_path_array.clone(),
+                Uint8Array::new_with_length(10).buffer(),
+            )
+            .await?;
+        let mut other = client
+            .mount(bucket_mount.bucket().id().to_string(), private_pem, None)
+            .await?;
+
+        mount
+            .write(
+                write_path_array.clone(),
+                Uint8Array::new_with_length(20).buffer(),
+            )
+            .await?;
+        let err = other
+            .write(write_path_array, Uint8Array::new_with_length(30).buffer())
+            .await
+            .expect_err("wrote over a file changed remotely");
+        assert_eq!(Reflect::get(&err, &"code".into())?, "conflict");
+        assert_eq!(Reflect::get(&err, &"name".into())?, "ConflictError");
+        Ok(())
+    }
+
     #[wasm_bindgen_test]
     async fn write_rm() -> TombResult<()> {
         let mut client = authenticated_client().await?;
@@ -502,6 +709,353 @@ mod test {
         Ok(())
     }
 
+    #[wasm_bindgen_test]
+    async fn write_read_history() -> TombResult<()> {
+        let mut client = authenticated_client().await?;
+        info!("tomb_wasm_test: write_read_history()");
+        let (private_pem, public_pem) = ecencryption_key_pair().await;
+        let bucket_mount = create_bucket_and_mount(&mut client, private_pem, public_pem).await?;
+        let mut mount = bucket_mount.mount();
+        let write_path_array: Array = js_array(&["cat.txt"]).into();
+
+        let first = Uint8Array::from(&b"hello kitty"[..]);
+        mount
+            .write(write_path_array.clone(), first.buffer())
+            .await?;
+        let first_version = mount.metadata()?.id();
+        let second = Uint8Array::from(&b"goodbye kitty!"[..]);
+        mount
+            .write(write_path_array.clone(), second.buffer())
+            .await?;
+
+        let history: Array = mount.file_history(write_path_array.clone()).await?;
+        assert_eq!(history.length(), 2);
+
+        // Reads default to the mounted version, but can go back to any other
+        let current = mount
+            .read_bytes(write_path_array.clone(), None, None)
+            .await?;
+        assert_eq!(current.to_vec(), second.to_vec());
+        let past = mount
+            .read_bytes(write_path_array.clone(), Some(first_version), None)
+            .await?;
+        assert_eq!(past.to_vec(), first.to_vec());
+        assert!(mount
+            .read_bytes(
+                write_path_array.clone(),
+                Some("not a version".to_string()),
+                None
+            )
+            .await
+            .is_err());
+
+        // Every revision listed in the history can be read back from the mounted version
+        for (revision, expected) in [(0, &second), (1, &first)] {
+            let entry = history.get(revision);
+            let listed = Reflect::get(&entry, &JsValue::from_str("revision"))?
+                .as_f64()
+                .expect("revision is a number") as u32;
+            assert_eq!(listed, revision);
+            let read = mount
+                .read_bytes(write_path_array.clone(), None, Some(listed))
+                .await?;
+            assert_eq!(read.to_vec(), expected.to_vec());
+        }
+        assert!(mount
+            .read_bytes(write_path_array, None, Some(2))
+            .await
+            .is_err());
+        Ok(())
+    }
+
+    #[wasm_bindgen_test]
+    async fn write_progress() -> TombResult<()> {
+        let mut client = authenticated_client().await?;
+        info!("tomb_wasm_test: write_progress()");
+        let (private_pem, public_pem) = ecencryption_key_pair().await;
+        let bucket_mount = create_bucket_and_mount(&mut client, private_pem, public_pem).await?;
+        let mut mount = bucket_mount.mount();
+
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let on_progress = {
+            let events = events.clone();
+            Closure::<dyn FnMut(JsValue)>::new(move |event| events.borrow_mut().push(event))
+        };
+        mount.on_progress(Some(
+            on_progress.as_ref().unchecked_ref::<Function>().clone(),
+        ));
+        let content = Uint8Array::from(&b"hello kitty"[..]);
+        mount
+            .write(js_array(&["cat.txt"]).into(), content.buffer())
+            .await?;
+
+        // Writing pushes the change, uploading the new content along with it
+        let field = |event: &JsValue, key: &str| {
+            Reflect::get(event, &key.into()).map(|value| value.as_string())
+        };
+        let mut phases = Vec::new();
+        for event in events.borrow().iter() {
+            if field(event, "type")?.as_deref() == Some("phaseChanged") {
+                phases.push(field(event, "phase")?.unwrap_or_default());
+            }
+        }
+        assert_eq!(phases, vec!["pushing", "uploading"]);
+        let last = events
+            .borrow()
+            .last()
+            .cloned()
+            .expect("no progress reported");
+        assert_eq!(field(&last, "type")?.as_deref(), Some("bytesUploaded"));
+        assert_eq!(
+            Reflect::get(&last, &"uploaded".into())?,
+            Reflect::get(&last, &"total".into())?
+        );
+
+        // Progress stops being reported once the callback is taken away
+        mount.on_progress(None);
+        let reported = events.borrow().len();
+        mount.mkdir(js_array(&["dogs"]).into()).await?;
+        assert_eq!(events.borrow().len(), reported);
+        Ok(())
+    }
+
+    #[wasm_bindgen_test]
+    async fn write_stream_read() -> TombResult<()> {
+        let mut client = authenticated_client().await?;
+        info!("tomb_wasm_test: write_stream_read()");
+        let (private_pem, public_pem) = ecencryption_key_pair().await;
+        let bucket_mount = create_bucket_and_mount(&mut client, private_pem, public_pem).await?;
+        let mut mount = bucket_mount.mount();
+        let write_path_array: Array = js_array(&["cat.txt"]).into();
+
+        let chunks = (0..4u8)
+            .map(|i| Ok(JsValue::from(Uint8Array::from(&[i; 1000][..]))))
+            .collect::<Vec<_>>();
+        let stream = ReadableStream::from_stream(futures_util::stream::iter(chunks)).into_raw();
+        let progress = Rc::new(RefCell::new(Vec::new()));
+        let on_progress = {
+            let progress = progress.clone();
+            Closure::<dyn FnMut(f64)>::new(move |written| progress.borrow_mut().push(written))
+        };
+        mount
+            .write_stream(
+                write_path_array.clone(),
+                stream.into(),
+                Some(on_progress.as_ref().unchecked_ref::<Function>().clone()),
+            )
+            .await?;
+        assert_eq!(*progress.borrow(), vec![1000.0, 2000.0, 3000.0, 4000.0]);
+        // The content was spooled through IndexedDB and uploaded from there, never held in memory
+        assert!(mount.content_blockstore().data_size() < 4000);
+
+        let content = mount.read_bytes(write_path_array, None, None).await?;
+        let expected = (0..4u8).flat_map(|i| [i; 1000]).collect::<Vec<_>>();
+        assert_eq!(content.to_vec(), expected);
+
+        // Only streams can be streamed
+        assert!(mount
+            .write_stream(js_array(&["dog.txt"]).into(), "woof".into(), None)
+            .await
+            .is_err());
+        Ok(())
+    }
+
+    #[wasm_bindgen_test]
+    async fn write_tag_ls() -> TombResult<()> {
+        let mut client = authenticated_client().await?;
+        info!("tomb_wasm_test: write_tag_ls()");
+        let (private_pem, public_pem) = ecencryption_key_pair().await;
+        let bucket_mount = create_bucket_and_mount(&mut client, private_pem, public_pem).await?;
+        let mut mount = bucket_mount.mount();
+        let write_path_array: Array = js_array(&["cat.txt"]).into();
+        let content_buffer = Uint8Array::from(&b"hello kitty"[..]);
+        mount
+            .write(write_path_array.clone(), content_buffer.buffer())
+            .await?;
+
+        mount
+            .set_metadata(write_path_array.clone(), "color".into(), "orange".into())
+            .await?;
+        mount
+            .set_metadata(write_path_array.clone(), "lives".into(), 9.into())
+            .await?;
+        mount
+            .set_metadata(write_path_array.clone(), "lives".into(), JsValue::NULL)
+            .await?;
+
+        let metadata = mount.get_metadata(write_path_array.clone()).await?;
+        let tags = Reflect::get(&metadata, &"tags".into())?;
+        assert_eq!(Reflect::get(&tags, &"color".into())?, "orange");
+        assert!(Reflect::get(&tags, &"lives".into())?.is_undefined());
+
+        // Tags show up when listing too
+        let ls: Array = mount.ls(js_array(&[]).into()).await?;
+        let entry_metadata = Reflect::get(&ls.get(0), &"metadata".into())?;
+        let entry_tags = Reflect::get(&entry_metadata, &"tags".into())?;
+        assert_eq!(Reflect::get(&entry_tags, &"color".into())?, "orange");
+
+        // Directories can't be tagged
+        assert!(mount
+            .set_metadata(js_array(&[]).into(), "color".into(), "orange".into())
+            .await
+            .is_err());
+        Ok(())
+    }
+
+    #[wasm_bindgen_test]
+    async fn write_usage_breakdown() -> TombResult<()> {
+        let mut client = authenticated_client().await?;
+        info!("tomb_wasm_test: write_usage_breakdown()");
+        let (private_pem, public_pem) = ecencryption_key_pair().await;
+        let bucket_mount = create_bucket_and_mount(&mut client, private_pem, public_pem).await?;
+        let mut mount = bucket_mount.mount();
+        mount
+            .write(
+                js_array(&["cats", "cat.txt"]).into(),
+                Uint8Array::new_with_length(1024).buffer(),
+            )
+            .await?;
+
+        let breakdown = mount.usage_breakdown().await?;
+        let by_directory = Reflect::get(&breakdown, &"byDirectory".into())?;
+        assert_eq!(Reflect::get(&by_directory, &"/".into())?, 1024);
+        assert_eq!(Reflect::get(&by_directory, &"/cats".into())?, 1024);
+        let by_mime_type = Reflect::get(&breakdown, &"byMimeType".into())?;
+        assert_eq!(Reflect::get(&by_mime_type, &"text/plain".into())?, 1024);
+        let by_version: Array = Reflect::get(&breakdown, &"byVersion".into())?.into();
+        assert_eq!(by_version.get(0), 1024);
+        Ok(())
+    }
+
+    #[wasm_bindgen_test]
+    async fn transaction_commit() -> TombResult<()> {
+        let mut client = authenticated_client().await?;
+        info!("tomb_wasm_test: transaction_commit()");
+        let (private_pem, public_pem) = ecencryption_key_pair().await;
+        let bucket_mount =
+            create_bucket_and_mount(&mut client, private_pem.clone(), public_pem).await?;
+        let mut mount = bucket_mount.mount();
+        let metadata_id = mount.metadata()?.id();
+
+        mount.begin()?;
+        assert!(mount.in_transaction());
+        for name in ["one.bin", "two.bin", "three.bin"] {
+            let content_buffer = Uint8Array::new_with_length(10);
+            mount
+                .write(js_array(&[name]).into(), content_buffer.buffer())
+                .await?;
+        }
+        mount.mkdir(js_array(&["cats"]).into()).await?;
+        // Nothing has been pushed yet
+        assert_eq!(mount.metadata()?.id(), metadata_id);
+        mount.commit().await?;
+        assert!(!mount.in_transaction());
+        assert_ne!(mount.metadata()?.id(), metadata_id);
+
+        // Everything arrived in a single new version
+        let mut mount = client
+            .mount(bucket_mount.bucket().id().to_string(), private_pem, None)
+            .await?;
+        let ls: Array = mount.ls(js_array(&[]).into()).await?;
+        assert_eq!(ls.length(), 4);
+        Ok(())
+    }
+
+    #[wasm_bindgen_test]
+    async fn transaction_rollback() -> TombResult<()> {
+        let mut client = authenticated_client().await?;
+        info!("tomb_wasm_test: transaction_rollback()");
+        let (private_pem, public_pem) = ecencryption_key_pair().await;
+        let bucket_mount = create_bucket_and_mount(&mut client, private_pem, public_pem).await?;
+        let mut mount = bucket_mount.mount();
+        let ls_path_array: Array = js_array(&[]).into();
+
+        mount.begin()?;
+        assert!(mount.begin().is_err());
+        let content_buffer = Uint8Array::new_with_length(10);
+        mount
+            .write(js_array(&["zero.bin"]).into(), content_buffer.buffer())
+            .await?;
+        let ls: Array = mount.ls(ls_path_array.clone()).await?;
+        assert_eq!(ls.length(), 1);
+        mount.rollback()?;
+        let ls: Array = mount.ls(ls_path_array).await?;
+        assert_eq!(ls.length(), 0);
+        assert!(mount.commit().await.is_err());
+        Ok(())
+    }
+
+    #[wasm_bindgen_test]
+    async fn transaction_rollback_failed_commit() -> TombResult<()> {
+        let mut client = authenticated_client().await?;
+        info!("tomb_wasm_test: transaction_rollback_failed_commit()");
+        let (private_pem, public_pem) = ecencryption_key_pair().await;
+        let bucket_mount =
+            create_bucket_and_mount(&mut client, private_pem.clone(), public_pem).await?;
+        let bucket_id = bucket_mount.bucket().id().to_string();
+        let mut mount = bucket_mount.mount();
+        let mut other = client
+            .mount(bucket_id.clone(), private_pem.clone(), None)
+            .await?;
+        let content = Uint8Array::new_with_length(10).buffer();
+
+        mount.begin()?;
+        mount
+            .write(js_array(&["rolled_back.bin"]).into(), content.clone())
+            .await?;
+        // Pushed by another tab while the transaction was open
+        other
+            .write(js_array(&["there.bin"]).into(), content.clone())
+            .await?;
+        // The commit merges and pushes its metadata, but its content never arrives
+        let restore_fetch = fail_uploads()?;
+        let committed = mount.commit().await;
+        restore_fetch.call0(&JsValue::NULL)?;
+        assert!(committed.is_err());
+        assert!(mount.in_transaction());
+
+        mount.rollback()?;
+        mount.write(js_array(&["here.bin"]).into(), content).await?;
+        assert!(!mount.dirty());
+
+        let mut mount = client.mount(bucket_id, private_pem, None).await?;
+        let ls: Array = mount.ls(js_array(&[]).into()).await?;
+        assert_eq!(ls.length(), 2);
+        Ok(())
+    }
+
+    #[wasm_bindgen_test]
+    async fn sync_after_failed_upload() -> TombResult<()> {
+        let mut client = authenticated_client().await?;
+        info!("tomb_wasm_test: sync_after_failed_upload()");
+        let (private_pem, public_pem) = ecencryption_key_pair().await;
+        let bucket_mount =
+            create_bucket_and_mount(&mut client, private_pem.clone(), public_pem).await?;
+        let bucket_id = bucket_mount.bucket().id().to_string();
+        let mut mount = bucket_mount.mount();
+        let content = Uint8Array::from(&b"hello kitty"[..]);
+
+        // The metadata is pushed, but its content never arrives
+        let restore_fetch = fail_uploads()?;
+        let written = mount
+            .write(js_array(&["cat.txt"]).into(), content.buffer())
+            .await;
+        restore_fetch.call0(&JsValue::NULL)?;
+        assert!(written.is_err());
+        assert!(mount.dirty());
+
+        // The remote never moved on from the version before, so the change is pushed on top of it
+        mount.sync_queued().await?;
+        assert!(!mount.dirty());
+
+        let mut mount = client.mount(bucket_id, private_pem, None).await?;
+        let content = mount
+            .read_bytes(js_array(&["cat.txt"]).into(), None, None)
+            .await?;
+        assert_eq!(content.to_vec(), b"hello kitty");
+        Ok(())
+    }
+
     // // TODO: this is failing due to the server not receiving appropriate updates in a timely manner
     // #[wasm_bindgen_test]
     // async fn write_remount_mv() -> TombResult<()> {
@@ -527,7 +1081,7 @@ mod test {
     //     gloo_timers::future::TimeoutFuture::new(5_000).await;
 
     //     let mut mount = client
-    //         .mount(bucket_mount.bucket().id().to_string(), private_pem)
+    //         .mount(bucket_mount.bucket().id().to_string(), private_pem, None)
     //         .await
     //         .expect("remount failed");
     //     let mv_from_path_array: Array = js_array(&["zero.bin"]).into();